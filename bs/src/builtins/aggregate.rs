@@ -0,0 +1,116 @@
+//! Reductions over vectors.
+//!
+//! Nulls are skipped by every reduction but `count`, `first` and `last`. Reductions with an identity
+//! (`sum`, `prod`, `count`) return it for an empty vector, the others return null, or for `Bool[]`,
+//! which has no null, the identity of `and` (`min`) and `or` (`max`).
+
+use crate::rt::runtime::Runtime;
+use ffi::values::sym::Sym;
+use ffi::values::time::{Date, Time, Timespan, Timestamp};
+use ffi::values::{NULL_FLOAT64, NULL_INT64};
+
+const LANES: usize = 8;
+
+/// Names of the reductions, which queries evaluate over the rows of every group.
+pub(crate) const REDUCTIONS: &[&str] = &["sum", "prod", "min", "max", "avg", "count", "first", "last", "var", "dev"];
+
+fn non_null_i64(v: &[i64]) -> impl Iterator<Item = i64> + '_ { v.iter().copied().filter(|x| *x != NULL_INT64) }
+
+fn non_null_f64(v: &[f64]) -> impl Iterator<Item = f64> + '_ { v.iter().copied().filter(|x| !x.is_nan()) }
+
+/// Sums floats in independent lanes, which lets the compiler vectorise the loop.
+fn sum_f64(v: &[f64]) -> f64 {
+    let mut lanes = [0.0; LANES];
+    let chunks = v.chunks_exact(LANES);
+    let rest = non_null_f64(chunks.remainder()).sum::<f64>();
+    for chunk in chunks {
+        for (lane, x) in lanes.iter_mut().zip(chunk) {
+            *lane += if x.is_nan() { 0.0 } else { *x };
+        }
+    }
+    lanes.iter().sum::<f64>() + rest
+}
+
+fn sum_i64(v: &[i64]) -> i64 { non_null_i64(v).fold(0, |acc, x| acc.wrapping_add(x)) }
+
+fn sum_bool(v: &[bool]) -> i64 { v.iter().filter(|x| **x).count() as i64 }
+
+fn avg_f64(v: &[f64]) -> f64 {
+    match non_null_f64(v).count() {
+        0 => NULL_FLOAT64,
+        n => sum_f64(v) / n as f64,
+    }
+}
+
+fn var_f64(v: &[f64]) -> f64 {
+    let avg = avg_f64(v);
+    match non_null_f64(v).count() {
+        0 => NULL_FLOAT64,
+        n => non_null_f64(v).map(|x| (x - avg) * (x - avg)).sum::<f64>() / n as f64,
+    }
+}
+
+/// Registers the reductions of dates and times, which are ordered as their `Int64`.
+macro_rules! register_temporal {
+    ($runtime:expr, $($ty:ident),*) => {
+        $(
+            $runtime.register_fn("min", |v: &[$ty]| {
+                $ty(v.iter().map(|x| x.0).filter(|x| *x != NULL_INT64).min().unwrap_or(NULL_INT64))
+            });
+            $runtime.register_fn("max", |v: &[$ty]| {
+                $ty(v.iter().map(|x| x.0).filter(|x| *x != NULL_INT64).max().unwrap_or(NULL_INT64))
+            });
+            $runtime.register_fn("count", |v: &[$ty]| v.len() as i64);
+            $runtime.register_fn("first", |v: &[$ty]| v.first().copied().unwrap_or($ty(NULL_INT64)));
+            $runtime.register_fn("last", |v: &[$ty]| v.last().copied().unwrap_or($ty(NULL_INT64)));
+        )*
+    };
+}
+
+pub(crate) fn init(runtime: &mut Runtime<'_>) {
+    runtime.register_fn("sum", |v: &[i64]| sum_i64(v));
+    runtime.register_fn("sum", |v: &[f64]| sum_f64(v));
+    runtime.register_fn("sum", |v: &[bool]| sum_bool(v));
+
+    runtime.register_fn("prod", |v: &[i64]| non_null_i64(v).fold(1i64, |acc, x| acc.wrapping_mul(x)));
+    runtime.register_fn("prod", |v: &[f64]| non_null_f64(v).product::<f64>());
+    runtime.register_fn("prod", |v: &[bool]| v.iter().all(|x| *x) as i64);
+
+    runtime.register_fn("min", |v: &[i64]| non_null_i64(v).min().unwrap_or(NULL_INT64));
+    runtime.register_fn("min", |v: &[f64]| v.iter().copied().reduce(f64::min).unwrap_or(NULL_FLOAT64));
+    runtime.register_fn("min", |v: &[bool]| v.iter().all(|x| *x));
+
+    runtime.register_fn("max", |v: &[i64]| non_null_i64(v).max().unwrap_or(NULL_INT64));
+    runtime.register_fn("max", |v: &[f64]| v.iter().copied().reduce(f64::max).unwrap_or(NULL_FLOAT64));
+    runtime.register_fn("max", |v: &[bool]| v.iter().any(|x| *x));
+
+    runtime.register_fn("avg", |v: &[i64]| match non_null_i64(v).count() {
+        0 => NULL_FLOAT64,
+        n => non_null_i64(v).map(|x| x as f64).sum::<f64>() / n as f64,
+    });
+    runtime.register_fn("avg", |v: &[f64]| avg_f64(v));
+    runtime.register_fn("avg", |v: &[bool]| match v.len() {
+        0 => NULL_FLOAT64,
+        n => sum_bool(v) as f64 / n as f64,
+    });
+
+    runtime.register_fn("count", |v: &[i64]| v.len() as i64);
+    runtime.register_fn("count", |v: &[f64]| v.len() as i64);
+    runtime.register_fn("count", |v: &[bool]| v.len() as i64);
+    runtime.register_fn("count", |v: &[Sym]| v.len() as i64);
+
+    runtime.register_fn("first", |v: &[i64]| v.first().copied().unwrap_or(NULL_INT64));
+    runtime.register_fn("first", |v: &[f64]| v.first().copied().unwrap_or(NULL_FLOAT64));
+    runtime.register_fn("first", |v: &[bool]| v.first().copied().unwrap_or(false));
+    runtime.register_fn("first", |v: &[Sym]| v.first().copied().unwrap_or(Sym::NULL));
+
+    runtime.register_fn("last", |v: &[i64]| v.last().copied().unwrap_or(NULL_INT64));
+    runtime.register_fn("last", |v: &[f64]| v.last().copied().unwrap_or(NULL_FLOAT64));
+    runtime.register_fn("last", |v: &[bool]| v.last().copied().unwrap_or(false));
+    runtime.register_fn("last", |v: &[Sym]| v.last().copied().unwrap_or(Sym::NULL));
+
+    register_temporal!(runtime, Date, Time, Timestamp, Timespan);
+
+    runtime.register_fn("var", |v: &[f64]| var_f64(v));
+    runtime.register_fn("dev", |v: &[f64]| var_f64(v).sqrt());
+}
@@ -0,0 +1,165 @@
+//! Construction, lookup and arithmetic of dictionaries.
+//!
+//! Arithmetic between dictionaries lines up their keys: the result has the keys of the left one,
+//! followed by the keys only found in the right one. A key missing on either side stands for the
+//! identity of the operation, so `(1!2) - (3!4)` is `[1, 3]![2, -4]`.
+
+use crate::rt::error::raise;
+use crate::rt::runtime::Runtime;
+use ffi::external::{HostRet, HostValue};
+use ffi::values::dict::{DictKey, DictRef, TypedDict};
+use ffi::values::sym::Sym;
+use ffi::values::time::{Date, Time, Timespan, Timestamp};
+use ffi::values::vector::VecElement;
+use ffi::values::NULL_INT64;
+
+/// Value types the builtins are registered for, a missing key is looked up as `NULL`.
+trait Elem: VecElement + 'static {}
+
+impl<T: VecElement + 'static> Elem for T {}
+
+/// Value types dictionaries can be combined by arithmetic over, which behaves as between scalars.
+trait Num: Elem {
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn add(self, rhs: Self) -> Self;
+    fn sub(self, rhs: Self) -> Self;
+    fn mul(self, rhs: Self) -> Self;
+    fn div(self, rhs: Self) -> Self;
+}
+
+impl Num for i64 {
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+
+    fn add(self, rhs: Self) -> Self { null_or(self, rhs, self.wrapping_add(rhs)) }
+
+    fn sub(self, rhs: Self) -> Self { null_or(self, rhs, self.wrapping_sub(rhs)) }
+
+    fn mul(self, rhs: Self) -> Self { null_or(self, rhs, self.wrapping_mul(rhs)) }
+
+    // dividing by zero yields null
+    fn div(self, rhs: Self) -> Self {
+        match rhs {
+            0 => NULL_INT64,
+            _ => null_or(self, rhs, self.wrapping_div(rhs)),
+        }
+    }
+}
+
+fn null_or(lhs: i64, rhs: i64, res: i64) -> i64 {
+    match lhs == NULL_INT64 || rhs == NULL_INT64 {
+        true => NULL_INT64,
+        false => res,
+    }
+}
+
+impl Num for f64 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+
+    fn add(self, rhs: Self) -> Self { self + rhs }
+
+    fn sub(self, rhs: Self) -> Self { self - rhs }
+
+    fn mul(self, rhs: Self) -> Self { self * rhs }
+
+    fn div(self, rhs: Self) -> Self { self / rhs }
+}
+
+/// Dictionary of `keys` and `values`, as built by `keys ! values`.
+fn dict<K: DictKey, V: Elem>(keys: &[K], values: &[V]) -> TypedDict<K, V> {
+    if keys.len() != values.len() {
+        raise("Dict keys and values must have the same length");
+        return TypedDict::new(vec![], vec![]);
+    }
+    TypedDict::new(keys.to_vec(), values.to_vec())
+}
+
+fn at<K: DictKey, V: Elem>(d: DictRef<K, V>, key: K) -> V { d.get(key).unwrap_or(V::NULL) }
+
+fn at_vec<K: DictKey, V: Elem>(d: DictRef<K, V>, keys: &[K]) -> Vec<V> {
+    keys.iter().map(|k| d.get(*k).unwrap_or(V::NULL)).collect()
+}
+
+fn key<K: DictKey, V: Elem>(d: DictRef<K, V>) -> Vec<K> { d.keys().to_vec() }
+
+fn value<K: DictKey, V: Elem>(d: DictRef<K, V>) -> Vec<V> { d.values().to_vec() }
+
+/// Combines `a` and `b` by `op`, lining up their keys.
+fn combine<K: DictKey, V: Num>(a: DictRef<K, V>, b: DictRef<K, V>, op: fn(V, V) -> V, identity: V) -> TypedDict<K, V> {
+    let mut keys = a.keys().to_vec();
+    let mut values: Vec<V> = a
+        .values()
+        .iter()
+        .zip(a.keys())
+        .map(|(x, k)| op(*x, b.get(*k).unwrap_or(identity)))
+        .collect();
+
+    for (k, y) in b.keys().iter().zip(b.values()) {
+        if a.find(*k).is_none() {
+            keys.push(*k);
+            values.push(op(identity, *y));
+        }
+    }
+    TypedDict::new(keys, values)
+}
+
+fn add<K: DictKey, V: Num>(a: DictRef<K, V>, b: DictRef<K, V>) -> TypedDict<K, V> { combine(a, b, V::add, V::ZERO) }
+
+fn sub<K: DictKey, V: Num>(a: DictRef<K, V>, b: DictRef<K, V>) -> TypedDict<K, V> { combine(a, b, V::sub, V::ZERO) }
+
+fn mul<K: DictKey, V: Num>(a: DictRef<K, V>, b: DictRef<K, V>) -> TypedDict<K, V> { combine(a, b, V::mul, V::ONE) }
+
+fn div<K: DictKey, V: Num>(a: DictRef<K, V>, b: DictRef<K, V>) -> TypedDict<K, V> { combine(a, b, V::div, V::ONE) }
+
+fn register<K: DictKey + HostValue + 'static, V: Elem + HostRet>(runtime: &mut Runtime<'_>)
+where
+    Vec<K>: HostRet,
+    Vec<V>: HostRet,
+    TypedDict<K, V>: HostRet,
+{
+    runtime.register_fn("dict", dict::<K, V>);
+    runtime.register_fn("at", at::<K, V>);
+    runtime.register_fn("at", at_vec::<K, V>);
+    runtime.register_fn("key", key::<K, V>);
+    runtime.register_fn("value", value::<K, V>);
+}
+
+fn register_num<K: DictKey + 'static, V: Num>(runtime: &mut Runtime<'_>)
+where
+    TypedDict<K, V>: HostRet,
+{
+    runtime.register_fn("+", add::<K, V>);
+    runtime.register_fn("-", sub::<K, V>);
+    runtime.register_fn("*", mul::<K, V>);
+    runtime.register_fn("/", div::<K, V>);
+}
+
+fn register_keys<K: DictKey + HostValue + 'static>(runtime: &mut Runtime<'_>)
+where
+    Vec<K>: HostRet,
+    TypedDict<K, i64>: HostRet,
+    TypedDict<K, f64>: HostRet,
+    TypedDict<K, bool>: HostRet,
+    TypedDict<K, Sym>: HostRet,
+{
+    register::<K, i64>(runtime);
+    register::<K, f64>(runtime);
+    register::<K, bool>(runtime);
+    register::<K, Sym>(runtime);
+    register_num::<K, i64>(runtime);
+    register_num::<K, f64>(runtime);
+}
+
+pub(crate) fn init(runtime: &mut Runtime<'_>) {
+    register_keys::<i64>(runtime);
+    register_keys::<f64>(runtime);
+    register_keys::<bool>(runtime);
+    register_keys::<Sym>(runtime);
+    register_keys::<Date>(runtime);
+    register_keys::<Time>(runtime);
+    register_keys::<Timestamp>(runtime);
+    register_keys::<Timespan>(runtime);
+}
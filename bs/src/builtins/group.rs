@@ -0,0 +1,46 @@
+//! The `group` primitive, mapping every distinct element of a vector to the indices it occurs at,
+//! e.g. `` group(`a`b`a) `` is `` `a`b!([0, 2]; [1]) ``.
+
+use crate::rt::group::{hash, Grouping};
+use crate::rt::runtime::Runtime;
+use ffi::external::{HostRet, HostValue};
+use ffi::values::dict::{DictKey, GroupDict, GroupDictRef};
+use ffi::values::sym::Sym;
+use ffi::values::time::{Date, Time, Timespan, Timestamp};
+
+fn group<K: DictKey>(vec: &[K]) -> GroupDict<K> {
+    let grouping = Grouping::new(vec.len(), |i| hash(vec[i].bits()), |i, j| vec[i].bits() == vec[j].bits());
+    let (order, bounds) = grouping.order();
+    let keys = grouping.firsts.iter().map(|i| vec[*i as usize]).collect();
+    let indices = bounds
+        .windows(2)
+        .map(|b| order[b[0] as usize..b[1] as usize].to_vec())
+        .collect();
+    GroupDict::new(keys, indices)
+}
+
+/// Indices of `key`, none if it is missing.
+fn at<K: DictKey>(g: GroupDictRef<K>, key: K) -> Vec<i64> { g.get(key).map_or(vec![], |i| i.to_vec()) }
+
+fn key<K: DictKey>(g: GroupDictRef<K>) -> Vec<K> { g.keys().to_vec() }
+
+fn register<K: DictKey + HostValue + 'static>(runtime: &mut Runtime<'_>)
+where
+    Vec<K>: HostRet,
+    GroupDict<K>: HostRet,
+{
+    runtime.register_fn("group", group::<K>);
+    runtime.register_fn("at", at::<K>);
+    runtime.register_fn("key", key::<K>);
+}
+
+pub(crate) fn init(runtime: &mut Runtime<'_>) {
+    register::<i64>(runtime);
+    register::<f64>(runtime);
+    register::<bool>(runtime);
+    register::<Sym>(runtime);
+    register::<Date>(runtime);
+    register::<Time>(runtime);
+    register::<Timestamp>(runtime);
+    register::<Timespan>(runtime);
+}
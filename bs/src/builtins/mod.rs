@@ -1,22 +1,29 @@
-use crate::ffi::external::*;
-use crate::ffi::types::fn_type::FnType;
-use crate::ffi::types::Type as BSType;
-use crate::ffi::values::Value as BSValue;
-use crate::rt::runtime::get_runtime;
-use ffi::values::OpaqueValue;
+mod aggregate;
+mod dict;
+mod group;
+mod sort;
+mod time;
+mod vector;
 
-#[no_mangle]
-#[allow(improper_ctypes_definitions)]
-pub extern "C" fn test() -> OpaqueValue { BSValue::from(vec![1, 2, 3]).into() }
+pub(crate) use aggregate::REDUCTIONS;
 
-#[no_mangle]
-pub extern "C" fn dump_module() -> OpaqueValue {
-    let module = get_runtime().unwrap().get_module("repl").unwrap();
-    module.module.dump();
-    BSValue::from(()).into()
-}
+use crate::rt::error::raise;
+use crate::rt::runtime::{with_running, Runtime};
+
+pub(crate) fn init(runtime: &mut Runtime<'_>) {
+    runtime.register_fn("test", || vec![1_i64, 2, 3]);
+    runtime.register_fn("signal", |msg: &str| raise(msg));
+    runtime.register_fn("dump_module", || {
+        let dumped = with_running(|runtime| runtime.get_module("repl").map(|module| module.module.dump()));
+        if dumped.flatten().is_none() {
+            raise("'dump_module' can only be called by an evaluated expression");
+        }
+    });
 
-pub(crate) fn init() {
-    register_external("test".into(), FnType::new(vec![], BSType::VecInt64).const_value(test as _));
-    register_external("dump_module".into(), FnType::new(vec![], BSType::Null).const_value(dump_module as _));
+    aggregate::init(runtime);
+    dict::init(runtime);
+    group::init(runtime);
+    sort::init(runtime);
+    time::init(runtime);
+    vector::init(runtime);
 }
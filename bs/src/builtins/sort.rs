@@ -0,0 +1,176 @@
+//! Sorting and ranking of vectors.
+//!
+//! Every sort is stable and orders null, which includes every NaN, before any other value, so
+//! `desc` puts nulls last. Elements are graded by an unsigned key preserving their order, which a
+//! radix sort orders in linear time. Symbols are ordered by name, the null symbol first.
+
+use crate::rt::error::raise;
+use crate::rt::runtime::Runtime;
+use ffi::external::HostRet;
+use ffi::values::sym::Sym;
+use ffi::values::time::{Date, Time, Timespan, Timestamp};
+use ffi::values::vector::VecElement;
+
+/// Inputs shorter than this are graded by a comparison sort, which is faster for them.
+const RADIX_MIN_LEN: usize = 256;
+
+const RADIX_BITS: usize = 8;
+const RADIX_BUCKETS: usize = 1 << RADIX_BITS;
+
+/// Element types which can be sorted.
+trait SortKey: Copy + 'static {
+    /// Keys of the elements of `v`, ordered as the elements.
+    fn keys(v: &[Self]) -> Vec<u64>;
+}
+
+impl SortKey for i64 {
+    // null is i64::MIN, which is already the lowest key
+    fn keys(v: &[Self]) -> Vec<u64> { v.iter().map(|x| (*x as u64) ^ (1 << 63)).collect() }
+}
+
+fn float_key(x: f64) -> u64 {
+    if x.is_nan() {
+        return 0;
+    }
+
+    // -0.0 and 0.0 are equal
+    let bits = (x + 0.0).to_bits();
+    match bits >> 63 {
+        1 => !bits,
+        _ => bits | (1 << 63),
+    }
+}
+
+impl SortKey for f64 {
+    fn keys(v: &[Self]) -> Vec<u64> { v.iter().map(|x| float_key(*x)).collect() }
+}
+
+/// Dates and times, ordered as their `Int64`.
+macro_rules! impl_sort_key_temporal {
+    ($($ty:ident),*) => {
+        $(
+            impl SortKey for $ty {
+                fn keys(v: &[Self]) -> Vec<u64> { v.iter().map(|x| (x.0 as u64) ^ (1 << 63)).collect() }
+            }
+        )*
+    };
+}
+
+impl_sort_key_temporal!(Date, Time, Timestamp, Timespan);
+
+impl SortKey for Sym {
+    // the ids of symbols follow their interning, so the key is the rank of the name instead
+    fn keys(v: &[Self]) -> Vec<u64> {
+        let names: Vec<&str> = v.iter().map(|s| s.name()).collect();
+        let mut sorted = names.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        names
+            .iter()
+            .map(|name| sorted.binary_search(name).expect("name") as u64)
+            .collect()
+    }
+}
+
+/// Stable least significant digit radix sort of the indices of `keys`.
+fn radix_grade(keys: &[u64]) -> Vec<usize> {
+    let mut grade: Vec<usize> = (0..keys.len()).collect();
+    let mut buffer = vec![0; keys.len()];
+
+    for shift in (0..64).step_by(RADIX_BITS) {
+        let digit = |i: usize| (keys[i] >> shift) as usize & (RADIX_BUCKETS - 1);
+
+        let mut offsets = [0; RADIX_BUCKETS];
+        for i in 0..keys.len() {
+            offsets[digit(i)] += 1;
+        }
+
+        // every key has the same digit, so the pass would not move anything
+        if offsets.contains(&keys.len()) {
+            continue;
+        }
+
+        let mut total = 0;
+        for offset in offsets.iter_mut() {
+            let count = *offset;
+            *offset = total;
+            total += count;
+        }
+
+        for i in grade.iter() {
+            let bucket = &mut offsets[digit(*i)];
+            buffer[*bucket] = *i;
+            *bucket += 1;
+        }
+        std::mem::swap(&mut grade, &mut buffer);
+    }
+
+    grade
+}
+
+/// Indices which sort `v`, ascending or descending.
+fn grade<T: SortKey>(v: &[T], desc: bool) -> Vec<usize> {
+    let keys: Vec<u64> = T::keys(v)
+        .into_iter()
+        .map(|key| if desc { !key } else { key })
+        .collect();
+
+    match keys.len() < RADIX_MIN_LEN {
+        true => {
+            let mut grade: Vec<usize> = (0..keys.len()).collect();
+            grade.sort_by_key(|i| keys[*i]);
+            grade
+        }
+        false => radix_grade(&keys),
+    }
+}
+
+fn sort<T: SortKey>(v: &[T], desc: bool) -> Vec<T> { grade(v, desc).into_iter().map(|i| v[i]).collect() }
+
+fn igrade<T: SortKey>(v: &[T], desc: bool) -> Vec<i64> { grade(v, desc).into_iter().map(|i| i as i64).collect() }
+
+/// Position of every element in the ascending sort of `v`.
+fn rank<T: SortKey>(v: &[T]) -> Vec<i64> {
+    let mut ranks = vec![0; v.len()];
+    for (rank, i) in grade(v, false).into_iter().enumerate() {
+        ranks[i] = rank as i64;
+    }
+    ranks
+}
+
+/// Bucket of every element when `v` is split into `n` buckets of equal size by rank.
+fn xrank<T: SortKey>(n: i64, v: &[T]) -> Vec<i64> {
+    if n <= 0 {
+        raise("xrank takes a positive number of buckets");
+        return vec![];
+    }
+
+    let len = v.len() as i64;
+    // `n * rank` can overflow, the bucket can not
+    rank(v)
+        .into_iter()
+        .map(|rank| (n as i128 * rank as i128 / len as i128) as i64)
+        .collect()
+}
+
+fn register<T: SortKey + VecElement>(runtime: &mut Runtime<'_>)
+where
+    Vec<T>: HostRet,
+{
+    runtime.register_fn("asc", |v: &[T]| sort(v, false));
+    runtime.register_fn("desc", |v: &[T]| sort(v, true));
+    runtime.register_fn("iasc", |v: &[T]| igrade(v, false));
+    runtime.register_fn("idesc", |v: &[T]| igrade(v, true));
+    runtime.register_fn("rank", |v: &[T]| rank(v));
+    runtime.register_fn("xrank", |n: i64, v: &[T]| xrank(n, v));
+}
+
+pub(crate) fn init(runtime: &mut Runtime<'_>) {
+    register::<i64>(runtime);
+    register::<f64>(runtime);
+    register::<Sym>(runtime);
+    register::<Date>(runtime);
+    register::<Time>(runtime);
+    register::<Timestamp>(runtime);
+    register::<Timespan>(runtime);
+}
@@ -0,0 +1,75 @@
+//! Parts of dates and times, and `xbar`, rounding a value down to a multiple of a bucket size, e.g.
+//! `xbar(0D00:05:00, 09:32:10.000)` is `09:30:00.000`.
+//!
+//! A null date or time has null parts, and rounds down to null.
+
+use crate::rt::runtime::Runtime;
+use ffi::values::time::*;
+use ffi::values::{NULL_FLOAT64, NULL_INT64};
+
+fn civil(days: i64) -> Option<(i64, i64, i64)> { (days != NULL_INT64).then(|| civil_from_days(days)) }
+
+fn days(ts: Timestamp) -> i64 {
+    match ts.0 {
+        NULL_INT64 => NULL_INT64,
+        ns => ns.div_euclid(NS_PER_DAY),
+    }
+}
+
+/// Nanoseconds since midnight of a timestamp.
+fn time_of(ts: Timestamp) -> Time {
+    match ts.0 {
+        NULL_INT64 => Time(NULL_INT64),
+        ns => Time(ns.rem_euclid(NS_PER_DAY)),
+    }
+}
+
+/// Part of the time `t`, of `unit` nanoseconds, below `range` of them.
+fn part(t: Time, unit: i64, range: i64) -> i64 {
+    match t.0 {
+        NULL_INT64 => NULL_INT64,
+        ns => ns.div_euclid(unit).rem_euclid(range),
+    }
+}
+
+fn xbar_int(bucket: i64, x: i64) -> i64 {
+    match (bucket, x) {
+        (NULL_INT64, _) | (_, NULL_INT64) | (0, _) => NULL_INT64,
+        (bucket, x) => x.div_euclid(bucket) * bucket,
+    }
+}
+
+fn xbar_float(bucket: f64, x: f64) -> f64 {
+    match bucket == 0.0 {
+        true => NULL_FLOAT64,
+        false => (x / bucket).floor() * bucket,
+    }
+}
+
+pub(crate) fn init(runtime: &mut Runtime<'_>) {
+    runtime.register_fn("year", |d: Date| civil(d.0).map_or(NULL_INT64, |(y, _, _)| y));
+    runtime.register_fn("month", |d: Date| civil(d.0).map_or(NULL_INT64, |(_, m, _)| m));
+    runtime.register_fn("day", |d: Date| civil(d.0).map_or(NULL_INT64, |(_, _, d)| d));
+    runtime.register_fn("year", |ts: Timestamp| civil(days(ts)).map_or(NULL_INT64, |(y, _, _)| y));
+    runtime.register_fn("month", |ts: Timestamp| civil(days(ts)).map_or(NULL_INT64, |(_, m, _)| m));
+    runtime.register_fn("day", |ts: Timestamp| civil(days(ts)).map_or(NULL_INT64, |(_, _, d)| d));
+
+    runtime.register_fn("hour", |t: Time| part(t, NS_PER_HOUR, 24));
+    runtime.register_fn("minute", |t: Time| part(t, NS_PER_MIN, 60));
+    runtime.register_fn("second", |t: Time| part(t, NS_PER_SEC, 60));
+    runtime.register_fn("hour", |ts: Timestamp| part(time_of(ts), NS_PER_HOUR, 24));
+    runtime.register_fn("minute", |ts: Timestamp| part(time_of(ts), NS_PER_MIN, 60));
+    runtime.register_fn("second", |ts: Timestamp| part(time_of(ts), NS_PER_SEC, 60));
+
+    runtime.register_fn("date", |ts: Timestamp| Date(days(ts)));
+    runtime.register_fn("time", time_of);
+
+    runtime.register_fn("xbar", xbar_int);
+    runtime.register_fn("xbar", xbar_float);
+    runtime.register_fn("xbar", |bucket: i64, x: &[i64]| x.iter().map(|x| xbar_int(bucket, *x)).collect::<Vec<_>>());
+    runtime.register_fn("xbar", |bucket: f64, x: &[f64]| x.iter().map(|x| xbar_float(bucket, *x)).collect::<Vec<_>>());
+    runtime.register_fn("xbar", |days: i64, d: Date| Date(xbar_int(days, d.0)));
+    runtime.register_fn("xbar", |span: Timespan, t: Time| Time(xbar_int(span.0, t.0)));
+    runtime.register_fn("xbar", |span: Timespan, ts: Timestamp| Timestamp(xbar_int(span.0, ts.0)));
+    runtime.register_fn("xbar", |span: Timespan, x: Timespan| Timespan(xbar_int(span.0, x.0)));
+}
@@ -0,0 +1,176 @@
+//! Construction and manipulation of vectors.
+//!
+//! Counts taken by `take`, `drop` and `rotate` come first and count from the end when negative.
+
+use crate::rt::runtime::Runtime;
+use ffi::external::{HostRet, HostValue};
+use ffi::values::sym::Sym;
+use ffi::values::time::{Date, Time, Timespan, Timestamp};
+use ffi::values::vector::VecElement;
+use ffi::values::{NULL_FLOAT64, NULL_INT64};
+use std::collections::HashSet;
+
+/// Element types the builtins are registered for.
+trait Elem: Copy + 'static {
+    /// Value `take` pads an empty vector with, and `fill` replaces.
+    const NULL: Self;
+
+    fn is_null(self) -> bool;
+
+    /// Identity of the value for `distinct`.
+    fn key(self) -> u64;
+}
+
+impl Elem for i64 {
+    const NULL: Self = NULL_INT64;
+
+    fn is_null(self) -> bool { self == NULL_INT64 }
+
+    fn key(self) -> u64 { self as u64 }
+}
+
+impl Elem for f64 {
+    const NULL: Self = NULL_FLOAT64;
+
+    fn is_null(self) -> bool { self.is_nan() }
+
+    // every NaN is the same null
+    fn key(self) -> u64 {
+        match self.is_nan() {
+            true => NULL_FLOAT64.to_bits(),
+            false => self.to_bits(),
+        }
+    }
+}
+
+impl Elem for bool {
+    const NULL: Self = false;
+
+    fn is_null(self) -> bool { false }
+
+    fn key(self) -> u64 { self as u64 }
+}
+
+impl Elem for Sym {
+    const NULL: Self = Sym::NULL;
+
+    fn is_null(self) -> bool { self.is_null() }
+
+    fn key(self) -> u64 { self.0 as u64 }
+}
+
+/// Dates and times, null as an `Int64`.
+macro_rules! impl_elem_temporal {
+    ($($ty:ident),*) => {
+        $(
+            impl Elem for $ty {
+                const NULL: Self = $ty(NULL_INT64);
+
+                fn is_null(self) -> bool { self.0 == NULL_INT64 }
+
+                fn key(self) -> u64 { self.0 as u64 }
+            }
+        )*
+    };
+}
+
+impl_elem_temporal!(Date, Time, Timestamp, Timespan);
+
+fn til(n: i64) -> Vec<i64> { (0..n.max(0)).collect() }
+
+fn enlist<T: Elem>(x: T) -> Vec<T> { vec![x] }
+
+fn join<T: Elem>(a: &[T], b: &[T]) -> Vec<T> { [a, b].concat() }
+
+/// Takes `n` elements, repeating the vector if it is shorter than `n`.
+fn take_n<T: Elem>(n: i64, v: &[T]) -> Vec<T> {
+    let len = n.unsigned_abs() as usize;
+    if v.is_empty() {
+        return vec![T::NULL; len];
+    }
+
+    let start = match n < 0 {
+        true => (v.len() - len % v.len()) % v.len(),
+        false => 0,
+    };
+    v.iter().cycle().skip(start).take(len).copied().collect()
+}
+
+fn drop_n<T: Elem>(n: i64, v: &[T]) -> Vec<T> {
+    let len = (n.unsigned_abs() as usize).min(v.len());
+    match n < 0 {
+        true => v[..v.len() - len].to_vec(),
+        false => v[len..].to_vec(),
+    }
+}
+
+fn reverse<T: Elem>(v: &[T]) -> Vec<T> { v.iter().rev().copied().collect() }
+
+fn distinct<T: Elem>(v: &[T]) -> Vec<T> {
+    let mut seen = HashSet::new();
+    v.iter().filter(|x| seen.insert(x.key())).copied().collect()
+}
+
+/// Indices of the true elements.
+fn where_bool(v: &[bool]) -> Vec<i64> {
+    v.iter()
+        .enumerate()
+        .filter(|(_, x)| **x)
+        .map(|(i, _)| i as i64)
+        .collect()
+}
+
+/// Every index repeated as many times as its element says.
+fn where_i64(v: &[i64]) -> Vec<i64> {
+    v.iter()
+        .enumerate()
+        .flat_map(|(i, n)| std::iter::repeat_n(i as i64, (*n).max(0) as usize))
+        .collect()
+}
+
+fn null_vec<T: Elem>(v: &[T]) -> Vec<bool> { v.iter().map(|x| x.is_null()).collect() }
+
+fn fill<T: Elem>(v: &[T], x: T) -> Vec<T> { v.iter().map(|e| if e.is_null() { x } else { *e }).collect() }
+
+/// Rotates the vector left by `n` elements.
+fn rotate<T: Elem>(n: i64, v: &[T]) -> Vec<T> {
+    if v.is_empty() {
+        return vec![];
+    }
+
+    let mut res = v.to_vec();
+    res.rotate_left(n.rem_euclid(v.len() as i64) as usize);
+    res
+}
+
+fn register<T: Elem + VecElement + HostValue>(runtime: &mut Runtime<'_>)
+where
+    Vec<T>: HostRet,
+{
+    runtime.register_fn("enlist", enlist::<T>);
+    runtime.register_fn("join", join::<T>);
+    runtime.register_fn("take", take_n::<T>);
+    runtime.register_fn("drop", drop_n::<T>);
+    runtime.register_fn("reverse", reverse::<T>);
+    runtime.register_fn("distinct", distinct::<T>);
+    runtime.register_fn("rotate", rotate::<T>);
+    runtime.register_fn("null", |x: T| x.is_null());
+    runtime.register_fn("null", null_vec::<T>);
+}
+
+pub(crate) fn init(runtime: &mut Runtime<'_>) {
+    register::<i64>(runtime);
+    register::<f64>(runtime);
+    register::<bool>(runtime);
+    register::<Sym>(runtime);
+    register::<Date>(runtime);
+    register::<Time>(runtime);
+    register::<Timestamp>(runtime);
+    register::<Timespan>(runtime);
+
+    runtime.register_fn("til", til);
+    runtime.register_fn("where", where_bool);
+    runtime.register_fn("where", where_i64);
+    runtime.register_fn("fill", fill::<i64>);
+    runtime.register_fn("fill", fill::<f64>);
+}
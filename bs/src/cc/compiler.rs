@@ -1,21 +1,33 @@
+mod query;
+
 use super::transform::*;
 
+use crate::llvm::enums::IntPredicate;
 use crate::llvm::values::ValueIntrinsics;
-use crate::ops::binary;
+use crate::ops::{binary, json, serial, table};
 use crate::parse::ast::ExprBody;
-use crate::parse::ast::{infer_types, Expr, Function};
+use crate::parse::ast::{infer_types, join_keys, Expr, Function};
+use crate::parse::span::Span;
 use crate::result::*;
-use crate::rt::runtime::RuntimeModule;
+use crate::rt::error::{catch, raise_at, raised_at, ErrorSite};
+use crate::rt::runtime::{converts_to, Globals, RuntimeModule};
+use crate::rt::table::{table_add, table_column, table_new};
 use ffi::types::fn_type::FnType as BsFnType;
 use ffi::types::Type as BSType;
 use ffi::values::fn_value::FnValue as BsFnValue;
+use ffi::values::rc::rc_fns;
+use ffi::values::sym::Sym;
+use ffi::values::time;
+use ffi::values::vector::{VEC_DATA, VEC_LEN};
 use ffi::values::Value as BSValue;
 use ffi::values::NULL_VALUE;
+use llvm::basic_block::BasicBlock;
 use llvm::builder::Builder;
 use llvm::context::Context;
+use llvm::types::fn_type::FnType;
 use llvm::types::Type;
 use llvm::values::fn_value::FnValue;
-use llvm::values::prelude::PhiValue;
+use llvm::values::prelude::{PhiValue, PtrValue};
 use llvm::values::Value;
 use std::collections::HashMap;
 use std::mem::transmute;
@@ -25,9 +37,19 @@ pub struct Compiler<'a, 'b> {
     context: &'a mut Context,
     builder: &'a mut Builder<'b>,
     modules: &'a mut HashMap<String, RuntimeModule<'b>>,
+    globals: &'a mut Globals,
     function: Function,
     variables: HashMap<String, Value<'b>>,
+    owned: Vec<(Value<'b>, BSType)>,
     fn_value_opt: Option<FnValue<'b>>,
+    unwind_bb: Option<BasicBlock<'b>>,
+    /// Handlers of the enclosing `try` expressions, innermost last, with the number of `temps`
+    /// outside of each.
+    handlers: Vec<(BasicBlock<'b>, usize)>,
+    /// Heap values being evaluated, such as the arguments of a call before the next one, which
+    /// are released if an error is raised meanwhile.
+    temps: Vec<(Value<'a>, BSType)>,
+    checked: bool,
 }
 
 impl<'a, 'b> Compiler<'a, 'b> {
@@ -36,9 +58,30 @@ impl<'a, 'b> Compiler<'a, 'b> {
         context: &'a mut Context,
         builder: &'a mut Builder<'b>,
         modules: &'a mut HashMap<String, RuntimeModule<'b>>,
+        globals: &'a mut Globals,
         function: Function,
     ) -> Self {
-        Compiler { module, context, builder, modules, function, variables: HashMap::new(), fn_value_opt: None }
+        Compiler {
+            module,
+            context,
+            builder,
+            modules,
+            globals,
+            function,
+            variables: HashMap::new(),
+            owned: vec![],
+            fn_value_opt: None,
+            unwind_bb: None,
+            handlers: vec![],
+            temps: vec![],
+            checked: false,
+        }
+    }
+
+    /// Makes `Int64` arithmetic raise an error when it overflows.
+    pub fn checked(mut self, checked: bool) -> Self {
+        self.checked = checked;
+        self
     }
 
     fn module(&mut self) -> &mut RuntimeModule<'b> { self.modules.get_mut(self.module).unwrap() }
@@ -46,45 +89,361 @@ impl<'a, 'b> Compiler<'a, 'b> {
     fn compile_load_local(&mut self, ty: BSType, name: &str) -> Option<Value<'a>> {
         unsafe {
             self.variables.get(name).map(|ptr| {
-                self.builder
-                    .build_load(transmute(llvm_type_from_bs_type(ty, self.context)), (*ptr).into(), name)
+                self.builder.build_load(
+                    transmute::<Type<'_>, Type<'_>>(llvm_type_from_bs_type(ty, self.context)),
+                    (*ptr).into(),
+                    name,
+                )
             })
         }
     }
 
     fn compile_load_global(&mut self, name: &str) -> Option<Value<'a>> {
-        match self.module().get_global(name) {
+        match self.globals.get(name) {
             Some((ty, ptr)) => unsafe {
-                let ptr_ty = self
-                    .context
-                    .ptr_type(llvm_type_from_bs_type(ty.clone(), self.context).into());
+                let ptr_ty = self.context.ptr_type(llvm_type_from_bs_type(ty.clone(), self.context));
                 let val_ptr = self.context.i64_type().const_value(ptr as _).to_ptr(ptr_ty);
-                Some(transmute(
-                    self.builder
-                        .build_load(llvm_type_from_bs_type(ty, self.context), val_ptr.into(), name),
-                ))
+                Some(transmute::<Value<'_>, Value<'_>>(self.builder.build_load(
+                    llvm_type_from_bs_type(ty, self.context),
+                    val_ptr,
+                    name,
+                )))
             },
             None => None,
         }
     }
 
+    /// Stores `val` into the global `name`, releasing its previous value. The global keeps the
+    /// evaluated reference and the assignment yields another one.
+    fn compile_store_global(&mut self, name: &str, val: Value<'a>, ty: &BSType, span: Option<Span>) -> BSResult<()> {
+        self.globals.declare(name, ty, span)?;
+        let (_, ptr) = self.globals.get(name).unwrap();
+
+        let ptr_ty = self.context.ptr_type(llvm_type_from_bs_type(ty.clone(), self.context));
+        let val_ptr = self.context.i64_type().const_value(ptr as _).to_ptr(ptr_ty);
+
+        unsafe {
+            if rc_fns(ty).is_some() {
+                let old = self.builder.build_load(
+                    transmute::<Type<'_>, Type<'_>>(llvm_type_from_bs_type(ty.clone(), self.context)),
+                    transmute::<PtrValue<'_>, PtrValue<'_>>(val_ptr),
+                    name,
+                );
+                self.compile_rc(false, transmute::<Value<'_>, Value<'_>>(old), ty);
+            }
+
+            self.builder
+                .build_store(transmute::<Value<'_>, Value<'_>>(val_ptr.into()), transmute::<Value<'_>, Value<'_>>(val));
+        }
+        self.compile_rc(true, val, ty);
+        ok(())
+    }
+
+    /// Loads the field `index` of the header of the vector `vec`, see `ffi::values::vector`.
+    fn compile_vec_field(&mut self, vec: Value<'a>, ty: &BSType, index: u32, name: &str) -> Value<'a> {
+        let header_ty: Type<'_> = llvm_vec_type(ty.clone(), self.context).into();
+        let field_ty: Type<'_> = match index {
+            VEC_DATA => self
+                .context
+                .ptr_type(llvm_elem_type_from_bs_type(ty.clone(), self.context))
+                .into(),
+            _ => self.context.i64_type().into(),
+        };
+
+        unsafe {
+            let ptr = self.builder.build_struct_gep(
+                transmute::<Type<'_>, Type<'_>>(header_ty),
+                transmute::<Value<'_>, Value<'_>>(vec),
+                index,
+                name,
+            );
+            transmute(
+                self.builder
+                    .build_load(transmute::<Type<'_>, Type<'_>>(field_ty), ptr.into(), name),
+            )
+        }
+    }
+
+    /// Stores `len` as the length of the vector `vec`, whose elements up to it must be stored.
+    fn compile_vec_set_len(&mut self, vec: Value<'a>, ty: &BSType, len: Value<'a>) {
+        let header_ty: Type<'_> = llvm_vec_type(ty.clone(), self.context).into();
+        unsafe {
+            let ptr = self.builder.build_struct_gep(
+                transmute::<Type<'_>, Type<'_>>(header_ty),
+                transmute::<Value<'_>, Value<'_>>(vec),
+                VEC_LEN,
+                "lenptr",
+            );
+            self.builder.build_store(ptr, transmute::<Value<'_>, Value<'_>>(len));
+        }
+    }
+
+    /// Emits a call adding (`retain`) or dropping a reference to `val` if `ty` is a heap type.
+    fn compile_rc(&mut self, retain: bool, val: Value<'a>, ty: &BSType) {
+        let (retain_fn, release_fn) = match rc_fns(ty) {
+            Some(fns) => fns,
+            None => return,
+        };
+
+        let name = format!("{}.{}", if retain { "retain" } else { "release" }, ty);
+        let arg_ty = llvm_type_from_bs_type(ty.clone(), self.context);
+        let fn_ty = self.context.fn_type(self.context.void_type().into(), &[arg_ty], false);
+
+        let rt_module = self.modules.get_mut(self.module).unwrap();
+        let fn_val = match rt_module.module.get_function(&name) {
+            Some(fn_val) => fn_val,
+            None => {
+                let fn_val = rt_module.module.add_function(&name, fn_ty);
+                let addr = if retain { retain_fn } else { release_fn };
+                rt_module.engine.add_global_mapping(fn_val, addr as usize);
+                fn_val
+            }
+        };
+
+        unsafe {
+            self.builder.build_call(
+                transmute::<FnType<'_>, FnType<'_>>(fn_ty),
+                fn_val,
+                &[transmute::<Value<'_>, Value<'_>>(val)],
+                "",
+            );
+        }
+    }
+
+    /// Emits a call of the function `name` of `rt::error`, bound to `addr`, passing it a new
+    /// `ErrorSite`. The function returns a bool if `test` is set.
+    fn compile_error_call(&mut self, name: &str, addr: usize, test: bool, msg: &str, span: Option<Span>) -> Value<'a> {
+        let site = Box::new(ErrorSite { msg: msg.to_string(), span });
+        let site_ptr = self
+            .context
+            .i64_type()
+            .const_value(site.as_ref() as *const ErrorSite as i64);
+
+        let ptr_ty = self.context.ptr_type(self.context.i64_type().into());
+        let ret_ty = match test {
+            true => self.context.i1_type().into(),
+            false => self.context.void_type().into(),
+        };
+        let fn_ty = self.context.fn_type(ret_ty, &[ptr_ty.into()], false);
+
+        let rt_module = self.modules.get_mut(self.module).unwrap();
+        rt_module.error_sites.push(site);
+        let fn_val = match rt_module.module.get_function(name) {
+            Some(fn_val) => fn_val,
+            None => {
+                let fn_val = rt_module.module.add_function(name, fn_ty);
+                rt_module.engine.add_global_mapping(fn_val, addr);
+                fn_val
+            }
+        };
+
+        unsafe {
+            let site_ptr = transmute::<Value<'_>, Value<'_>>(site_ptr.to_ptr(ptr_ty).into());
+            transmute(
+                self.builder
+                    .build_call(transmute::<FnType<'_>, FnType<'_>>(fn_ty), fn_val, &[site_ptr], ""),
+            )
+        }
+    }
+
+    /// Block returning null from the function once an error is raised, releasing its local
+    /// variables, see `rt::error`. It is completed by `compile_fn` once every variable is known.
+    fn unwind_block(&mut self) -> BasicBlock<'b> {
+        match self.unwind_bb {
+            Some(unwind_bb) => unwind_bb,
+            None => {
+                let unwind_bb = self.context.append_basic_block(self.fn_value(), "unwind");
+                self.unwind_bb = Some(unwind_bb);
+                unwind_bb
+            }
+        }
+    }
+
+    /// Keeps `val` of type `ty` in `temps` if it is a heap value, until the temporaries are dropped
+    /// by `pop_temps`.
+    fn push_temp(&mut self, val: Value<'a>, ty: &BSType) {
+        if rc_fns(ty).is_some() {
+            self.temps.push((val, ty.clone()));
+        }
+    }
+
+    /// Drops the temporaries pushed since there were `len` of them, which are now owned elsewhere.
+    fn pop_temps(&mut self, len: usize) { self.temps.truncate(len); }
+
+    /// Emits a branch to the handler of the enclosing `try`, or else to the unwind block, taken if
+    /// `cond` is true, which first releases the temporary `temp` and the `temps` the handler does
+    /// not see, and raises the error `raise` at `span`, if given.
+    fn compile_unwind_if(
+        &mut self,
+        cond: Value<'a>,
+        temp: Option<(Value<'a>, &BSType)>,
+        raise: Option<&str>,
+        span: Option<Span>,
+    ) {
+        let parent = self.fn_value();
+        let fail_bb = self.context.append_basic_block(parent, "fail");
+        let cont_bb = self.context.append_basic_block(parent, "cont");
+        self.builder.build_conditional_branch(cond, fail_bb, cont_bb);
+
+        self.builder.position_at_end(fail_bb);
+        if let Some((val, ty)) = temp {
+            self.compile_rc(false, val, ty);
+        }
+        let (unwind_bb, outside) = match self.handlers.last() {
+            Some(handler) => *handler,
+            None => (self.unwind_block(), 0),
+        };
+        let temps = std::mem::take(&mut self.temps);
+        for (val, ty) in &temps[outside..] {
+            self.compile_rc(false, *val, ty);
+        }
+        self.temps = temps;
+        if let Some(msg) = raise {
+            self.compile_error_call("bs.raise", raise_at as *const () as usize, false, msg, span);
+        }
+        self.builder.build_unconditional_branch(unwind_bb);
+
+        self.builder.position_at_end(cont_bb);
+    }
+
+    /// Emits a branch raising the error `msg` at `span` if `failed` is true.
+    fn compile_raise_if(&mut self, failed: Value<'a>, msg: &str, span: Option<Span>) {
+        self.compile_unwind_if(failed, None, Some(msg), span);
+    }
+
+    /// Emits a test for an error raised by the call at `span`, returning from the function if there
+    /// is one, after releasing the result of the call `res`.
+    fn compile_check_raised(&mut self, res: Value<'a>, ty: &BSType, span: Option<Span>) {
+        let raised = self.compile_error_call("bs.raised_at", raised_at as *const () as usize, true, "", span);
+        self.compile_unwind_if(raised, Some((res, ty)), None, span);
+    }
+
+    /// Emits a call of the runtime function `name`, bound to `addr`, passing it `args` and
+    /// returning a value of type `ret`.
+    ///
+    /// A function taking or returning vectors is declared once for every vector type, as the types
+    /// of its arguments differ, and so does its address if it is generic over the element type.
+    fn compile_rt_call(&mut self, name: &str, addr: usize, args: &[(Value<'a>, BSType)], ret: &BSType) -> Value<'a> {
+        let mut symbol = name.to_string();
+        for ty in args.iter().map(|(_, ty)| ty).chain([ret]).filter(|ty| ty.is_vec()) {
+            symbol = format!("{}.{}", symbol, ty);
+        }
+        let name = symbol.as_str();
+
+        let arg_tys: Vec<Type> = args
+            .iter()
+            .map(|(_, ty)| llvm_type_from_bs_type(ty.clone(), self.context))
+            .collect();
+        let ret_ty = llvm_type_from_bs_type(ret.clone(), self.context);
+        let fn_ty = self.context.fn_type(ret_ty, &arg_tys, false);
+
+        let rt_module = self.modules.get_mut(self.module).unwrap();
+        let fn_val = match rt_module.module.get_function(name) {
+            Some(fn_val) => fn_val,
+            None => {
+                let fn_val = rt_module.module.add_function(name, fn_ty);
+                rt_module.engine.add_global_mapping(fn_val, addr);
+                fn_val
+            }
+        };
+
+        let args: Vec<Value<'a>> = args.iter().map(|(val, _)| *val).collect();
+        unsafe {
+            transmute(self.builder.build_call(
+                transmute::<FnType<'_>, FnType<'_>>(fn_ty),
+                fn_val,
+                transmute::<&[Value<'_>], &[Value<'_>]>(args.as_slice()),
+                "",
+            ))
+        }
+    }
+
+    /// Emits a call taking the raised error, which yields its message as a string.
+    fn compile_catch(&mut self) -> Value<'a> {
+        let fn_ty = self.context.fn_type(self.context.i64_type().into(), &[], false);
+        let rt_module = self.modules.get_mut(self.module).unwrap();
+        let fn_val = match rt_module.module.get_function("bs.catch") {
+            Some(fn_val) => fn_val,
+            None => {
+                let fn_val = rt_module.module.add_function("bs.catch", fn_ty);
+                rt_module.engine.add_global_mapping(fn_val, catch as *const () as usize);
+                fn_val
+            }
+        };
+
+        unsafe {
+            transmute(
+                self.builder
+                    .build_call(transmute::<FnType<'_>, FnType<'_>>(fn_ty), fn_val, &[], "msg"),
+            )
+        }
+    }
+
+    /// Emits the release of the local variables `owned`, arguments are borrowed from the caller.
+    fn compile_release_locals(&mut self, owned: &[(Value<'b>, BSType)]) {
+        for (ptr, ty) in owned {
+            let val = unsafe {
+                self.builder.build_load(
+                    transmute::<Type<'_>, Type<'_>>(llvm_type_from_bs_type(ty.clone(), self.context)),
+                    (*ptr).into(),
+                    "",
+                )
+            };
+            self.compile_rc(false, unsafe { transmute::<Value<'_>, Value<'_>>(val) }, ty);
+        }
+    }
+
+    /// Compiles a sequence of expressions, releasing every result but the last one.
+    fn compile_block(&mut self, exprs: &[Expr]) -> BSResult<Option<Value<'a>>> {
+        let mut last = None;
+        for (i, e) in exprs.iter().enumerate() {
+            let val = self.compile_expr(e)?;
+            if i + 1 < exprs.len() {
+                self.compile_rc(false, val, &e.get_type()?);
+            }
+            last = Some(val);
+        }
+        ok(last)
+    }
+
     fn compile_expr(&mut self, expr: &Expr) -> BSResult<Value<'a>> {
         match &expr.body {
             ExprBody::Null => ok(self.context.i64_type().const_value(NULL_VALUE).into()),
             ExprBody::Bool(b) => ok(self.context.i1_type().const_value(*b).into()),
             ExprBody::Int64(v) => ok(self.context.i64_type().const_value(*v).into()),
             ExprBody::Float64(v) => ok(self.context.f64_type().const_value(*v).into()),
-            ExprBody::VecInt64(v) => unsafe {
-                ok(transmute(llvm_value_from_bs_value(BSValue::from(v.clone()), self.context)))
-            },
-            ExprBody::VecFloat64(v) => unsafe {
-                ok(transmute(llvm_value_from_bs_value(BSValue::from(v.clone()), self.context)))
-            },
+            ExprBody::Temporal(_, v) => ok(self.context.i64_type().const_value(*v).into()),
+            ExprBody::Sym(name) => ok(self.context.i64_type().const_value(Sym::new(name).0).into()),
+            ExprBody::VecInt64(_)
+            | ExprBody::VecFloat64(_)
+            | ExprBody::VecBool(_)
+            | ExprBody::VecSym(_)
+            | ExprBody::VecTemporal(..)
+            | ExprBody::Str(_) => {
+                let value = match &expr.body {
+                    ExprBody::VecInt64(v) => BSValue::from(v.clone()),
+                    ExprBody::VecFloat64(v) => BSValue::from(v.clone()),
+                    ExprBody::VecBool(v) => BSValue::from(v.clone()),
+                    ExprBody::VecSym(v) => BSValue::from(v.iter().map(|s| Sym::new(s)).collect::<Vec<_>>()),
+                    ExprBody::VecTemporal(ty, v) => time::vec(ty, v.clone()),
+                    ExprBody::Str(s) => BSValue::from(s.as_str()),
+                    _ => unreachable!(),
+                };
+
+                // literals are owned by the module, evaluating one yields a new reference
+                let val = unsafe { transmute::<Value<'_>, Value<'_>>(llvm_value_from_bs_value(&value, self.context)) };
+                self.module().constants.push(value);
+                self.compile_rc(true, val, &expr.get_type()?);
+                ok(val)
+            }
             ExprBody::Variable(ref name) => match self
                 .compile_load_local(expr.get_type()?, name.as_str())
                 .or_else(|| self.compile_load_global(name.as_str()))
             {
-                Some(v) => ok(v),
+                Some(v) => {
+                    self.compile_rc(true, v, &expr.get_type()?);
+                    ok(v)
+                }
                 None => compile_error(
                     format!("Undefined variable: '{}'", name),
                     "Define the variable before using it".into(),
@@ -93,30 +452,185 @@ impl<'a, 'b> Compiler<'a, 'b> {
             },
 
             ExprBody::Binary { op, lhs, rhs } => {
-                let lhs_e = self.compile_expr(&lhs)?;
-                let rhs_e = self.compile_expr(&rhs)?;
-                binary::compile(self.builder, *op, (lhs_e, lhs.get_type()?), (rhs_e, rhs.get_type()?), expr.span)
+                let lhs_e = self.compile_expr(lhs)?;
+                let rhs_e = self.compile_expr(rhs)?;
+                if self.checked {
+                    let module = &self.modules[self.module].module;
+                    let lhs = (lhs_e, lhs.get_type()?);
+                    let rhs = (rhs_e, rhs.get_type()?);
+                    if let Some((res, overflow)) =
+                        binary::compile_checked(self.builder, self.context, module, *op, lhs, rhs)
+                    {
+                        self.compile_raise_if(overflow, "Integer overflow", expr.span);
+                        return ok(res);
+                    }
+                }
+                binary::compile(
+                    self.builder,
+                    self.context,
+                    *op,
+                    (lhs_e, lhs.get_type()?),
+                    (rhs_e, rhs.get_type()?),
+                    expr.span,
+                )
             }
 
             ExprBody::Assign { name, body, global } => {
                 let ty = body.get_type()?;
-                let body = self.compile_expr(&body)?;
+                let body = self.compile_expr(body)?;
 
                 if *global {
-                    self.modules
-                        .get_mut(self.module)
-                        .unwrap()
-                        .add_global(name, bs_value_from_llvm_value(body.clone(), ty.clone()));
+                    self.compile_store_global(name, body, &ty, expr.span)?;
                 } else {
-                    let ptr = self.create_entry_block_alloca(name, llvm_type_from_bs_type(ty, &self.context));
+                    let ptr = match rc_fns(&ty) {
+                        Some(_) => {
+                            let ptr = self.create_owned_alloca(name, &ty);
+                            self.owned.push((ptr, ty.clone()));
+                            ptr
+                        }
+                        None => self.create_entry_block_alloca(name, llvm_type_from_bs_type(ty.clone(), self.context)),
+                    };
                     self.builder.build_store(ptr, body);
-                    self.variables.insert(name.clone(), ptr.clone());
+                    self.variables.insert(name.clone(), ptr);
+
+                    // the variable keeps the evaluated reference, the assignment yields another one
+                    self.compile_rc(true, body, &ty);
                 }
                 ok(body)
             }
 
+            ExprBody::Table { columns } => {
+                let ty = expr.get_type()?;
+                let mut table = self.compile_rt_call("bs.table_new", table_new as *const () as usize, &[], &ty);
+                for (name, column) in columns {
+                    let column_ty = column.get_type()?;
+                    let temps = self.temps.len();
+                    self.push_temp(table, &ty);
+                    let column = self.compile_expr(column)?;
+                    self.pop_temps(temps);
+                    let name = self.context.i64_type().const_value(Sym::new(name).0).into();
+                    let args = [(table, ty.clone()), (name, BSType::Sym), (column, column_ty)];
+                    table = self.compile_rt_call("bs.table_add", table_add as *const () as usize, &args, &ty);
+                }
+                self.compile_check_raised(table, &ty, expr.span);
+                ok(table)
+            }
+
+            ExprBody::Column { table, name } => {
+                let table_ty = table.get_type()?;
+                let index = match &table_ty {
+                    BSType::Table(columns) => columns.iter().position(|(n, _)| n == name).expect("column"),
+                    _ => unreachable!(),
+                };
+                let table = self.compile_expr(table)?;
+                let index = self.context.i64_type().const_value(index as i64).into();
+                let args = [(table, table_ty.clone()), (index, BSType::Int64)];
+                let ty = expr.get_type()?;
+                let column = self.compile_rt_call("bs.table_column", table_column as *const () as usize, &args, &ty);
+                self.compile_rc(false, table, &table_ty);
+                ok(column)
+            }
+
+            ExprBody::Query(query) => self.compile_query(query, &expr.get_type()?),
+
+            ExprBody::Call { name, args }
+                if table_call(name, args).is_some()
+                    || matches!(name.as_str(), "read_csv" | "from_json" | "deserialize" | "read_splayed") =>
+            {
+                let call = match name.as_str() {
+                    "read_csv" => table::read_csv(expr.get_type()?),
+                    "from_json" => json::from_json(expr.get_type()?),
+                    "deserialize" => serial::deserialize(&args[0].get_type()?, expr.get_type()?),
+                    "read_splayed" => table::read_splayed(expr.get_type()?),
+                    _ => table_call(name, args).expect("table call"),
+                };
+
+                let temps = self.temps.len();
+                let mut vals = vec![];
+                for arg in args {
+                    let (val, ty) = (self.compile_expr(arg)?, arg.get_type()?);
+                    self.push_temp(val, &ty);
+                    vals.push((val, ty));
+                }
+                self.pop_temps(temps);
+                let res = self.compile_rt_call(call.symbol, call.addr, &vals, &call.ty);
+                for (val, ty) in &vals {
+                    self.compile_rc(false, *val, ty);
+                }
+                if call.raises {
+                    self.compile_check_raised(res, &call.ty, expr.span);
+                }
+                ok(res)
+            }
+
+            ExprBody::Call { name, args } if name == "len" && args.len() == 1 && args[0].get_type()?.is_vec() => {
+                let ty = args[0].get_type()?;
+                let vec = self.compile_expr(&args[0])?;
+                let len = self.compile_vec_field(vec, &ty, VEC_LEN, "len");
+                self.compile_rc(false, vec, &ty);
+                ok(len)
+            }
+
+            ExprBody::Call { name, args } if name == "at" && args.len() == 2 && args[0].get_type()?.is_vec() => {
+                let ty = args[0].get_type()?;
+                let vec = self.compile_expr(&args[0])?;
+                let temps = self.temps.len();
+                self.push_temp(vec, &ty);
+                let index = self.compile_expr(&args[1])?;
+
+                // compared unsigned, a negative index is out of bounds as well
+                let len = self.compile_vec_field(vec, &ty, VEC_LEN, "len");
+                let out_of_bounds = self
+                    .builder
+                    .build_int_compare(IntPredicate::UGE, index, len, "outofbounds");
+                self.compile_raise_if(out_of_bounds, "Index out of bounds", expr.span);
+                self.pop_temps(temps);
+
+                let data = self.compile_vec_field(vec, &ty, VEC_DATA, "data");
+                let elem_ty = || llvm_elem_type_from_bs_type(ty.clone(), self.context);
+                let elem = unsafe {
+                    let ptr = self.builder.build_in_bounds_gep(
+                        transmute::<Type<'_>, Type<'_>>(elem_ty()),
+                        transmute::<Value<'_>, Value<'_>>(data),
+                        transmute::<Value<'_>, Value<'_>>(index),
+                        "elemptr",
+                    );
+                    transmute::<Value<'_>, Value<'_>>(self.builder.build_load(
+                        transmute::<Type<'_>, Type<'_>>(elem_ty()),
+                        ptr.into(),
+                        "elem",
+                    ))
+                };
+                self.compile_rc(false, vec, &ty);
+                ok(elem)
+            }
+
             ExprBody::Call { name, args } => {
-                let ret_ty = match self.module().get_global(name) {
+                let mut arg_types = vec![];
+                for arg in args {
+                    arg_types.push(arg.get_type()?);
+                }
+                let specialization = match self.globals.generic(name, &arg_types) {
+                    Some(generic) => Some(generic.specialize(&arg_types, expr.span)?),
+                    None => None,
+                };
+                let symbol = match &specialization {
+                    Some(f) => f.symbol(),
+                    None => self.globals.resolve(name, &arg_types, expr.span)?,
+                };
+
+                let (fn_arg_types, ret_ty) = match self.globals.get(&symbol) {
+                    // a specialization is compiled into the module once, after the function calling it
+                    None if specialization.is_some() => {
+                        let f = specialization.unwrap();
+                        let ret_ty = expr.get_type()?;
+                        let module = self.module();
+                        if !module.specializations.contains_key(&symbol) {
+                            module.specializations.insert(symbol.clone(), ret_ty.clone());
+                            module.pending.push(f.clone());
+                        }
+                        (f.arg_types(), Box::new(ret_ty))
+                    }
                     Some((ty, _)) => match ty {
                         BSType::Fn(fn_ty) => {
                             // check if args of call match the args of the function
@@ -139,7 +653,7 @@ impl<'a, 'b> Compiler<'a, 'b> {
                             }
 
                             for (i, (arg, ty)) in args.iter().zip(fn_ty.args.iter()).enumerate() {
-                                if arg.get_type()? != *ty {
+                                if !converts_to(&arg.get_type()?, ty) {
                                     return compile_error(
                                         "Invalid arguments".into(),
                                         format!(
@@ -154,7 +668,7 @@ impl<'a, 'b> Compiler<'a, 'b> {
                                 }
                             }
 
-                            fn_ty.ret.clone()
+                            (fn_ty.args.clone(), fn_ty.ret.clone())
                         }
                         _ => {
                             return compile_error(
@@ -173,42 +687,76 @@ impl<'a, 'b> Compiler<'a, 'b> {
                     }
                 };
 
-                let fn_val = self
-                    .module()
-                    .module
-                    .get_function(name.as_str())
-                    .ok_or_else(|| BSError::CompileError {
-                        msg: format!("Undefined function '{}'", name),
-                        desc: "Function not found".to_string(),
-                        span: expr.span,
-                    })?;
+                // functions of earlier inputs are declared on their first call, as they may be
+                // compiled into the module after the caller
+                let fn_val = match self.module().module.get_function(symbol.as_str()) {
+                    Some(fn_val) => fn_val,
+                    None => {
+                        let mut param_types = vec![];
+                        for param in fn_arg_types.iter() {
+                            param_types.push(llvm_type_from_bs_type(param.clone(), self.context));
+                        }
+                        let fn_type = self.context.fn_type(
+                            llvm_type_from_bs_type(ret_ty.as_ref().clone(), self.context),
+                            &param_types,
+                            false,
+                        );
+                        self.modules[self.module].module.add_function(symbol.as_str(), fn_type)
+                    }
+                };
 
                 let mut call_types = vec![];
                 let mut call_args = vec![];
 
-                for arg in args {
-                    let arg = self.compile_expr(arg)?;
-                    call_args.push(arg);
+                // host closures receive their environment as a hidden first argument
+                if let Some(env) = self
+                    .globals
+                    .get_value(&symbol)
+                    .and_then(|v| v.as_fn())
+                    .and_then(|f| f.get_env())
+                {
+                    call_types.push(self.context.i64_type().into());
+                    call_args.push(self.context.i64_type().const_value(env as i64).into());
                 }
 
-                for arg in args {
-                    let ty = arg.get_type()?;
-                    let tp = llvm_type_from_bs_type(ty, &self.context);
-                    call_types.push(tp);
+                let temps = self.temps.len();
+                let mut arg_vals = vec![];
+                for (arg, param) in args.iter().zip(fn_arg_types.iter()) {
+                    let val = self.compile_expr(arg)?;
+                    self.push_temp(val, &arg.get_type()?);
+                    arg_vals.push(val);
+
+                    // a null argument is passed as the null of the parameter type
+                    match arg.get_type()? == *param {
+                        true => call_args.push(val),
+                        false => call_args.push(unsafe {
+                            transmute::<Value<'_>, Value<'_>>(llvm_null_from_bs_type(param.clone(), self.context))
+                        }),
+                    }
+                }
+
+                for param in fn_arg_types {
+                    call_types.push(llvm_type_from_bs_type(param, self.context));
                 }
 
                 let fn_ty = self.context.fn_type(
-                    llvm_type_from_bs_type(ret_ty.as_ref().clone(), &self.context),
+                    llvm_type_from_bs_type(ret_ty.as_ref().clone(), self.context),
                     &call_types,
                     false,
                 );
 
-                unsafe {
-                    ok(transmute(
-                        self.builder
-                            .build_call(fn_ty.into(), fn_val.into(), &call_args, "calltmp"),
-                    ))
+                let res = unsafe {
+                    transmute::<Value<'_>, Value<'_>>(self.builder.build_call(fn_ty, fn_val, &call_args, "calltmp"))
+                };
+                self.pop_temps(temps);
+
+                // arguments are borrowed by the callee
+                for (arg, val) in args.iter().zip(arg_vals) {
+                    self.compile_rc(false, val, &arg.get_type()?);
                 }
+
+                self.compile_check_raised(res, &ret_ty, expr.span);
+                ok(res)
             }
 
             ExprBody::Cond { cond, cons, altr } => {
@@ -224,7 +772,7 @@ impl<'a, 'b> Compiler<'a, 'b> {
 
                 // build then block
                 self.builder.position_at_end(then_bb);
-                let then_val = cons.iter().map(|e| self.compile_expr(&e)).last().unwrap()?;
+                let then_val = self.compile_block(cons)?.unwrap();
 
                 self.builder.build_unconditional_branch(cont_bb);
 
@@ -232,11 +780,10 @@ impl<'a, 'b> Compiler<'a, 'b> {
 
                 // build else block
                 self.builder.position_at_end(else_bb);
-                let else_val = altr
-                    .iter()
-                    .map(|e| self.compile_expr(&e))
-                    .last()
-                    .unwrap_or_else(|| ok(self.context.i64_type().const_value(NULL_VALUE).into()))?;
+                let else_val = match self.compile_block(altr)? {
+                    Some(val) => val,
+                    None => self.context.i64_type().const_value(NULL_VALUE).into(),
+                };
                 self.builder.build_unconditional_branch(cont_bb);
 
                 let else_bb = self.builder.get_insert_block().unwrap();
@@ -247,7 +794,7 @@ impl<'a, 'b> Compiler<'a, 'b> {
                 let ty = cons.last().map(|l| l.get_type()).unwrap_or_else(|| ok(BSType::Null))?;
 
                 // TODO: get rid of unsafe here
-                let ty = unsafe { transmute(llvm_type_from_bs_type(ty, self.context)) };
+                let ty = unsafe { transmute::<Type<'_>, Type<'_>>(llvm_type_from_bs_type(ty, self.context)) };
 
                 let phi = self.builder.build_phi(ty, "iftmp");
 
@@ -257,6 +804,40 @@ impl<'a, 'b> Compiler<'a, 'b> {
                 ok(phi.into())
             }
 
+            ExprBody::Try { body, var, handler } => {
+                let parent = self.fn_value();
+                let catch_bb = self.context.append_basic_block(parent, "catch");
+                let cont_bb = self.context.append_basic_block(parent, "trycont");
+
+                // errors raised by the body branch to the handler instead of out of the function
+                self.handlers.push((catch_bb, self.temps.len()));
+                let body_val = self.compile_block(body);
+                self.handlers.pop();
+                let body_val = body_val?.unwrap();
+                self.builder.build_unconditional_branch(cont_bb);
+                let body_bb = self.builder.get_insert_block().unwrap();
+
+                // the handler takes the error, its message is a local variable
+                self.builder.position_at_end(catch_bb);
+                let msg = self.compile_catch();
+                let ptr = self.create_owned_alloca(var, &BSType::Str);
+                self.owned.push((ptr, BSType::Str));
+                self.builder.build_store(ptr, msg);
+                self.variables.insert(var.clone(), ptr);
+
+                let handler_val = self.compile_block(handler)?.unwrap();
+                self.builder.build_unconditional_branch(cont_bb);
+                let handler_bb = self.builder.get_insert_block().unwrap();
+
+                self.builder.position_at_end(cont_bb);
+                let ty =
+                    unsafe { transmute::<Type<'_>, Type<'_>>(llvm_type_from_bs_type(expr.get_type()?, self.context)) };
+                let phi: PhiValue<'_> = self.builder.build_phi(ty, "trytmp").into();
+                phi.add_incoming(&[(body_val, body_bb), (handler_val, handler_bb)]);
+
+                ok(phi.into())
+            }
+
             e => compile_error(format!("Compiler: unknown expression: {:?}", e), "".to_string(), expr.span),
         }
     }
@@ -273,10 +854,39 @@ impl<'a, 'b> Compiler<'a, 'b> {
             Some(first_instr) => builder.position_before(&first_instr),
             None => builder.position_at_end(entry),
         }
-        let ini = unsafe { std::mem::transmute(ty) };
+        let ini = unsafe { std::mem::transmute::<Type<'_>, Type<'_>>(ty) };
         builder.build_alloca(ini, name)
     }
 
+    /// Creates a stack allocation for a heap value in the entry block of the function, initialized
+    /// to null so that it can be released on every path out of the function.
+    fn create_owned_alloca(&self, name: &str, ty: &BSType) -> Value<'b> {
+        let builder = self.context.create_builder().expect("unable to create builder");
+
+        let entry = self.fn_value().get_first_basic_block().unwrap();
+
+        match entry.get_first_instruction() {
+            Some(first_instr) => builder.position_before(&first_instr),
+            None => builder.position_at_end(entry),
+        }
+        let ini =
+            unsafe { std::mem::transmute::<Type<'_>, Type<'_>>(llvm_type_from_bs_type(ty.clone(), self.context)) };
+        let ptr = builder.build_alloca(ini, name);
+        builder.build_store(ptr, unsafe {
+            std::mem::transmute::<Value<'_>, Value<'_>>(llvm_null_from_bs_type(ty.clone(), self.context))
+        });
+        ptr
+    }
+
+    /// Whether the function is a global, unlike the top-level expression, which can not be called,
+    /// and specializations, which are private to their module.
+    fn is_global(&self) -> bool {
+        !self.function.topl
+            && !self.modules[self.module]
+                .specializations
+                .contains_key(&self.function.symbol())
+    }
+
     pub fn compile_prototype(&mut self, ret_type: BSType) -> BSResult<FnValue<'b>> {
         let rt_module = self.modules.get_mut(self.module).unwrap();
         let proto = &self.function;
@@ -284,20 +894,27 @@ impl<'a, 'b> Compiler<'a, 'b> {
         let args_types = proto
             .args
             .iter()
-            .map(|(_, ty)| llvm_type_from_bs_type(ty.clone(), &self.context))
+            .map(|(_, ty)| llvm_type_from_bs_type(ty.clone(), self.context))
             .collect::<Vec<Type<'_>>>();
 
         let args_types = args_types.as_slice();
 
         let fn_type = self
             .context
-            .fn_type(llvm_type_from_bs_type(ret_type.clone(), &self.context), args_types, false);
-        let fn_val = rt_module.module.add_function(proto.name.as_str(), fn_type);
+            .fn_type(llvm_type_from_bs_type(ret_type.clone(), self.context), args_types, false);
+        // the function may already be declared by a call compiled before it
+        let symbol = proto.symbol();
+        let fn_val = match rt_module.module.get_function(symbol.as_str()) {
+            Some(fn_val) if fn_val.get_first_basic_block().is_none() => fn_val,
+            _ => rt_module.module.add_function(symbol.as_str(), fn_type),
+        };
 
         let bs_ty = BsFnType::new(proto.args.iter().map(|(_, ty)| ty.clone()).collect(), ret_type);
         let bs_val = BSValue::from(BsFnValue::new(bs_ty, 0 as _));
 
-        rt_module.add_global(proto.name.as_str(), bs_val);
+        if self.is_global() {
+            self.globals.add_overload(proto.name.as_str(), bs_val);
+        }
 
         // set arguments names
         for (i, mut arg) in fn_val.get_params_iter().enumerate() {
@@ -314,40 +931,76 @@ impl<'a, 'b> Compiler<'a, 'b> {
             args_variables.insert(a.clone(), t.clone());
         }
 
-        let globals = &self.modules.get(self.module).unwrap().globals;
-        let ret_ty = infer_types(&mut self.function.body, globals, &mut args_variables)?;
-        let function = self.compile_prototype(ret_ty.clone())?;
-
         // got external function, returning only compiled prototype
         if self.function.body.is_empty() {
+            // a C function passes scalars, and its result is read as the type declared
+            let invalid =
+                |desc: String| compile_error(format!("Invalid extern function '{}'", self.function.name), desc, None);
+            let ret_ty = match &self.function.ret {
+                Some(ty) => ty.clone(),
+                None => return invalid("Extern functions must declare their return type".to_string()),
+            };
+            if let Some(ty) = self
+                .function
+                .arg_types()
+                .iter()
+                .chain([&ret_ty])
+                .find(|ty| !ty.is_scalar())
+            {
+                return invalid(format!("Extern functions take and return scalars, not '{}'", ty));
+            }
+            let function = self.compile_prototype(ret_ty.clone())?;
             return ok((function, ret_ty));
         }
 
+        let ret_ty = infer_types(&mut self.function.body, self.globals, &mut args_variables)?;
+        if let Some(declared) = &self.function.ret {
+            if *declared != ret_ty {
+                return compile_error(
+                    format!("Return type mismatch in '{}'", self.function.name),
+                    format!("'{}' is declared to return '{}', but returns '{}'", self.function.name, declared, ret_ty),
+                    self.function.body.last().and_then(|e| e.span),
+                );
+            }
+        }
+
+        let function = self.compile_prototype(ret_ty.clone())?;
+
         let entry = self.context.append_basic_block(function, "entry");
         self.builder.position_at_end(entry);
 
         self.fn_value_opt = Some(function);
 
         for (i, arg) in function.get_params_iter().enumerate() {
-            let ty = arg.get_llvm_type_ref();
             let arg_name = self.function.args[i].0.as_str();
-            let alloca = self.create_entry_block_alloca(arg_name, Type::new(ty));
+            let alloca = self.create_entry_block_alloca(arg_name, arg.get_type());
             self.builder.build_store(alloca, arg);
-            self.variables.insert(self.function.args[i].0.clone(), alloca.into());
+            self.variables.insert(self.function.args[i].0.clone(), alloca);
         }
 
         // compile body, returning last expression
         let last_expr = {
             // TODO: Fix this hack
             let body: &mut Vec<_> = unsafe { std::mem::transmute(&mut self.function.body) };
-            body.into_iter().map(|e| self.compile_expr(&e)).last().unwrap()?
+            self.compile_block(body)?.unwrap()
         };
 
+        let owned = std::mem::take(&mut self.owned);
+        self.compile_release_locals(&owned);
+
         // println!("body: {:?}", last_expr);
         // println!("ret: {:?}", ret_ty);
 
         self.builder.build_return(last_expr);
 
+        if let Some(unwind_bb) = self.unwind_bb {
+            self.builder.position_at_end(unwind_bb);
+            self.compile_release_locals(&owned);
+            let null =
+                unsafe { transmute::<Value<'_>, Value<'_>>(llvm_null_from_bs_type(ret_ty.clone(), self.context)) };
+            self.builder.build_return(null);
+        }
+
         // return the whole thing after verification and optimization
         match function.verify() {
             Ok(_) => {
@@ -355,10 +1008,9 @@ impl<'a, 'b> Compiler<'a, 'b> {
                 let fn_ty =
                     BsFnType::new(self.function.args.iter().map(|(_, ty)| ty.clone()).collect(), ret_ty.clone());
                 let fn_val = BsFnValue::new(fn_ty, function.as_llvm_value_ref() as _);
-                self.modules
-                    .get_mut(self.module)
-                    .unwrap()
-                    .add_global(self.function.name.as_str(), fn_val.into());
+                if self.is_global() {
+                    self.globals.add_overload(self.function.name.as_str(), fn_val.into());
+                }
                 ok((function, ret_ty))
             }
             Err(e) => {
@@ -370,3 +1022,16 @@ impl<'a, 'b> Compiler<'a, 'b> {
 
     pub fn compile(&mut self) -> BSResult<(FnValue<'b>, BSType)> { self.compile_fn() }
 }
+
+/// The table builtin called by `name` with `args`, see `ops::table`.
+fn table_call(name: &str, args: &[Expr]) -> Option<table::TableCall> {
+    let arg_types: Option<Vec<BSType>> = args.iter().map(|arg| arg.expr_type.clone()).collect();
+    let arg_types = arg_types?;
+    if let (true, [arg]) = (name == "to_json", arg_types.as_slice()) {
+        return json::to_json(arg);
+    }
+    if name == "serialize" {
+        return serial::serialize(&arg_types);
+    }
+    table::call(name, &arg_types).or_else(|| table::join(name, join_keys(args), &arg_types)?.ok())
+}
@@ -0,0 +1,635 @@
+//! Queries, compiled into loops over the rows of a table.
+//!
+//! The conditions and the columns computed for every row are evaluated by a single loop, which
+//! stores the index of every row selected and the values computed for it into vectors of the
+//! length of the table. The columns reducing the rows are then evaluated by a loop over the groups,
+//! which passes the reductions the vectors of their arguments over the rows of every group.
+//!
+//! The vectors built by a query are held by local variables until it ends, so that they are
+//! released if it raises an error.
+
+use super::*;
+use crate::parse::ast::{Query, QueryColumn, QueryKind};
+use crate::rt::group::Agg;
+use crate::rt::query::*;
+use crate::rt::table::{table_count, table_delete_rows, table_drop, table_rows, table_set};
+use ffi::values::time::{Date, Time, Timespan, Timestamp};
+
+/// Address of the instance of the generic runtime function `$f` for the elements of the vector
+/// type `$ty`.
+macro_rules! rt_fn {
+    ($f:ident, $ty:expr) => {
+        match $ty {
+            BSType::VecInt64 => $f::<i64> as *const () as usize,
+            BSType::VecFloat64 => $f::<f64> as *const () as usize,
+            BSType::VecBool => $f::<bool> as *const () as usize,
+            BSType::VecSym => $f::<Sym> as *const () as usize,
+            BSType::VecDate => $f::<Date> as *const () as usize,
+            BSType::VecTime => $f::<Time> as *const () as usize,
+            BSType::VecTimestamp => $f::<Timestamp> as *const () as usize,
+            BSType::VecTimespan => $f::<Timespan> as *const () as usize,
+            ty => unreachable!("query column of type {}", ty),
+        }
+    };
+}
+
+fn make_dict_fn(keys: &BSType, vals: &BSType) -> usize {
+    macro_rules! make_dict_fn {
+        ($k:ty) => {
+            match vals {
+                BSType::VecInt64 => make_dict::<$k, i64> as *const () as usize,
+                BSType::VecFloat64 => make_dict::<$k, f64> as *const () as usize,
+                BSType::VecBool => make_dict::<$k, bool> as *const () as usize,
+                BSType::VecDate => make_dict::<$k, Date> as *const () as usize,
+                BSType::VecTime => make_dict::<$k, Time> as *const () as usize,
+                BSType::VecTimestamp => make_dict::<$k, Timestamp> as *const () as usize,
+                BSType::VecTimespan => make_dict::<$k, Timespan> as *const () as usize,
+                _ => make_dict::<$k, Sym> as *const () as usize,
+            }
+        };
+    }
+
+    match keys {
+        BSType::VecInt64 => make_dict_fn!(i64),
+        BSType::VecFloat64 => make_dict_fn!(f64),
+        BSType::VecBool => make_dict_fn!(bool),
+        BSType::VecDate => make_dict_fn!(Date),
+        BSType::VecTime => make_dict_fn!(Time),
+        BSType::VecTimestamp => make_dict_fn!(Timestamp),
+        BSType::VecTimespan => make_dict_fn!(Timespan),
+        _ => make_dict_fn!(Sym),
+    }
+}
+
+/// The aggregate a reduced column of `query` is, if it reduces a single argument, and the index of
+/// the argument in the reductions of the query.
+fn aggregate_of(query: &Query, column: &QueryColumn) -> Option<(Agg, usize)> {
+    match &column.expr.body {
+        ExprBody::Call { name, args } => match (Agg::from_name(name), args.as_slice()) {
+            (Some(agg), [Expr { body: ExprBody::Variable(arg), .. }]) => {
+                query.reductions.iter().position(|r| r.name == *arg).map(|i| (agg, i))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// A vector held by a local variable of the query.
+#[derive(Clone)]
+struct Temp<'b> {
+    ptr: Value<'b>,
+    ty: BSType,
+}
+
+impl<'a, 'b> Compiler<'a, 'b> {
+    pub(super) fn compile_query(&mut self, query: &Query, ty: &BSType) -> BSResult<Value<'a>> {
+        let table_ty = query.table.get_type()?;
+        let table = self.compile_expr(&query.table)?;
+
+        // deleting columns does not read any row
+        if query.kind == QueryKind::Delete && !query.columns.is_empty() {
+            let mut table = table;
+            for column in &query.columns {
+                let name = self.context.i64_type().const_value(Sym::new(&column.name).0).into();
+                let args = [(table, table_ty.clone()), (name, BSType::Sym)];
+                table = self.compile_rt_call("bs.table_drop", table_drop as *const () as usize, &args, ty);
+            }
+            return ok(table);
+        }
+
+        let mut temps = vec![self.create_temp("table", &table_ty, table)];
+        let n = self.compile_rt_call(
+            "bs.table_count",
+            table_count as *const () as usize,
+            &[(table, table_ty.clone())],
+            &BSType::Int64,
+        );
+
+        let mut columns = vec![];
+        for (i, (name, column_ty)) in table_ty.table_columns().iter().enumerate() {
+            let index = self.context.i64_type().const_value(i as i64).into();
+            let args = [(table, table_ty.clone()), (index, BSType::Int64)];
+            let column = self.compile_rt_call("bs.table_column", table_column as *const () as usize, &args, column_ty);
+            temps.push(self.create_temp(name, column_ty, column));
+            columns.push((name.clone(), column_ty.clone(), column));
+        }
+
+        // the row loop computes the columns of every row, the keys and the arguments of reductions
+        let row_columns: Vec<_> = match query.kind {
+            QueryKind::Delete => vec![],
+            _ => query.columns.iter().filter(|c| !c.reduced).collect(),
+        };
+        let mut exprs: Vec<&Expr> = row_columns.iter().map(|c| &c.expr).collect();
+        exprs.extend(query.by.iter().chain(&query.reductions).map(|c| &c.expr));
+        let (outs, len) = self.compile_row_loop(&columns, n, &query.filter, &exprs)?;
+        temps.extend(outs.iter().cloned());
+
+        let rows = self.load_temp(&outs[0]);
+        let (row_outs, outs) = outs[1..].split_at(row_columns.len());
+        let (keys, args) = outs.split_at(query.by.len());
+
+        let res = match query.kind {
+            QueryKind::Select if query.columns.is_empty() => {
+                let args = [(table, table_ty.clone()), (rows, BSType::VecInt64)];
+                self.compile_rt_call("bs.table_rows", table_rows as *const () as usize, &args, ty)
+            }
+            QueryKind::Delete => {
+                let args = [(table, table_ty.clone()), (rows, BSType::VecInt64)];
+                self.compile_rt_call("bs.table_delete_rows", table_delete_rows as *const () as usize, &args, ty)
+            }
+            _ => {
+                let (groups, mut reduced) = self.compile_groups(query, keys, args, len, &mut temps)?;
+                let mut row_outs = row_outs.iter();
+                let mut values = vec![];
+                for column in &query.columns {
+                    match column.reduced {
+                        true => values.push(reduced.remove(0)),
+                        false => values.push(row_outs.next().expect("row column").clone()),
+                    }
+                }
+
+                match query.kind {
+                    QueryKind::Select => {
+                        let keys = self.compile_group_keys(keys, &groups, &mut temps);
+                        let names = query.by.iter().chain(&query.columns).map(|c| c.name.as_str());
+                        self.compile_new_table(names, &keys.iter().chain(&values).cloned().collect::<Vec<_>>(), ty)
+                    }
+                    QueryKind::Exec if query.by.is_empty() && query.columns[0].reduced => {
+                        let data = self.load_temp(&values[0]);
+                        let data = self.compile_vec_field(data, &values[0].ty, VEC_DATA, "data");
+                        let zero = self.context.i64_type().const_value(0).into();
+                        self.compile_load_elem(data, &values[0].ty, zero)
+                    }
+                    QueryKind::Exec if query.by.is_empty() => {
+                        let vec = self.load_temp(&values[0]);
+                        self.compile_rc(true, vec, ty);
+                        vec
+                    }
+                    QueryKind::Exec => {
+                        let keys = self.compile_group_keys(keys, &groups, &mut temps);
+                        let (keys, vals) = (&keys[0], &values[0]);
+                        let args = [(self.load_temp(keys), keys.ty.clone()), (self.load_temp(vals), vals.ty.clone())];
+                        for (val, ty) in &args {
+                            self.compile_rc(true, *val, ty);
+                        }
+                        self.compile_rt_call("bs.make_dict", make_dict_fn(&keys.ty, &vals.ty), &args, ty)
+                    }
+                    _ => self.compile_update(query, table, &table_ty, rows, n, &values, ty),
+                }
+            }
+        };
+
+        self.compile_release_temps(&temps);
+        ok(res)
+    }
+
+    /// Emits the loop over the `n` rows of the table of `columns`, which stores the index of every
+    /// row meeting every condition of `filter`, and the values of `exprs` for it, into new vectors.
+    /// Returns the vectors, the indices first, and the number of rows selected.
+    fn compile_row_loop(
+        &mut self,
+        columns: &[(String, BSType, Value<'a>)],
+        n: Value<'a>,
+        filter: &[Expr],
+        exprs: &[&Expr],
+    ) -> BSResult<(Vec<Temp<'b>>, Value<'a>)> {
+        let mut outs = vec![self.create_vec_temp("rows", &BSType::VecInt64, n)];
+        for expr in exprs {
+            let ty = expr.get_type()?.vec_type().expect("vector");
+            outs.push(self.create_vec_temp("column", &ty, n));
+        }
+        let out_data: Vec<Value<'a>> = outs
+            .iter()
+            .map(|out| {
+                let vec = self.load_temp(out);
+                self.compile_vec_field(vec, &out.ty, VEC_DATA, "data")
+            })
+            .collect();
+
+        // the columns of the table are bound to the elements of the row
+        let variables = self.variables.clone();
+        let mut elems = vec![];
+        for (name, ty, column) in columns {
+            let data = self.compile_vec_field(*column, ty, VEC_DATA, "data");
+            let ptr = self.create_entry_block_alloca(name, llvm_elem_type_from_bs_type(ty.clone(), self.context));
+            self.variables.insert(name.clone(), ptr);
+            elems.push((data, ty, ptr));
+        }
+
+        let i64_ty = self.context.i64_type();
+        let len_ptr = self.create_entry_block_alloca("selected", i64_ty.into());
+        self.builder.build_store(len_ptr, i64_ty.const_value(0).into());
+
+        self.compile_loop(n, |c, row, next_bb| {
+            for (data, ty, ptr) in &elems {
+                let elem = c.compile_load_elem(*data, ty, row);
+                unsafe { c.builder.build_store(*ptr, transmute::<Value<'_>, Value<'_>>(elem)) };
+            }
+            for cond in filter {
+                let selected = c.compile_expr(cond)?;
+                let select_bb = c.context.append_basic_block(c.fn_value(), "select");
+                c.builder.build_conditional_branch(selected, select_bb, next_bb);
+                c.builder.position_at_end(select_bb);
+            }
+
+            let len = c.compile_load_i64(len_ptr, "len");
+            c.compile_store_elem(out_data[0], &BSType::VecInt64, len, row);
+            for ((expr, out), data) in exprs.iter().zip(&outs[1..]).zip(&out_data[1..]) {
+                let val = c.compile_expr(expr)?;
+                c.compile_store_elem(*data, &out.ty, len, val);
+            }
+            let len = c.builder.build_int_add(len, i64_ty.const_value(1).into(), "len");
+            unsafe { c.builder.build_store(len_ptr, transmute::<Value<'_>, Value<'_>>(len)) };
+            ok(())
+        })?;
+        self.variables = variables;
+
+        let len = self.compile_load_i64(len_ptr, "len");
+        for out in &outs {
+            let vec = self.load_temp(out);
+            self.compile_vec_set_len(vec, &out.ty, len);
+        }
+        ok((outs, len))
+    }
+
+    /// Emits the grouping of the `len` rows selected by the vectors `keys`, in a single group if
+    /// there are none, and the reduced columns of every group, from the vectors `args` of the
+    /// arguments of the reductions. Returns the groups, null for a single one, followed by the
+    /// values of the reduced columns, by group, or for `update`, by row.
+    ///
+    /// A column which is a reduction of a single argument is aggregated by `rt::group` in a pass
+    /// over the rows. The others are computed by a loop over the groups, which passes their
+    /// reductions the vectors of their arguments over the rows of every group.
+    fn compile_groups(
+        &mut self,
+        query: &Query,
+        keys: &[Temp<'b>],
+        args: &[Temp<'b>],
+        len: Value<'a>,
+        temps: &mut Vec<Temp<'b>>,
+    ) -> BSResult<(Temp<'b>, Vec<Temp<'b>>)> {
+        let int64 = BSType::VecInt64;
+        let null = unsafe { transmute::<Value<'_>, Value<'_>>(llvm_null_from_bs_type(int64.clone(), self.context)) };
+        let gids = self.create_temp("groups", &int64, null);
+        temps.push(gids.clone());
+        for key in keys {
+            let args = [(self.load_temp(&gids), int64.clone()), (self.load_temp(key), key.ty.clone())];
+            let ids = self.compile_rt_call("bs.group", rt_fn!(group, &key.ty), &args, &int64);
+            self.compile_replace_temp(&gids, ids);
+        }
+        let args_gids = [(self.load_temp(&gids), int64.clone())];
+        let groups =
+            self.compile_rt_call("bs.group_count", group_count as *const () as usize, &args_gids, &BSType::Int64);
+
+        // `update` stores the values of a group at its rows, the others store one per group
+        let update = query.kind == QueryKind::Update;
+        let mut reduced = vec![];
+        let mut looped = vec![];
+        for column in query.columns.iter().filter(|c| c.reduced) {
+            let ty = column.expr.get_type()?.vec_type().expect("vector");
+            let out = match aggregate_of(query, column) {
+                Some((agg, i)) => {
+                    let arg = &args[i];
+                    let agg = self.context.i64_type().const_value(agg as i64).into();
+                    let args = [
+                        (agg, BSType::Int64),
+                        (self.load_temp(&gids), int64.clone()),
+                        (groups, BSType::Int64),
+                        (self.load_temp(arg), arg.ty.clone()),
+                    ];
+                    let aggs = self.compile_rt_call("bs.aggregate", rt_fn!(aggregate, &arg.ty), &args, &ty);
+                    let aggs_temp = self.create_temp(&column.name, &ty, aggs);
+                    match update {
+                        false => aggs_temp,
+                        true => {
+                            temps.push(aggs_temp);
+                            let args =
+                                [(aggs, ty.clone()), (self.load_temp(&gids), int64.clone()), (len, BSType::Int64)];
+                            let vals = self.compile_rt_call("bs.broadcast", rt_fn!(broadcast, &ty), &args, &ty);
+                            self.create_temp(&column.name, &ty, vals)
+                        }
+                    }
+                }
+                None => {
+                    let out = self.create_vec_temp(&column.name, &ty, if update { len } else { groups });
+                    looped.push((column, out.clone()));
+                    out
+                }
+            };
+            temps.push(out.clone());
+            reduced.push(out);
+        }
+
+        if !looped.is_empty() {
+            self.compile_group_loop(query, &looped, args, &gids, len, groups, temps)?;
+        }
+        ok((gids, reduced))
+    }
+
+    /// Emits the loop computing the reduced columns `looped` of every group of `gids`, storing
+    /// their values into the vectors paired with them, by group, or for `update`, by row.
+    #[allow(clippy::too_many_arguments)]
+    fn compile_group_loop(
+        &mut self,
+        query: &Query,
+        looped: &[(&QueryColumn, Temp<'b>)],
+        args: &[Temp<'b>],
+        gids: &Temp<'b>,
+        len: Value<'a>,
+        groups: Value<'a>,
+        temps: &mut Vec<Temp<'b>>,
+    ) -> BSResult<()> {
+        let int64 = BSType::VecInt64;
+        let args_by_group = [(self.load_temp(gids), int64.clone()), (len, BSType::Int64)];
+        let order = self.compile_rt_call("bs.group_order", group_order as *const () as usize, &args_by_group, &int64);
+        let order = self.create_temp("order", &int64, order);
+        let bounds =
+            self.compile_rt_call("bs.group_bounds", group_bounds as *const () as usize, &args_by_group, &int64);
+        let bounds = self.create_temp("bounds", &int64, bounds);
+        temps.extend([order.clone(), bounds.clone()]);
+        let bounds_vec = self.load_temp(&bounds);
+        let bounds_data = self.compile_vec_field(bounds_vec, &int64, VEC_DATA, "data");
+
+        let update = query.kind == QueryKind::Update;
+        let out_data: Vec<Value<'a>> = looped
+            .iter()
+            .map(|(_, out)| {
+                let vec = self.load_temp(out);
+                self.compile_vec_field(vec, &out.ty, VEC_DATA, "data")
+            })
+            .collect();
+
+        // the reductions take the vectors of their arguments over the group, but those aggregated
+        // in a pass over the rows
+        let aggregated: Vec<usize> = query
+            .columns
+            .iter()
+            .filter_map(|c| aggregate_of(query, c).map(|(_, i)| i))
+            .collect();
+        let variables = self.variables.clone();
+        let mut subs = vec![];
+        for (i, (arg, column)) in args.iter().zip(&query.reductions).enumerate() {
+            if aggregated.contains(&i) {
+                continue;
+            }
+            let null =
+                unsafe { transmute::<Value<'_>, Value<'_>>(llvm_null_from_bs_type(arg.ty.clone(), self.context)) };
+            let sub = self.create_temp(&column.name, &arg.ty, null);
+            self.variables.insert(column.name.clone(), sub.ptr);
+            temps.push(sub.clone());
+            subs.push((arg, sub));
+        }
+
+        let one = self.context.i64_type().const_value(1).into();
+        self.compile_loop(groups, |c, group, _| {
+            let lo = c.compile_load_elem(bounds_data, &int64, group);
+            let next = c.builder.build_int_add(group, one, "next");
+            let hi = c.compile_load_elem(bounds_data, &int64, next);
+            for (arg, sub) in &subs {
+                let args = [
+                    (c.load_temp(arg), arg.ty.clone()),
+                    (c.load_temp(&order), int64.clone()),
+                    (lo, BSType::Int64),
+                    (hi, BSType::Int64),
+                ];
+                let vec = c.compile_rt_call("bs.take_range", rt_fn!(take_range, &arg.ty), &args, &arg.ty);
+                c.compile_replace_temp(sub, vec);
+            }
+
+            for ((column, out), data) in looped.iter().zip(&out_data) {
+                let val = c.compile_expr(&column.expr)?;
+                if update {
+                    let args = [
+                        (c.load_temp(out), out.ty.clone()),
+                        (c.load_temp(&order), int64.clone()),
+                        (lo, BSType::Int64),
+                        (hi, BSType::Int64),
+                        (val, column.expr.get_type()?),
+                    ];
+                    c.compile_rt_call("bs.fill_range", rt_fn!(fill_range, &out.ty), &args, &out.ty);
+                } else {
+                    c.compile_store_elem(*data, &out.ty, group, val);
+                }
+            }
+            ok(())
+        })?;
+        self.variables = variables;
+
+        for (_, out) in looped {
+            let vec = self.load_temp(out);
+            self.compile_vec_set_len(vec, &out.ty, if update { len } else { groups });
+        }
+        ok(())
+    }
+
+    /// Emits the vectors of the value of every key of `keys` for every group of `gids`.
+    fn compile_group_keys(&mut self, keys: &[Temp<'b>], gids: &Temp<'b>, temps: &mut Vec<Temp<'b>>) -> Vec<Temp<'b>> {
+        if keys.is_empty() {
+            return vec![];
+        }
+
+        let int64 = BSType::VecInt64;
+        let args = [(self.load_temp(gids), int64.clone())];
+        let firsts = self.compile_rt_call("bs.group_firsts", group_firsts as *const () as usize, &args, &int64);
+        let firsts = self.create_temp("firsts", &int64, firsts);
+        temps.push(firsts.clone());
+
+        let mut vecs = vec![];
+        for key in keys {
+            let args = [(self.load_temp(key), key.ty.clone()), (self.load_temp(&firsts), int64.clone())];
+            let vec = self.compile_rt_call("bs.take", rt_fn!(take, &key.ty), &args, &key.ty);
+            let vec = self.create_temp("key", &key.ty, vec);
+            temps.push(vec.clone());
+            vecs.push(vec);
+        }
+        vecs
+    }
+
+    /// Emits a new table of the vectors `columns`, named `names`.
+    fn compile_new_table<'n>(
+        &mut self,
+        names: impl Iterator<Item = &'n str>,
+        columns: &[Temp<'b>],
+        ty: &BSType,
+    ) -> Value<'a> {
+        let mut table = self.compile_rt_call("bs.table_new", table_new as *const () as usize, &[], ty);
+        for (name, column) in names.zip(columns) {
+            let vec = self.load_temp(column);
+            self.compile_rc(true, vec, &column.ty);
+            let name = self.context.i64_type().const_value(Sym::new(name).0).into();
+            let args = [(table, ty.clone()), (name, BSType::Sym), (vec, column.ty.clone())];
+            table = self.compile_rt_call("bs.table_add", table_add as *const () as usize, &args, ty);
+        }
+        table
+    }
+
+    /// Emits the table updating the `n` rows of `table` at `rows` with the columns `values`.
+    #[allow(clippy::too_many_arguments)]
+    fn compile_update(
+        &mut self,
+        query: &Query,
+        table: Value<'a>,
+        table_ty: &BSType,
+        rows: Value<'a>,
+        n: Value<'a>,
+        values: &[Temp<'b>],
+        ty: &BSType,
+    ) -> Value<'a> {
+        self.compile_rc(true, table, table_ty);
+        let mut res = table;
+        for (column, vals) in query.columns.iter().zip(values) {
+            let base = match table_ty
+                .table_columns()
+                .iter()
+                .position(|(name, _)| *name == column.name)
+            {
+                Some(i) => {
+                    let index = self.context.i64_type().const_value(i as i64).into();
+                    let args = [(table, table_ty.clone()), (index, BSType::Int64)];
+                    self.compile_rt_call("bs.table_column", table_column as *const () as usize, &args, &vals.ty)
+                }
+                None => unsafe {
+                    transmute::<Value<'_>, Value<'_>>(llvm_null_from_bs_type(vals.ty.clone(), self.context))
+                },
+            };
+            let args = [
+                (base, vals.ty.clone()),
+                (n, BSType::Int64),
+                (rows, BSType::VecInt64),
+                (self.load_temp(vals), vals.ty.clone()),
+            ];
+            let column_vec = self.compile_rt_call("bs.scatter", rt_fn!(scatter, &vals.ty), &args, &vals.ty);
+            self.compile_rc(false, base, &vals.ty);
+
+            let name = self.context.i64_type().const_value(Sym::new(&column.name).0).into();
+            let args = [(res, ty.clone()), (name, BSType::Sym), (column_vec, vals.ty.clone())];
+            res = self.compile_rt_call("bs.table_set", table_set as *const () as usize, &args, ty);
+        }
+        res
+    }
+
+    /// Emits a loop calling `body` for every index from 0 to `n`, which may branch to the block it
+    /// is passed to skip to the next index.
+    fn compile_loop(
+        &mut self,
+        n: Value<'a>,
+        mut body: impl FnMut(&mut Self, Value<'a>, BasicBlock<'b>) -> BSResult<()>,
+    ) -> BSResult<()> {
+        let parent = self.fn_value();
+        let i64_ty = self.context.i64_type();
+        let index_ptr = self.create_entry_block_alloca("index", i64_ty.into());
+        self.builder.build_store(index_ptr, i64_ty.const_value(0).into());
+
+        let cond_bb = self.context.append_basic_block(parent, "loop");
+        let body_bb = self.context.append_basic_block(parent, "body");
+        let next_bb = self.context.append_basic_block(parent, "next");
+        let end_bb = self.context.append_basic_block(parent, "end");
+        self.builder.build_unconditional_branch(cond_bb);
+
+        self.builder.position_at_end(cond_bb);
+        let index = self.compile_load_i64(index_ptr, "index");
+        let more = self.builder.build_int_compare(IntPredicate::SLT, index, n, "more");
+        self.builder.build_conditional_branch(more, body_bb, end_bb);
+
+        self.builder.position_at_end(body_bb);
+        body(self, index, next_bb)?;
+        self.builder.build_unconditional_branch(next_bb);
+
+        self.builder.position_at_end(next_bb);
+        let next = self.builder.build_int_add(index, i64_ty.const_value(1).into(), "next");
+        unsafe {
+            self.builder
+                .build_store(index_ptr, transmute::<Value<'_>, Value<'_>>(next))
+        };
+        self.builder.build_unconditional_branch(cond_bb);
+
+        self.builder.position_at_end(end_bb);
+        ok(())
+    }
+
+    fn compile_load_i64(&mut self, ptr: Value<'b>, name: &str) -> Value<'a> {
+        unsafe {
+            transmute(
+                self.builder
+                    .build_load(self.context.i64_type().into(), ptr.into(), name),
+            )
+        }
+    }
+
+    /// Loads the element `index` of the elements `data` of a vector of type `ty`.
+    fn compile_load_elem(&mut self, data: Value<'a>, ty: &BSType, index: Value<'a>) -> Value<'a> {
+        let elem_ty = || llvm_elem_type_from_bs_type(ty.clone(), self.context);
+        unsafe {
+            let ptr = self.builder.build_in_bounds_gep(
+                transmute::<Type<'_>, Type<'_>>(elem_ty()),
+                transmute::<Value<'_>, Value<'_>>(data),
+                transmute::<Value<'_>, Value<'_>>(index),
+                "elemptr",
+            );
+            transmute(
+                self.builder
+                    .build_load(transmute::<Type<'_>, Type<'_>>(elem_ty()), ptr.into(), "elem"),
+            )
+        }
+    }
+
+    /// Stores `val` as the element `index` of the elements `data` of a vector of type `ty`.
+    fn compile_store_elem(&mut self, data: Value<'a>, ty: &BSType, index: Value<'a>, val: Value<'a>) {
+        let elem_ty = llvm_elem_type_from_bs_type(ty.clone(), self.context);
+        unsafe {
+            let ptr = self.builder.build_in_bounds_gep(
+                transmute::<Type<'_>, Type<'_>>(elem_ty),
+                transmute::<Value<'_>, Value<'_>>(data),
+                transmute::<Value<'_>, Value<'_>>(index),
+                "elemptr",
+            );
+            self.builder.build_store(ptr, transmute::<Value<'_>, Value<'_>>(val));
+        }
+    }
+
+    /// Creates a local variable of the query holding `val`.
+    fn create_temp(&mut self, name: &str, ty: &BSType, val: Value<'a>) -> Temp<'b> {
+        let ptr = self.create_owned_alloca(name, ty);
+        self.owned.push((ptr, ty.clone()));
+        unsafe { self.builder.build_store(ptr, transmute::<Value<'_>, Value<'_>>(val)) };
+        Temp { ptr, ty: ty.clone() }
+    }
+
+    /// Creates a local variable of the query holding a new empty vector with room for `cap`
+    /// elements.
+    fn create_vec_temp(&mut self, name: &str, ty: &BSType, cap: Value<'a>) -> Temp<'b> {
+        let vec = self.compile_rt_call("bs.vec_alloc", rt_fn!(vec_alloc, ty), &[(cap, BSType::Int64)], ty);
+        self.create_temp(name, ty, vec)
+    }
+
+    fn load_temp(&mut self, temp: &Temp<'b>) -> Value<'a> {
+        let ty = llvm_type_from_bs_type(temp.ty.clone(), self.context);
+        unsafe {
+            transmute(
+                self.builder
+                    .build_load(transmute::<Type<'_>, Type<'_>>(ty), temp.ptr.into(), ""),
+            )
+        }
+    }
+
+    /// Stores `val` into the local variable `temp`, releasing its previous value.
+    fn compile_replace_temp(&mut self, temp: &Temp<'b>, val: Value<'a>) {
+        let old = self.load_temp(temp);
+        self.compile_rc(false, old, &temp.ty);
+        unsafe {
+            self.builder
+                .build_store(temp.ptr, transmute::<Value<'_>, Value<'_>>(val))
+        };
+    }
+
+    /// Releases the local variables `temps` as the query ends, leaving them null for the release
+    /// of every local variable as the function returns.
+    fn compile_release_temps(&mut self, temps: &[Temp<'b>]) {
+        for temp in temps {
+            let null =
+                unsafe { transmute::<Value<'_>, Value<'_>>(llvm_null_from_bs_type(temp.ty.clone(), self.context)) };
+            self.compile_replace_temp(temp, null);
+        }
+    }
+}
@@ -1,22 +1,25 @@
 use ffi::types::Type as BSType;
 use ffi::values::Value as BSValue;
-use ffi::values::NULL_VALUE;
+use ffi::values::{NULL_FLOAT64, NULL_INT64, NULL_VALUE};
 use llvm::context::Context;
+use llvm::types::prelude::StructType;
 use llvm::types::Type as LLVMType;
 use llvm::values::prelude::*;
 use llvm::values::Value as LLVMValue;
 
-pub fn llvm_value_from_bs_value<'a>(bs_value: BSValue, context: &'a Context) -> LLVMValue<'a> {
+/// Embeds `bs_value` as a constant, heap values by address: they must outlive the compiled code.
+pub fn llvm_value_from_bs_value<'a>(bs_value: &BSValue, context: &'a Context) -> LLVMValue<'a> {
     match bs_value.get_type() {
         BSType::Null => context.i64_type().const_value(NULL_VALUE).into(),
-        BSType::Bool => context.i1_type().const_value(bs_value.into()).into(),
-        BSType::Int64 => context.i64_type().const_value(bs_value.into()).into(),
-        BSType::Float64 => context.f64_type().const_value(bs_value.into()).into(),
-        BSType::VecInt64 => context
-            .ptr_type(context.i64_type().into())
+        BSType::Bool => context.i1_type().const_value(bs_value.clone().into()).into(),
+        BSType::Int64 => context.i64_type().const_value(bs_value.clone().into()).into(),
+        BSType::Float64 => context.f64_type().const_value(bs_value.clone().into()).into(),
+        ty if ty.is_vec() => context
+            .ptr_type(llvm_vec_type(ty.clone(), context).into())
             .const_value(bs_value.as_raw() as _)
             .into(),
-        // BSType::VecFloat64(v) => into_llvm_struct(tag, transmute::<_, i64>(v), context),
+        BSType::Sym | BSType::Str => context.i64_type().const_value(bs_value.as_raw()).into(),
+        ty if ty.is_temporal() => context.i64_type().const_value(bs_value.as_raw()).into(),
         _ => unimplemented!(),
     }
 }
@@ -26,37 +29,94 @@ pub fn bs_value_from_llvm_value(value: LLVMValue, ty: BSType) -> BSValue {
         BSType::Null => BSValue::from(()),
         BSType::Bool => {
             let val: I1Value<'_> = value.into();
-            let val: bool = val.get_constant().into();
+            let val: bool = val.get_constant();
             BSValue::from(val)
         }
         BSType::Int64 => {
             let val: I64Value<'_> = value.into();
-            let val: i64 = val.get_constant().into();
+            let val: i64 = val.get_constant();
             BSValue::from(val)
         }
         BSType::Float64 => {
             let val: F64Value<'_> = value.into();
-            let val: f64 = val.get_constant().into();
+            let val: f64 = val.get_constant();
             BSValue::from(val)
         }
-        BSType::VecInt64 => {
+        BSType::Sym => {
+            let val: I64Value<'_> = value.into();
+            let val: i64 = val.get_constant();
+            BSValue::from(ffi::values::sym::Sym(val))
+        }
+        ty if ty.is_temporal() => {
+            let val: I64Value<'_> = value.into();
+            BSValue::from_raw_parts(ty, val.get_constant())
+        }
+        ty if ty.is_vec() => {
             let val: PtrValue<'_> = value.into();
-            BSValue::from_raw_parts(ty, val.const_to_i64().into())
+            unsafe { BSValue::from_raw_borrowed(ty, val.const_to_i64().into()) }
         }
         _ => todo!(),
     }
 }
 
+/// Null value of `bs_type`: the null sentinel of a scalar, or a null pointer for a heap type.
+pub fn llvm_null_from_bs_type<'a>(bs_type: BSType, context: &'a Context) -> LLVMValue<'a> {
+    match bs_type {
+        BSType::Null => context.i64_type().const_value(NULL_VALUE).into(),
+        BSType::Bool => context.i1_type().const_value(false).into(),
+        BSType::Int64 | BSType::Date | BSType::Time | BSType::Timestamp | BSType::Timespan => {
+            context.i64_type().const_value(NULL_INT64).into()
+        }
+        BSType::Float64 => context.f64_type().const_value(NULL_FLOAT64).into(),
+        ty if ty.is_vec() => context
+            .ptr_type(llvm_vec_type(ty, context).into())
+            .const_value(std::ptr::null())
+            .into(),
+        BSType::Sym | BSType::Str | BSType::Bytes | BSType::Dict(..) | BSType::Table(_) | BSType::List => {
+            context.i64_type().const_value(0).into()
+        }
+        _ => unimplemented!(),
+    }
+}
+
 pub fn llvm_type_from_bs_type<'a>(bs_type: BSType, context: &'a Context) -> LLVMType<'a> {
     match bs_type {
-        BSType::Null => context.i64_type().into(),
+        BSType::Null
+        | BSType::Sym
+        | BSType::Str
+        | BSType::Bytes
+        | BSType::Dict(..)
+        | BSType::Table(_)
+        | BSType::List => context.i64_type().into(),
         BSType::Bool => context.i1_type().into(),
-        BSType::Int64 => context.i64_type().into(),
+        BSType::Int64 | BSType::Date | BSType::Time | BSType::Timestamp | BSType::Timespan => context.i64_type().into(),
         BSType::Float64 => context.f64_type().into(),
-        BSType::VecInt64 => context.ptr_type(context.i64_type().into()).into(),
-        // BSType::VecFloat64 => context
-        //     .struct_type(&[context.i64_type().into(), context.i64_type().into()], false)
-        //     .into(),
+        ty if ty.is_vec() => context.ptr_type(llvm_vec_type(ty, context).into()).into(),
+        _ => unimplemented!(),
+    }
+}
+
+/// Type of the elements of the vector type `bs_type`.
+pub fn llvm_elem_type_from_bs_type<'a>(bs_type: BSType, context: &'a Context) -> LLVMType<'a> {
+    match bs_type {
+        BSType::VecInt64 | BSType::VecSym => context.i64_type().into(),
+        ty if ty.is_temporal_vec() => context.i64_type().into(),
+        BSType::VecFloat64 => context.f64_type().into(),
+        BSType::VecBool => context.i1_type().into(),
         _ => unimplemented!(),
     }
 }
+
+/// Layout of `ffi::values::vector::VecHeader` for the vector type `bs_type`.
+pub fn llvm_vec_type<'a>(bs_type: BSType, context: &'a Context) -> StructType<'a> {
+    let data_ty = context.ptr_type(llvm_elem_type_from_bs_type(bs_type, context)).into();
+    // rc, len, cap, tag, data
+    let fields = [
+        context.i64_type().into(),
+        context.i64_type().into(),
+        context.i64_type().into(),
+        context.i64_type().into(),
+        data_ty,
+    ];
+    context.struct_type(&fields, false)
+}
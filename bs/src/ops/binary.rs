@@ -3,7 +3,11 @@ use crate::parse::ast::BinaryOp;
 use crate::parse::span::Span;
 use crate::result::*;
 use ffi::types::Type as BSType;
+use ffi::values::time::NS_PER_DAY;
+use ffi::values::NULL_INT64;
 use llvm::builder::Builder;
+use llvm::context::Context;
+use llvm::module::Module;
 use llvm::values::Value;
 use std::collections::HashMap;
 use BSType::*;
@@ -33,6 +37,34 @@ lazy_static! {
         m.insert((GreaterOrEqual, Int64, Int64), Bool);
         m.insert((NotEqual, Int64, Int64), Bool);
 
+        m.insert((Equal, Sym, Sym), Bool);
+        m.insert((NotEqual, Sym, Sym), Bool);
+
+        // dates and times are compared as their Int64, and added to or subtracted from as in k
+        for ty in [Date, Time, Timestamp, Timespan] {
+            for op in [Equal, NotEqual, Less, Greater, LessOrEqual, GreaterOrEqual] {
+                m.insert((op, ty.clone(), ty.clone()), Bool);
+            }
+        }
+        m.insert((Add, Date, Int64), Date);
+        m.insert((Add, Int64, Date), Date);
+        m.insert((Sub, Date, Int64), Date);
+        m.insert((Sub, Date, Date), Int64);
+        m.insert((Add, Date, Time), Timestamp);
+        for ty in [Time, Timestamp] {
+            m.insert((Add, ty.clone(), Timespan), ty.clone());
+            m.insert((Add, Timespan, ty.clone()), ty.clone());
+            m.insert((Sub, ty.clone(), Timespan), ty.clone());
+            m.insert((Sub, ty.clone(), ty.clone()), Timespan);
+        }
+        m.insert((Add, Timespan, Timespan), Timespan);
+        m.insert((Sub, Timespan, Timespan), Timespan);
+        m.insert((Mul, Timespan, Int64), Timespan);
+        m.insert((Mul, Int64, Timespan), Timespan);
+        m.insert((Div, Timespan, Int64), Timespan);
+        m.insert((Div, Timespan, Timespan), Int64);
+        m.insert((Rem, Timespan, Timespan), Timespan);
+
         m.insert((Add, Float64, Float64), Float64);
         m.insert((Sub, Float64, Float64), Float64);
         m.insert((Mul, Float64, Float64), Float64);
@@ -62,8 +94,149 @@ pub fn infer_type(op: BinaryOp, lhs: BSType, rhs: BSType, span: Option<Span>) ->
     }
 }
 
-pub fn compile<'a, 'b>(
-    builder: &'a Builder<'b>,
+/// Yields null instead of the `Int64` `result` if either operand is null.
+fn propagate_null<'b>(
+    builder: &Builder<'b>,
+    context: &Context,
+    lhs: Value<'b>,
+    rhs: Value<'b>,
+    result: Value<'b>,
+) -> Value<'b> {
+    let null = context.i64_type().const_value(NULL_INT64).into();
+    let is_null = any_null(builder, context, lhs, rhs);
+    builder.build_select(is_null, null, result, "nulltmp")
+}
+
+/// Whether either `Int64` operand is null.
+fn any_null<'b>(builder: &Builder<'b>, context: &Context, lhs: Value<'b>, rhs: Value<'b>) -> Value<'b> {
+    let null = context.i64_type().const_value(NULL_INT64).into();
+    let lhs_null = builder.build_int_compare(IntPredicate::EQ, lhs, null, "lhsnull");
+    let rhs_null = builder.build_int_compare(IntPredicate::EQ, rhs, null, "rhsnull");
+    builder.build_or(lhs_null, rhs_null, "isnull")
+}
+
+/// Divides the `Int64` `lhs` by `rhs`, or with `rem` takes the remainder. As in k, dividing by zero
+/// yields null rather than trapping.
+fn int_div<'b>(builder: &Builder<'b>, context: &Context, lhs: Value<'b>, rhs: Value<'b>, rem: bool) -> Value<'b> {
+    let i64_type = context.i64_type();
+    let is_zero = builder.build_int_compare(IntPredicate::EQ, rhs, i64_type.const_value(0).into(), "iszero");
+    let is_null = any_null(builder, context, lhs, rhs);
+    // null is i64::MIN, so this also excludes the overflowing i64::MIN / -1
+    let undefined = builder.build_or(is_zero, is_null, "undefined");
+
+    let divisor = builder.build_select(undefined, i64_type.const_value(1).into(), rhs, "divisor");
+    let res = match rem {
+        true => builder.build_rem(lhs, divisor, "remtmp"),
+        false => builder.build_int_div(lhs, divisor, "divtmp"),
+    };
+    builder.build_select(undefined, i64_type.const_value(NULL_INT64).into(), res, "divtmp")
+}
+
+/// Calls the LLVM overflow intrinsic `intrinsic` of two `Int64`, returning the result and whether
+/// it overflowed.
+fn call_overflow<'b>(
+    builder: &Builder<'b>,
+    context: &Context,
+    module: &Module<'b>,
+    intrinsic: &str,
+    lhs: Value<'b>,
+    rhs: Value<'b>,
+) -> (Value<'b>, Value<'b>) {
+    let ret_type = context.struct_type(&[context.i64_type().into(), context.i1_type().into()], false);
+    let fn_type = context.fn_type(ret_type.into(), &[context.i64_type().into(), context.i64_type().into()], false);
+    let fn_val = match module.get_function(intrinsic) {
+        Some(fn_val) => fn_val,
+        None => module.add_function(intrinsic, fn_type),
+    };
+
+    let res = builder.build_call(fn_type, fn_val, &[lhs, rhs], "checkedtmp");
+    let value = builder.build_extract_value(res, 0, "value").expect("value");
+    let overflow = builder.build_extract_value(res, 1, "overflow").expect("overflow");
+    (value, overflow)
+}
+
+/// Compiles the `Int64` addition, subtraction or multiplication `op` with the LLVM overflow
+/// intrinsics, returning the result and whether it overflowed, or `None` for any other operation.
+/// A date and a time are added as the nanoseconds of a timestamp, the days multiplied and added
+/// with the intrinsics as well. A result of null counts as an overflow, while null operands still
+/// yield null.
+pub fn compile_checked<'b>(
+    builder: &Builder<'b>,
+    context: &Context,
+    module: &Module<'b>,
+    op: BinaryOp,
+    lhs: (Value<'b>, BSType),
+    rhs: (Value<'b>, BSType),
+) -> Option<(Value<'b>, Value<'b>)> {
+    let (value, overflow) = match (op, &lhs.1, &rhs.1) {
+        (Add, Date, Time) => {
+            let ns_per_day = context.i64_type().const_value(NS_PER_DAY).into();
+            let mul = "llvm.smul.with.overflow.i64";
+            let (days, days_overflow) = call_overflow(builder, context, module, mul, lhs.0, ns_per_day);
+            let add = "llvm.sadd.with.overflow.i64";
+            let (value, add_overflow) = call_overflow(builder, context, module, add, days, rhs.0);
+            (value, builder.build_or(days_overflow, add_overflow, "overflow"))
+        }
+        _ => {
+            let intrinsic = match (op, int64_repr(&lhs.1), int64_repr(&rhs.1)) {
+                (Add, Int64, Int64) => "llvm.sadd.with.overflow.i64",
+                (Sub, Int64, Int64) => "llvm.ssub.with.overflow.i64",
+                (Mul, Int64, Int64) => "llvm.smul.with.overflow.i64",
+                _ => return None,
+            };
+            call_overflow(builder, context, module, intrinsic, lhs.0, rhs.0)
+        }
+    };
+    let (lhs, rhs) = (lhs.0, rhs.0);
+
+    let null = context.i64_type().const_value(NULL_INT64).into();
+    let is_null = any_null(builder, context, lhs, rhs);
+    let res_null = builder.build_int_compare(IntPredicate::EQ, value, null, "resnull");
+    let overflow = builder.build_or(overflow, res_null, "overflow");
+    let not_null = builder.build_not(is_null, "notnull");
+    let overflow = builder.build_and(overflow, not_null, "overflow");
+
+    Some((builder.build_select(is_null, null, value, "checkedtmp"), overflow))
+}
+
+/// Whether the float `lhs` is less than `rhs`, or with `or_equal` less than or equal to it. Null
+/// (NaN) is less than any other float and equal to itself.
+fn float_less<'b>(builder: &Builder<'b>, lhs: Value<'b>, rhs: Value<'b>, or_equal: bool) -> Value<'b> {
+    let lhs_null = builder.build_float_compare(FloatPredicate::UNO, lhs, lhs, "lhsnull");
+    match or_equal {
+        true => {
+            let le = builder.build_float_compare(FloatPredicate::OLE, lhs, rhs, "letmp");
+            builder.build_or(le, lhs_null, "letmp")
+        }
+        false => {
+            let lt = builder.build_float_compare(FloatPredicate::OLT, lhs, rhs, "lttmp");
+            let rhs_value = builder.build_float_compare(FloatPredicate::ORD, rhs, rhs, "rhsvalue");
+            let null_lt = builder.build_and(lhs_null, rhs_value, "lttmp");
+            builder.build_or(lt, null_lt, "lttmp")
+        }
+    }
+}
+
+/// Whether the floats `lhs` and `rhs` are equal, which two nulls (NaN) are.
+fn float_equal<'b>(builder: &Builder<'b>, lhs: Value<'b>, rhs: Value<'b>) -> Value<'b> {
+    let eq = builder.build_float_compare(FloatPredicate::OEQ, lhs, rhs, "eqtmp");
+    let lhs_null = builder.build_float_compare(FloatPredicate::UNO, lhs, lhs, "lhsnull");
+    let rhs_null = builder.build_float_compare(FloatPredicate::UNO, rhs, rhs, "rhsnull");
+    let both_null = builder.build_and(lhs_null, rhs_null, "bothnull");
+    builder.build_or(eq, both_null, "eqtmp")
+}
+
+/// Type of the operand `ty` as compiled, `Int64` for a date or time.
+fn int64_repr(ty: &BSType) -> BSType {
+    match ty.is_temporal() {
+        true => Int64,
+        false => ty.clone(),
+    }
+}
+
+pub fn compile<'b>(
+    builder: &Builder<'b>,
+    context: &Context,
     op: BinaryOp,
     lhs: (Value<'b>, BSType),
     rhs: (Value<'b>, BSType),
@@ -74,19 +247,35 @@ pub fn compile<'a, 'b>(
 
     use BSType::*;
     use BinaryOp::*;
-    use FloatPredicate as FP;
     use IntPredicate as IP;
 
-    let result = match (op, lhs_type, rhs_type) {
-        (Add, Int64, Int64) => builder.build_int_add(lhs, rhs, "addtmp"),
+    // a date and a time of day are added as the nanoseconds of a timestamp
+    if (op, &lhs_type, &rhs_type) == (Add, &Date, &Time) {
+        let ns_per_day = context.i64_type().const_value(NS_PER_DAY).into();
+        let days = builder.build_int_mul(lhs, ns_per_day, "daystmp");
+        let res = builder.build_int_add(days, rhs, "addtmp");
+        return ok(propagate_null(builder, context, lhs, rhs, res));
+    }
+
+    let result = match (op, int64_repr(&lhs_type), int64_repr(&rhs_type)) {
+        (Add, Int64, Int64) => {
+            let res = builder.build_int_add(lhs, rhs, "addtmp");
+            propagate_null(builder, context, lhs, rhs, res)
+        }
         (Add, Float64, Float64) => builder.build_float_add(lhs, rhs, "addtmp"),
-        (Div, Int64, Int64) => builder.build_int_div(lhs, rhs, "divtmp"),
+        (Div, Int64, Int64) => int_div(builder, context, lhs, rhs, false),
         (Div, Float64, Float64) => builder.build_float_div(lhs, rhs, "divtmp"),
-        (Sub, Int64, Int64) => builder.build_int_sub(lhs, rhs, "subtmp"),
+        (Sub, Int64, Int64) => {
+            let res = builder.build_int_sub(lhs, rhs, "subtmp");
+            propagate_null(builder, context, lhs, rhs, res)
+        }
         (Sub, Float64, Float64) => builder.build_float_sub(lhs, rhs, "subtmp"),
-        (Mul, Int64, Int64) => builder.build_int_mul(lhs, rhs, "multmp"),
+        (Mul, Int64, Int64) => {
+            let res = builder.build_int_mul(lhs, rhs, "multmp");
+            propagate_null(builder, context, lhs, rhs, res)
+        }
         (Mul, Float64, Float64) => builder.build_float_mul(lhs, rhs, "multmp"),
-        (Rem, Int64, Int64) => builder.build_rem(lhs, rhs, "remtmp"),
+        (Rem, Int64, Int64) => int_div(builder, context, lhs, rhs, true),
         (Rem, Float64, Float64) => builder.build_rem(lhs, rhs, "remtmp"),
         (Or, Int64, Int64) => builder.build_or(lhs, rhs, "ortmp"),
         (Or, Float64, Float64) => builder.build_or(lhs, rhs, "ortmp"),
@@ -99,23 +288,26 @@ pub fn compile<'a, 'b>(
         // (Shr, Int64, Int64) => self.builder.build_shr(lhs, rhs, "shrtmp"),
         // (Shr, Float64, Float64) => self.builder.build_shr(lhs, rhs, "shrtmp"),
         (Equal, Bool, Bool) => builder.build_int_compare(IP::EQ, lhs, rhs, "eqtmp"),
-        (Equal, Int64, Int64) => builder.build_int_compare(IP::EQ, lhs, rhs, "eqtmp"),
-        (Equal, Float64, Float64) => builder.build_float_compare(FP::UEQ, lhs, rhs, "eqtmp"),
+        (Equal, Int64, Int64) | (Equal, Sym, Sym) => builder.build_int_compare(IP::EQ, lhs, rhs, "eqtmp"),
+        (Equal, Float64, Float64) => float_equal(builder, lhs, rhs),
         (Less, Bool, Bool) => builder.build_int_compare(IP::SLT, lhs, rhs, "lttmp"),
         (Less, Int64, Int64) => builder.build_int_compare(IP::SLT, lhs, rhs, "lttmp"),
-        (Less, Float64, Float64) => builder.build_float_compare(FP::ULT, lhs, rhs, "lttmp"),
+        (Less, Float64, Float64) => float_less(builder, lhs, rhs, false),
         (LessOrEqual, Bool, Bool) => builder.build_int_compare(IP::SLE, lhs, rhs, "letmp"),
         (LessOrEqual, Int64, Int64) => builder.build_int_compare(IP::SLE, lhs, rhs, "letmp"),
-        (LessOrEqual, Float64, Float64) => builder.build_float_compare(FP::ULE, lhs, rhs, "letmp"),
+        (LessOrEqual, Float64, Float64) => float_less(builder, lhs, rhs, true),
         (Greater, Bool, Bool) => builder.build_int_compare(IP::SGT, lhs, rhs, "gttmp"),
         (Greater, Int64, Int64) => builder.build_int_compare(IP::SGT, lhs, rhs, "gttmp"),
-        (Greater, Float64, Float64) => builder.build_float_compare(FP::UGT, lhs, rhs, "gttmp"),
+        (Greater, Float64, Float64) => float_less(builder, rhs, lhs, false),
         (GreaterOrEqual, Bool, Bool) => builder.build_int_compare(IP::SGE, lhs, rhs, "getmp"),
         (GreaterOrEqual, Int64, Int64) => builder.build_int_compare(IP::SGE, lhs, rhs, "getmp"),
-        (GreaterOrEqual, Float64, Float64) => builder.build_float_compare(FP::UGE, lhs, rhs, "getmp"),
+        (GreaterOrEqual, Float64, Float64) => float_less(builder, rhs, lhs, true),
         (NotEqual, Bool, Bool) => builder.build_int_compare(IP::NE, lhs, rhs, "neqtmp"),
-        (NotEqual, Int64, Int64) => builder.build_int_compare(IP::NE, lhs, rhs, "neqtmp"),
-        (NotEqual, Float64, Float64) => builder.build_float_compare(FP::UNE, lhs, rhs, "neqtmp"),
+        (NotEqual, Int64, Int64) | (NotEqual, Sym, Sym) => builder.build_int_compare(IP::NE, lhs, rhs, "neqtmp"),
+        (NotEqual, Float64, Float64) => {
+            let eq = float_equal(builder, lhs, rhs);
+            builder.build_not(eq, "neqtmp")
+        }
         (op, _, _) => {
             return compile_error(
                 format!("Unsupported binary op: '{}'", op),
@@ -0,0 +1,39 @@
+//! The builtins writing values as JSON and reading them back, of any types. They are typed here and
+//! compiled into calls of the functions of `rt::json`.
+
+use crate::ops::table::TableCall;
+use crate::rt::json::*;
+use ffi::types::Type as BSType;
+use ffi::values::time::{Date, Time, Timespan, Timestamp};
+
+/// The call of `to_json` with an argument of the type `arg`, if it can be written.
+pub fn to_json(arg: &BSType) -> Option<TableCall> {
+    let call = |symbol, addr: usize| Some(TableCall { symbol, addr, ty: BSType::Str, raises: false });
+
+    match arg {
+        BSType::Int64 => call("bs.int_to_json", int_to_json as *const () as usize),
+        BSType::Float64 => call("bs.float_to_json", float_to_json as *const () as usize),
+        BSType::Bool => call("bs.bool_to_json", bool_to_json as *const () as usize),
+        BSType::Sym => call("bs.sym_to_json", sym_to_json as *const () as usize),
+        BSType::Str => call("bs.str_to_json", str_to_json as *const () as usize),
+        BSType::Date => call("bs.date_to_json", temporal_to_json::<Date> as *const () as usize),
+        BSType::Time => call("bs.time_to_json", temporal_to_json::<Time> as *const () as usize),
+        BSType::Timestamp => call("bs.timestamp_to_json", temporal_to_json::<Timestamp> as *const () as usize),
+        BSType::Timespan => call("bs.timespan_to_json", temporal_to_json::<Timespan> as *const () as usize),
+        ty if ty.is_vec() => call("bs.vec_to_json", vec_to_json as *const () as usize),
+        BSType::Dict(..) => call("bs.dict_to_json", dict_to_json as *const () as usize),
+        BSType::Table(_) => call("bs.table_to_json", table_to_json as *const () as usize),
+        BSType::List => call("bs.list_to_json", list_to_json as *const () as usize),
+        _ => None,
+    }
+}
+
+/// The call of `from_json` of a string and the name of the type `ty` of the value read.
+pub fn from_json(ty: BSType) -> TableCall {
+    let (symbol, addr) = match ty {
+        BSType::Float64 => ("bs.json_read_float", json_read_float as *const () as usize),
+        BSType::Bool => ("bs.json_read_bool", json_read_bool as *const () as usize),
+        _ => ("bs.json_read", json_read as *const () as usize),
+    };
+    TableCall { symbol, addr, ty, raises: true }
+}
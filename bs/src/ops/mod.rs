@@ -1,2 +1,5 @@
 pub mod binary;
+pub mod json;
+pub mod serial;
+pub mod table;
 pub mod unary;
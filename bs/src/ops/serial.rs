@@ -0,0 +1,72 @@
+//! The builtins serializing values to bytes in a binary format, or writing them to files, and
+//! reading them back, of any types. They are typed here and compiled into calls of the functions of
+//! `rt::serial`.
+
+use crate::ops::table::TableCall;
+use crate::rt::serial::*;
+use ffi::types::Type as BSType;
+use ffi::values::time::{Date, Time, Timespan, Timestamp};
+
+/// The call of `serialize` of a value, or of a path and a value, of the types `args`, if it can be
+/// written, returning its bytes or their number.
+pub fn serialize(args: &[BSType]) -> Option<TableCall> {
+    let (arg, file) = match args {
+        [arg] => (arg, false),
+        [BSType::Str, arg] => (arg, true),
+        _ => return None,
+    };
+    let ty = if file { BSType::Int64 } else { BSType::Bytes };
+    let call = |symbol, addr: usize| Some(TableCall { symbol, addr, ty, raises: true });
+
+    match (arg, file) {
+        (BSType::Int64, false) => call("bs.int_serialize", int_serialize as *const () as usize),
+        (BSType::Float64, false) => call("bs.float_serialize", float_serialize as *const () as usize),
+        (BSType::Bool, false) => call("bs.bool_serialize", bool_serialize as *const () as usize),
+        (BSType::Sym, false) => call("bs.sym_serialize", sym_serialize as *const () as usize),
+        (BSType::Str, false) => call("bs.str_serialize", str_serialize as *const () as usize),
+        (BSType::Date, false) => call("bs.date_serialize", temporal_serialize::<Date> as *const () as usize),
+        (BSType::Time, false) => call("bs.time_serialize", temporal_serialize::<Time> as *const () as usize),
+        (BSType::Timestamp, false) => {
+            call("bs.timestamp_serialize", temporal_serialize::<Timestamp> as *const () as usize)
+        }
+        (BSType::Timespan, false) => {
+            call("bs.timespan_serialize", temporal_serialize::<Timespan> as *const () as usize)
+        }
+        (ty, false) if ty.is_vec() => call("bs.vec_serialize", vec_serialize as *const () as usize),
+        (BSType::Dict(..), false) => call("bs.dict_serialize", dict_serialize as *const () as usize),
+        (BSType::Table(_), false) => call("bs.table_serialize", table_serialize as *const () as usize),
+        (BSType::List, false) => call("bs.list_serialize", list_serialize as *const () as usize),
+        (BSType::Int64, true) => call("bs.int_serialize_file", int_serialize_file as *const () as usize),
+        (BSType::Float64, true) => call("bs.float_serialize_file", float_serialize_file as *const () as usize),
+        (BSType::Bool, true) => call("bs.bool_serialize_file", bool_serialize_file as *const () as usize),
+        (BSType::Sym, true) => call("bs.sym_serialize_file", sym_serialize_file as *const () as usize),
+        (BSType::Str, true) => call("bs.str_serialize_file", str_serialize_file as *const () as usize),
+        (BSType::Date, true) => call("bs.date_serialize_file", temporal_serialize_file::<Date> as *const () as usize),
+        (BSType::Time, true) => call("bs.time_serialize_file", temporal_serialize_file::<Time> as *const () as usize),
+        (BSType::Timestamp, true) => {
+            call("bs.timestamp_serialize_file", temporal_serialize_file::<Timestamp> as *const () as usize)
+        }
+        (BSType::Timespan, true) => {
+            call("bs.timespan_serialize_file", temporal_serialize_file::<Timespan> as *const () as usize)
+        }
+        (ty, true) if ty.is_vec() => call("bs.vec_serialize_file", vec_serialize_file as *const () as usize),
+        (BSType::Dict(..), true) => call("bs.dict_serialize_file", dict_serialize_file as *const () as usize),
+        (BSType::Table(_), true) => call("bs.table_serialize_file", table_serialize_file as *const () as usize),
+        (BSType::List, true) => call("bs.list_serialize_file", list_serialize_file as *const () as usize),
+        _ => None,
+    }
+}
+
+/// The call of `deserialize` of bytes, or of a path if `arg` is a String, and the name of the type
+/// `ty` of the value read.
+pub fn deserialize(arg: &BSType, ty: BSType) -> TableCall {
+    let (symbol, addr) = match (&ty, *arg == BSType::Str) {
+        (BSType::Float64, false) => ("bs.deserialize_float", deserialize_float as *const () as usize),
+        (BSType::Bool, false) => ("bs.deserialize_bool", deserialize_bool as *const () as usize),
+        (_, false) => ("bs.deserialize", crate::rt::serial::deserialize as *const () as usize),
+        (BSType::Float64, true) => ("bs.deserialize_file_float", deserialize_file_float as *const () as usize),
+        (BSType::Bool, true) => ("bs.deserialize_file_bool", deserialize_file_bool as *const () as usize),
+        (_, true) => ("bs.deserialize_file", deserialize_file as *const () as usize),
+    };
+    TableCall { symbol, addr, ty, raises: true }
+}
@@ -0,0 +1,132 @@
+//! Builtins taking or returning a table, of any columns. They are typed here and compiled into
+//! calls of the functions of `rt::table`, `rt::join`, `rt::csv` and `rt::splay`.
+
+use crate::rt::csv::{csv_read, csv_write, csv_write_delim};
+use crate::rt::join::*;
+use crate::rt::splay::{splay_append, splay_read, splay_write};
+use crate::rt::table::*;
+use ffi::types::Type as BSType;
+
+/// A call of a builtin typed by `ops`: the name and address of the function it is compiled into,
+/// its result type and whether it can raise an error.
+pub struct TableCall {
+    pub symbol: &'static str,
+    pub addr: usize,
+    pub ty: BSType,
+    pub raises: bool,
+}
+
+/// Type of the table returned by `meta`.
+pub fn meta_type() -> BSType { BSType::Table(vec![("c".into(), BSType::VecSym), ("t".into(), BSType::VecSym)]) }
+
+/// The table builtin `name` called with arguments of types `args`, if there is one.
+pub fn call(name: &str, args: &[BSType]) -> Option<TableCall> {
+    let call = |symbol, addr: usize, ty, raises| Some(TableCall { symbol, addr, ty, raises });
+
+    match (name, args) {
+        ("count", [t]) if t.is_table() => {
+            call("bs.table_count", table_count as *const () as usize, BSType::Int64, false)
+        }
+        ("cols", [t]) if t.is_table() => call("bs.table_cols", table_cols as *const () as usize, BSType::VecSym, false),
+        ("meta", [t]) if t.is_table() => call("bs.table_meta", table_meta as *const () as usize, meta_type(), false),
+        ("at", [t, BSType::Int64]) if t.is_table() => {
+            call("bs.table_row", table_row as *const () as usize, t.clone(), true)
+        }
+        ("at", [t, BSType::VecInt64]) if t.is_table() => {
+            call("bs.table_rows", table_rows as *const () as usize, t.clone(), true)
+        }
+        ("write_csv", [BSType::Str, t]) if t.is_table() => {
+            call("bs.csv_write", csv_write as *const () as usize, BSType::Int64, true)
+        }
+        ("write_csv", [BSType::Str, t, BSType::Str]) if t.is_table() => {
+            call("bs.csv_write_delim", csv_write_delim as *const () as usize, BSType::Int64, true)
+        }
+        ("write_splayed", [BSType::Str, t]) if t.is_table() => {
+            call("bs.splay_write", splay_write as *const () as usize, BSType::Int64, true)
+        }
+        ("append_splayed", [BSType::Str, t]) if t.is_table() => {
+            call("bs.splay_append", splay_append as *const () as usize, BSType::Int64, true)
+        }
+        _ => None,
+    }
+}
+
+/// A call of `read_csv`, of the path, the letters of the types of the columns, the delimiter and
+/// the name of the type `ty` of the table, see `rt::csv`.
+pub fn read_csv(ty: BSType) -> TableCall {
+    TableCall { symbol: "bs.csv_read", addr: csv_read as *const () as usize, ty, raises: true }
+}
+
+/// A call of `read_splayed`, of the directory and the name of the type `ty` of the table, see
+/// `rt::splay`.
+pub fn read_splayed(ty: BSType) -> TableCall {
+    TableCall { symbol: "bs.splay_read", addr: splay_read as *const () as usize, ty, raises: true }
+}
+
+/// Names of the joins, which take the key columns as a symbol literal but `uj`, see `join`.
+pub const JOINS: &[&str] = &["lj", "ij", "uj", "aj"];
+
+/// The join `name` of two tables, of the types `args` following the symbol vector of the key
+/// columns, named by `keys` if it is a literal, but for `uj`. There is none if the arguments are
+/// not tables, and an error if their columns do not match.
+pub fn join(name: &str, keys: Option<&[String]>, args: &[BSType]) -> Option<Result<TableCall, String>> {
+    let call = |symbol, addr: usize, ty| TableCall { symbol, addr, ty, raises: name == "aj" };
+
+    let (left, right) = match (name, args) {
+        ("uj", [left, right]) if left.is_table() && right.is_table() => {
+            let res = joined_type(left, right, &[]).map(|ty| call("bs.table_uj", table_uj as *const () as usize, ty));
+            return Some(res);
+        }
+        ("lj" | "ij" | "aj", [BSType::VecSym, left, right]) if left.is_table() && right.is_table() => (left, right),
+        _ => return None,
+    };
+    let keys = match keys {
+        Some(keys) if !keys.is_empty() => keys,
+        _ => return Some(Err(format!("The key columns of '{}' must be named by a symbol literal", name))),
+    };
+
+    for key in keys {
+        match (left.column_type(key), right.column_type(key)) {
+            (Some(l), Some(r)) if l == r => {}
+            (Some(l), Some(r)) => {
+                return Some(Err(format!(
+                    "Key column '{}' is {} in the left table, but {} in the right one",
+                    key, l, r
+                )))
+            }
+            _ => return Some(Err(format!("Key column '{}' is not a column of both tables", key))),
+        }
+    }
+    if name == "aj" {
+        let time = keys.last().expect("time column");
+        if !left
+            .column_type(time)
+            .is_some_and(|ty| matches!(ty, BSType::VecInt64 | BSType::VecFloat64) || ty.is_temporal_vec())
+        {
+            return Some(Err(format!("The time column '{}' of 'aj' must be Int64, Float64, a date or a time", time)));
+        }
+    }
+
+    let res = joined_type(left, right, keys).map(|ty| match name {
+        "lj" => call("bs.table_lj", table_lj as *const () as usize, ty),
+        "ij" => call("bs.table_ij", table_ij as *const () as usize, ty),
+        _ => call("bs.table_aj", table_aj as *const () as usize, ty),
+    });
+    Some(res)
+}
+
+/// Type of the table of the columns of `left`, followed by those of `right` but `keys` and those
+/// of `left`, which must be of the same type.
+fn joined_type(left: &BSType, right: &BSType, keys: &[String]) -> Result<BSType, String> {
+    let mut columns = left.table_columns().to_vec();
+    for (name, ty) in right.table_columns().iter().filter(|(name, _)| !keys.contains(name)) {
+        match left.column_type(name) {
+            Some(l) if l != ty => {
+                return Err(format!("Column '{}' is {} in the left table, but {} in the right one", name, l, ty))
+            }
+            Some(_) => {}
+            None => columns.push((name.clone(), ty.clone())),
+        }
+    }
+    Ok(BSType::Table(columns))
+}
@@ -1,10 +1,16 @@
-use crate::ops::binary;
-use crate::parse::span::Span;
+use crate::builtins::REDUCTIONS;
+use crate::ops::{binary, json, serial, table};
+use crate::parse::parser::Parser;
+use crate::parse::span::{Source, Span};
 use crate::result::*;
+use crate::rt;
+use crate::rt::csv;
+use crate::rt::runtime::{overload_symbol, Globals};
 use ffi::types::Type as BSType;
-use ffi::values::Value as BSValue;
+use ffi::values::splay;
 use std::collections::HashMap;
 use std::fmt;
+use std::path::Path;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 #[repr(u8)]
@@ -51,33 +57,131 @@ impl fmt::Display for BinaryOp {
 pub enum ExprBody {
     Null,
 
-    Binary { op: BinaryOp, lhs: Box<Expr>, rhs: Box<Expr> },
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
 
-    Dot { lhs: Box<Expr>, rhs: Box<Expr> },
+    Dot {
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
 
-    Call { name: String, args: Vec<Expr> },
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
 
-    Cond { cond: Box<Expr>, cons: Vec<Expr>, altr: Vec<Expr> },
+    Cond {
+        cond: Box<Expr>,
+        cons: Vec<Expr>,
+        altr: Vec<Expr>,
+    },
 
-    For { var_name: String, start: Box<Expr>, end: Box<Expr>, step: Option<Box<Expr>>, body: Box<Expr> },
+    // evaluates `body`, or `handler` with the error message bound to `var` if `body` raises one
+    Try {
+        body: Vec<Expr>,
+        var: String,
+        handler: Vec<Expr>,
+    },
 
-    Assign { name: String, body: Box<Expr>, global: bool },
+    For {
+        var_name: String,
+        start: Box<Expr>,
+        end: Box<Expr>,
+        step: Option<Box<Expr>>,
+        body: Box<Expr>,
+    },
 
-    Iterator { res_type: BSType, count: usize },
+    Assign {
+        name: String,
+        body: Box<Expr>,
+        global: bool,
+    },
+
+    Iterator {
+        res_type: BSType,
+        count: usize,
+    },
 
     VecInt64(Vec<i64>),
 
     VecFloat64(Vec<f64>),
 
+    VecBool(Vec<bool>),
+
+    VecSym(Vec<String>),
+
+    /// Dates or times of the vector type, see `ffi::values::time`
+    VecTemporal(BSType, Vec<i64>),
+
+    Sym(String),
+
+    Str(String),
+
+    // table of the named columns, in order
+    Table {
+        columns: Vec<(String, Expr)>,
+    },
+
+    // column `name` of `table`
+    Column {
+        table: Box<Expr>,
+        name: String,
+    },
+
+    Query(Box<Query>),
+
     Bool(bool),
 
     Int64(i64),
 
     Float64(f64),
 
+    /// A date or time of the type, see `ffi::values::time`
+    Temporal(BSType, i64),
+
     Variable(String),
 }
 
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum QueryKind {
+    Select,
+    Exec,
+    Update,
+    Delete,
+}
+
+/// A q-style query of a table. Its expressions are evaluated for every row, with the columns of
+/// the table bound to the elements of the row, but for the columns reducing the rows of a group.
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub kind: QueryKind,
+    /// Columns computed, or deleted by `delete`.
+    pub columns: Vec<QueryColumn>,
+    /// Columns grouping the rows.
+    pub by: Vec<QueryColumn>,
+    pub table: Box<Expr>,
+    /// Conditions the rows are selected by, all of which must hold.
+    pub filter: Vec<Expr>,
+    /// Arguments of the reductions of the columns, lifted out of them when inferring their types.
+    /// The reductions take the vectors of the arguments over a group, named after the columns here.
+    pub reductions: Vec<QueryColumn>,
+}
+
+#[derive(Debug, Clone)]
+pub struct QueryColumn {
+    pub name: String,
+    pub expr: Expr,
+    /// Whether the column reduces the rows of a group to a single value.
+    pub reduced: bool,
+}
+
+impl QueryColumn {
+    pub fn new(name: String, expr: Expr) -> Self { QueryColumn { name, expr, reduced: false } }
+}
+
 #[derive(Debug, Clone)]
 pub struct Expr {
     pub body: ExprBody,
@@ -95,11 +199,60 @@ impl Expr {
         }
     }
 
-    pub fn infer_type(
-        &mut self,
-        globals: &HashMap<String, Box<BSValue>>,
-        variables: &mut HashMap<String, BSType>,
-    ) -> BSResult<BSType> {
+    /// Completes the arguments of the calls reading files, within the expression, by the types of
+    /// the values read, see `Function::complete_reads`.
+    fn complete_reads(&mut self) -> BSResult<()> {
+        use ExprBody::*;
+
+        match &mut self.body {
+            Call { name, args } => {
+                complete_all_reads(args)?;
+                match name.as_str() {
+                    "read_csv" => read_csv_type(args, self.span)?,
+                    "deserialize" | "read_splayed" => stored_type(name, args, self.span)?,
+                    _ => BSType::Null,
+                };
+                ok(())
+            }
+            Binary { lhs, rhs, .. } | Dot { lhs, rhs } => {
+                lhs.complete_reads()?;
+                rhs.complete_reads()
+            }
+            Cond { cond, cons, altr } => {
+                cond.complete_reads()?;
+                complete_all_reads(cons)?;
+                complete_all_reads(altr)
+            }
+            Try { body, handler, .. } => {
+                complete_all_reads(body)?;
+                complete_all_reads(handler)
+            }
+            For { start, end, step, body, .. } => {
+                start.complete_reads()?;
+                end.complete_reads()?;
+                if let Some(step) = step {
+                    step.complete_reads()?;
+                }
+                body.complete_reads()
+            }
+            Assign { body, .. } => body.complete_reads(),
+            Table { columns } => columns.iter_mut().try_for_each(|(_, e)| e.complete_reads()),
+            Column { table, .. } => table.complete_reads(),
+            Query(query) => {
+                query.table.complete_reads()?;
+                complete_all_reads(&mut query.filter)?;
+                query
+                    .columns
+                    .iter_mut()
+                    .chain(&mut query.by)
+                    .chain(&mut query.reductions)
+                    .try_for_each(|column| column.expr.complete_reads())
+            }
+            _ => ok(()),
+        }
+    }
+
+    pub fn infer_type(&mut self, globals: &Globals, variables: &mut HashMap<String, BSType>) -> BSResult<BSType> {
         use ExprBody::*;
 
         if let Some(ty) = &self.expr_type {
@@ -123,6 +276,10 @@ impl Expr {
                 self.expr_type = Some(BSType::Float64);
                 ok(BSType::Float64)
             }
+            Temporal(ref ty, _) => {
+                self.expr_type = Some(ty.clone());
+                ok(ty.clone())
+            }
             VecInt64(_) => {
                 self.expr_type = Some(BSType::VecInt64);
                 ok(BSType::VecInt64)
@@ -131,7 +288,71 @@ impl Expr {
                 self.expr_type = Some(BSType::VecFloat64);
                 ok(BSType::VecFloat64)
             }
-            Assign { name, body, global } => {
+            VecBool(_) => {
+                self.expr_type = Some(BSType::VecBool);
+                ok(BSType::VecBool)
+            }
+            VecSym(_) => {
+                self.expr_type = Some(BSType::VecSym);
+                ok(BSType::VecSym)
+            }
+            VecTemporal(ref ty, _) => {
+                self.expr_type = Some(ty.clone());
+                ok(ty.clone())
+            }
+            Sym(_) => {
+                self.expr_type = Some(BSType::Sym);
+                ok(BSType::Sym)
+            }
+            Str(_) => {
+                self.expr_type = Some(BSType::Str);
+                ok(BSType::Str)
+            }
+            Table { columns } => {
+                let mut types: Vec<(String, BSType)> = vec![];
+                for (name, column) in columns.iter_mut() {
+                    let ty = column.infer_type(globals, variables)?;
+                    if !ty.is_vec() {
+                        return compile_error(
+                            "Invalid table".to_string(),
+                            format!("Column '{}' is of type {}, but table columns must be vectors", name, ty),
+                            column.span,
+                        );
+                    }
+                    if types.iter().any(|(n, _)| n == name) {
+                        return compile_error(
+                            "Invalid table".to_string(),
+                            format!("Column '{}' is defined twice", name),
+                            column.span,
+                        );
+                    }
+                    types.push((name.clone(), ty));
+                }
+                let ty = BSType::Table(types);
+                self.expr_type = Some(ty.clone());
+                ok(ty)
+            }
+            Column { table, name } => {
+                let table_ty = table.infer_type(globals, variables)?;
+                match table_ty.column_type(name) {
+                    Some(ty) => {
+                        let ty = ty.clone();
+                        self.expr_type = Some(ty.clone());
+                        ok(ty)
+                    }
+                    None => compile_error(
+                        "Unknown column".to_string(),
+                        format!("{} has no column '{}'", table_ty, name),
+                        self.span,
+                    ),
+                }
+            }
+            Query(query) => {
+                let ty = query.infer_type(globals, variables, self.span)?;
+                self.expr_type = Some(ty.clone());
+                ok(ty)
+            }
+            Assign { name, body, .. } => {
                 let body_ty = body.infer_type(globals, variables)?;
                 self.expr_type = Some(body_ty.clone());
                 variables.insert(name.clone(), body_ty.clone());
@@ -142,7 +363,7 @@ impl Expr {
                     self.expr_type = Some(ty.clone());
                     ok(ty.clone())
                 }
-                None => match globals.get(name) {
+                None => match globals.get_value(name) {
                     Some(val) => {
                         self.expr_type = Some(val.get_infered_type().clone());
                         ok(val.get_infered_type().clone())
@@ -153,20 +374,111 @@ impl Expr {
             Binary { op, ref mut lhs, ref mut rhs } => {
                 let lhs_type = lhs.infer_type(globals, variables)?;
                 let rhs_type = rhs.infer_type(globals, variables)?;
+
+                // dictionaries are combined by the builtins named after the operator
+                if lhs_type.is_dict() || rhs_type.is_dict() {
+                    let args = vec![lhs.as_ref().clone(), rhs.as_ref().clone()];
+                    self.body = Call { name: op.to_string(), args };
+                    return self.infer_type(globals, variables);
+                }
+
                 let res_type = binary::infer_type(*op, lhs_type, rhs_type, self.span)?;
                 self.expr_type = Some(res_type.clone());
                 ok(res_type)
             }
 
             Call { name, args } => {
+                // the key columns of a join are named by a symbol, or a symbol vector
+                if table::JOINS.contains(&name.as_str()) {
+                    if let Some(Expr { body: Sym(key), .. }) = args.first_mut() {
+                        args[0].body = VecSym(vec![key.clone()]);
+                    }
+                }
+                if matches!(name.as_str(), "read_csv" | "from_json" | "deserialize" | "read_splayed") {
+                    let ty = match name.as_str() {
+                        "read_csv" => read_csv_type(args, self.span)?,
+                        "from_json" => from_json_type(args, self.span)?,
+                        _ => stored_type(name, args, self.span)?,
+                    };
+                    infer_types(args, globals, variables)?;
+                    match (name.as_str(), args[0].get_type()?) {
+                        (_, BSType::Str) | ("deserialize", BSType::Bytes) => {}
+                        ("deserialize", _) => {
+                            let desc = "The first argument of 'deserialize' must be a String or Bytes".to_string();
+                            return compile_error("Invalid arguments".to_string(), desc, self.span);
+                        }
+                        _ => {
+                            let desc = format!("The first argument of '{}' must be a String", name);
+                            return compile_error("Invalid arguments".to_string(), desc, self.span);
+                        }
+                    }
+                    self.expr_type = Some(ty.clone());
+                    return ok(ty);
+                }
                 infer_types(args, globals, variables)?;
-                match globals.get(name) {
-                    Some(val) => {
-                        self.expr_type = Some(val.get_infered_type().clone());
-                        ok(val.get_infered_type().clone())
+
+                let mut arg_types = vec![];
+                for arg in args.iter() {
+                    arg_types.push(arg.get_type()?);
+                }
+
+                // builtins taking a table of any columns
+                if let Some(call) = table::call(name, &arg_types) {
+                    self.expr_type = Some(call.ty.clone());
+                    return ok(call.ty);
+                }
+
+                if let (true, [arg]) = (name == "to_json", arg_types.as_slice()) {
+                    if json::to_json(arg).is_some() {
+                        self.expr_type = Some(BSType::Str);
+                        return ok(BSType::Str);
+                    }
+                }
+                if let (true, Some(call)) = (name == "serialize", serial::serialize(&arg_types)) {
+                    self.expr_type = Some(call.ty.clone());
+                    return ok(call.ty);
+                }
+
+                match table::join(name, join_keys(args), &arg_types) {
+                    Some(Ok(call)) => {
+                        self.expr_type = Some(call.ty.clone());
+                        return ok(call.ty);
+                    }
+                    Some(Err(msg)) => return compile_error("Invalid join".to_string(), msg, self.span),
+                    None => {}
+                }
+
+                // length of a vector, compiled inline
+                if name == "len" && args.len() == 1 && args[0].get_type()?.is_vec() {
+                    self.expr_type = Some(BSType::Int64);
+                    return ok(BSType::Int64);
+                }
+
+                // element of a vector, compiled inline with a bounds check
+                if name == "at" && args.len() == 2 && args[0].get_type()?.is_vec() {
+                    if args[1].get_type()? != BSType::Int64 {
+                        return compile_error(
+                            "Invalid arguments".to_string(),
+                            format!("The index of 'at' must be Int64, but '{}' was given", args[1].get_type()?),
+                            self.span,
+                        );
                     }
-                    None => compile_error("Unknown function".to_string(), name.clone(), self.span),
+                    let ty = args[0].get_type()?.elem_type();
+                    self.expr_type = ty.clone();
+                    return ok(ty.expect("vector"));
                 }
+
+                let ty = match globals.generic(name, &arg_types) {
+                    Some(generic) => generic
+                        .specialize(&arg_types, self.span)?
+                        .infer_specialization(globals, self.span)?,
+                    None => {
+                        let symbol = globals.resolve(name, &arg_types, self.span)?;
+                        globals.get_value(&symbol).unwrap().get_infered_type()
+                    }
+                };
+                self.expr_type = Some(ty.clone());
+                ok(ty)
             }
 
             Cond { cond, cons, altr } => {
@@ -194,7 +506,24 @@ impl Expr {
                 ok(cons_type)
             }
 
-            Iterator { res_type, count } => {
+            Try { body, var, handler } => {
+                let body_type = infer_types(body, globals, variables)?;
+                variables.insert(var.clone(), BSType::Str);
+                let handler_type = infer_types(handler, globals, variables)?;
+
+                if body_type != handler_type {
+                    return compile_error(
+                        "Both branches of try must have the same type".to_string(),
+                        format!("Found {} in the try branch and {} in the catch branch", body_type, handler_type),
+                        self.span,
+                    );
+                }
+
+                self.expr_type = Some(body_type.clone());
+                ok(body_type)
+            }
+
+            Iterator { res_type, .. } => {
                 self.expr_type = Some(res_type.clone());
                 ok(res_type.clone())
             }
@@ -208,11 +537,356 @@ impl Expr {
     }
 }
 
-pub fn infer_types(
-    exprs: &mut [Expr],
-    globals: &HashMap<String, Box<BSValue>>,
-    variables: &mut HashMap<String, BSType>,
-) -> BSResult<BSType> {
+impl Query {
+    fn infer_type(
+        &mut self,
+        globals: &Globals,
+        variables: &mut HashMap<String, BSType>,
+        span: Option<Span>,
+    ) -> BSResult<BSType> {
+        let table_ty = self.table.infer_type(globals, variables)?;
+        let table_columns = match &table_ty {
+            BSType::Table(columns) => columns.clone(),
+            ty => {
+                return compile_error(
+                    "Invalid query".to_string(),
+                    format!("Queries read a table, but {} was given", ty),
+                    self.table.span,
+                )
+            }
+        };
+
+        // expressions of a row see its elements in place of the columns
+        let mut row = variables.clone();
+        for (name, ty) in &table_columns {
+            row.insert(name.clone(), ty.elem_type().expect("vector"));
+        }
+
+        for cond in self.filter.iter_mut() {
+            let ty = cond.infer_type(globals, &mut row)?;
+            if ty != BSType::Bool {
+                return compile_error(
+                    "Invalid query".to_string(),
+                    format!("Conditions of where must be Bool, but {} was given", ty),
+                    cond.span,
+                );
+            }
+        }
+        for key in self.by.iter_mut() {
+            key.expr.infer_type(globals, &mut row)?;
+            key.vec_type()?;
+        }
+
+        if self.kind == QueryKind::Delete {
+            return self.infer_delete(table_ty);
+        }
+
+        for column in self.columns.iter_mut() {
+            let lifted = self.reductions.len();
+            column.reduced = lift_reductions(&mut column.expr, &mut self.reductions);
+
+            for arg in self.reductions[lifted..].iter_mut() {
+                arg.expr.infer_type(globals, &mut row)?;
+            }
+
+            // a reduced column sees the vectors of the arguments of its reductions, but no row
+            let mut reduced = variables.clone();
+            for arg in &self.reductions {
+                reduced.insert(arg.name.clone(), arg.vec_type()?);
+            }
+            match column.reduced {
+                true => column.expr.infer_type(globals, &mut reduced)?,
+                false => column.expr.infer_type(globals, &mut row)?,
+            };
+            column.vec_type()?;
+        }
+
+        let all_reduced = self.columns.iter().all(|c| c.reduced);
+        let any_reduced = self.columns.iter().any(|c| c.reduced);
+        let invalid = |msg: &str| compile_error("Invalid query".to_string(), msg.to_string(), span);
+
+        match self.kind {
+            QueryKind::Select if self.columns.is_empty() => match self.by.is_empty() {
+                true => ok(table_ty),
+                false => invalid("Columns must be selected by group"),
+            },
+            QueryKind::Select => {
+                if self.by.is_empty() && any_reduced && !all_reduced {
+                    return invalid(
+                        "Columns reducing the rows can not be selected along with the rows, unless by group",
+                    );
+                }
+                if !self.by.is_empty() && !all_reduced {
+                    return invalid("Columns selected by group must reduce its rows, e.g. with sum or last");
+                }
+
+                let mut types: Vec<(String, BSType)> = vec![];
+                for column in self.by.iter().chain(&self.columns) {
+                    if types.iter().any(|(name, _)| *name == column.name) {
+                        return invalid(&format!("Column '{}' is selected twice", column.name));
+                    }
+                    types.push((column.name.clone(), column.vec_type()?));
+                }
+                ok(BSType::Table(types))
+            }
+            QueryKind::Exec => {
+                if self.columns.len() != 1 {
+                    return invalid("exec computes a single column");
+                }
+                let column = &self.columns[0];
+                match (self.by.as_slice(), column.reduced) {
+                    ([], true) => column.expr.get_type(),
+                    ([], false) => column.vec_type(),
+                    ([key], true) => ok(BSType::dict(key.vec_type()?, column.vec_type()?)),
+                    ([_], false) => invalid("Columns computed by group must reduce its rows, e.g. with sum or last"),
+                    _ => invalid("exec groups by a single column"),
+                }
+            }
+            _ => {
+                let mut types = table_columns;
+                for column in &self.columns {
+                    let ty = column.vec_type()?;
+                    match types.iter().find(|(name, _)| *name == column.name) {
+                        Some((_, old)) if *old != ty => {
+                            return invalid(&format!(
+                                "Column '{}' is of type {}, but is updated with {}",
+                                column.name, old, ty
+                            ))
+                        }
+                        Some(_) => {}
+                        None => types.push((column.name.clone(), ty)),
+                    }
+                }
+                ok(BSType::Table(types))
+            }
+        }
+    }
+
+    /// Type of a `delete` query, which removes either columns or the rows selected.
+    fn infer_delete(&self, table_ty: BSType) -> BSResult<BSType> {
+        let invalid = |msg: String, span| compile_error("Invalid query".to_string(), msg, span);
+        if !self.by.is_empty() {
+            return invalid("delete does not group rows".to_string(), self.by[0].expr.span);
+        }
+        if !self.columns.is_empty() && !self.filter.is_empty() {
+            return invalid("delete removes either columns or rows".to_string(), self.filter[0].span);
+        }
+
+        let mut types = match table_ty {
+            BSType::Table(types) => types,
+            _ => unreachable!(),
+        };
+        for column in &self.columns {
+            match (&column.expr.body, types.iter().position(|(name, _)| *name == column.name)) {
+                (ExprBody::Variable(_), Some(i)) => {
+                    types.remove(i);
+                }
+                _ => return invalid(format!("Can not delete the column '{}'", column.name), column.expr.span),
+            }
+        }
+        ok(BSType::Table(types))
+    }
+}
+
+impl QueryColumn {
+    /// Type of the vector of the values of the column.
+    fn vec_type(&self) -> BSResult<BSType> {
+        let ty = self.expr.get_type()?;
+        match ty.vec_type() {
+            Some(ty) => ok(ty),
+            None => compile_error(
+                "Invalid query".to_string(),
+                format!(
+                    "Column '{}' is of type {}, but query columns must be Int64, Float64, Bool, Symbol, dates or times",
+                    self.name, ty
+                ),
+                self.expr.span,
+            ),
+        }
+    }
+}
+
+/// Replaces the argument of every reduction in `expr` by a variable, named after the argument
+/// added to `reductions`, and returns whether there was any.
+fn lift_reductions(expr: &mut Expr, reductions: &mut Vec<QueryColumn>) -> bool {
+    let span = expr.span;
+    match &mut expr.body {
+        ExprBody::Call { name, args } if args.len() == 1 && REDUCTIONS.contains(&name.as_str()) => {
+            let name = format!("#{}", reductions.len());
+            let arg = std::mem::replace(&mut args[0], Expr::new(ExprBody::Variable(name.clone()), span));
+            reductions.push(QueryColumn::new(name, arg));
+            true
+        }
+        ExprBody::Call { args, .. } => lift_all_reductions(args, reductions),
+        ExprBody::Binary { lhs, rhs, .. } => {
+            let lhs = lift_reductions(lhs, reductions);
+            lift_reductions(rhs, reductions) || lhs
+        }
+        ExprBody::Cond { cond, cons, altr } => {
+            let cond = lift_reductions(cond, reductions);
+            let cons = lift_all_reductions(cons, reductions);
+            lift_all_reductions(altr, reductions) || cons || cond
+        }
+        _ => false,
+    }
+}
+
+fn lift_all_reductions(exprs: &mut [Expr], reductions: &mut Vec<QueryColumn>) -> bool {
+    exprs
+        .iter_mut()
+        .fold(false, |lifted, e| lift_reductions(e, reductions) | lifted)
+}
+
+fn complete_all_reads(exprs: &mut [Expr]) -> BSResult<()> { exprs.iter_mut().try_for_each(Expr::complete_reads) }
+
+/// Type of the table read by `read_csv(path, types, delim)`, named by `types` if it is the name of
+/// a table type, the columns of which are read by name, or else read from the header of the file,
+/// and its first rows unless there are letters of `types`. The types and delimiter are optional
+/// string literals, and the path is one unless the table type is named. The arguments are
+/// completed by the letters of the types of the columns, empty if they are read by name, the
+/// delimiter and the name of the type, which the table read when running is checked against.
+fn read_csv_type(args: &mut Vec<Expr>, span: Option<Span>) -> BSResult<BSType> {
+    let invalid = |desc: String| compile_error("Invalid arguments".to_string(), desc, span);
+
+    let mut strs = vec![];
+    for arg in args.iter().skip(1) {
+        match &arg.body {
+            ExprBody::Str(s) => strs.push(s.as_str()),
+            _ => return invalid("The column types and delimiter of 'read_csv' must be string literals".to_string()),
+        }
+    }
+    let table_type = |s: &str| match Parser::parse_type_str(s) {
+        BSResult::Ok(ty) if ty.is_table() => Some(ty),
+        _ => None,
+    };
+    let (types, delim, ty) = match strs.as_slice() {
+        [] => (None, ",", None),
+        [types, delim, ty] => match table_type(ty) {
+            Some(ty) => (Some(*types), *delim, Some(ty)),
+            None => return invalid(format!("'{}' is not a table type", ty)),
+        },
+        [types, delim @ ..] if delim.len() < 2 => match table_type(types) {
+            Some(ty) => (Some(""), delim.first().copied().unwrap_or(","), Some(ty)),
+            None => (Some(*types), delim.first().copied().unwrap_or(","), None),
+        },
+        _ => {
+            return invalid(
+                "'read_csv' takes a path, and optionally column types or a table type, and a delimiter".to_string(),
+            )
+        }
+    };
+    let delim = match csv::delimiter(delim) {
+        Ok(delim) => delim,
+        Err(msg) => return invalid(msg),
+    };
+    if let Some(Err(msg)) = types.map(|types| types.chars().try_for_each(|c| csv::column_type(c).map(|_| ()))) {
+        return invalid(msg);
+    }
+
+    let (ty, types) = match (ty, &args[0].body) {
+        (Some(BSType::Table(columns)), _) => {
+            if let Some((_, ty)) = columns.iter().find(|(_, ty)| csv::column_letter(ty).is_none()) {
+                return invalid(format!("Columns of type {} can not be read from CSV", ty));
+            }
+            (BSType::Table(columns), types.unwrap_or_default().to_string())
+        }
+        (_, ExprBody::Str(path)) => match csv::schema(path, types, delim) {
+            Ok((columns, types)) => (BSType::Table(columns), types),
+            Err(err) => return io_error(err.msg, path.clone(), err.line, span),
+        },
+        _ => {
+            let desc = "The table type of 'read_csv' must be given unless the path is a literal".to_string();
+            return invalid(desc);
+        }
+    };
+
+    let arg = |s: String| Expr::new(ExprBody::Str(s), span);
+    args.truncate(1);
+    args.extend([arg(types), arg((delim as char).to_string()), arg(ty.to_string())]);
+    ok(ty)
+}
+
+/// Type of the value read by `from_json(text, ty)`, named by the string literal `ty`, or else
+/// inferred from the string literal `text`. The arguments are completed by the name of the type.
+fn from_json_type(args: &mut Vec<Expr>, span: Option<Span>) -> BSResult<BSType> {
+    let invalid = |desc: String| compile_error("Invalid arguments".to_string(), desc, span);
+
+    let ty = match args.iter().map(|arg| &arg.body).collect::<Vec<_>>().as_slice() {
+        [ExprBody::Str(text)] => match rt::json::parse(text).and_then(|json| rt::json::infer_type(&json)) {
+            Ok(ty) => ty,
+            Err(msg) => return compile_error("Invalid JSON".to_string(), msg, span),
+        },
+        [_, ExprBody::Str(ty)] => match Parser::parse_type_str(ty) {
+            BSResult::Ok(ty) => ty,
+            BSResult::Err(_) => return invalid(format!("'{}' is not a valid type", ty)),
+        },
+        [_] => return invalid("The type of 'from_json' must be given unless the text is a literal".to_string()),
+        _ => return invalid("'from_json' takes a String, and optionally the name of a type".to_string()),
+    };
+    if !rt::json::is_readable(&ty) {
+        return invalid(format!("Values of type {} can not be read from JSON", ty));
+    }
+    // the type is read by name when running
+    if !matches!(Parser::parse_type_str(&ty.to_string()), BSResult::Ok(ref named) if *named == ty) {
+        return invalid(format!("Type {} has column names which can not be read", ty));
+    }
+
+    args.truncate(1);
+    args.push(Expr::new(ExprBody::Str(ty.to_string()), span));
+    ok(ty)
+}
+
+/// Type of the value read by `deserialize(bytes, ty)` or `deserialize(path, ty)`, or the table read
+/// by `read_splayed(path, ty)`, named by the string literal `ty`, or else read from the file or
+/// directory at the string literal `path`, once, see `Function::complete_reads`. The arguments are
+/// completed by the name of the type.
+fn stored_type(name: &str, args: &mut Vec<Expr>, span: Option<Span>) -> BSResult<BSType> {
+    let invalid = |desc: String| compile_error("Invalid arguments".to_string(), desc, span);
+
+    let ty = match args.iter().map(|arg| &arg.body).collect::<Vec<_>>().as_slice() {
+        [ExprBody::Str(path)] => {
+            let ty = match name {
+                "deserialize" => rt::serial::file_type(path),
+                _ => splay::read_type(Path::new(path)),
+            };
+            match ty {
+                Ok(ty) => ty,
+                Err(msg) => return io_error(msg, path.clone(), None, span),
+            }
+        }
+        [_, ExprBody::Str(ty)] => match Parser::parse_type_str(ty) {
+            BSResult::Ok(ty) => ty,
+            BSResult::Err(_) => return invalid(format!("'{}' is not a valid type", ty)),
+        },
+        [_] => return invalid(format!("The type of '{}' must be given unless the path is a literal", name)),
+        _ => return invalid(format!("'{}' takes a path, and optionally the name of a type", name)),
+    };
+    match name {
+        "deserialize" if serial::serialize(std::slice::from_ref(&ty)).is_none() => {
+            return invalid(format!("Values of type {} can not be deserialized", ty))
+        }
+        "read_splayed" if !ty.is_table() => return invalid(format!("'read_splayed' reads a table, not {}", ty)),
+        _ => {}
+    }
+    // the type is read by name when running
+    if !matches!(Parser::parse_type_str(&ty.to_string()), BSResult::Ok(ref named) if *named == ty) {
+        return invalid(format!("Type {} has column names which can not be read", ty));
+    }
+
+    args.truncate(1);
+    args.push(Expr::new(ExprBody::Str(ty.to_string()), span));
+    ok(ty)
+}
+
+/// Key columns of a join, if they are named by a symbol literal, see `ops::table::join`.
+pub fn join_keys(args: &[Expr]) -> Option<&[String]> {
+    match args.first().map(|arg| &arg.body) {
+        Some(ExprBody::VecSym(keys)) => Some(keys),
+        _ => None,
+    }
+}
+
+pub fn infer_types(exprs: &mut [Expr], globals: &Globals, variables: &mut HashMap<String, BSType>) -> BSResult<BSType> {
     let mut res_ty = BSType::Null;
     for e in exprs {
         res_ty = e.infer_type(globals, variables)?;
@@ -223,7 +897,97 @@ pub fn infer_types(
 #[derive(Clone, Debug)]
 pub struct Function {
     pub name: String,
+    /// Type parameters of a generic function, which is compiled once per binding of them.
+    pub params: Vec<String>,
     pub args: Vec<(String, BSType)>,
+    pub ret: Option<BSType>,
     pub body: Vec<Expr>,
     pub topl: bool,
+    /// Shared library an `extern` function is loaded from.
+    pub lib: Option<String>,
+    /// Input the function is parsed from, which its spans point into.
+    pub source: Option<Source>,
+}
+
+impl Function {
+    /// Name the function is compiled as, which tells the overloads of its name apart.
+    pub fn symbol(&self) -> String {
+        match self.topl {
+            true => self.name.clone(),
+            false => overload_symbol(&self.name, &self.arg_types()),
+        }
+    }
+
+    pub fn arg_types(&self) -> Vec<BSType> { self.args.iter().map(|(_, ty)| ty.clone()).collect() }
+
+    pub fn is_generic(&self) -> bool { !self.params.is_empty() }
+
+    /// Completes the arguments of the calls reading the files named by literals by the types of the
+    /// values read, so that the files are read once, when the function is defined, rather than
+    /// whenever it is recompiled.
+    pub fn complete_reads(&mut self) -> BSResult<()> { complete_all_reads(&mut self.body) }
+
+    /// Specializes the generic function for a call with arguments of types `args`, binding every
+    /// type parameter to the type of the arguments declared with it.
+    pub fn specialize(&self, args: &[BSType], span: Option<Span>) -> BSResult<Function> {
+        if self.args.len() != args.len() {
+            return compile_error(
+                format!("'{}' takes {} arguments, but {} were given", self.name, self.args.len(), args.len()),
+                format!("'{}' is generic over {}", self.name, self.params.join(", ")),
+                span,
+            );
+        }
+
+        let mut bindings: HashMap<&str, &BSType> = HashMap::new();
+        for (i, ((_, param), arg)) in self.args.iter().zip(args).enumerate() {
+            let expected = match param {
+                BSType::Param(name) => *bindings.entry(name.as_str()).or_insert(arg),
+                ty => ty,
+            };
+            if expected != arg {
+                return compile_error(
+                    "Invalid arguments".into(),
+                    format!("Argument {} of '{}' is of type '{}', but '{}' was given", i, self.name, expected, arg),
+                    span,
+                );
+            }
+        }
+
+        let bind = |ty: &BSType| match ty {
+            BSType::Param(name) => bindings[name.as_str()].clone(),
+            ty => ty.clone(),
+        };
+
+        ok(Function {
+            name: self.name.clone(),
+            params: vec![],
+            args: self.args.iter().map(|(name, ty)| (name.clone(), bind(ty))).collect(),
+            ret: self.ret.as_ref().map(bind),
+            body: self.body.clone(),
+            topl: false,
+            lib: None,
+            source: self.source.clone(),
+        })
+    }
+
+    /// Infers the return type of a specialization, see `Globals::begin_specialization`.
+    pub fn infer_specialization(&self, globals: &Globals, span: Option<Span>) -> BSResult<BSType> {
+        if let Some(ret) = &self.ret {
+            return ok(ret.clone());
+        }
+
+        let symbol = self.symbol();
+        if !globals.begin_specialization(&symbol) {
+            return compile_error(
+                format!("Can not infer the return type of '{}'", self.name),
+                format!("{} calls itself, so its return type must be declared", symbol),
+                span,
+            );
+        }
+
+        let mut variables = self.args.iter().cloned().collect();
+        let ret = infer_types(&mut self.body.clone(), globals, &mut variables);
+        globals.end_specialization(&symbol);
+        ret
+    }
 }
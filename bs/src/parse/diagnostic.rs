@@ -1,4 +1,4 @@
-use crate::parse::span::Span;
+use crate::parse::span::{Source, Span};
 use crate::result::BSError;
 use colored::Colorize;
 use std::fmt;
@@ -31,8 +31,25 @@ impl fmt::Display for Diagnostic<'_> {
                 }
                 None => format_diagnoistic_header("CompileError", msg, f),
             },
-            BSError::RuntimeError(msg) => format_diagnoistic_header("RuntimeError", msg, f),
-            _ => write!(f, "E: {}", ""),
+            BSError::RuntimeError { msg, span } => match span {
+                Some(span) => {
+                    format_diagnostic(self.name, self.input, "RuntimeError", msg, "Raised here", span, f)
+                }
+                None => format_diagnoistic_header("RuntimeError", msg, f),
+            },
+            BSError::IOError { msg, path, line, span } => {
+                let location = match line {
+                    Some(line) => format!("{}:{}", path, line),
+                    None => path.clone(),
+                };
+                match span {
+                    Some(span) => format_diagnostic(self.name, self.input, "IOError", msg, &location, span, f),
+                    None => {
+                        format_diagnoistic_header("IOError", msg, f)?;
+                        writeln!(f, "  {} {}", "-->".blue().bold(), location)
+                    }
+                }
+            }
         }
     }
 }
@@ -50,7 +67,20 @@ fn format_diagnostic(
     span: &Span,
     f: &mut fmt::Formatter<'_>,
 ) -> fmt::Result {
-    let line = &input[span.line_start..span.line_end];
+    // the span may point into the source of a function defined by an earlier input, which is
+    // unknown once the function is no longer defined
+    let source = Source::text(span.source);
+    let input = match (span.source, &source) {
+        (0, _) => Some(input),
+        (_, source) => source.as_deref(),
+    };
+    let line = match input.and_then(|input| input.get(span.line_start..span.line_end)) {
+        Some(line) => line,
+        None => {
+            format_diagnoistic_header(tag, msg, f)?;
+            return writeln!(f, "  {}", desc);
+        }
+    };
     let lbl_start = span.label_start.wrapping_sub(span.line_start);
     let lbl_end = span.label_end.wrapping_sub(span.line_start);
 
@@ -79,7 +109,7 @@ fn format_diagnostic(
     // write line
     write!(
         f,
-        "{:<3}{} {}   {} {} {}",
+        "{:<3}{} {}\n   {} {} {}",
         format!("{}", span.line_number).blue().bold(),
         "|".blue().bold(),
         line,
@@ -1,5 +1,8 @@
-use crate::parse::span::Span;
+use crate::parse::span::{Source, Span};
 use crate::result::*;
+use ffi::types::Type as BSType;
+use ffi::values::time::*;
+use ffi::values::{Value, NULL_FLOAT64, NULL_INT64};
 use std::fmt;
 use std::iter::Peekable;
 use std::ops::DerefMut;
@@ -11,8 +14,10 @@ pub enum Token<'a> {
     Comment(&'a str), // #asdfasdf
     Ident(&'a str),   // asdfasdf
     Bool(bool),       // true, false
-    Int64(i64),       // 123
-    Float64(f64),     // 123.123
+    Int64(i64),       // 123, 0N
+    Float64(f64),     // 123.123, 0n
+    Str(String),      // "asdf", unescaped
+    Sym(&'a str),     // `asdf
     LeftParen,        // (
     RightParen,       // )
     LeftSquare,       // [
@@ -39,6 +44,7 @@ pub enum Token<'a> {
     Or,               // ||
     And,              // &&
     Minus,            // -
+    Arrow,            // ->
     Plus,             // +
     Asterisk,         // *
     Slash,            // /
@@ -51,8 +57,12 @@ pub enum Token<'a> {
     Extern,           // extern
     If,               // if
     Else,             // else
+    Try,              // try
+    Catch,            // catch
     Null,             // null
     EOF,              // end of input
+    // dates and times, as in 2026.10.18, 09:30:00.000, 2026.10.18D09:30:00.000, 0D01:00:00, 0Nd
+    Temporal(BSType, i64),
 }
 
 impl<'a> fmt::Display for Token<'a> {
@@ -63,6 +73,9 @@ impl<'a> fmt::Display for Token<'a> {
             Token::Bool(b) => write!(f, "{}", b),
             Token::Int64(i) => write!(f, "{}", i),
             Token::Float64(v) => write!(f, "{}", v),
+            Token::Temporal(ref ty, v) => write!(f, "{}", Value::from_raw_parts(ty.clone(), v)),
+            Token::Str(ref s) => write!(f, "{:?}", s),
+            Token::Sym(s) => write!(f, "`{}", s),
             Token::LeftParen => write!(f, "("),
             Token::RightParen => write!(f, ")"),
             Token::LeftSquare => write!(f, "["),
@@ -89,6 +102,7 @@ impl<'a> fmt::Display for Token<'a> {
             Token::Or => write!(f, "||"),
             Token::And => write!(f, "&&"),
             Token::Minus => write!(f, "-"),
+            Token::Arrow => write!(f, "->"),
             Token::Plus => write!(f, "+"),
             Token::Asterisk => write!(f, "*"),
             Token::Slash => write!(f, "/"),
@@ -101,12 +115,100 @@ impl<'a> fmt::Display for Token<'a> {
             Token::Extern => write!(f, "extern"),
             Token::If => write!(f, "if"),
             Token::Else => write!(f, "else"),
+            Token::Try => write!(f, "try"),
+            Token::Catch => write!(f, "catch"),
             Token::Null => write!(f, "null"),
             Token::EOF => write!(f, "EOF"),
         }
     }
 }
 
+/// Number of digits at the start of `s`.
+fn digits(s: &[u8]) -> usize { s.iter().take_while(|c| c.is_ascii_digit()).count() }
+
+/// Value of the digits `s`, if it does not overflow.
+fn number(s: &[u8]) -> Option<i64> {
+    s.iter()
+        .try_fold(0i64, |n, c| n.checked_mul(10)?.checked_add((c - b'0') as i64))
+}
+
+/// Nanoseconds of the time of day `hh:mm[:ss[.fraction]]` at the start of `s`, if it is valid, and
+/// its length.
+fn time_of_day(s: &[u8]) -> Option<(Option<i64>, usize)> {
+    let two_digits = |at: usize| s.len() > at + 1 && digits(&s[at..]) == 2;
+    if !(two_digits(0) && s.get(2) == Some(&b':') && two_digits(3)) {
+        return None;
+    }
+    let (h, m) = (number(&s[..2])?, number(&s[3..5])?);
+    let (mut sec, mut ns, mut len) = (0, 0, 5);
+    if s.get(5) == Some(&b':') && two_digits(6) {
+        sec = number(&s[6..8])?;
+        len = 8;
+        let n = match s.get(8) {
+            Some(b'.') => digits(&s[9..]),
+            _ => 0,
+        };
+        if (1..=9).contains(&n) {
+            ns = number(&s[9..9 + n])? * 10i64.pow(9 - n as u32);
+            len = 9 + n;
+        }
+    }
+    let valid = h < 24 && m < 60 && sec < 60;
+    Some((valid.then_some(h * NS_PER_HOUR + m * NS_PER_MIN + sec * NS_PER_SEC + ns), len))
+}
+
+/// Date or time literal at the start of `s`, of its type, its value if it is valid, and its length:
+/// a date `yyyy.mm.dd`, a time `hh:mm[:ss[.fraction]]`, a timestamp of a date and a time separated
+/// by `D`, or a timespan of a number of days and a time separated by `D`.
+fn temporal(s: &[u8]) -> Option<(BSType, Option<i64>, usize)> {
+    let sign = (s.first() == Some(&b'-')) as usize;
+    let n = digits(&s[sign..]);
+    let after = sign + n;
+
+    let is_date = sign == 0
+        && n == 4
+        && s.get(4) == Some(&b'.')
+        && digits(&s[5..]) == 2
+        && s.get(7) == Some(&b'.')
+        && digits(&s[8..]) == 2;
+    if is_date {
+        let days = days_from_civil(number(&s[..4])?, number(&s[5..7])?, number(&s[8..10])?);
+        return match s.get(10).filter(|c| **c == b'D').and_then(|_| time_of_day(&s[11..])) {
+            Some((ns, len)) => {
+                let ns = days
+                    .zip(ns)
+                    .and_then(|(days, ns)| days.checked_mul(NS_PER_DAY)?.checked_add(ns));
+                Some((BSType::Timestamp, ns, 11 + len))
+            }
+            None => Some((BSType::Date, days, 10)),
+        };
+    }
+
+    if n > 0 && s.get(after) == Some(&b'D') {
+        if let Some((ns, len)) = time_of_day(&s[after + 1..]) {
+            let days = number(&s[sign..after]);
+            let span = days
+                .zip(ns)
+                .and_then(|(days, ns)| days.checked_mul(NS_PER_DAY)?.checked_add(ns));
+            let span = span.map(|span| if sign == 1 { -span } else { span });
+            return Some((BSType::Timespan, span, after + 1 + len));
+        }
+    }
+
+    match (sign, time_of_day(s)) {
+        (0, Some((ns, len))) => Some((BSType::Time, ns, len)),
+        _ => None,
+    }
+}
+
+/// Type and value of the date or time `s`, which must be a whole literal.
+pub(crate) fn parse_temporal(s: &str) -> Option<(BSType, i64)> {
+    match temporal(s.as_bytes())? {
+        (ty, Some(value), len) if len == s.len() => Some((ty, value)),
+        _ => None,
+    }
+}
+
 /// Defines a lexer which transforms an input `String` into
 /// a `Token` stream.
 pub struct Lexer<'a> {
@@ -122,8 +224,15 @@ impl<'a> Lexer<'a> {
         Lexer { input, chars: Box::new(input.chars().peekable()), span: Default::default(), last: None }
     }
 
+    /// Creates a new `Lexer` of the text of `source`, whose spans point into it.
+    pub fn with_source(source: &'a Source) -> Lexer<'a> {
+        let mut lexer = Lexer::new(&source.text);
+        lexer.span.source = source.id;
+        lexer
+    }
+
     /// Lexes and returns the next `Token` from the source code.
-    pub fn next(&mut self) -> BSResult<Token<'a>> {
+    pub fn next_token(&mut self) -> BSResult<Token<'a>> {
         let tok = self._next()?;
         self.last = Some(tok.clone());
         ok(tok)
@@ -144,13 +253,12 @@ impl<'a> Lexer<'a> {
                 let c = ch.unwrap();
 
                 if !c.is_whitespace() {
-                    if *c == '\n' {
-                        self.span.line_end = self.span.label_end.saturating_sub(1);
-                        self.span.line_start = self.span.label_end + 1;
-                        self.span.line_number += 1;
-                    }
                     break;
                 }
+                if *c == '\n' {
+                    self.span.line_start = self.span.label_end + 1;
+                    self.span.line_number += 1;
+                }
             }
 
             chars.next();
@@ -190,35 +298,123 @@ impl<'a> Lexer<'a> {
             '$' => ok(Token::Dollar),
             '%' => ok(Token::Percent),
             '\'' => ok(Token::SingleQuote),
-            '"' => ok(Token::DoubleQuote),
-            '`' => ok(Token::BackTick),
 
-            '#' => {
-                // Comment
-                loop {
-                    let ch = chars.next();
-                    if ch == Some('\n') {
+            '`' => {
+                // Symbol literal, which may be empty
+                while let Some(&ch) = chars.peek() {
+                    if ch != '_' && ch != '.' && !ch.is_alphanumeric() {
                         break;
                     }
-                    self.span.label_end += 1;
+
+                    chars.next();
+                    self.span.label_end += ch.len_utf8();
+                }
+
+                ok(Token::Sym(&src[self.span.label_start + 1..self.span.label_end]))
+            }
+
+            '#' => {
+                // Comment, up to the line break
+                while let Some(ch) = chars.next_if(|ch| *ch != '\n') {
+                    self.span.label_end += ch.len_utf8();
                 }
 
                 ok(Token::Comment(&src[self.span.label_start..self.span.label_end]))
             }
 
+            '"' => {
+                // String literal, of the escaped characters `\\`, `\"`, `\n`, `\t` and `\r`
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => {
+                            self.span.label_end += 1;
+                            break;
+                        }
+                        Some('\\') => {
+                            self.span.label_end += 1;
+                            let escaped = match chars.next() {
+                                Some(ch) => {
+                                    self.span.label_end += ch.len_utf8();
+                                    ch
+                                }
+                                None => continue,
+                            };
+                            match escaped {
+                                '\\' | '"' => s.push(escaped),
+                                'n' => s.push('\n'),
+                                't' => s.push('\t'),
+                                'r' => s.push('\r'),
+                                ch => {
+                                    let desc = format!("Unknown escape '\\{}'", ch);
+                                    return parse_error("Invalid string literal", desc, Some(self.span()));
+                                }
+                            }
+                        }
+                        Some(ch) => {
+                            self.span.label_end += ch.len_utf8();
+                            s.push(ch);
+                        }
+                        None => {
+                            return parse_error(
+                                "Unterminated string literal",
+                                "Expected closing '\"'".to_string(),
+                                Some(self.span()),
+                            )
+                        }
+                    }
+                }
+
+                ok(Token::Str(s))
+            }
+
+            '-' if chars.peek().map(|c| *c == '>').unwrap_or_else(|| false) => {
+                chars.next();
+                self.span.label_end += 1;
+                ok(Token::Arrow)
+            }
+
             '-' if self
                 .last
                 .as_ref()
-                .map(|t| match t {
-                    Token::Int64(_) | Token::Float64(_) | Token::Ident(_) => true,
-                    _ => false,
-                })
+                .map(|t| matches!(t, Token::Int64(_) | Token::Float64(_) | Token::Temporal(..) | Token::Ident(_)))
                 .unwrap_or_else(|| false) =>
             {
                 ok(Token::Minus)
             }
 
+            // typed nulls
+            '0' if matches!(chars.peek(), Some('N' | 'n')) => {
+                let null = chars.next();
+                self.span.label_end += 1;
+                let ty = match (null, chars.peek()) {
+                    (Some('N'), Some('d')) => BSType::Date,
+                    (Some('N'), Some('t')) => BSType::Time,
+                    (Some('N'), Some('p')) => BSType::Timestamp,
+                    (Some('N'), Some('n')) => BSType::Timespan,
+                    (Some('N'), _) => return ok(Token::Int64(NULL_INT64)),
+                    _ => return ok(Token::Float64(NULL_FLOAT64)),
+                };
+                chars.next();
+                self.span.label_end += 1;
+                ok(Token::Temporal(ty, NULL_INT64))
+            }
+
             '-' | '0'..='9' => {
+                // dates and times, made of ASCII characters only
+                if let Some((ty, value, len)) = temporal(&src.as_bytes()[self.span.label_start..]) {
+                    if value.is_none() {
+                        self.span.label_end = self.span.label_start + len;
+                        let desc = format!("Expected a valid {} literal", ty);
+                        return parse_error("Invalid date or time literal", desc, Some(self.span()));
+                    }
+                    for _ in 1..len {
+                        chars.next();
+                    }
+                    self.span.label_end = self.span.label_start + len;
+                    return ok(Token::Temporal(ty, value.expect("value")));
+                }
+
                 // Parse number literal
                 let mut is_float = false;
                 while let Some(&ch) = chars.peek() {
@@ -227,9 +423,7 @@ impl<'a> Lexer<'a> {
                             break;
                         }
                         is_float = true;
-                    } else if ch == '-' {
-                        break;
-                    } else if !ch.is_digit(10) {
+                    } else if !ch.is_ascii_digit() {
                         break;
                     }
 
@@ -338,6 +532,8 @@ impl<'a> Lexer<'a> {
                     "extern" => ok(Token::Extern),
                     "if" => ok(Token::If),
                     "else" => ok(Token::Else),
+                    "try" => ok(Token::Try),
+                    "catch" => ok(Token::Catch),
                     ident => ok(Token::Ident(ident)),
                 }
             }
@@ -349,14 +545,9 @@ impl<'a> Lexer<'a> {
     }
 
     pub fn span(&mut self) -> Span {
-        // update span line end
-        for c in self.input[self.span.line_end..].chars() {
-            self.span.line_end += 1;
-            if c == '\n' {
-                break;
-            }
-        }
-
+        // the line ends at its line break, or the end of the input
+        let line = &self.input[self.span.line_start..];
+        self.span.line_end = self.span.line_start + line.find('\n').unwrap_or(line.len());
         self.span
     }
 }
@@ -1,6 +1,6 @@
 use crate::parse::ast::*;
 use crate::parse::lexer::{Lexer, Token};
-use crate::parse::span::Span;
+use crate::parse::span::{Source, Span};
 use crate::result::*;
 use ffi::types::Type as BSType;
 use std::collections::HashSet;
@@ -10,15 +10,21 @@ pub struct Parser<'a> {
     lexer: Lexer<'a>,
     curr: Token<'a>,
     top_level: bool,
+    /// Source of the functions parsed, if the input is one.
+    source: Option<&'a Source>,
 }
 
-#[allow(unused_must_use)]
 #[allow(unused_must_use)]
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Self {
         let lexer = Lexer::new(input);
 
-        Parser { lexer, curr: Token::EOF, top_level: true }
+        Parser { lexer, curr: Token::EOF, top_level: true, source: None }
+    }
+
+    /// Creates a parser of the text of `source`, which the functions parsed keep.
+    pub fn with_source(source: &'a Source) -> Self {
+        Parser { lexer: Lexer::with_source(source), curr: Token::EOF, top_level: true, source: Some(source) }
     }
 
     fn span(&mut self) -> Option<Span> { Some(self.lexer.span()) }
@@ -27,28 +33,20 @@ impl<'a> Parser<'a> {
     /// indicates that the end of the file has been unexpectedly reached.
     /// This allows to use the `self.advance()?;` syntax.
     fn advance(&mut self) -> BSResult<()> {
-        let token = self.lexer.next()?;
+        let token = self.lexer.next_token()?;
         self.curr = token;
         ok(())
     }
 
     /// Returns a value indicating whether or not the `Parser`
     /// has reached the end of the input.
-    fn at_end(&self) -> bool {
-        match self.curr {
-            Token::EOF | Token::RightBrace => true,
-            _ => false,
-        }
-    }
+    fn at_end(&self) -> bool { matches!(self.curr, Token::EOF | Token::RightBrace) }
 
     fn at_term(&self) -> bool {
-        match self.curr {
-            Token::EOF | Token::RightParen | Token::RightBrace | Token::RightSquare => true,
-            _ => false,
-        }
+        matches!(self.curr, Token::EOF | Token::RightParen | Token::RightBrace | Token::RightSquare)
     }
 
-    fn expect(&mut self, expected: Token<'a>) -> BSResult<Token> {
+    fn expect(&mut self, expected: Token<'a>) -> BSResult<Token<'_>> {
         if self.curr == expected {
             let tok = self.curr.clone();
             self.advance()?;
@@ -58,8 +56,53 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Parses the type named `src`, e.g. `Int64[]!Float64[]`.
+    pub fn parse_type_str(src: &str) -> BSResult<BSType> {
+        let mut parser = Parser::new(src);
+        parser.advance()?;
+        let ty = parser.parse_type()?;
+        match parser.curr {
+            EOF => ok(ty),
+            _ => parse_error("Invalid type", format!("Unexpected text after the type '{}'", ty), parser.span()),
+        }
+    }
+
     fn parse_type(&mut self) -> BSResult<BSType> {
+        let ty = self.parse_vec_type()?;
+        match self.curr {
+            // dictionary of keys and values, e.g. `Int64[]!Float64[]`
+            Excl => {
+                self.advance()?;
+                ok(BSType::dict(ty, self.parse_vec_type()?))
+            }
+            _ => ok(ty),
+        }
+    }
+
+    fn parse_vec_type(&mut self) -> BSResult<BSType> {
         match self.curr {
+            // list of values of any types
+            LeftSquare => {
+                self.advance()?;
+                self.expect(RightSquare)?;
+                ok(BSType::List)
+            }
+            // table of named columns, e.g. `Table(price: Float64[], qty: Int64[])`
+            Token::Ident("Table") => {
+                self.advance()?;
+                self.expect(LeftParen)?;
+                let mut columns = vec![];
+                while let Ident(name) = self.curr {
+                    self.advance()?;
+                    self.expect(Colon)?;
+                    columns.push((name.to_string(), self.parse_type()?));
+                    if self.curr == Comma {
+                        self.advance()?;
+                    }
+                }
+                self.expect(RightParen)?;
+                ok(BSType::Table(columns))
+            }
             Token::Ident(name) => {
                 self.advance()?;
                 if self.curr == Token::LeftSquare {
@@ -80,6 +123,17 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Parses the type of an argument or result of a function with the type parameters `params`.
+    fn parse_param_type(&mut self, params: &[String]) -> BSResult<BSType> {
+        match self.curr {
+            Token::Ident(name) if params.iter().any(|p| p == name) => {
+                self.advance()?;
+                ok(BSType::Param(name.to_string()))
+            }
+            _ => self.parse_type(),
+        }
+    }
+
     /// Parses an expression that starts with an identifier (either a variable or a function call).
     fn parse_ident_expr(&mut self) -> BSResult<Expr> {
         let name = match self.curr {
@@ -98,30 +152,46 @@ impl<'a> Parser<'a> {
         self.advance()?;
 
         match self.curr {
-            LeftParen => {
+            LeftParen => self.parse_call_args(name, span),
+            // `v[i]` and `d[k]` index a vector or a dictionary
+            LeftSquare => {
                 self.advance()?;
-                let mut args = vec![];
-                self.top_level = false;
-
-                while self.curr != RightParen {
-                    let arg = self.parse_expr()?;
-                    args.push(arg);
+                let index = self.parse_expr()?;
+                self.expect(RightSquare)?;
+                let var = Expr::new(ExprBody::Variable(name.to_string()), Some(span));
+                ok(Expr::new(ExprBody::Call { name: "at".to_string(), args: vec![var, index] }, Some(span)))
+            }
+            // `signal "msg"` raises an error without parentheses, as in k
+            Token::Str(_) if name == "signal" => {
+                let msg = self.parse_unary_expr()?;
+                ok(Expr::new(ExprBody::Call { name: name.to_string(), args: vec![msg] }, Some(span)))
+            }
+            _ => ok(Expr::new(ExprBody::Variable(name.to_string()), Some(span))),
+        }
+    }
 
-                    if self.curr == Comma {
-                        self.advance()?;
-                        continue;
-                    } else {
-                        break;
-                    }
-                }
+    /// Parses the arguments of a call of `name`, starting at the opening parenthesis.
+    fn parse_call_args(&mut self, name: &str, span: Span) -> BSResult<Expr> {
+        self.advance()?;
+        let mut args = vec![];
+        self.top_level = false;
 
-                self.expect(RightParen)?;
-                self.top_level = true;
+        while self.curr != RightParen {
+            let arg = self.parse_expr()?;
+            args.push(arg);
 
-                ok(Expr::new(ExprBody::Call { name: name.to_string(), args }, Some(span)))
+            if self.curr == Comma {
+                self.advance()?;
+                continue;
+            } else {
+                break;
             }
-            _ => ok(Expr::new(ExprBody::Variable(name.to_string()), Some(span))),
         }
+
+        self.expect(RightParen)?;
+        self.top_level = true;
+
+        ok(Expr::new(ExprBody::Call { name: name.to_string(), args }, Some(span)))
     }
 
     fn parse_cond_expr(&mut self) -> BSResult<Expr> {
@@ -144,29 +214,92 @@ impl<'a> Parser<'a> {
         ok(Expr::new(ExprBody::Cond { cond: Box::new(cond), cons: then, altr: els }, Some(self.lexer.span())))
     }
 
+    fn parse_try_expr(&mut self) -> BSResult<Expr> {
+        let span = self.lexer.span();
+        self.advance()?;
+
+        self.expect(LeftBrace)?;
+        let body = self.parse_exprs()?;
+        self.expect(RightBrace)?;
+
+        self.expect(Catch)?;
+        let var = match self.curr {
+            Ident(var) => var.to_string(),
+            _ => return parse_error("Invalid syntax", "Expected the name of the error here".to_string(), self.span()),
+        };
+        self.advance()?;
+
+        self.expect(LeftBrace)?;
+        let handler = self.parse_exprs()?;
+        self.expect(RightBrace)?;
+
+        ok(Expr::new(ExprBody::Try { body, var, handler }, Some(span)))
+    }
+
     fn parse_vec_literal(&mut self) -> BSResult<Expr> {
         let mut vec_i64 = vec![];
         let mut vec_f64 = vec![];
+        let mut vec_bool = vec![];
+        let mut vec_temporal = vec![];
+        let mut temporal_ty = None;
 
         loop {
             self.advance()?;
 
             match &self.curr {
+                Bool(_) | Int64(_) | Float64(_) if temporal_ty.is_some() => {
+                    return parse_error(
+                        "Invalid vector literal",
+                        "Can not mix dates or times and other literals in a vector literal".to_string(),
+                        self.span(),
+                    )
+                }
+                Temporal(ty, v)
+                    if vec_i64.is_empty()
+                        && vec_f64.is_empty()
+                        && vec_bool.is_empty()
+                        && temporal_ty.as_ref().is_none_or(|t| t == ty) =>
+                {
+                    temporal_ty = Some(ty.clone());
+                    vec_temporal.push(*v);
+                }
+                Temporal(ty, _) => {
+                    return parse_error(
+                        "Invalid vector literal",
+                        format!("Can not mix a {} and other literals in a vector literal", ty),
+                        self.span(),
+                    )
+                }
+                Bool(_) if !vec_i64.is_empty() || !vec_f64.is_empty() => {
+                    return parse_error(
+                        "Invalid vector literal",
+                        "Can not mix bools and numbers in a vector literal".to_string(),
+                        self.span(),
+                    )
+                }
+                Int64(_) | Float64(_) if !vec_bool.is_empty() => {
+                    return parse_error(
+                        "Invalid vector literal",
+                        "Can not mix bools and numbers in a vector literal".to_string(),
+                        self.span(),
+                    )
+                }
+                Bool(v) => vec_bool.push(*v),
                 Int64(v) => {
-                    if vec_f64.len() == 0 {
+                    if vec_f64.is_empty() {
                         vec_i64.push(*v);
                     } else {
                         vec_f64.push(*v as f64);
                     }
                 }
                 Float64(v) => {
-                    if vec_i64.len() == 0 {
+                    if vec_i64.is_empty() {
                         vec_f64.push(*v);
                     } else {
                         for v in vec_i64.drain(..) {
                             vec_f64.push(v as f64);
                         }
-                        vec_f64.push(*v as f64);
+                        vec_f64.push(*v);
                     }
                 }
                 Comma => {}
@@ -174,7 +307,7 @@ impl<'a> Parser<'a> {
                 _ => {
                     return parse_error(
                         "Invalid number literal",
-                        "Expected int or float in vector literal here".to_string(),
+                        "Expected int, float, bool, date or time in vector literal here".to_string(),
                         self.span(),
                     )
                 }
@@ -183,13 +316,109 @@ impl<'a> Parser<'a> {
 
         self.advance()?;
 
-        if vec_i64.is_empty() {
+        if let Some(ty) = temporal_ty.and_then(|ty| ty.vec_type()) {
+            ok(Expr::new(ExprBody::VecTemporal(ty, vec_temporal), self.span()))
+        } else if !vec_bool.is_empty() {
+            ok(Expr::new(ExprBody::VecBool(vec_bool), self.span()))
+        } else if vec_i64.is_empty() {
             ok(Expr::new(ExprBody::VecFloat64(vec_f64), self.span()))
         } else {
             ok(Expr::new(ExprBody::VecInt64(vec_i64), self.span()))
         }
     }
 
+    /// Parses the columns of a table literal, after its opening `([]`.
+    fn parse_table_literal(&mut self, span: Option<Span>) -> BSResult<Expr> {
+        let top_level = self.top_level;
+        self.top_level = false;
+
+        let mut columns = vec![];
+        loop {
+            let name = match self.curr {
+                Ident(name) => name.to_string(),
+                _ => {
+                    return parse_error("Invalid table", "Expected the name of a column here".to_string(), self.span())
+                }
+            };
+            self.advance()?;
+            self.expect(Colon)?;
+            columns.push((name, self.parse_expr()?));
+
+            match self.curr {
+                SemiColon => self.advance()?,
+                _ => break,
+            }
+        }
+
+        self.expect(RightParen)?;
+        self.top_level = top_level;
+
+        ok(Expr::new(ExprBody::Table { columns }, span))
+    }
+
+    /// Parses a q-style query, `select`, `exec`, `update` or `delete` columns, optionally grouped
+    /// `by` columns, `from` a table, optionally of the rows `where` every condition holds.
+    fn parse_query_expr(&mut self) -> BSResult<Expr> {
+        let span = self.span();
+        let kind = match self.curr {
+            Ident("select") => QueryKind::Select,
+            Ident("exec") => QueryKind::Exec,
+            Ident("update") => QueryKind::Update,
+            _ => QueryKind::Delete,
+        };
+        self.advance()?;
+
+        let top_level = self.top_level;
+        self.top_level = false;
+
+        let columns = self.parse_query_columns()?;
+        let mut by = vec![];
+        if self.curr == Ident("by") {
+            self.advance()?;
+            by = self.parse_query_columns()?;
+        }
+        self.expect(Ident("from"))?;
+        let table = self.parse_expr()?;
+
+        let mut filter = vec![];
+        if self.curr == Ident("where") {
+            loop {
+                self.advance()?;
+                filter.push(self.parse_expr()?);
+                if self.curr != Comma {
+                    break;
+                }
+            }
+        }
+        self.top_level = top_level;
+
+        let query = Query { kind, columns, by, table: Box::new(table), filter, reductions: vec![] };
+        ok(Expr::new(ExprBody::Query(Box::new(query)), span))
+    }
+
+    /// Parses the columns of a query, `name: expr` or `expr`, named after the column it computes.
+    fn parse_query_columns(&mut self) -> BSResult<Vec<QueryColumn>> {
+        let mut columns = vec![];
+        while self.curr != Ident("by") && self.curr != Ident("from") {
+            let expr = self.parse_expr()?;
+            let column = match (&expr.body, &self.curr) {
+                (ExprBody::Variable(name), Colon) => {
+                    let name = name.clone();
+                    self.advance()?;
+                    QueryColumn::new(name, self.parse_expr()?)
+                }
+                _ => QueryColumn::new(query_column_name(&expr), expr),
+            };
+            columns.push(column);
+
+            match self.curr {
+                Comma => self.advance()?,
+                _ => break,
+            }
+        }
+        ok(columns)
+    }
+
     fn parse_dot_expr(&mut self) -> BSResult<Expr> {
         match self.curr {
             Period => {
@@ -198,21 +427,24 @@ impl<'a> Parser<'a> {
                 self.expect(RightParen)?;
                 ok(Expr::new(ExprBody::Null, self.span()))
             }
-            _ => {
-                return parse_error(
-                    "Expected combinator",
-                    "Consider using one of map, filter, fold, zip.. etc. here".to_string(),
-                    self.span(),
-                );
-            }
+            _ => parse_error(
+                "Expected combinator",
+                "Consider using one of map, filter, fold, zip.. etc. here".to_string(),
+                self.span(),
+            ),
         }
     }
 
     fn parse_unary_expr(&mut self) -> BSResult<Expr> {
         match self.curr {
             Null => {
+                let span = self.lexer.span();
                 self.advance()?;
-                ok(Expr::new(ExprBody::Null, self.span()))
+                match self.curr {
+                    // the `null` builtin
+                    LeftParen => self.parse_call_args("null", span),
+                    _ => ok(Expr::new(ExprBody::Null, self.span())),
+                }
             }
             Bool(v) => {
                 self.advance()?;
@@ -226,14 +458,46 @@ impl<'a> Parser<'a> {
                 self.advance()?;
                 ok(Expr::new(ExprBody::Float64(v), self.span()))
             }
+            Temporal(ref ty, v) => {
+                let ty = ty.clone();
+                self.advance()?;
+                ok(Expr::new(ExprBody::Temporal(ty, v), self.span()))
+            }
+            Token::Str(ref s) => {
+                let s = s.clone();
+                self.advance()?;
+                ok(Expr::new(ExprBody::Str(s), self.span()))
+            }
+            Token::Sym(_) => {
+                let span = self.span();
+                // adjacent symbols make a vector, as in `a`b`c
+                let mut syms = vec![];
+                while let Token::Sym(s) = self.curr {
+                    syms.push(s.to_string());
+                    self.advance()?;
+                }
+                match syms.len() {
+                    1 => ok(Expr::new(ExprBody::Sym(syms.remove(0)), span)),
+                    _ => ok(Expr::new(ExprBody::VecSym(syms), span)),
+                }
+            }
             LeftSquare => self.parse_vec_literal(),
+            Ident("select" | "exec" | "update" | "delete") => self.parse_query_expr(),
             Ident(_) => self.parse_ident_expr(),
             If => self.parse_cond_expr(),
+            Try => self.parse_try_expr(),
             LeftParen => {
+                let span = self.span();
                 self.advance()?;
                 let expr = self.parse_expr()?;
-                self.expect(RightParen)?;
-                ok(expr)
+                match (&expr.body, &self.curr) {
+                    // `([] name: column; ...)` is a table, as in q
+                    (ExprBody::VecFloat64(v), Ident(_)) if v.is_empty() => self.parse_table_literal(span),
+                    _ => {
+                        self.expect(RightParen)?;
+                        ok(expr)
+                    }
+                }
             }
             _ => parse_error(
                 "Invalid expression",
@@ -279,6 +543,13 @@ impl<'a> Parser<'a> {
                 ok(Expr::new(ExprBody::Binary { op, lhs: Box::new(lhs), rhs: Box::new(rhs) }, span))
             }
 
+            // `keys ! values` makes a dictionary
+            Excl => {
+                self.advance()?;
+                let rhs = self.parse_expr()?;
+                ok(Expr::new(ExprBody::Call { name: "dict".to_string(), args: vec![lhs, rhs] }, span))
+            }
+
             Assign => {
                 self.advance()?;
                 let span = self.span();
@@ -299,6 +570,15 @@ impl<'a> Parser<'a> {
 
             Period => {
                 self.advance()?;
+
+                // column of a table
+                if let Ident(name) = self.curr {
+                    let span = self.span();
+                    self.advance()?;
+                    let column = Expr::new(ExprBody::Column { table: Box::new(lhs), name: name.to_string() }, span);
+                    return self.parse_binary_expr(column);
+                }
+
                 let rhs = self.parse_dot_expr()?;
                 ok(Expr::new(ExprBody::Dot { lhs: Box::new(lhs), rhs: Box::new(rhs) }, Some(self.lexer.span())))
             }
@@ -347,6 +627,16 @@ impl<'a> Parser<'a> {
 
     fn parse_function_proto(&mut self) -> BSResult<Function> {
         self.advance()?;
+
+        let lib = match self.curr {
+            Token::Str(ref lib) => {
+                let lib = lib.clone();
+                self.advance()?; // eat library name
+                Some(lib)
+            }
+            _ => None,
+        };
+
         let name = match self.curr {
             Token::Ident(name) => {
                 self.advance()?; // eat ident
@@ -355,6 +645,29 @@ impl<'a> Parser<'a> {
             _ => parse_error("Invalid syntax", "Expected identifier".into(), self.span()),
         }?;
 
+        let mut params: Vec<String> = vec![];
+        if self.curr == Less {
+            self.advance()?;
+            while self.curr != Greater {
+                match self.curr {
+                    Token::Ident(param) if !params.iter().any(|p| p == param) => params.push(param.to_string()),
+                    Token::Ident(_) => {
+                        return parse_error(
+                            "Invalid function definition",
+                            "Duplicate type parameter".to_string(),
+                            self.span(),
+                        )
+                    }
+                    _ => return parse_error("Invalid syntax", "Expected type parameter here".to_string(), self.span()),
+                }
+                self.advance()?;
+                if self.curr == Comma {
+                    self.advance()?;
+                }
+            }
+            self.expect(Greater)?;
+        }
+
         let mut args = vec![];
 
         if self.curr == Or {
@@ -379,7 +692,7 @@ impl<'a> Parser<'a> {
 
                 self.advance()?;
                 self.expect(Colon)?;
-                let ty = self.parse_type()?;
+                let ty = self.parse_param_type(&params)?;
                 args.push((arg_name.to_string(), ty));
                 if self.curr == Comma {
                     self.advance()?;
@@ -389,7 +702,27 @@ impl<'a> Parser<'a> {
             self.expect(Bar)?;
         }
 
-        ok(Function { name: name.into(), args, body: vec![], topl: false })
+        let ret = match self.curr {
+            Arrow => {
+                self.advance()?;
+                Some(self.parse_param_type(&params)?)
+            }
+            _ => None,
+        };
+
+        if let Some(param) = params
+            .iter()
+            .find(|p| !args.iter().any(|(_, ty)| *ty == BSType::Param(p.to_string())))
+        {
+            return parse_error(
+                "Invalid function definition",
+                format!("Type parameter '{}' is not used by any argument", param),
+                self.span(),
+            );
+        }
+
+        let source = self.source.cloned();
+        ok(Function { name: name.into(), params, args, ret, body: vec![], topl: false, lib, source })
     }
 
     fn parse_function_body(&mut self, proto: Function) -> BSResult<Function> {
@@ -397,7 +730,15 @@ impl<'a> Parser<'a> {
         let body = self.parse_exprs()?;
         self.expect(Token::RightBrace)?;
 
-        ok(Function { name: proto.name, args: proto.args, body, topl: false })
+        if proto.lib.is_some() {
+            return parse_error(
+                "Invalid function definition",
+                "Only extern functions can be loaded from a library".to_string(),
+                self.span(),
+            );
+        }
+
+        ok(Function { body, ..proto })
     }
 
     pub fn parse_module(&mut self) -> BSResult<Vec<Function>> {
@@ -416,10 +757,32 @@ impl<'a> Parser<'a> {
                     self.top_level = true;
                     ok(func)
                 }
-                Extern => self.parse_function_proto(),
+                Extern => {
+                    let proto = self.parse_function_proto()?;
+                    if proto.is_generic() {
+                        return parse_error(
+                            "Invalid function definition",
+                            "Extern functions can not be generic".to_string(),
+                            self.span(),
+                        );
+                    }
+                    if self.curr == SemiColon {
+                        self.advance()?;
+                    }
+                    ok(proto)
+                }
                 _ => {
                     let body = self.parse_exprs()?;
-                    ok(Function { name: "top-level".into(), args: vec![], body: body, topl: true })
+                    ok(Function {
+                        name: "top-level".into(),
+                        params: vec![],
+                        args: vec![],
+                        ret: None,
+                        body,
+                        topl: true,
+                        lib: None,
+                        source: self.source.cloned(),
+                    })
                 }
             }?;
 
@@ -445,3 +808,15 @@ impl<'a> Parser<'a> {
         }
     }
 }
+
+/// Value of a string literal, whose escaped characters the lexer keeps as is.
+/// Name of the query column computed by `expr`, after the first variable it reads, as in q.
+fn query_column_name(expr: &Expr) -> String {
+    match &expr.body {
+        ExprBody::Variable(name) => name.clone(),
+        ExprBody::Column { name, .. } => name.clone(),
+        ExprBody::Call { args, .. } if !args.is_empty() => query_column_name(&args[0]),
+        ExprBody::Binary { lhs, .. } => query_column_name(lhs),
+        _ => "x".to_string(),
+    }
+}
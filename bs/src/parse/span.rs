@@ -1,5 +1,10 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Span {
+    /// Id of the `Source` the span points into, 0 for a text which is not one.
+    pub source: usize,
     pub line_number: usize,
     pub line_start: usize,
     pub line_end: usize,
@@ -16,6 +21,7 @@ impl Span {
         label_end: usize,
     ) -> Span {
         Span {
+            source: 0,
             line_number,
             line_start,
             line_end,
@@ -38,6 +44,7 @@ impl Span {
 impl Default for Span {
     fn default() -> Self {
         Span {
+            source: 0,
             line_number: 1,
             line_start: 0,
             line_end: 0,
@@ -46,3 +53,32 @@ impl Default for Span {
         }
     }
 }
+
+/// Texts of the live sources by id, see `Source::text`.
+static SOURCES: Mutex<Vec<(usize, Weak<str>)>> = Mutex::new(vec![]);
+
+static NEXT_SOURCE: AtomicUsize = AtomicUsize::new(1);
+
+/// Text of an input whose spans point into it by id. The functions parsed from it keep it alive,
+/// so that an error of a function is shown against the input defining it, not the latest one.
+#[derive(Clone, Debug)]
+pub struct Source {
+    pub id: usize,
+    pub text: Arc<str>,
+}
+
+impl Source {
+    pub fn new(text: &str) -> Source {
+        let source = Source { id: NEXT_SOURCE.fetch_add(1, Ordering::Relaxed), text: text.into() };
+        let mut sources = SOURCES.lock().unwrap();
+        sources.retain(|(_, text)| text.strong_count() > 0);
+        sources.push((source.id, Arc::downgrade(&source.text)));
+        source
+    }
+
+    /// Text of the source `id`, unless it is no longer alive.
+    pub fn text(id: usize) -> Option<Arc<str>> {
+        let sources = SOURCES.lock().unwrap();
+        sources.iter().find(|(s, _)| *s == id).and_then(|(_, text)| text.upgrade())
+    }
+}
@@ -15,8 +15,17 @@ pub enum BSError {
         desc: String,
         span: Option<Span>,
     },
-    RuntimeError(String),
-    IOError(String),
+    RuntimeError {
+        msg: String,
+        span: Option<Span>,
+    },
+    /// Failure to read or write the file `path`, at `line` if it is malformed there.
+    IOError {
+        msg: String,
+        path: String,
+        line: Option<usize>,
+        span: Option<Span>,
+    },
 }
 
 pub fn parse_error<T>(msg: &'static str, desc: String, span: Option<Span>) -> BSResult<T> {
@@ -28,11 +37,11 @@ pub fn compile_error<T>(msg: String, desc: String, span: Option<Span>) -> BSResu
 }
 
 pub fn runtime_error<T>(msg: String) -> BSResult<T> {
-    BSResult::Err(BSError::RuntimeError(msg.to_string()))
+    BSResult::Err(BSError::RuntimeError { msg, span: None })
 }
 
-pub fn io_error<T>(msg: String) -> BSResult<T> {
-    BSResult::Err(BSError::IOError(msg.to_string()))
+pub fn io_error<T>(msg: String, path: String, line: Option<usize>, span: Option<Span>) -> BSResult<T> {
+    BSResult::Err(BSError::IOError { msg, path, line, span })
 }
 
 pub fn ok<T>(v: T) -> BSResult<T> {
@@ -68,25 +77,25 @@ impl<T> From<io::Result<T>> for BSResult<T> {
     fn from(res: io::Result<T>) -> Self {
         match res {
             Ok(v) => Self::Ok(v),
-            Err(e) => Self::Err(BSError::IOError(e.to_string())),
+            Err(e) => Self::Err(BSError::IOError { msg: e.to_string(), path: String::new(), line: None, span: None }),
         }
     }
 }
 
-impl<T> Into<Result<T, BSError>> for BSResult<T> {
-    fn into(self) -> Result<T, BSError> {
-        match self {
-            Self::Ok(v) => Ok(v),
-            Self::Err(e) => Err(e),
+impl<T> From<BSResult<T>> for Result<T, BSError> {
+    fn from(val: BSResult<T>) -> Self {
+        match val {
+            BSResult::Ok(v) => Ok(v),
+            BSResult::Err(e) => Err(e),
         }
     }
 }
 
-impl<T> Into<io::Result<T>> for BSResult<T> {
-    fn into(self) -> io::Result<T> {
-        match self {
-            Self::Ok(v) => Ok(v),
-            Self::Err(e) => Err(io::Error::new(io::ErrorKind::Other, format!("{:?}", e))),
+impl<T> From<BSResult<T>> for io::Result<T> {
+    fn from(val: BSResult<T>) -> Self {
+        match val {
+            BSResult::Ok(v) => Ok(v),
+            BSResult::Err(e) => Err(io::Error::other(format!("{:?}", e))),
         }
     }
 }
@@ -95,7 +104,7 @@ impl<T> BSResult<T> {
     pub fn expect(self, msg: &str) -> T {
         match self {
             Self::Ok(t) => t,
-            Self::Err(e) => panic!("{msg}: ", msg = msg),
+            Self::Err(e) => panic!("{msg}: {e:?}"),
         }
     }
 }
@@ -0,0 +1,483 @@
+//! Reading and writing tables as CSV files.
+//!
+//! A file starts with a header naming its columns. The type of every column is given by a letter,
+//! `J` for Int64, `F` for Float64, `B` for Bool, `S` for Symbol, `D` for Date, `T` for Time, `P`
+//! for Timestamp and `N` for Timespan, or a space to skip it, e.g. `"JF S"`, or else inferred from
+//! the first rows. The columns may also be named by a table type, of the columns read by name, the
+//! others being skipped. Dates and times are written as their literals. Unless their table type is
+//! named, the columns of a file read by `read_csv` are read once, when the function reading it is
+//! defined, so its path is a literal, see `parse::ast`. The file read when running must hold a
+//! table of the type compiled. Fields may be quoted by `"`, which is written
+//! twice within a quoted field, and empty fields are null.
+
+use crate::parse::lexer::parse_temporal;
+use crate::parse::parser::Parser;
+use crate::result::BSResult;
+use crate::rt::error::raise_io;
+use ffi::external::HostArg;
+use ffi::types::Type as BSType;
+use ffi::values::sym::Sym;
+use ffi::values::table::Table;
+use ffi::values::time::{self, Date, Time, Timespan, Timestamp};
+use ffi::values::vector::{VecElement, VecHeader};
+use ffi::values::Value as BSValue;
+use ffi::values::{NULL_FLOAT64, NULL_INT64};
+use std::fs;
+
+/// Number of rows the types of the columns are inferred from.
+const SAMPLE: usize = 1000;
+
+/// A file which can not be read or written, malformed at `line` if there is one.
+#[derive(Debug)]
+pub struct CsvError {
+    pub msg: String,
+    pub line: Option<usize>,
+}
+
+fn error<T>(msg: String, line: usize) -> Result<T, CsvError> { Err(CsvError { msg, line: Some(line) }) }
+
+fn read_file(path: &str) -> Result<String, CsvError> {
+    fs::read_to_string(path).map_err(|e| CsvError { msg: e.to_string(), line: None })
+}
+
+/// Type of the columns of the letter `c`, none for a skipped column.
+pub fn column_type(c: char) -> Result<Option<BSType>, String> {
+    match c {
+        'J' => Ok(Some(BSType::VecInt64)),
+        'F' => Ok(Some(BSType::VecFloat64)),
+        'B' => Ok(Some(BSType::VecBool)),
+        'S' => Ok(Some(BSType::VecSym)),
+        'D' => Ok(Some(BSType::VecDate)),
+        'T' => Ok(Some(BSType::VecTime)),
+        'P' => Ok(Some(BSType::VecTimestamp)),
+        'N' => Ok(Some(BSType::VecTimespan)),
+        ' ' => Ok(None),
+        c => Err(format!("Unknown column type '{}', expected one of 'JFBSDTPN' or a space", c)),
+    }
+}
+
+/// Letter of the columns of type `ty`, none if they can not be read.
+pub fn column_letter(ty: &BSType) -> Option<char> {
+    "JFBSDTPN"
+        .chars()
+        .find(|c| column_type(*c).ok().flatten().as_ref() == Some(ty))
+}
+
+/// The records of a CSV text, with the line each of them starts at.
+struct Records<'a> {
+    text: &'a str,
+    pos: usize,
+    line: usize,
+    delim: u8,
+}
+
+impl<'a> Records<'a> {
+    fn new(text: &'a str, delim: u8) -> Self { Records { text, pos: 0, line: 1, delim } }
+
+    fn peek(&self) -> Option<u8> { self.text.as_bytes().get(self.pos).copied() }
+
+    fn field(&mut self) -> Result<String, CsvError> {
+        if self.peek() != Some(b'"') {
+            let start = self.pos;
+            while !matches!(self.peek(), None | Some(b'\n')) && self.peek() != Some(self.delim) {
+                self.pos += 1;
+            }
+            return Ok(self.text[start..self.pos].trim_end_matches('\r').to_string());
+        }
+
+        let (line, mut field) = (self.line, String::new());
+        self.pos += 1;
+        loop {
+            match self.text[self.pos..].find('"') {
+                Some(n) => {
+                    field.push_str(&self.text[self.pos..self.pos + n]);
+                    self.pos += n + 1;
+                }
+                None => return error("Unterminated quoted field".to_string(), line),
+            }
+            // a quote written twice stands for one
+            if self.peek() == Some(b'"') {
+                field.push('"');
+                self.pos += 1;
+                continue;
+            }
+            if self.peek() == Some(b'\r') && matches!(self.text.as_bytes().get(self.pos + 1), None | Some(b'\n')) {
+                self.pos += 1;
+            }
+            match self.peek() {
+                None | Some(b'\n') => break,
+                Some(c) if c == self.delim => break,
+                Some(_) => {
+                    let line = line + field.matches('\n').count();
+                    return error("Unexpected character after a quoted field".to_string(), line);
+                }
+            }
+        }
+        self.line += field.matches('\n').count();
+        Ok(field)
+    }
+}
+
+impl Iterator for Records<'_> {
+    type Item = Result<(usize, Vec<String>), CsvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // blank lines are skipped
+        while matches!(self.peek(), Some(b'\n' | b'\r')) {
+            self.line += (self.peek() == Some(b'\n')) as usize;
+            self.pos += 1;
+        }
+        self.peek()?;
+
+        let (line, mut fields) = (self.line, vec![]);
+        loop {
+            match self.field() {
+                Ok(field) => fields.push(field),
+                Err(err) => {
+                    self.pos = self.text.len();
+                    return Some(Err(err));
+                }
+            }
+            match self.peek() {
+                Some(b'\n') => {
+                    self.pos += 1;
+                    self.line += 1;
+                    break;
+                }
+                None => break,
+                Some(_) => self.pos += 1,
+            }
+        }
+        Some(Ok((line, fields)))
+    }
+}
+
+fn header(records: &mut Records) -> Result<Vec<String>, CsvError> {
+    let header = match records.next() {
+        Some(header) => header?.1,
+        None => return error("Missing header".to_string(), 1),
+    };
+    for (i, name) in header.iter().enumerate() {
+        if header[..i].contains(name) {
+            return error(format!("Column '{}' is named twice", name), 1);
+        }
+    }
+    Ok(header)
+}
+
+fn check_len(fields: &[String], len: usize, line: usize) -> Result<(), CsvError> {
+    match fields.len() == len {
+        true => Ok(()),
+        false => error(format!("Expected {} fields, found {}", len, fields.len()), line),
+    }
+}
+
+/// Letter of the narrowest type of the non empty `fields`, a symbol if they are all empty.
+fn infer_type<'a>(fields: impl Iterator<Item = &'a str>) -> char {
+    let fields: Vec<&str> = fields.filter(|f| !f.is_empty()).collect();
+    if fields.is_empty() {
+        'S'
+    } else if fields.iter().all(|f| f.parse::<i64>().is_ok()) {
+        'J'
+    } else if fields.iter().all(|f| f.parse::<f64>().is_ok()) {
+        'F'
+    } else if fields.iter().all(|f| parse_bool(f).is_some()) {
+        'B'
+    } else if let Some((ty, _)) = parse_temporal(fields[0])
+        .filter(|(ty, _)| fields.iter().all(|f| parse_temporal(f).is_some_and(|(t, _)| t == *ty)))
+    {
+        match ty {
+            BSType::Date => 'D',
+            BSType::Time => 'T',
+            BSType::Timestamp => 'P',
+            _ => 'N',
+        }
+    } else {
+        'S'
+    }
+}
+
+/// Columns of the file `path`, of the `types` if there are some, else inferred, and the letters
+/// of their types.
+pub fn schema(path: &str, types: Option<&str>, delim: u8) -> Result<(Vec<(String, BSType)>, String), CsvError> {
+    let text = read_file(path)?;
+    let mut records = Records::new(&text, delim);
+    let header = header(&mut records)?;
+
+    let types = match types {
+        Some(types) if types.chars().count() == header.len() => types.to_string(),
+        Some(types) => {
+            let msg =
+                format!("Expected {} column types, but the file has {} columns", types.chars().count(), header.len());
+            return error(msg, 1);
+        }
+        None => {
+            let mut rows = vec![];
+            for record in records.take(SAMPLE) {
+                let (line, fields) = record?;
+                check_len(&fields, header.len(), line)?;
+                rows.push(fields);
+            }
+            (0..header.len())
+                .map(|c| infer_type(rows.iter().map(|row| row[c].as_str())))
+                .collect()
+        }
+    };
+
+    let mut columns = vec![];
+    for (name, c) in header.into_iter().zip(types.chars()) {
+        if let Some(ty) = column_type(c).map_err(|msg| CsvError { msg, line: None })? {
+            columns.push((name, ty));
+        }
+    }
+    Ok((columns, types))
+}
+
+fn parse_bool(field: &str) -> Option<bool> {
+    match field {
+        "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// A column being read, of the elements of its type.
+enum Column {
+    Int64(Vec<i64>),
+    Float64(Vec<f64>),
+    Bool(Vec<bool>),
+    Sym(Vec<Sym>),
+    /// Dates or times of the type, as their `Int64`
+    Temporal(BSType, Vec<i64>),
+    Skipped,
+}
+
+impl Column {
+    fn new(c: char) -> Column {
+        match c {
+            'J' => Column::Int64(vec![]),
+            'F' => Column::Float64(vec![]),
+            'B' => Column::Bool(vec![]),
+            'S' => Column::Sym(vec![]),
+            'D' => Column::Temporal(BSType::Date, vec![]),
+            'T' => Column::Temporal(BSType::Time, vec![]),
+            'P' => Column::Temporal(BSType::Timestamp, vec![]),
+            'N' => Column::Temporal(BSType::Timespan, vec![]),
+            _ => Column::Skipped,
+        }
+    }
+
+    fn push(&mut self, field: &str) -> Result<(), String> {
+        let invalid = |ty: &dyn std::fmt::Display| format!("Invalid {} '{}'", ty, field);
+        match self {
+            Column::Int64(v) if field.is_empty() => v.push(NULL_INT64),
+            Column::Int64(v) => v.push(field.parse().map_err(|_| invalid(&"Int64"))?),
+            Column::Float64(v) if field.is_empty() => v.push(NULL_FLOAT64),
+            Column::Float64(v) => v.push(field.parse().map_err(|_| invalid(&"Float64"))?),
+            Column::Bool(v) if field.is_empty() => v.push(false),
+            Column::Bool(v) => v.push(parse_bool(field).ok_or_else(|| invalid(&"Bool"))?),
+            Column::Sym(v) => v.push(Sym::new(field)),
+            Column::Temporal(_, v) if field.is_empty() => v.push(NULL_INT64),
+            Column::Temporal(ty, v) => match parse_temporal(field) {
+                Some((t, x)) if t == *ty => v.push(x),
+                _ => return Err(invalid(ty)),
+            },
+            Column::Skipped => {}
+        }
+        Ok(())
+    }
+
+    fn into_value(self) -> Option<BSValue> {
+        match self {
+            Column::Int64(v) => Some(v.into()),
+            Column::Float64(v) => Some(v.into()),
+            Column::Bool(v) => Some(v.into()),
+            Column::Sym(v) => Some(v.into()),
+            Column::Temporal(ty, v) => Some(time::vec(&ty.vec_type().expect("vector"), v)),
+            Column::Skipped => None,
+        }
+    }
+}
+
+/// Types of the columns read from a file.
+#[derive(Clone, Copy)]
+pub enum Columns<'a> {
+    /// Letters of the types of every column of the file, in order
+    Letters(&'a str),
+    /// Names and types of the columns read, the others being skipped
+    Named(&'a [(String, BSType)]),
+}
+
+/// Reads the table of the file `path`, of the `columns`.
+pub fn read(path: &str, columns: Columns, delim: u8) -> Result<Table, CsvError> {
+    let text = read_file(path)?;
+    let mut records = Records::new(&text, delim);
+    let header = header(&mut records)?;
+    let types: String = match columns {
+        Columns::Letters(types) if types.chars().count() != header.len() => {
+            return error(format!("Expected {} columns, found {}", types.chars().count(), header.len()), 1)
+        }
+        Columns::Letters(types) => types.to_string(),
+        Columns::Named(columns) => {
+            if let Some((name, _)) = columns.iter().find(|(name, _)| !header.contains(name)) {
+                return error(format!("Missing column '{}'", name), 1);
+            }
+            let letter = |name: &String| match columns.iter().find(|(n, _)| n == name) {
+                Some((_, ty)) => column_letter(ty).expect("csv column type"),
+                None => ' ',
+            };
+            header.iter().map(letter).collect()
+        }
+    };
+
+    let mut parsed: Vec<Column> = types.chars().map(Column::new).collect();
+    for record in records {
+        let (line, fields) = record?;
+        check_len(&fields, header.len(), line)?;
+        for ((column, field), name) in parsed.iter_mut().zip(&fields).zip(&header) {
+            column
+                .push(field)
+                .map_err(|msg| CsvError { msg: format!("{} in column '{}'", msg, name), line: Some(line) })?;
+        }
+    }
+
+    let mut values: Vec<(&String, BSValue)> = header
+        .iter()
+        .zip(parsed)
+        .filter_map(|(name, column)| Some((name, column.into_value()?)))
+        .collect();
+    // the columns named are in the order of their type
+    if let Columns::Named(columns) = columns {
+        values.sort_by_key(|(name, _)| columns.iter().position(|(n, _)| n == *name));
+    }
+
+    let mut table = Table::default();
+    for (name, column) in values {
+        table = table.with_column(name, column).expect("csv column");
+    }
+    Ok(table)
+}
+
+/// Text of `field`, quoted if it holds the delimiter, a quote or a line break.
+fn quote(field: &str, delim: u8) -> String {
+    match field
+        .bytes()
+        .any(|c| c == delim || c == b'"' || c == b'\n' || c == b'\r')
+    {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+/// Text of the element `row` of the vector `column`, empty if it is null.
+fn cell(column: &BSValue, row: usize, delim: u8) -> String {
+    fn elem<T: VecElement>(column: &BSValue, row: usize) -> T {
+        unsafe { VecHeader::from_raw(column.as_raw()).as_slice::<T>()[row] }
+    }
+
+    fn temporal(x: BSValue) -> String {
+        match x.as_raw() {
+            NULL_INT64 => String::new(),
+            _ => x.to_string(),
+        }
+    }
+
+    match column.get_type() {
+        BSType::VecInt64 => match elem::<i64>(column, row) {
+            NULL_INT64 => String::new(),
+            x => x.to_string(),
+        },
+        BSType::VecFloat64 => match elem::<f64>(column, row) {
+            x if x.is_nan() => String::new(),
+            x => format!("{:?}", x),
+        },
+        BSType::VecBool => elem::<bool>(column, row).to_string(),
+        BSType::VecSym => quote(elem::<Sym>(column, row).name(), delim),
+        BSType::VecDate => temporal(elem::<Date>(column, row).into()),
+        BSType::VecTime => temporal(elem::<Time>(column, row).into()),
+        BSType::VecTimestamp => temporal(elem::<Timestamp>(column, row).into()),
+        BSType::VecTimespan => temporal(elem::<Timespan>(column, row).into()),
+        ty => unreachable!("table column of type {}", ty),
+    }
+}
+
+/// Writes `table` to the file `path`, returning the number of rows written.
+pub fn write(path: &str, table: &Table, delim: u8) -> Result<usize, CsvError> {
+    let sep = (delim as char).to_string();
+    let names: Vec<String> = table.names().iter().map(|n| quote(n, delim)).collect();
+    let mut text = names.join(&sep);
+    text.push('\n');
+    for row in 0..table.len() {
+        let cells: Vec<String> = table.columns().iter().map(|c| cell(c, row, delim)).collect();
+        text.push_str(&cells.join(&sep));
+        text.push('\n');
+    }
+    fs::write(path, text).map_err(|e| CsvError { msg: e.to_string(), line: None })?;
+    Ok(table.len())
+}
+
+fn string<'a>(raw: i64) -> &'a str { unsafe { <&str>::from_abi(raw) } }
+
+/// Byte of the delimiter `delim`, which must be a single character but a quote or a line break.
+pub fn delimiter(delim: &str) -> Result<u8, String> {
+    match delim.as_bytes() {
+        [c] if *c != b'"' && *c != b'\n' => Ok(*c),
+        _ => Err(format!("Invalid delimiter '{}', expected a single character", delim)),
+    }
+}
+
+/// Returns the table of the CSV file `path`, of the columns of the letters `types`, or else of the
+/// columns of the type named `ty` read by name, delimited by `delim`, all of them strings, raising
+/// an error unless it is of the type `ty`.
+pub(crate) extern "C" fn csv_read(path: i64, types: i64, delim: i64, ty: i64) -> i64 {
+    let (path, ty) = (string(path), string(ty));
+    let named = match Parser::parse_type_str(ty) {
+        BSResult::Ok(BSType::Table(columns)) => columns,
+        _ => vec![],
+    };
+    let columns = match string(types) {
+        "" => Columns::Named(&named),
+        types => Columns::Letters(types),
+    };
+    let res = delimiter(string(delim))
+        .map_err(|msg| CsvError { msg, line: None })
+        .and_then(|delim| read(path, columns, delim))
+        .and_then(|table| match table.get_type() {
+            table_ty if table_ty.to_string() == ty => Ok(table),
+            table_ty => error(format!("The file holds a table of type {}, not {}", table_ty, ty), 1),
+        });
+    match res {
+        Ok(table) => BSValue::from(table).into_raw(),
+        Err(err) => {
+            raise_io(&err.msg, path, err.line);
+            0
+        }
+    }
+}
+
+/// Writes `table` to the CSV file `path`, delimited by `delim`, returning the number of rows.
+pub(crate) extern "C" fn csv_write_delim(path: i64, table: i64, delim: i64) -> i64 {
+    let (path, table) = (string(path), unsafe { Table::from_raw(table) });
+    let res = delimiter(string(delim))
+        .map_err(|msg| CsvError { msg, line: None })
+        .and_then(|delim| write(path, table, delim));
+    match res {
+        Ok(rows) => rows as i64,
+        Err(err) => {
+            raise_io(&err.msg, path, err.line);
+            0
+        }
+    }
+}
+
+/// Writes `table` to the CSV file `path`, delimited by commas, returning the number of rows.
+pub(crate) extern "C" fn csv_write(path: i64, table: i64) -> i64 {
+    let (path, table) = (string(path), unsafe { Table::from_raw(table) });
+    match write(path, table, b',') {
+        Ok(rows) => rows as i64,
+        Err(err) => {
+            raise_io(&err.msg, path, err.line);
+            0
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Embedding API for Rust host applications.
+//!
+//! An [`Engine`] owns a runtime. Sources are compiled once with [`Engine::compile`], after which
+//! their functions can be looked up with a Rust signature and called repeatedly:
+//!
+//! ```ignore
+//! let mut engine = Engine::new()?;
+//! engine.compile("fn scale |x:Int64, k:Float64| { k * 2.0 }")?;
+//! let scale = engine.get_fn::<(i64, f64), f64>("scale")?;
+//! assert_eq!(scale.call((1, 1.5)), 3.0);
+//! ```
+//!
+//! Every compiled module stays alive as long as the engine, so a [`TypedFn`] is valid for as long
+//! as it borrows the engine. Signature or type mismatches are reported as [`BSError`]s.
+
+use crate::result::*;
+use crate::rt::error;
+use crate::rt::runtime::{overload_symbol, Runtime};
+use ffi::external::HostFn;
+use ffi::types::fn_type::FnType;
+use ffi::types::native_type::NativeType;
+use ffi::types::Type as BSType;
+use ffi::values::Value as BSValue;
+use std::marker::PhantomData;
+use std::mem::transmute;
+
+/// Argument lists which can be passed to a compiled function, implemented for tuples of
+/// [`NativeType`]s.
+pub trait NativeArgs {
+    fn bs_types() -> Vec<BSType>;
+
+    /// Calls the function at `addr`.
+    ///
+    /// # Safety
+    /// `addr` must point to a function whose signature matches `Self` and `R`.
+    unsafe fn call<R: NativeType>(self, addr: usize) -> R;
+}
+
+macro_rules! impl_native_args {
+    ($($arg:ident),*) => {
+        impl<$($arg: NativeType),*> NativeArgs for ($($arg,)*) {
+            fn bs_types() -> Vec<BSType> { vec![$($arg::bs_type()),*] }
+
+            #[allow(non_snake_case)]
+            unsafe fn call<R: NativeType>(self, addr: usize) -> R {
+                let ($($arg,)*) = self;
+                let f: extern "C" fn($($arg),*) -> R = transmute(addr);
+                f($($arg),*)
+            }
+        }
+    };
+}
+
+impl_native_args!();
+impl_native_args!(A);
+impl_native_args!(A, B);
+impl_native_args!(A, B, C);
+impl_native_args!(A, B, C, D);
+impl_native_args!(A, B, C, D, E);
+impl_native_args!(A, B, C, D, E, F);
+
+type Signature<A, R> = fn(A) -> R;
+
+/// A compiled function with a checked Rust signature.
+pub struct TypedFn<'e, A, R> {
+    addr: usize,
+    _phantom: PhantomData<(&'e Engine, Signature<A, R>)>,
+}
+
+impl<'e, A: NativeArgs, R: NativeType> TypedFn<'e, A, R> {
+    pub fn call(&self, args: A) -> R { unsafe { args.call(self.addr) } }
+
+    /// Calls the function, returning the error it raises, if any, instead of its result.
+    pub fn try_call(&self, args: A) -> BSResult<R> {
+        error::take();
+        let result = self.call(args);
+        error::check()?;
+        ok(result)
+    }
+}
+
+/// Handle to a compiled source.
+#[derive(Debug, Clone)]
+pub struct Script {
+    module: String,
+    functions: Vec<String>,
+}
+
+impl Script {
+    /// Name of the module the source was compiled into.
+    pub fn module(&self) -> &str { &self.module }
+
+    /// Functions defined by the source.
+    pub fn functions(&self) -> &[String] { &self.functions }
+}
+
+pub struct Engine {
+    runtime: Box<Runtime<'static>>,
+    scripts: usize,
+    current: Option<String>,
+}
+
+impl Engine {
+    pub fn new() -> BSResult<Self> { ok(Self { runtime: Runtime::new()?, scripts: 0, current: None }) }
+
+    /// Compiles `source` into a new module. Functions defined by earlier scripts remain callable
+    /// from it; top-level expressions are compiled but not run.
+    pub fn compile(&mut self, source: &str) -> BSResult<Script> {
+        let module = format!("script{}", self.scripts);
+        let (_, functions) = self.runtime.compile_module(&module, source)?;
+        self.scripts += 1;
+        self.current = Some(module.clone());
+
+        ok(Script { module, functions })
+    }
+
+    /// Makes integer arithmetic of scripts compiled afterwards raise an error on overflow, see
+    /// [`TypedFn::try_call`].
+    pub fn set_checked_arithmetic(&mut self, checked: bool) { self.runtime.set_checked_arithmetic(checked) }
+
+    /// Registers a Rust function or closure callable from scripts compiled afterwards.
+    pub fn register_fn<Args, F: HostFn<Args>>(&mut self, name: &str, f: F) { self.runtime.register_fn(name, f) }
+
+    /// Compiles and runs `source`, returning the value of its last top-level expression.
+    pub fn eval(&mut self, source: &str) -> BSResult<BSValue> { self.runtime.parse_eval(source) }
+
+    /// Looks up the function `name` of the latest compiled script, checking its signature
+    /// against `A` and `R`.
+    pub fn get_fn<A: NativeArgs, R: NativeType>(&self, name: &str) -> BSResult<TypedFn<'_, A, R>> {
+        let module = match &self.current {
+            Some(module) => module,
+            None => return runtime_error("No script has been compiled".to_string()),
+        };
+
+        let globals = self.runtime.globals();
+        let symbol = overload_symbol(name, &A::bs_types());
+        let expected = FnType::new(A::bs_types(), R::bs_type());
+
+        let fn_ty = match globals.get_value(&symbol).map(|v| v.get_type()) {
+            Some(BSType::Fn(fn_ty)) => fn_ty.clone(),
+            _ if !globals.overloads(name).is_empty() => {
+                return compile_error(
+                    format!("Signature mismatch for '{}'", name),
+                    format!(
+                        "'{}' was requested, but only {} are defined",
+                        BSType::Fn(expected),
+                        globals.overloads(name).join(", ")
+                    ),
+                    None,
+                )
+            }
+            _ => match globals.get_value(name) {
+                Some(value) => {
+                    return compile_error(
+                        format!("'{}' is not a function", name),
+                        format!("'{}' can not be called", value.get_type()),
+                        None,
+                    )
+                }
+                None => {
+                    return compile_error(
+                        format!("Undefined function '{}'", name),
+                        "Function not found".to_string(),
+                        None,
+                    )
+                }
+            },
+        };
+
+        if fn_ty != expected {
+            return compile_error(
+                format!("Signature mismatch for '{}'", name),
+                format!("'{}' is '{}', but '{}' was requested", name, BSType::Fn(fn_ty), BSType::Fn(expected)),
+                None,
+            );
+        }
+
+        let addr = self.runtime.get_module(module).unwrap().get_function_address(&symbol)?;
+
+        ok(TypedFn { addr, _phantom: PhantomData })
+    }
+
+    /// Sets the global `name`, visible to scripts compiled afterwards and to already compiled ones.
+    /// Compiled code relies on the type of a global, which can therefore not be changed.
+    pub fn set_global<T: Into<BSValue>>(&mut self, name: &str, value: T) -> BSResult<()> {
+        let value = value.into();
+        if let Some(old) = self.runtime.globals().get_value(name) {
+            if old.get_type() != value.get_type() {
+                return runtime_error(format!(
+                    "Global '{}' is of type '{}', it can not be set to a value of type '{}'",
+                    name,
+                    old.get_type(),
+                    value.get_type()
+                ));
+            }
+        }
+        self.runtime.globals_mut().add(name, value);
+        ok(())
+    }
+
+    pub fn get_global<T: NativeType>(&self, name: &str) -> BSResult<T> {
+        match self.runtime.globals().get_value(name) {
+            Some(value) => match T::from_value(value) {
+                Some(v) => ok(v),
+                None => runtime_error(format!(
+                    "Global '{}' is of type '{}', but '{}' was requested",
+                    name,
+                    value.get_type(),
+                    T::bs_type()
+                )),
+            },
+            None => runtime_error(format!("Undefined global '{}'", name)),
+        }
+    }
+
+    pub fn runtime(&mut self) -> &mut Runtime<'static> { &mut self.runtime }
+}
@@ -0,0 +1,87 @@
+//! Runtime errors raised by compiled code.
+//!
+//! Compiled code can not unwind, so an error is raised by recording it for the current thread.
+//! Compiled functions test for a raised error after every call and every check that can fail, and
+//! then return null right away, releasing their local variables. The runtime takes the error once
+//! the top-level function has returned, and reports it instead of the result. Only the first error
+//! raised is kept.
+//!
+//! Builtins raise errors with [`raise`], and otherwise return as usual. Other values being
+//! evaluated when an error is raised, such as the arguments of a call before the failing one, are
+//! released on the way to the handler of the enclosing `try`, or out of the function.
+
+use crate::parse::span::Span;
+use crate::result::*;
+use ffi::values::Value;
+use std::cell::RefCell;
+
+/// An error raised by compiled code.
+#[derive(Debug, Clone)]
+pub struct RaisedError {
+    pub msg: String,
+    pub span: Option<Span>,
+    /// File, and line in it, of an I/O error.
+    pub file: Option<(String, Option<usize>)>,
+}
+
+/// A check or call in compiled code which can fail, kept by its module and referred to by address.
+pub(crate) struct ErrorSite {
+    pub msg: String,
+    pub span: Option<Span>,
+}
+
+thread_local! {
+    static RAISED: RefCell<Option<RaisedError>> = const { RefCell::new(None) };
+}
+
+/// Raises the error `msg`, unless another error is raised already.
+pub fn raise(msg: &str) { raise_error(RaisedError { msg: msg.to_string(), span: None, file: None }) }
+
+/// Raises the I/O error `msg` for the file `path`, at `line` if there is one.
+pub fn raise_io(msg: &str, path: &str, line: Option<usize>) {
+    raise_error(RaisedError { msg: msg.to_string(), span: None, file: Some((path.to_string(), line)) })
+}
+
+fn raise_error(error: RaisedError) {
+    RAISED.with(|raised| {
+        raised.borrow_mut().get_or_insert(error);
+    })
+}
+
+/// Takes the raised error, if any.
+pub(crate) fn take() -> Option<RaisedError> { RAISED.with(|raised| raised.borrow_mut().take()) }
+
+/// Takes the raised error, if any, as a runtime or I/O error.
+pub(crate) fn check() -> BSResult<()> {
+    match take() {
+        Some(RaisedError { msg, span, file: None }) => BSResult::Err(BSError::RuntimeError { msg, span }),
+        Some(RaisedError { msg, span, file: Some((path, line)) }) => io_error(msg, path, line, span),
+        None => ok(()),
+    }
+}
+
+/// Called by compiled code when the check `site` fails.
+pub(crate) extern "C" fn raise_at(site: *const ErrorSite) {
+    let site = unsafe { &*site };
+    raise_error(RaisedError { msg: site.msg.clone(), span: site.span, file: None })
+}
+
+/// Called by compiled code after the call `site`, returning whether an error has been raised. An
+/// error raised by a builtin is located at the call.
+pub(crate) extern "C" fn raised_at(site: *const ErrorSite) -> bool {
+    RAISED.with(|raised| match raised.borrow_mut().as_mut() {
+        Some(error) => {
+            if error.span.is_none() {
+                error.span = unsafe { (*site).span };
+            }
+            true
+        }
+        None => false,
+    })
+}
+
+/// Called by compiled code handling an error, taking it and returning its message as a string.
+pub(crate) extern "C" fn catch() -> i64 {
+    let msg = take().map(|error| error.msg).unwrap_or_default();
+    Value::from(msg).into_raw()
+}
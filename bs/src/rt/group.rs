@@ -0,0 +1,285 @@
+//! Hash grouping and aggregation of rows.
+//!
+//! Rows are grouped by an open addressing table of group ids, probed linearly, which compares the
+//! keys of a row with those of the first row of a group, so it holds no more than an id per group.
+//! Groups are numbered from 0 in order of appearance, which lets the aggregates of every group be
+//! accumulated into vectors indexed by group, in a single pass over the rows and without ordering
+//! them by group.
+
+use ffi::values::sym::Sym;
+use ffi::values::time::{Date, Time, Timespan, Timestamp};
+use ffi::values::vector::VecElement;
+use ffi::values::Value as BSValue;
+use ffi::values::{NULL_FLOAT64, NULL_INT64};
+
+const EMPTY: u32 = u32::MAX;
+
+/// Mixes the bits of a key, so that keys differing in their high bits probe different slots.
+pub fn hash(bits: u64) -> u64 {
+    let h = (bits ^ (bits >> 33)).wrapping_mul(0xff51_afd7_ed55_8ccd);
+    let h = (h ^ (h >> 33)).wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^ (h >> 33)
+}
+
+/// Groups of rows, numbered from 0 in order of appearance.
+pub struct Grouping {
+    /// Group of every row.
+    pub ids: Vec<i64>,
+    /// First row of every group.
+    pub firsts: Vec<i64>,
+}
+
+impl Grouping {
+    /// Groups `len` rows by the hash of their keys `hash` and whether two rows have equal keys `eq`.
+    pub fn new(len: usize, hash: impl Fn(usize) -> u64, eq: impl Fn(usize, usize) -> bool) -> Grouping {
+        let mut slots = vec![EMPTY; 16];
+        let mut hashes: Vec<u64> = vec![];
+        let mut firsts = vec![];
+        let mut ids = Vec::with_capacity(len);
+
+        for row in 0..len {
+            // the table is at most half full
+            if 2 * hashes.len() >= slots.len() {
+                slots = vec![EMPTY; 2 * slots.len()];
+                for (id, h) in hashes.iter().enumerate() {
+                    let mut slot = *h as usize & (slots.len() - 1);
+                    while slots[slot] != EMPTY {
+                        slot = (slot + 1) & (slots.len() - 1);
+                    }
+                    slots[slot] = id as u32;
+                }
+            }
+
+            let h = hash(row);
+            let mut slot = h as usize & (slots.len() - 1);
+            let id = loop {
+                match slots[slot] {
+                    EMPTY => {
+                        slots[slot] = hashes.len() as u32;
+                        hashes.push(h);
+                        firsts.push(row as i64);
+                        break hashes.len() - 1;
+                    }
+                    id if eq(firsts[id as usize] as usize, row) => break id as usize,
+                    _ => slot = (slot + 1) & (slots.len() - 1),
+                }
+            };
+            ids.push(id as i64);
+        }
+        Grouping { ids, firsts }
+    }
+
+    pub fn len(&self) -> usize { self.firsts.len() }
+
+    pub fn is_empty(&self) -> bool { self.firsts.is_empty() }
+
+    /// Rows sorted by group, keeping the order of the rows of every group, and the start of every
+    /// group in it, followed by the number of rows.
+    pub fn order(&self) -> (Vec<i64>, Vec<i64>) { order(&self.ids, self.len()) }
+}
+
+/// Rows of the groups `ids` of `groups` groups, sorted by group, and the bounds of the groups in
+/// them, see `Grouping::order`.
+pub fn order(ids: &[i64], groups: usize) -> (Vec<i64>, Vec<i64>) {
+    let mut bounds = vec![0; groups + 1];
+    for id in ids {
+        bounds[*id as usize + 1] += 1;
+    }
+    for g in 0..groups {
+        bounds[g + 1] += bounds[g];
+    }
+
+    let mut next = bounds.clone();
+    let mut order = vec![0; ids.len()];
+    for (row, id) in ids.iter().enumerate() {
+        order[next[*id as usize] as usize] = row as i64;
+        next[*id as usize] += 1;
+    }
+    (order, bounds)
+}
+
+/// Reductions computed by group in a single pass over the rows, see `builtins::aggregate`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(i64)]
+pub enum Agg {
+    Sum = 0,
+    Prod,
+    Min,
+    Max,
+    Avg,
+    Count,
+    First,
+    Last,
+}
+
+impl Agg {
+    pub fn from_name(name: &str) -> Option<Agg> {
+        match name {
+            "sum" => Some(Agg::Sum),
+            "prod" => Some(Agg::Prod),
+            "min" => Some(Agg::Min),
+            "max" => Some(Agg::Max),
+            "avg" => Some(Agg::Avg),
+            "count" => Some(Agg::Count),
+            "first" => Some(Agg::First),
+            "last" => Some(Agg::Last),
+            _ => None,
+        }
+    }
+
+    pub fn from_raw(raw: i64) -> Agg {
+        [Agg::Sum, Agg::Prod, Agg::Min, Agg::Max, Agg::Avg, Agg::Count, Agg::First, Agg::Last][raw as usize]
+    }
+}
+
+/// Groups of the rows, all of them in a single group if there are no ids.
+#[derive(Clone, Copy)]
+pub struct Groups<'a> {
+    pub ids: Option<&'a [i64]>,
+    pub len: usize,
+}
+
+impl Groups<'_> {
+    fn fold<T: Copy, A: Copy>(&self, vals: &[T], init: A, f: impl Fn(A, T) -> A) -> Vec<A> {
+        let mut acc = vec![init; self.len];
+        match self.ids {
+            Some(ids) => {
+                for (id, x) in ids.iter().zip(vals) {
+                    acc[*id as usize] = f(acc[*id as usize], *x);
+                }
+            }
+            None => acc[0] = vals.iter().fold(init, |a, x| f(a, *x)),
+        }
+        acc
+    }
+
+    fn count<T: Copy>(&self, vals: &[T], counted: impl Fn(T) -> bool) -> Vec<i64> {
+        self.fold(vals, 0, |n, x| n + counted(x) as i64)
+    }
+
+    fn first<T: VecElement>(&self, vals: &[T]) -> Vec<T> {
+        let first = self.fold(vals, None, |a: Option<T>, x| a.or(Some(x)));
+        first.into_iter().map(|x| x.unwrap_or(T::NULL)).collect()
+    }
+
+    fn last<T: VecElement>(&self, vals: &[T]) -> Vec<T> { self.fold(vals, T::NULL, |_, x| x) }
+
+    fn avg(&self, sums: Vec<f64>, counts: Vec<i64>) -> Vec<f64> {
+        let avg = |(s, n): (f64, i64)| if n == 0 { NULL_FLOAT64 } else { s / n as f64 };
+        sums.into_iter().zip(counts).map(avg).collect()
+    }
+}
+
+/// Vector elements which can be aggregated by group, into a vector of the type the reduction of
+/// the same name returns.
+pub trait Aggregate: VecElement {
+    fn aggregate(agg: Agg, groups: Groups, vals: &[Self]) -> BSValue;
+}
+
+impl Aggregate for i64 {
+    fn aggregate(agg: Agg, groups: Groups, vals: &[i64]) -> BSValue {
+        let non_null = |f: fn(i64, i64) -> i64| move |a, x| if x == NULL_INT64 { a } else { f(a, x) };
+        let extreme = |f: fn(i64, i64) -> i64| {
+            move |a, x| {
+                if a == NULL_INT64 {
+                    x
+                } else if x == NULL_INT64 {
+                    a
+                } else {
+                    f(a, x)
+                }
+            }
+        };
+        match agg {
+            Agg::Sum => groups.fold(vals, 0, non_null(i64::wrapping_add)).into(),
+            Agg::Prod => groups.fold(vals, 1, non_null(i64::wrapping_mul)).into(),
+            Agg::Min => groups.fold(vals, NULL_INT64, extreme(i64::min)).into(),
+            Agg::Max => groups.fold(vals, NULL_INT64, extreme(i64::max)).into(),
+            Agg::Avg => {
+                let sums = groups.fold(vals, 0.0, |a, x| if x == NULL_INT64 { a } else { a + x as f64 });
+                groups.avg(sums, groups.count(vals, |x| x != NULL_INT64)).into()
+            }
+            Agg::Count => groups.count(vals, |_| true).into(),
+            Agg::First => groups.first(vals).into(),
+            Agg::Last => groups.last(vals).into(),
+        }
+    }
+}
+
+impl Aggregate for f64 {
+    fn aggregate(agg: Agg, groups: Groups, vals: &[f64]) -> BSValue {
+        let non_null = |f: fn(f64, f64) -> f64| move |a, x: f64| if x.is_nan() { a } else { f(a, x) };
+        match agg {
+            Agg::Sum => groups.fold(vals, 0.0, non_null(|a, x| a + x)).into(),
+            Agg::Prod => groups.fold(vals, 1.0, non_null(|a, x| a * x)).into(),
+            // the min and max of a number and NaN are the number
+            Agg::Min => groups.fold(vals, NULL_FLOAT64, f64::min).into(),
+            Agg::Max => groups.fold(vals, NULL_FLOAT64, f64::max).into(),
+            Agg::Avg => {
+                let sums = groups.fold(vals, 0.0, non_null(|a, x| a + x));
+                groups.avg(sums, groups.count(vals, |x| !x.is_nan())).into()
+            }
+            Agg::Count => groups.count(vals, |_| true).into(),
+            Agg::First => groups.first(vals).into(),
+            Agg::Last => groups.last(vals).into(),
+        }
+    }
+}
+
+impl Aggregate for bool {
+    fn aggregate(agg: Agg, groups: Groups, vals: &[bool]) -> BSValue {
+        match agg {
+            Agg::Sum => groups.count(vals, |x| x).into(),
+            Agg::Prod => groups.fold(vals, 1, |a, x| a & x as i64).into(),
+            Agg::Min => groups.fold(vals, true, |a, x| a && x).into(),
+            Agg::Max => groups.fold(vals, false, |a, x| a || x).into(),
+            Agg::Avg => {
+                let sums = groups.fold(vals, 0.0, |a, x| a + x as i64 as f64);
+                groups.avg(sums, groups.count(vals, |_| true)).into()
+            }
+            Agg::Count => groups.count(vals, |_| true).into(),
+            Agg::First => groups.first(vals).into(),
+            Agg::Last => groups.last(vals).into(),
+        }
+    }
+}
+
+impl Aggregate for Sym {
+    fn aggregate(agg: Agg, groups: Groups, vals: &[Sym]) -> BSValue {
+        match agg {
+            Agg::Count => groups.count(vals, |_| true).into(),
+            Agg::First => groups.first(vals).into(),
+            Agg::Last => groups.last(vals).into(),
+            agg => unreachable!("{:?} of symbols", agg),
+        }
+    }
+}
+
+/// Dates and times, of which only the extremes, the count and the first and last are aggregated.
+macro_rules! impl_aggregate_temporal {
+    ($($ty:ident),*) => {
+        $(
+            impl Aggregate for $ty {
+                fn aggregate(agg: Agg, groups: Groups, vals: &[$ty]) -> BSValue {
+                    let extreme = |f: fn(i64, i64) -> i64| {
+                        move |a: $ty, x: $ty| match (a.0, x.0) {
+                            (NULL_INT64, _) => x,
+                            (_, NULL_INT64) => a,
+                            (a, x) => $ty(f(a, x)),
+                        }
+                    };
+                    match agg {
+                        Agg::Min => groups.fold(vals, <$ty>::NULL, extreme(i64::min)).into(),
+                        Agg::Max => groups.fold(vals, <$ty>::NULL, extreme(i64::max)).into(),
+                        Agg::Count => groups.count(vals, |_| true).into(),
+                        Agg::First => groups.first(vals).into(),
+                        Agg::Last => groups.last(vals).into(),
+                        agg => unreachable!("{:?} of dates and times", agg),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_aggregate_temporal!(Date, Time, Timestamp, Timespan);
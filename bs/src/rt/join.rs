@@ -0,0 +1,233 @@
+//! Joins of tables, see `ops::table::join`.
+//!
+//! The rows of both tables are matched by grouping them together by their key columns: a row of
+//! the left table is matched by the first row of the right table in its group, if the group has
+//! one, the row its first row is. The types of the tables are checked when compiling, so the key
+//! columns of both are known to exist and be of the same type.
+
+use crate::rt::error::raise;
+use crate::rt::group::{hash, order, Grouping};
+use ffi::types::Type as BSType;
+use ffi::values::dict::DictKey;
+use ffi::values::sym::Sym;
+use ffi::values::table::Table;
+use ffi::values::time::{Date, Time, Timespan, Timestamp};
+use ffi::values::vector::{VecElement, VecHeader};
+use ffi::values::Value as BSValue;
+
+/// Row of the right table matched by a row of the left one, none if it is negative.
+const NO_MATCH: i64 = -1;
+
+fn slice<'a, T: VecElement>(column: &BSValue) -> &'a [T] { unsafe { VecHeader::from_raw(column.as_raw()).as_slice() } }
+
+fn names(keys: i64) -> Vec<&'static str> {
+    let keys = unsafe { VecHeader::from_raw(keys).as_slice::<Sym>() };
+    keys.iter().map(|k| k.name()).collect()
+}
+
+/// Bits of every element of the vector `column`, see `DictKey::bits`.
+fn bits(column: &BSValue) -> Vec<u64> {
+    fn bits<T: DictKey>(column: &BSValue) -> Vec<u64> { slice::<T>(column).iter().map(|x| x.bits()).collect() }
+
+    match column.get_type() {
+        BSType::VecInt64 => bits::<i64>(column),
+        BSType::VecFloat64 => bits::<f64>(column),
+        BSType::VecBool => bits::<bool>(column),
+        BSType::VecSym => bits::<Sym>(column),
+        BSType::VecDate => bits::<Date>(column),
+        BSType::VecTime => bits::<Time>(column),
+        BSType::VecTimestamp => bits::<Timestamp>(column),
+        BSType::VecTimespan => bits::<Timespan>(column),
+        ty => unreachable!("table column of type {}", ty),
+    }
+}
+
+/// Groups the rows of `right`, followed by those of `left`, by their `keys`.
+fn group(left: &Table, right: &Table, keys: &[&str]) -> Grouping {
+    let key_bits = |table: &Table| -> Vec<Vec<u64>> {
+        keys.iter()
+            .map(|k| bits(table.column(k).expect("key column")))
+            .collect()
+    };
+    let (left_bits, right_bits) = (key_bits(left), key_bits(right));
+    let split = right.len();
+    let key = |c: usize, row: usize| match row < split {
+        true => right_bits[c][row],
+        false => left_bits[c][row - split],
+    };
+
+    Grouping::new(
+        split + left.len(),
+        |row| (0..keys.len()).fold(0, |h, c| hash(h ^ key(c, row))),
+        |i, j| (0..keys.len()).all(|c| key(c, i) == key(c, j)),
+    )
+}
+
+/// First row of `right` with the `keys` of every row of `left`.
+fn matches(left: &Table, right: &Table, keys: &[&str]) -> Vec<i64> {
+    let grouping = group(left, right, keys);
+    let split = right.len() as i64;
+    grouping.ids[right.len()..]
+        .iter()
+        .map(|id| match grouping.firsts[*id as usize] {
+            first if first < split => first,
+            _ => NO_MATCH,
+        })
+        .collect()
+}
+
+/// Table of the `rows` of `left`, with the non key columns of `right` at the rows `matches` of it,
+/// or else those of `left`, or nulls.
+fn joined(left: &Table, right: &Table, keys: &[&str], rows: &[i64], matches: &[i64]) -> Table {
+    fn fill<T: VecElement>(column: &BSValue, base: Option<&BSValue>, rows: &[i64], matches: &[i64]) -> Vec<T> {
+        let column = slice::<T>(column);
+        let base = base.map(slice::<T>);
+        rows.iter()
+            .zip(matches)
+            .map(|(row, m)| match (*m, base) {
+                (NO_MATCH, Some(base)) => base[*row as usize],
+                (NO_MATCH, None) => T::NULL,
+                (m, _) => column[m as usize],
+            })
+            .collect()
+    }
+
+    let mut table = left.rows(rows);
+    for (name, column) in right.names().iter().zip(right.columns()) {
+        if keys.contains(&name.as_str()) {
+            continue;
+        }
+        let base = left.column(name);
+        let column = match column.get_type() {
+            BSType::VecInt64 => BSValue::from(fill::<i64>(column, base, rows, matches)),
+            BSType::VecFloat64 => BSValue::from(fill::<f64>(column, base, rows, matches)),
+            BSType::VecBool => BSValue::from(fill::<bool>(column, base, rows, matches)),
+            BSType::VecSym => BSValue::from(fill::<Sym>(column, base, rows, matches)),
+            BSType::VecDate => BSValue::from(fill::<Date>(column, base, rows, matches)),
+            BSType::VecTime => BSValue::from(fill::<Time>(column, base, rows, matches)),
+            BSType::VecTimestamp => BSValue::from(fill::<Timestamp>(column, base, rows, matches)),
+            BSType::VecTimespan => BSValue::from(fill::<Timespan>(column, base, rows, matches)),
+            ty => unreachable!("table column of type {}", ty),
+        };
+        table = table.set_column(name, column).expect("joined column");
+    }
+    table
+}
+
+/// Returns the table `left` with the columns of `right` at the first row with the same values of
+/// the columns `keys`, a symbol vector.
+pub(crate) extern "C" fn table_lj(keys: i64, left: i64, right: i64) -> i64 {
+    let (left, right, keys) = unsafe { (Table::from_raw(left), Table::from_raw(right), names(keys)) };
+    let matches = matches(left, right, &keys);
+    let rows: Vec<i64> = (0..left.len() as i64).collect();
+    BSValue::from(joined(left, right, &keys, &rows, &matches)).into_raw()
+}
+
+/// Returns the rows of `left` matched by a row of `right`, as joined by `table_lj`.
+pub(crate) extern "C" fn table_ij(keys: i64, left: i64, right: i64) -> i64 {
+    let (left, right, keys) = unsafe { (Table::from_raw(left), Table::from_raw(right), names(keys)) };
+    let (rows, matches): (Vec<i64>, Vec<i64>) = matches(left, right, &keys)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, m)| *m != NO_MATCH)
+        .map(|(row, m)| (row as i64, m))
+        .unzip();
+    BSValue::from(joined(left, right, &keys, &rows, &matches)).into_raw()
+}
+
+/// Returns the rows of `left` followed by those of `right`, with the columns of both, null where a
+/// table has no such column.
+pub(crate) extern "C" fn table_uj(left: i64, right: i64) -> i64 {
+    fn column<T: VecElement>(column: Option<&BSValue>, len: usize) -> Vec<T> {
+        column.map_or_else(|| vec![T::NULL; len], |c| slice::<T>(c).to_vec())
+    }
+
+    fn concat<T: VecElement>(left: Option<&BSValue>, right: Option<&BSValue>, lens: (usize, usize)) -> Vec<T> {
+        let mut vec = column::<T>(left, lens.0);
+        vec.extend(column::<T>(right, lens.1));
+        vec
+    }
+
+    let (left, right) = unsafe { (Table::from_raw(left), Table::from_raw(right)) };
+    let lens = (left.len(), right.len());
+    let mut table = Table::default();
+    for name in left
+        .names()
+        .iter()
+        .chain(right.names().iter().filter(|n| left.column(n).is_none()))
+    {
+        let (l, r) = (left.column(name), right.column(name));
+        let column = match l.or(r).expect("column").get_type() {
+            BSType::VecInt64 => BSValue::from(concat::<i64>(l, r, lens)),
+            BSType::VecFloat64 => BSValue::from(concat::<f64>(l, r, lens)),
+            BSType::VecBool => BSValue::from(concat::<bool>(l, r, lens)),
+            BSType::VecSym => BSValue::from(concat::<Sym>(l, r, lens)),
+            BSType::VecDate => BSValue::from(concat::<Date>(l, r, lens)),
+            BSType::VecTime => BSValue::from(concat::<Time>(l, r, lens)),
+            BSType::VecTimestamp => BSValue::from(concat::<Timestamp>(l, r, lens)),
+            BSType::VecTimespan => BSValue::from(concat::<Timespan>(l, r, lens)),
+            ty => unreachable!("table column of type {}", ty),
+        };
+        table = table.with_column(name, column).expect("union column");
+    }
+    BSValue::from(table).into_raw()
+}
+
+/// Last row of every group of `right`, as sorted by `order` and bounded by `bounds`, with a time
+/// at most that of the row of `left`, raising an error if the times of a group are not sorted.
+fn asof<T: VecElement + PartialOrd>(
+    left: &[T],
+    right: &[T],
+    ids: &[i64],
+    (order, bounds): (Vec<i64>, Vec<i64>),
+) -> Option<Vec<i64>> {
+    let sorted = bounds.windows(2).all(|b| {
+        let rows = &order[b[0] as usize..b[1] as usize];
+        rows.windows(2).all(|r| right[r[0] as usize] <= right[r[1] as usize])
+    });
+    if !sorted {
+        raise("The times of the right table of 'aj' must be sorted");
+        return None;
+    }
+
+    let matches = left.iter().zip(ids).map(|(time, id)| {
+        let rows = &order[bounds[*id as usize] as usize..bounds[*id as usize + 1] as usize];
+        match rows.partition_point(|r| right[*r as usize] <= *time) {
+            0 => NO_MATCH,
+            n => rows[n - 1],
+        }
+    });
+    Some(matches.collect())
+}
+
+/// Returns the table `left` with the columns of `right` at the last row with the same values of the
+/// columns `keys`, a symbol vector, but the last, and a time, the value of the last, at most that
+/// of the row of `left`. The times of the rows of `right` with the same keys must be sorted.
+pub(crate) extern "C" fn table_aj(keys: i64, left: i64, right: i64) -> i64 {
+    let (left, right, keys) = unsafe { (Table::from_raw(left), Table::from_raw(right), names(keys)) };
+    let (time, keys) = keys.split_last().expect("time column");
+
+    let grouping = group(left, right, keys);
+    let (right_ids, left_ids) = grouping.ids.split_at(right.len());
+    let order = order(right_ids, grouping.len());
+    let (left_times, right_times) = (left.column(time).expect("time"), right.column(time).expect("time"));
+    let matches = match left_times.get_type() {
+        BSType::VecInt64 => asof::<i64>(slice(left_times), slice(right_times), left_ids, order),
+        BSType::VecFloat64 => asof::<f64>(slice(left_times), slice(right_times), left_ids, order),
+        BSType::VecDate => asof::<Date>(slice(left_times), slice(right_times), left_ids, order),
+        BSType::VecTime => asof::<Time>(slice(left_times), slice(right_times), left_ids, order),
+        BSType::VecTimestamp => asof::<Timestamp>(slice(left_times), slice(right_times), left_ids, order),
+        BSType::VecTimespan => asof::<Timespan>(slice(left_times), slice(right_times), left_ids, order),
+        ty => unreachable!("time column of type {}", ty),
+    };
+
+    let mut keys = keys.to_vec();
+    keys.push(time);
+    match matches {
+        Some(matches) => {
+            let rows: Vec<i64> = (0..left.len() as i64).collect();
+            BSValue::from(joined(left, right, &keys, &rows, &matches)).into_raw()
+        }
+        None => 0,
+    }
+}
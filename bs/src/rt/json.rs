@@ -0,0 +1,656 @@
+//! Writing values as JSON and reading them back.
+//!
+//! Vectors and lists are written as arrays, dictionaries as objects of their keys and tables as
+//! arrays of records. Nulls are written as `null`, strings and symbols as strings, and dates and
+//! times as strings of their literals, which they are read back from.
+//!
+//! The type of a value read is known when compiling. It is given, or inferred from a literal:
+//! - an integer is an `Int64`, any other number or `null` a `Float64`;
+//! - an array of integers is an `Int64[]`, of other numbers a `Float64[]`, of booleans a `Bool[]`
+//!   and of strings a `Symbol[]`, any of them with nulls, of objects a table of the fields of the
+//!   objects, and of anything else a list. An empty array, or of nulls, is a `Float64[]`;
+//! - an object is a dictionary of symbols to the values of its fields, inferred as an array.
+
+use crate::parse::lexer::parse_temporal;
+use crate::parse::parser::Parser;
+use crate::result::BSResult;
+use crate::rt::error::raise;
+use ffi::external::HostArg;
+use ffi::types::Type as BSType;
+use ffi::values::dict::{Dict, DictKey};
+use ffi::values::sym::Sym;
+use ffi::values::time::{Date, Time, Timespan, Timestamp};
+use ffi::values::vector::{VecElement, VecHeader};
+use ffi::values::Value as BSValue;
+use ffi::values::{NULL_FLOAT64, NULL_INT64};
+use std::fmt::Write;
+
+/// A JSON value, of the fields of objects in order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn kind(&self) -> &'static str {
+        match self {
+            Json::Null => "null",
+            Json::Bool(_) => "a boolean",
+            Json::Int(_) | Json::Float(_) => "a number",
+            Json::Str(_) => "a string",
+            Json::Array(_) => "an array",
+            Json::Object(_) => "an object",
+        }
+    }
+}
+
+struct Reader<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn error<T>(&self, msg: &str) -> Result<T, String> { Err(format!("{} at offset {}", msg, self.pos)) }
+
+    fn skip_space(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&self) -> Option<u8> { self.text.as_bytes().get(self.pos).copied() }
+
+    fn eat(&mut self, token: &str) -> bool {
+        let found = self.text[self.pos..].starts_with(token);
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_space();
+        match self.peek() {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => Ok(Json::Str(self.string()?)),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ if self.eat("null") => Ok(Json::Null),
+            _ if self.eat("true") => Ok(Json::Bool(true)),
+            _ if self.eat("false") => Ok(Json::Bool(false)),
+            _ => self.error("Expected a value"),
+        }
+    }
+
+    /// Items of an array or object between `open` and `close`, each read by `item`.
+    fn items<T>(
+        &mut self,
+        open: &str,
+        close: &str,
+        item: fn(&mut Self) -> Result<T, String>,
+    ) -> Result<Vec<T>, String> {
+        self.eat(open);
+        let mut items = vec![];
+        self.skip_space();
+        if self.eat(close) {
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            self.skip_space();
+            if self.eat(close) {
+                return Ok(items);
+            }
+            if !self.eat(",") {
+                return self.error(&format!("Expected ',' or '{}'", close));
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> { Ok(Json::Array(self.items("[", "]", Self::value)?)) }
+
+    fn object(&mut self) -> Result<Json, String> {
+        let field = |reader: &mut Self| {
+            reader.skip_space();
+            if reader.peek() != Some(b'"') {
+                return reader.error("Expected a field name");
+            }
+            let name = reader.string()?;
+            reader.skip_space();
+            if !reader.eat(":") {
+                return reader.error("Expected ':'");
+            }
+            Ok((name, reader.value()?))
+        };
+        Ok(Json::Object(self.items("{", "}", field)?))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut s = String::new();
+        loop {
+            let rest = &self.text[self.pos..];
+            let n = match rest.find(['"', '\\']) {
+                Some(n) => n,
+                None => return self.error("Unterminated string"),
+            };
+            s.push_str(&rest[..n]);
+            self.pos += n + 1;
+            if rest.as_bytes()[n] == b'"' {
+                return Ok(s);
+            }
+
+            let escaped = match self.peek() {
+                Some(b'"') => '"',
+                Some(b'\\') => '\\',
+                Some(b'/') => '/',
+                Some(b'b') => '\u{8}',
+                Some(b'f') => '\u{c}',
+                Some(b'n') => '\n',
+                Some(b'r') => '\r',
+                Some(b't') => '\t',
+                Some(b'u') => {
+                    let code = self
+                        .text
+                        .get(self.pos + 1..self.pos + 5)
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok());
+                    self.pos += 4;
+                    match code.and_then(char::from_u32) {
+                        Some(c) => c,
+                        None => return self.error("Invalid unicode escape"),
+                    }
+                }
+                _ => return self.error("Invalid escape"),
+            };
+            s.push(escaped);
+            self.pos += 1;
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let rest = &self.text[self.pos..];
+        let len = rest
+            .find(|c: char| !matches!(c, '0'..='9' | '-' | '+' | '.' | 'e' | 'E'))
+            .unwrap_or(rest.len());
+        let number = &rest[..len];
+        let json = match number.parse::<i64>() {
+            Ok(x) => Json::Int(x),
+            Err(_) => match number.parse::<f64>() {
+                Ok(x) => Json::Float(x),
+                Err(_) => return self.error("Invalid number"),
+            },
+        };
+        self.pos += len;
+        Ok(json)
+    }
+}
+
+/// Reads the JSON text `text`.
+pub fn parse(text: &str) -> Result<Json, String> {
+    let mut reader = Reader { text, pos: 0 };
+    let json = reader.value()?;
+    reader.skip_space();
+    match reader.pos == text.len() {
+        true => Ok(json),
+        false => reader.error("Unexpected text after the value"),
+    }
+}
+
+/// Type of the vector of the scalars `items`, if they are all scalars of one type or null.
+fn infer_vec_type<'a>(items: impl Iterator<Item = &'a Json>) -> Option<BSType> {
+    let (mut ints, mut floats, mut bools, mut strs) = (false, false, false, false);
+    for item in items {
+        match item {
+            Json::Null => {}
+            Json::Int(_) => ints = true,
+            Json::Float(_) => floats = true,
+            Json::Bool(_) => bools = true,
+            Json::Str(_) => strs = true,
+            _ => return None,
+        }
+    }
+    match (ints, floats, bools, strs) {
+        (true, false, false, false) => Some(BSType::VecInt64),
+        (_, _, false, false) => Some(BSType::VecFloat64),
+        (false, false, true, false) => Some(BSType::VecBool),
+        (false, false, false, true) => Some(BSType::VecSym),
+        _ => None,
+    }
+}
+
+/// Names of the fields of the `records`, in order of appearance.
+fn field_names(records: &[Json]) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for record in records {
+        if let Json::Object(fields) = record {
+            for (name, _) in fields {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+    }
+    names
+}
+
+fn field<'a>(record: &'a Json, name: &str) -> &'a Json {
+    match record {
+        Json::Object(fields) => fields.iter().find(|(n, _)| n == name).map_or(&Json::Null, |(_, v)| v),
+        _ => &Json::Null,
+    }
+}
+
+/// Type of the value read from `json`, see the module documentation.
+pub fn infer_type(json: &Json) -> Result<BSType, String> {
+    match json {
+        Json::Null | Json::Float(_) => Ok(BSType::Float64),
+        Json::Int(_) => Ok(BSType::Int64),
+        Json::Bool(_) => Ok(BSType::Bool),
+        Json::Str(_) => Ok(BSType::Str),
+        Json::Array(items) if !items.is_empty() && items.iter().all(|i| matches!(i, Json::Object(_))) => {
+            let mut columns = vec![];
+            for name in field_names(items) {
+                match infer_vec_type(items.iter().map(|record| field(record, &name))) {
+                    Some(ty) => columns.push((name, ty)),
+                    None => return Err(format!("The fields '{}' of the records must be scalars of one type", name)),
+                }
+            }
+            Ok(BSType::Table(columns))
+        }
+        Json::Array(items) => Ok(infer_vec_type(items.iter()).unwrap_or(BSType::List)),
+        Json::Object(fields) => {
+            let values = infer_vec_type(fields.iter().map(|(_, v)| v)).unwrap_or(BSType::List);
+            Ok(BSType::dict(BSType::VecSym, values))
+        }
+    }
+}
+
+/// Whether values of the type `ty` can be read from JSON.
+pub fn is_readable(ty: &BSType) -> bool {
+    match ty {
+        BSType::Bool | BSType::Int64 | BSType::Float64 | BSType::Sym | BSType::Str | BSType::List => true,
+        ty if ty.is_temporal() => true,
+        ty if ty.is_vec() => true,
+        BSType::Dict(keys, values) => keys.is_vec() && (values.is_vec() || **values == BSType::List),
+        BSType::Table(columns) => columns.iter().all(|(_, ty)| ty.is_vec()),
+        _ => false,
+    }
+}
+
+/// Vector elements read from JSON, as the elements of arrays and the keys of objects.
+trait Elem: VecElement {
+    fn read(json: &Json) -> Option<Self>;
+    fn read_key(key: &str) -> Option<Self>;
+}
+
+impl Elem for i64 {
+    fn read(json: &Json) -> Option<Self> {
+        match json {
+            Json::Null => Some(NULL_INT64),
+            Json::Int(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    fn read_key(key: &str) -> Option<Self> { key.parse().ok() }
+}
+
+impl Elem for f64 {
+    fn read(json: &Json) -> Option<Self> {
+        match json {
+            Json::Null => Some(NULL_FLOAT64),
+            Json::Int(x) => Some(*x as f64),
+            Json::Float(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    fn read_key(key: &str) -> Option<Self> { key.parse().ok() }
+}
+
+impl Elem for bool {
+    fn read(json: &Json) -> Option<Self> {
+        match json {
+            Json::Null => Some(false),
+            Json::Bool(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    fn read_key(key: &str) -> Option<Self> { key.parse().ok() }
+}
+
+impl Elem for Sym {
+    fn read(json: &Json) -> Option<Self> {
+        match json {
+            Json::Null => Some(Sym::NULL),
+            Json::Str(s) => Some(Sym::new(s)),
+            _ => None,
+        }
+    }
+
+    fn read_key(key: &str) -> Option<Self> { Some(Sym::new(key)) }
+}
+
+/// Dates and times, read from strings of their literals.
+macro_rules! impl_elem_temporal {
+    ($($ty:ident),*) => {
+        $(
+            impl Elem for $ty {
+                fn read(json: &Json) -> Option<Self> {
+                    match json {
+                        Json::Null => Some($ty(NULL_INT64)),
+                        Json::Str(s) => Self::read_key(s),
+                        _ => None,
+                    }
+                }
+
+                fn read_key(key: &str) -> Option<Self> {
+                    match parse_temporal(key)? {
+                        (BSType::$ty, x) => Some($ty(x)),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_elem_temporal!(Date, Time, Timestamp, Timespan);
+
+fn mismatch<T>(ty: &BSType, json: &Json) -> Result<T, String> {
+    Err(format!("Expected {} in JSON, found {}", ty, json.kind()))
+}
+
+fn read_elem<T: Elem>(json: &Json, ty: &BSType) -> Result<T, String> {
+    T::read(json).map_or_else(|| mismatch(ty, json), Ok)
+}
+
+fn read_vec<'a, T: Elem>(items: impl Iterator<Item = &'a Json>, ty: &BSType) -> Result<BSValue, String>
+where
+    BSValue: From<Vec<T>>,
+{
+    let elem_ty = ty.elem_type().expect("vector");
+    let vec: Result<Vec<T>, String> = items.map(|item| read_elem::<T>(item, &elem_ty)).collect();
+    Ok(BSValue::from(vec?))
+}
+
+/// Vector of the type `ty`, or list, of the `items`.
+fn read_items<'a>(items: impl Iterator<Item = &'a Json>, ty: &BSType) -> Result<BSValue, String> {
+    match ty {
+        BSType::VecInt64 => read_vec::<i64>(items, ty),
+        BSType::VecFloat64 => read_vec::<f64>(items, ty),
+        BSType::VecBool => read_vec::<bool>(items, ty),
+        BSType::VecSym => read_vec::<Sym>(items, ty),
+        BSType::VecDate => read_vec::<Date>(items, ty),
+        BSType::VecTime => read_vec::<Time>(items, ty),
+        BSType::VecTimestamp => read_vec::<Timestamp>(items, ty),
+        BSType::VecTimespan => read_vec::<Timespan>(items, ty),
+        _ => {
+            let list: Result<Vec<BSValue>, String> = items.map(|item| read(item, &infer_type(item)?)).collect();
+            Ok(BSValue::from(list?))
+        }
+    }
+}
+
+fn read_dict<K: DictKey + Elem>(fields: &[(String, Json)], ty: &BSType) -> Result<BSValue, String>
+where
+    BSValue: From<Vec<K>>,
+{
+    let (keys_ty, values_ty) = match ty {
+        BSType::Dict(keys, values) => (keys, values),
+        _ => unreachable!("dictionary of type {}", ty),
+    };
+    let mut keys = vec![];
+    for (key, _) in fields {
+        match K::read_key(key) {
+            Some(key) => keys.push(key),
+            None => return Err(format!("Expected the keys of {} in JSON, found \"{}\"", keys_ty, key)),
+        }
+    }
+    let values = read_items(fields.iter().map(|(_, v)| v), values_ty)?;
+    Ok(<BSValue as From<Dict>>::from(Dict::new::<K>(BSValue::from(keys), values)))
+}
+
+/// Reads the value of the type `ty`, which must be readable, from `json`.
+pub fn read(json: &Json, ty: &BSType) -> Result<BSValue, String> {
+    match (ty, json) {
+        (BSType::Int64, json) => Ok(BSValue::from(read_elem::<i64>(json, ty)?)),
+        (BSType::Float64, json) => Ok(BSValue::from(read_elem::<f64>(json, ty)?)),
+        (BSType::Bool, json) => Ok(BSValue::from(read_elem::<bool>(json, ty)?)),
+        (BSType::Sym, json) => Ok(BSValue::from(read_elem::<Sym>(json, ty)?)),
+        (BSType::Date, json) => Ok(BSValue::from(read_elem::<Date>(json, ty)?)),
+        (BSType::Time, json) => Ok(BSValue::from(read_elem::<Time>(json, ty)?)),
+        (BSType::Timestamp, json) => Ok(BSValue::from(read_elem::<Timestamp>(json, ty)?)),
+        (BSType::Timespan, json) => Ok(BSValue::from(read_elem::<Timespan>(json, ty)?)),
+        (BSType::Str, Json::Str(s)) => Ok(BSValue::from(s.as_str())),
+        (ty, Json::Array(items)) if ty.is_vec() || *ty == BSType::List => read_items(items.iter(), ty),
+        (BSType::Dict(keys, _), Json::Object(fields)) => match **keys {
+            BSType::VecInt64 => read_dict::<i64>(fields, ty),
+            BSType::VecFloat64 => read_dict::<f64>(fields, ty),
+            BSType::VecBool => read_dict::<bool>(fields, ty),
+            BSType::VecDate => read_dict::<Date>(fields, ty),
+            BSType::VecTime => read_dict::<Time>(fields, ty),
+            BSType::VecTimestamp => read_dict::<Timestamp>(fields, ty),
+            BSType::VecTimespan => read_dict::<Timespan>(fields, ty),
+            _ => read_dict::<Sym>(fields, ty),
+        },
+        (BSType::Table(columns), Json::Array(records)) => {
+            let mut table = ffi::values::table::Table::default();
+            for (name, column_ty) in columns {
+                let column = read_items(records.iter().map(|record| field(record, name)), column_ty)?;
+                table = table.with_column(name, column)?;
+            }
+            Ok(BSValue::from(table))
+        }
+        (ty, json) => mismatch(ty, json),
+    }
+}
+
+fn write_str(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).expect("write"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn write_int(x: i64, out: &mut String) {
+    match x {
+        NULL_INT64 => out.push_str("null"),
+        x => write!(out, "{}", x).expect("write"),
+    }
+}
+
+fn write_float(x: f64, out: &mut String) {
+    match x.is_finite() {
+        true => write!(out, "{:?}", x).expect("write"),
+        false => out.push_str("null"),
+    }
+}
+
+fn write_temporal(x: BSValue, out: &mut String) {
+    match x.as_raw() {
+        NULL_INT64 => out.push_str("null"),
+        _ => write_str(&x.to_string(), out),
+    }
+}
+
+fn elems<'a, T: VecElement>(vec: &BSValue) -> &'a [T] { unsafe { VecHeader::from_raw(vec.as_raw()).as_slice() } }
+
+/// Writes the element `i` of the vector or list `items`.
+fn write_item(items: &BSValue, i: usize, out: &mut String) {
+    match items.get_type() {
+        BSType::VecInt64 => write_int(elems::<i64>(items)[i], out),
+        BSType::VecFloat64 => write_float(elems::<f64>(items)[i], out),
+        BSType::VecBool => out.push_str(if elems::<bool>(items)[i] { "true" } else { "false" }),
+        BSType::VecSym => write_str(elems::<Sym>(items)[i].name(), out),
+        BSType::VecDate => write_temporal(BSValue::from(elems::<Date>(items)[i]), out),
+        BSType::VecTime => write_temporal(BSValue::from(elems::<Time>(items)[i]), out),
+        BSType::VecTimestamp => write_temporal(BSValue::from(elems::<Timestamp>(items)[i]), out),
+        BSType::VecTimespan => write_temporal(BSValue::from(elems::<Timespan>(items)[i]), out),
+        _ => write(&items.as_list().expect("list")[i], out),
+    }
+}
+
+fn len(items: &BSValue) -> usize {
+    match items.as_list() {
+        Some(list) => list.len(),
+        None => unsafe { VecHeader::from_raw(items.as_raw()).len as usize },
+    }
+}
+
+/// Writes the JSON text of `value`.
+pub fn write(value: &BSValue, out: &mut String) {
+    let raw = value.as_raw();
+    match value.get_type() {
+        BSType::Bool => out.push_str(if raw != 0 { "true" } else { "false" }),
+        BSType::Int64 => write_int(raw, out),
+        BSType::Float64 => write_float(f64::from_bits(raw as u64), out),
+        BSType::Sym => write_str(Sym(raw).name(), out),
+        ty if ty.is_temporal() => write_temporal(value.clone(), out),
+        BSType::Str if raw != 0 => write_str(value.as_str().expect("string"), out),
+        ty if (ty.is_vec() || *ty == BSType::List) && raw != 0 => {
+            out.push('[');
+            for i in 0..len(value) {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_item(value, i, out);
+            }
+            out.push(']');
+        }
+        BSType::Dict(..) if raw != 0 => {
+            let dict = value.as_dict().expect("dictionary");
+            out.push('{');
+            for i in 0..dict.len() {
+                if i > 0 {
+                    out.push(',');
+                }
+                // keys other than symbols are written as strings of their JSON text
+                let mut key = String::new();
+                write_item(dict.keys(), i, &mut key);
+                match key.starts_with('"') {
+                    true => out.push_str(&key),
+                    false => write_str(&key, out),
+                }
+                out.push(':');
+                write_item(dict.values(), i, out);
+            }
+            out.push('}');
+        }
+        BSType::Table(_) if raw != 0 => {
+            let table = value.as_table().expect("table");
+            out.push('[');
+            for row in 0..table.len() {
+                if row > 0 {
+                    out.push(',');
+                }
+                out.push('{');
+                for (i, (name, column)) in table.names().iter().zip(table.columns()).enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write_str(name, out);
+                    out.push(':');
+                    write_item(column, row, out);
+                }
+                out.push('}');
+            }
+            out.push(']');
+        }
+        _ => out.push_str("null"),
+    }
+}
+
+/// JSON text of `value`.
+pub fn to_json(value: &BSValue) -> String {
+    let mut out = String::new();
+    write(value, &mut out);
+    out
+}
+
+fn json_string(value: &BSValue) -> i64 { BSValue::from(to_json(value)).into_raw() }
+
+pub(crate) extern "C" fn int_to_json(x: i64) -> i64 { json_string(&BSValue::from(x)) }
+
+pub(crate) extern "C" fn float_to_json(x: f64) -> i64 { json_string(&BSValue::from(x)) }
+
+pub(crate) extern "C" fn bool_to_json(x: bool) -> i64 { json_string(&BSValue::from(x)) }
+
+pub(crate) extern "C" fn sym_to_json(x: i64) -> i64 { json_string(&BSValue::from(Sym(x))) }
+
+/// Returns the JSON text of the date or time `x`, of the type `T`.
+pub(crate) extern "C" fn temporal_to_json<T>(x: T) -> i64
+where
+    BSValue: From<T>,
+{
+    json_string(&BSValue::from(x))
+}
+
+/// Returns the JSON text of the string, vector, dictionary, table or list `raw`, of any type of
+/// the kind `ty`.
+fn rc_to_json(ty: BSType, raw: i64) -> i64 {
+    match raw {
+        0 => json_string(&BSValue::from(())),
+        raw => json_string(&unsafe { BSValue::from_raw_borrowed(ty, raw) }),
+    }
+}
+
+pub(crate) extern "C" fn str_to_json(s: i64) -> i64 { rc_to_json(BSType::Str, s) }
+
+pub(crate) extern "C" fn vec_to_json(vec: i64) -> i64 {
+    match vec {
+        0 => rc_to_json(BSType::VecInt64, 0),
+        vec => rc_to_json(unsafe { VecHeader::from_raw(vec).tag.vec_type() }, vec),
+    }
+}
+
+pub(crate) extern "C" fn dict_to_json(dict: i64) -> i64 { rc_to_json(BSType::dict(BSType::Null, BSType::Null), dict) }
+
+pub(crate) extern "C" fn table_to_json(table: i64) -> i64 { rc_to_json(BSType::Table(vec![]), table) }
+
+pub(crate) extern "C" fn list_to_json(list: i64) -> i64 { rc_to_json(BSType::List, list) }
+
+/// Reads the value of the type named `ty` from the JSON string `text`, raising an error if it is
+/// not valid JSON of a value of that type.
+fn read_text(text: i64, ty: i64) -> Option<BSValue> {
+    let (text, ty) = unsafe { (<&str>::from_abi(text), <&str>::from_abi(ty)) };
+    let ty = match Parser::parse_type_str(ty) {
+        BSResult::Ok(ty) => ty,
+        BSResult::Err(_) => {
+            raise(&format!("'{}' is not a valid type", ty));
+            return None;
+        }
+    };
+    match parse(text).and_then(|json| read(&json, &ty)) {
+        Ok(value) => Some(value),
+        Err(msg) => {
+            raise(&format!("Invalid JSON: {}", msg));
+            None
+        }
+    }
+}
+
+/// Returns the value of the type named `ty`, but `Float64` and `Bool`, read from the JSON string
+/// `text`.
+pub(crate) extern "C" fn json_read(text: i64, ty: i64) -> i64 { read_text(text, ty).map_or(0, |v| v.into_raw()) }
+
+pub(crate) extern "C" fn json_read_float(text: i64, ty: i64) -> f64 {
+    read_text(text, ty).map_or(NULL_FLOAT64, |v| v.into())
+}
+
+pub(crate) extern "C" fn json_read_bool(text: i64, ty: i64) -> bool { read_text(text, ty).is_some_and(|v| v.into()) }
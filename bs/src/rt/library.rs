@@ -0,0 +1,59 @@
+use llvm::libc;
+use std::ffi::{CStr, CString};
+
+/// A shared library opened for `extern` declarations, closed when dropped.
+pub struct Library {
+    handle: *mut libc::c_void,
+}
+
+impl Library {
+    pub fn open(path: &str) -> Result<Self, String> {
+        let c_path = CString::new(path).map_err(|e| e.to_string())?;
+        let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+
+        if handle.is_null() {
+            return Err(last_error());
+        }
+
+        Ok(Self { handle })
+    }
+
+    pub fn symbol(&self, name: &str) -> Result<usize, String> {
+        let c_name = CString::new(name).map_err(|e| e.to_string())?;
+        let addr = unsafe { libc::dlsym(self.handle, c_name.as_ptr()) };
+
+        if addr.is_null() {
+            return Err(last_error());
+        }
+
+        Ok(addr as usize)
+    }
+}
+
+/// Looks up `name` among the symbols already loaded into the process.
+pub fn process_symbol(name: &str) -> Result<usize, String> {
+    let c_name = CString::new(name).map_err(|e| e.to_string())?;
+    let addr = unsafe { libc::dlsym(libc::RTLD_DEFAULT, c_name.as_ptr()) };
+
+    if addr.is_null() {
+        return Err(last_error());
+    }
+
+    Ok(addr as usize)
+}
+
+impl Drop for Library {
+    fn drop(&mut self) {
+        unsafe {
+            libc::dlclose(self.handle);
+        }
+    }
+}
+
+fn last_error() -> String {
+    let err = unsafe { libc::dlerror() };
+    if err.is_null() {
+        return "unknown error".to_string();
+    }
+    unsafe { CStr::from_ptr(err) }.to_string_lossy().into_owned()
+}
@@ -1 +1,12 @@
+pub mod csv;
+pub mod engine;
+pub mod error;
+pub mod group;
+pub mod join;
+pub mod json;
+pub mod library;
+pub mod query;
 pub mod runtime;
+pub mod serial;
+pub mod splay;
+pub mod table;
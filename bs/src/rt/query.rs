@@ -0,0 +1,133 @@
+//! Vectors built and grouped by compiled queries, see `cc::compiler::query`.
+//!
+//! The functions generic over the element type `T` are compiled into calls of the instance for
+//! the element type of the query column. Vectors passed in are borrowed unless stated otherwise,
+//! and the values returned are owned.
+
+use crate::rt::group::{hash, order, Agg, Aggregate, Grouping, Groups};
+use ffi::values::dict::{Dict, DictKey};
+use ffi::values::vector::{VecElement, VecHeader};
+use ffi::values::Value as BSValue;
+
+fn slice<'a, T: VecElement>(raw: i64) -> &'a [T] { unsafe { VecHeader::from_raw(raw).as_slice() } }
+
+/// Returns an empty vector with room for `cap` elements, which compiled code stores before setting
+/// its length.
+pub(crate) extern "C" fn vec_alloc<T: VecElement>(cap: i64) -> i64 {
+    VecHeader::alloc(Vec::<T>::with_capacity(cap as usize))
+}
+
+/// Returns the group of every row, numbering the groups of the vector `gids`, or of a single group
+/// if it is null, further by the vector `keys`. Groups are numbered from 0 in order of appearance.
+pub(crate) extern "C" fn group<K: DictKey>(gids: i64, keys: i64) -> i64 {
+    let keys = slice::<K>(keys);
+    let grouping = match gids {
+        0 => Grouping::new(keys.len(), |i| hash(keys[i].bits()), |i, j| keys[i].bits() == keys[j].bits()),
+        gids => {
+            let gids = slice::<i64>(gids);
+            Grouping::new(
+                keys.len(),
+                |i| hash(keys[i].bits() ^ hash(gids[i] as u64)),
+                |i, j| gids[i] == gids[j] && keys[i].bits() == keys[j].bits(),
+            )
+        }
+    };
+    VecHeader::alloc(grouping.ids)
+}
+
+/// Returns the number of groups of `gids`, a single group if it is null.
+pub(crate) extern "C" fn group_count(gids: i64) -> i64 {
+    match gids {
+        0 => 1,
+        gids => slice::<i64>(gids).iter().max().map_or(0, |max| max + 1),
+    }
+}
+
+/// Returns the rows sorted by the groups `gids` of `len` rows, keeping the order of the rows of
+/// every group.
+pub(crate) extern "C" fn group_order(gids: i64, len: i64) -> i64 {
+    match gids {
+        0 => VecHeader::alloc((0..len).collect()),
+        gids => VecHeader::alloc(order(slice(gids), group_count(gids) as usize).0),
+    }
+}
+
+/// Returns the start of every group of `gids` in the order of `group_order`, followed by `len`.
+pub(crate) extern "C" fn group_bounds(gids: i64, len: i64) -> i64 {
+    match gids {
+        0 => VecHeader::alloc(vec![0, len]),
+        gids => VecHeader::alloc(order(slice(gids), group_count(gids) as usize).1),
+    }
+}
+
+/// Returns the first row of every group of `gids`.
+pub(crate) extern "C" fn group_firsts(gids: i64) -> i64 {
+    let mut firsts = vec![];
+    for (row, gid) in slice::<i64>(gids).iter().enumerate() {
+        if *gid as usize == firsts.len() {
+            firsts.push(row as i64);
+        }
+    }
+    VecHeader::alloc(firsts)
+}
+
+/// Returns the aggregate `agg`, an `Agg`, of the vector `vals` by the `groups` groups `gids`, all
+/// of the rows if it is null.
+pub(crate) extern "C" fn aggregate<T: Aggregate>(agg: i64, gids: i64, groups: i64, vals: i64) -> i64 {
+    let ids = if gids == 0 { None } else { Some(slice::<i64>(gids)) };
+    T::aggregate(Agg::from_raw(agg), Groups { ids, len: groups as usize }, slice(vals)).into_raw()
+}
+
+/// Returns the element of `aggs` for the group of every row of `gids`, all of the `len` rows if it
+/// is null.
+pub(crate) extern "C" fn broadcast<T: VecElement>(aggs: i64, gids: i64, len: i64) -> i64 {
+    let aggs = slice::<T>(aggs);
+    match gids {
+        0 => VecHeader::alloc(vec![aggs[0]; len as usize]),
+        gids => VecHeader::alloc(slice::<i64>(gids).iter().map(|g| aggs[*g as usize]).collect()),
+    }
+}
+
+/// Returns the elements of `vec` at the in bounds `indices`.
+pub(crate) extern "C" fn take<T: VecElement>(vec: i64, indices: i64) -> i64 {
+    let vec = slice::<T>(vec);
+    VecHeader::alloc(slice::<i64>(indices).iter().map(|i| vec[*i as usize]).collect())
+}
+
+/// Returns the elements of `vec` at the rows `order[lo..hi]` of a group.
+pub(crate) extern "C" fn take_range<T: VecElement>(vec: i64, order: i64, lo: i64, hi: i64) -> i64 {
+    let vec = slice::<T>(vec);
+    let rows = &slice::<i64>(order)[lo as usize..hi as usize];
+    VecHeader::alloc(rows.iter().map(|i| vec[*i as usize]).collect())
+}
+
+/// Stores `x` in the vector `vec`, which must not be shared, at the rows `order[lo..hi]` of a group,
+/// and returns it, still borrowed.
+pub(crate) extern "C" fn fill_range<T: VecElement>(vec: i64, order: i64, lo: i64, hi: i64, x: T) -> i64 {
+    let header = unsafe { VecHeader::from_raw(vec) };
+    let data = header.data as *mut T;
+    for i in &slice::<i64>(order)[lo as usize..hi as usize] {
+        unsafe { *data.add(*i as usize) = x };
+    }
+    vec
+}
+
+/// Returns a vector of `len` elements, those of `base`, or nulls if it is null, but at the rows
+/// `indices`, where it holds the elements of `vals`.
+pub(crate) extern "C" fn scatter<T: VecElement>(base: i64, len: i64, indices: i64, vals: i64) -> i64 {
+    let mut vec = match base {
+        0 => vec![T::NULL; len as usize],
+        base => slice::<T>(base).to_vec(),
+    };
+    for (i, x) in slice::<i64>(indices).iter().zip(slice::<T>(vals)) {
+        vec[*i as usize] = *x;
+    }
+    VecHeader::alloc(vec)
+}
+
+/// Returns the dictionary of the owned vectors `keys` and `vals`.
+pub(crate) extern "C" fn make_dict<K: DictKey, V: VecElement>(keys: i64, vals: i64) -> i64 {
+    let keys = BSValue::from_raw_parts(K::VEC_TYPE, keys);
+    let vals = BSValue::from_raw_parts(V::VEC_TYPE, vals);
+    BSValue::from(Dict::new::<K>(keys, vals)).into_raw()
+}
@@ -3,183 +3,482 @@ use crate::cc::compiler::Compiler;
 use crate::cc::transform::llvm_type_from_bs_type;
 use crate::parse::ast::Function;
 use crate::parse::parser::*;
+use crate::parse::span::{Source, Span};
 use crate::result::*;
-use ffi::external;
+use crate::rt::error::{self, ErrorSite};
+use crate::rt::library::{process_symbol, Library};
+use ffi::external::{set_panic_handler, Externals, HostFn};
 use ffi::types::Type as BSType;
-use ffi::values::OpaqueValue;
 use ffi::values::Value as BSValue;
 use llvm::builder::Builder;
 use llvm::context::Context;
 use llvm::execution_engine::ExecutionEngine;
-use llvm::llvm_sys::support::LLVMAddSymbol;
 use llvm::module::Module;
-use llvm::utils::to_c_str;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
 use std::mem;
-use std::mem::transmute;
-
-static mut RUNTIME: Option<*mut Runtime> = None;
-
-pub fn set_runtime(runtime: *mut Runtime<'static>) { unsafe { RUNTIME = Some(runtime) } }
-
-pub fn get_runtime<'a>() -> Option<&'a mut Runtime<'static>> { unsafe { RUNTIME.as_mut().map(|r| &mut **r) } }
+use std::ptr;
 
 pub struct RuntimeModule<'a> {
     pub(crate) module: Module<'a>,
     pub(crate) engine: ExecutionEngine<'a>,
-    pub(crate) globals: HashMap<String, Box<BSValue>>,
+    /// Heap literals embedded in the compiled code.
+    pub(crate) constants: Vec<BSValue>,
+    /// Return types of the specializations of generic functions compiled into the module, by symbol.
+    pub(crate) specializations: HashMap<String, BSType>,
+    /// Specializations called by the module, but not compiled yet.
+    pub(crate) pending: Vec<Function>,
+    /// Checks of the compiled code which can raise an error, boxed as the code refers to them by
+    /// address.
+    #[allow(clippy::vec_box)]
+    pub(crate) error_sites: Vec<Box<ErrorSite>>,
 }
 
 impl<'a> RuntimeModule<'a> {
     pub fn new(name: String, context: &Context) -> BSResult<Self> {
         let module = context
             .create_module(name.as_str())
-            .map_err(|e| BSError::RuntimeError(e.to_string()))?;
+            .map_err(|e| BSError::RuntimeError { msg: e.to_string(), span: None })?;
         let engine = module
             .create_mcjit_execution_engine()
-            .map_err(|e| BSError::RuntimeError(e.to_string()))?;
+            .map_err(|e| BSError::RuntimeError { msg: e.to_string(), span: None })?;
 
-        ok(Self { module, engine, globals: HashMap::new() })
+        ok(Self {
+            module,
+            engine,
+            constants: vec![],
+            specializations: HashMap::new(),
+            pending: vec![],
+            error_sites: vec![],
+        })
     }
 
     pub fn amend_module(&mut self, name: String, context: &Context) -> BSResult<()> {
         self.module = context
             .create_module(name.as_str())
-            .map_err(|e| BSError::RuntimeError(e.to_string()))?;
+            .map_err(|e| BSError::RuntimeError { msg: e.to_string(), span: None })?;
         self.engine = self
             .module
             .create_mcjit_execution_engine()
-            .map_err(|e| BSError::RuntimeError(e.to_string()))?;
+            .map_err(|e| BSError::RuntimeError { msg: e.to_string(), span: None })?;
+        self.constants.clear();
+        self.specializations.clear();
+        self.pending.clear();
+        self.error_sites.clear();
         ok(())
     }
 
-    pub fn add_global(&mut self, name: &str, value: BSValue) { self.globals.insert(name.to_string(), Box::new(value)); }
+    pub fn get_function_address(&self, name: &str) -> BSResult<usize> {
+        self.engine
+            .get_function_address(name)
+            .map_err(|e| BSError::RuntimeError { msg: e.to_string(), span: None })
+            .into()
+    }
+}
 
-    pub fn get_global(&self, name: &str) -> Option<(BSType, *const i64)> {
-        match self.globals.get(name) {
-            Some(value) => Some((value.get_type().clone(), value.as_ref().as_ptr() as _)),
-            None => None,
+/// Global values shared by every module of a runtime.
+/// Compiled code refers to a global by the address of its box, so a global is updated in place
+/// while its type stays the same, and a box replaced by a value of another type is retired
+/// instead of freed.
+/// Functions can also be overloaded: every overload of a name is a global of its own, named by
+/// `overload_symbol`. Generic functions are kept as templates, specialized by every module calling
+/// them, and are only called if no overload takes the arguments as they are.
+#[derive(Default)]
+pub struct Globals {
+    values: HashMap<String, Box<BSValue>>,
+    overloads: HashMap<String, Vec<String>>,
+    generics: HashMap<String, Function>,
+    /// Specializations whose return type is being inferred.
+    specializing: RefCell<HashSet<String>>,
+    #[allow(clippy::vec_box)]
+    retired: Vec<Box<BSValue>>,
+}
+
+/// Whether an argument of type `arg` can be passed for a parameter of type `param`: either the
+/// types are equal, or `null` is passed for a scalar, which then receives its null value.
+pub fn converts_to(arg: &BSType, param: &BSType) -> bool { arg == param || (*arg == BSType::Null && param.is_scalar()) }
+
+/// Symbol of the overload of `name` taking arguments of types `args`, e.g. `sum(Int64[])`.
+pub fn overload_symbol(name: &str, args: &[BSType]) -> String {
+    let args: Vec<String> = args.iter().map(|ty| ty.to_string()).collect();
+    format!("{}({})", name, args.join(", "))
+}
+
+impl Globals {
+    pub fn add(&mut self, name: &str, value: BSValue) {
+        match self.values.get_mut(name) {
+            Some(old) if old.get_type() == value.get_type() => **old = value,
+            _ => {
+                if let Some(old) = self.values.insert(name.to_string(), Box::new(value)) {
+                    self.retired.push(old);
+                }
+            }
+        }
+    }
+
+    /// Makes sure the global `name` can hold a value of type `ty`, so that compiled code can store
+    /// into it. A new global is the null of its type until it is assigned, so the type must have
+    /// one, see `BSValue::null`.
+    pub fn declare(&mut self, name: &str, ty: &BSType, span: Option<Span>) -> BSResult<()> {
+        match (self.values.get(name), BSValue::null(ty)) {
+            (Some(old), _) if old.get_type() == ty => ok(()),
+            (_, Some(null)) => {
+                self.add(name, null);
+                ok(())
+            }
+            (_, None) => compile_error(
+                format!("Can not assign '{}'", name),
+                format!("Globals can not hold values of type {}", ty),
+                span,
+            ),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<(BSType, *const i64)> {
+        self.values
+            .get(name)
+            .map(|value| (value.get_type().clone(), value.as_ref().as_ptr() as _))
+    }
+
+    pub fn get_value(&self, name: &str) -> Option<&BSValue> { self.values.get(name).map(|v| v.as_ref()) }
+
+    /// Adds the function `value` to the overload set `name`, returning the symbol it is defined as.
+    pub fn add_overload(&mut self, name: &str, value: BSValue) -> String {
+        let symbol = match value.get_type() {
+            BSType::Fn(fn_ty) => overload_symbol(name, &fn_ty.args),
+            _ => name.to_string(),
+        };
+
+        let overloads = self.overloads.entry(name.to_string()).or_default();
+        if !overloads.contains(&symbol) {
+            overloads.push(symbol.clone());
+        }
+        self.add(&symbol, value);
+
+        symbol
+    }
+
+    /// Resolves a call of `name` with arguments of types `args` to the global to call: `name`
+    /// itself if it is defined, or else its overload taking `args`. Without an exact match, the
+    /// single overload the arguments convert to is called, see `converts_to`.
+    pub fn resolve(&self, name: &str, args: &[BSType], span: Option<Span>) -> BSResult<String> {
+        if self.values.contains_key(name) {
+            return ok(name.to_string());
+        }
+
+        let overloads = self.overloads(name);
+        if overloads.is_empty() {
+            return compile_error("Unknown function".to_string(), name.to_string(), span);
+        }
+
+        let symbol = overload_symbol(name, args);
+        if overloads.contains(&symbol) {
+            return ok(symbol);
+        }
+
+        let candidates: Vec<&String> = overloads
+            .iter()
+            .filter(|symbol| match self.values[*symbol].get_type() {
+                BSType::Fn(fn_ty) => {
+                    fn_ty.args.len() == args.len() && args.iter().zip(fn_ty.args.iter()).all(|(a, p)| converts_to(a, p))
+                }
+                _ => false,
+            })
+            .collect();
+
+        match candidates.as_slice() {
+            [symbol] => ok(symbol.to_string()),
+            [] => compile_error(
+                format!("No overload of '{}' matches the arguments", name),
+                format!("Called as {}, candidates are: {}", symbol, self.signatures(overloads)),
+                span,
+            ),
+            _ => compile_error(
+                format!("Ambiguous call of '{}'", name),
+                format!("Called as {}, candidates are: {}", symbol, self.signatures(&candidates)),
+                span,
+            ),
+        }
+    }
+
+    pub fn add_generic(&mut self, f: Function) { self.generics.insert(f.name.clone(), f); }
+
+    /// The generic function `name`, if a call with arguments of types `args` is to specialize it.
+    pub fn generic(&self, name: &str, args: &[BSType]) -> Option<&Function> {
+        match self.overloads(name).contains(&overload_symbol(name, args)) {
+            true => None,
+            false => self.generics.get(name),
         }
     }
+
+    /// Marks the return type of the specialization `symbol` as being inferred, returning false if
+    /// it already is, as it is then called recursively.
+    pub(crate) fn begin_specialization(&self, symbol: &str) -> bool {
+        self.specializing.borrow_mut().insert(symbol.to_string())
+    }
+
+    pub(crate) fn end_specialization(&self, symbol: &str) { self.specializing.borrow_mut().remove(symbol); }
+
+    /// Symbols of the overloads of `name`.
+    pub fn overloads(&self, name: &str) -> &[String] {
+        self.overloads.get(name).map(|o| o.as_slice()).unwrap_or_default()
+    }
+
+    fn signatures<S: AsRef<str>>(&self, symbols: &[S]) -> String {
+        let signatures: Vec<String> = symbols
+            .iter()
+            .map(|symbol| match self.values[symbol.as_ref()].get_type() {
+                BSType::Fn(fn_ty) => format!("{} -> {}", symbol.as_ref(), fn_ty.ret),
+                ty => format!("{}: {}", symbol.as_ref(), ty),
+            })
+            .collect();
+        signatures.join(", ")
+    }
 }
 
+/// An isolated BitSaber runtime: its LLVM context, modules, globals and host functions are owned
+/// by the instance, and host symbols are resolved per execution engine, so any number of runtimes
+/// can coexist in a process.
 pub struct Runtime<'a> {
     modules: HashMap<String, RuntimeModule<'a>>,
+    libraries: HashMap<String, Library>,
+    globals: Globals,
+    externals: Externals,
     builder: Builder<'a>,
     previous_functions: HashMap<String, Function>,
+    /// Latest input, which the errors of its top-level expression point into.
+    source: Option<Source>,
+    /// Whether integer arithmetic raises an error on overflow instead of wrapping around.
+    checked_arithmetic: bool,
     context: Context,
 }
 
 // Public methods
 impl<'a> Runtime<'a> {
     pub fn new() -> BSResult<Box<Self>> {
-        let context = Context::new().map_err(|e| BSError::RuntimeError(e.to_string()))?;
+        let context = Context::new().map_err(|e| BSError::RuntimeError { msg: e.to_string(), span: None })?;
         let modules = HashMap::new();
         let builder = context
             .create_builder()
-            .map_err(|e| BSError::RuntimeError(e.to_string()))?;
+            .map_err(|e| BSError::RuntimeError { msg: e.to_string(), span: None })?;
+
+        let mut runtime = Box::new(Self {
+            context,
+            modules,
+            libraries: HashMap::new(),
+            globals: Globals::default(),
+            externals: Externals::default(),
+            builder,
+            previous_functions: HashMap::new(),
+            source: None,
+            checked_arithmetic: false,
+        });
 
         // Initialize builtins
-        builtins::init();
+        builtins::init(&mut runtime);
+        set_panic_handler(error::raise);
+
+        ok(runtime)
+    }
+
+    /// Registers a Rust function or closure callable from compiled code under `name`,
+    /// e.g. `runtime.register_fn("scale", |x: i64, v: &[f64]| -> f64 { ... })`.
+    pub fn register_fn<Args, F: HostFn<Args>>(&mut self, name: &str, f: F) { self.externals.register_fn(name, f); }
+
+    /// Makes `Int64` addition, subtraction and multiplication compiled afterwards raise an error
+    /// when they overflow, rather than wrap around.
+    pub fn set_checked_arithmetic(&mut self, checked: bool) { self.checked_arithmetic = checked; }
+
+    pub fn parse_eval(&mut self, input: &str) -> BSResult<BSValue> {
+        match self.compile_module("repl", input)?.0 {
+            Some(ty) => self.call_top_level("repl", ty),
+            None => ok(BSValue::from(())),
+        }
+    }
+
+    pub fn get_module(&self, name: &str) -> Option<&RuntimeModule<'_>> { self.modules.get(name) }
+
+    pub fn globals(&self) -> &Globals { &self.globals }
+
+    pub fn globals_mut(&mut self) -> &mut Globals { &mut self.globals }
+}
+
+impl<'a> Runtime<'a> {
+    /// Compiles `input` into the module `name`, (re)creating it together with every previously
+    /// defined function. Returns the type of the top-level expression, if there is one, and the
+    /// names of the functions defined by `input`.
+    pub(crate) fn compile_module(&mut self, name: &str, input: &str) -> BSResult<(Option<BSType>, Vec<String>)> {
+        match self.modules.get_mut(name) {
+            Some(module) => module.amend_module(name.into(), &self.context)?,
+            None => {
+                let module = RuntimeModule::new(name.into(), &self.context)?;
+                self.modules.insert(name.into(), module);
+            }
+        }
 
         // add external symbols
-        external::with(|map| {
-            for (name, ext) in map {
-                Self::add_symbol(name, ext.get_ptr() as _);
+        for (ext_name, fn_val) in self.externals.iter() {
+            let fn_ty = fn_val.get_type();
+            let mut fn_args = vec![];
+
+            // host closures receive their environment as a hidden first argument
+            if fn_val.get_env().is_some() {
+                fn_args.push(self.context.i64_type().into());
+            }
+
+            for arg in fn_ty.args.iter() {
+                fn_args.push(llvm_type_from_bs_type(arg.clone(), &self.context));
             }
-        });
 
-        unsafe {
-            let rt = Box::new(transmute(Self { context, modules, builder, previous_functions: HashMap::new() }));
-            let ptr = Box::into_raw(rt);
-            set_runtime(ptr);
-            ok(Box::from_raw(ptr))
+            let fn_type = self.context.fn_type(
+                llvm_type_from_bs_type(fn_ty.ret.as_ref().clone(), &self.context),
+                &fn_args,
+                false,
+            );
+            let symbol = self.globals.add_overload(ext_name, BSValue::from(fn_val.clone()));
+
+            let rt_module = self.modules.get_mut(name).unwrap();
+            let fn_decl = rt_module.module.add_function(symbol.as_str(), fn_type);
+            rt_module.engine.add_global_mapping(fn_decl, fn_val.get_ptr() as _);
         }
-    }
 
-    pub fn add_symbol(name: &str, addr: i64) {
-        unsafe {
-            LLVMAddSymbol(to_c_str(name).as_ptr(), addr as _);
+        let source = self.source.insert(Source::new(input)).clone();
+        let mut parsed_fns = Parser::with_source(&source).parse()?;
+        for f in parsed_fns.iter_mut() {
+            f.complete_reads()?;
         }
+
+        // recompile every previously parsed function into the new module, unless it is redefined
+        let redefined: Vec<String> = parsed_fns.iter().map(|f| f.symbol()).collect();
+        for (symbol, f) in self.previous_functions.clone() {
+            if !redefined.contains(&symbol) {
+                self.compile_function(name, f)?;
+            }
+        }
+
+        let mut top_level_ty = None;
+        let mut defined = vec![];
+
+        for f in parsed_fns {
+            // generic functions are compiled once they are called
+            if f.is_generic() {
+                defined.push(f.name.clone());
+                self.globals.add_generic(f);
+                continue;
+            }
+
+            let is_top_level = f.topl;
+            let ret_ty = self.compile_function(name, f.clone())?;
+
+            if is_top_level {
+                top_level_ty = Some(ret_ty);
+            } else {
+                defined.push(f.name.clone());
+                self.previous_functions.insert(f.symbol(), f);
+            }
+        }
+
+        ok((top_level_ty, defined))
     }
 
-    pub fn parse_eval(&mut self, input: &str) -> BSResult<BSValue> {
-        unsafe {
-            let repl_module = self
-                .modules
-                .entry("repl".into())
-                .or_insert(RuntimeModule::new("repl".into(), &self.context)?);
-
-            repl_module.amend_module("repl".into(), &self.context)?;
-
-            // add external symbols
-            external::with(|map| {
-                for (name, fn_val) in map {
-                    let fn_ty = fn_val.get_type();
-                    let fn_args: Vec<_> = fn_ty
-                        .args
-                        .iter()
-                        .map(|arg| llvm_type_from_bs_type(arg.clone(), &self.context))
-                        .collect();
-
-                    let fn_type = self.context.fn_type(
-                        llvm_type_from_bs_type(fn_ty.ret.as_ref().clone(), &self.context),
-                        &fn_args,
-                        false,
-                    );
-                    self.modules
-                        .get_mut("repl".into())
-                        .unwrap()
-                        .module
-                        .add_function(name.as_str(), fn_type);
-
-                    self.modules
-                        .get_mut("repl".into())
-                        .unwrap()
-                        .add_global(name, BSValue::from(fn_val.clone()))
+    /// Compiles `f` into the module `name`, binding an `extern` function to the symbol of the same
+    /// name in its library, or in the process if it is declared without one.
+    fn compile_function(&mut self, name: &str, f: Function) -> BSResult<BSType> {
+        let addr = match &f.lib {
+            Some(lib) => {
+                if !self.libraries.contains_key(lib) {
+                    match Library::open(lib) {
+                        Ok(library) => self.libraries.insert(lib.clone(), library),
+                        Err(e) => return compile_error(format!("Could not load library '{}'", lib), e, None),
+                    };
                 }
-            });
 
-            // recompile every previously parsed function into the new module
-            for f in self.previous_functions.values() {
-                Compiler::new("repl", &mut self.context, &mut self.builder, &mut self.modules, f.clone()).compile()?;
+                match self.libraries[lib].symbol(&f.name) {
+                    Ok(addr) => Some(addr),
+                    Err(e) => return compile_error(format!("Could not find '{}' in '{}'", f.name, lib), e, None),
+                }
             }
+            None if f.body.is_empty() => match process_symbol(&f.name) {
+                Ok(addr) => Some(addr),
+                Err(e) => return compile_error(format!("Could not find '{}'", f.name), e, None),
+            },
+            None => None,
+        };
 
-            let parsed_fns = Parser::new(input).parse()?;
+        let (fn_val, ret_ty) =
+            Compiler::new(name, &mut self.context, &mut self.builder, &mut self.modules, &mut self.globals, f)
+                .checked(self.checked_arithmetic)
+                .compile()?;
+
+        if let Some(addr) = addr {
+            self.modules[name].engine.add_global_mapping(fn_val, addr);
+        }
+
+        // compile the specializations of generic functions called by `f`
+        while let Some(specialization) = self.modules.get_mut(name).unwrap().pending.pop() {
+            self.compile_function(name, specialization)?;
+        }
+
+        ok(ret_ty)
+    }
 
-            let mut top_level_fn = None;
+    /// Runs the top-level function of the module `name`, reading its result according to `ty`.
+    pub(crate) fn call_top_level(&mut self, name: &str, ty: BSType) -> BSResult<BSValue> {
+        let addr = self.modules.get(name).unwrap().get_function_address("top-level")?;
 
-            for f in parsed_fns {
-                let is_top_level = f.topl;
-                let (compiled_fn, ret_ty) =
-                    Compiler::new("repl", &mut self.context, &mut self.builder, &mut self.modules, f.clone())
-                        .compile()?;
+        // a call from the host may have left an error behind
+        error::take();
 
-                if is_top_level {
-                    top_level_fn = Some((compiled_fn, ret_ty));
-                } else {
-                    self.previous_functions.insert(f.name.clone(), f);
+        let _running = Running::enter(self);
+        let result = unsafe {
+            match ty {
+                BSType::Bool => {
+                    let f: extern "C" fn() -> bool = mem::transmute(addr);
+                    BSValue::from(f())
+                }
+                BSType::Float64 => {
+                    let f: extern "C" fn() -> f64 = mem::transmute(addr);
+                    BSValue::from(f())
+                }
+                ty => {
+                    let f: extern "C" fn() -> i64 = mem::transmute(addr);
+                    BSValue::from_raw_parts(ty, f())
                 }
             }
+        };
 
-            let runtime_module = &mut self.modules.get_mut("repl").unwrap();
-            let engine = &mut runtime_module.engine;
+        error::check()?;
+        ok(result)
+    }
+}
 
-            match top_level_fn {
-                Some((_, ty)) => {
-                    let addr = engine
-                        .get_function_address("top-level")
-                        .map_err(|e| BSError::RuntimeError(e.to_string()))?;
+thread_local! {
+    static RUNNING: Cell<*const Runtime<'static>> = const { Cell::new(ptr::null()) };
+}
 
-                    let f: extern "C" fn() -> OpaqueValue = mem::transmute(addr);
-                    let res = f();
-                    ok(BSValue::from_raw_parts(ty, *res))
-                }
+/// Marks a runtime as running top-level code on the current thread, until dropped.
+struct Running(*const Runtime<'static>);
 
-                None => ok(BSValue::from(())),
-            }
-        }
+impl Running {
+    fn enter(runtime: &Runtime<'_>) -> Self {
+        Running(RUNNING.with(|running| running.replace((runtime as *const Runtime<'_>).cast())))
     }
+}
 
-    pub fn get_module(&self, name: &str) -> Option<&RuntimeModule> { self.modules.get(name) }
+impl Drop for Running {
+    fn drop(&mut self) { RUNNING.with(|running| running.set(self.0)) }
 }
+
+/// Calls `f` with the runtime running top-level code on the current thread, if there is one, e.g.
+/// for a builtin to inspect the runtime calling it.
+pub(crate) fn with_running<R>(f: impl FnOnce(&Runtime<'_>) -> R) -> Option<R> {
+    let runtime = RUNNING.with(|running| running.get());
+    (!runtime.is_null()).then(|| f(unsafe { &*runtime }))
+}
+
+// SAFETY: a runtime exclusively owns its LLVM context and every object created in it. Host
+// closures are required to be `Send` and builtins keep no pointer to their runtime, see
+// `with_running`. The values of the globals can be shared with values held by the host, whose
+// reference counts are atomic, see `ffi::values::rc`. Moving the whole runtime to another thread is
+// therefore sound.
+unsafe impl Send for Runtime<'_> {}
@@ -0,0 +1,164 @@
+//! Serializing values to bytes in the binary format of `ffi::values::serial`, or to files, and
+//! reading them back. The bytes of a value are a `Bytes` value, the buffer serialized.
+//!
+//! The type of a value read is known when compiling, given by name or read from the file once, when
+//! the function reading it is defined, and the bytes or file read when running must hold a value of
+//! that type.
+
+use crate::rt::error::{raise, raise_io};
+use ffi::external::HostArg;
+use ffi::types::Type as BSType;
+use ffi::values::serial;
+use ffi::values::sym::Sym;
+use ffi::values::vector::VecHeader;
+use ffi::values::Value as BSValue;
+use ffi::values::NULL_FLOAT64;
+use std::fs;
+
+fn string<'a>(raw: i64) -> &'a str { unsafe { <&str>::from_abi(raw) } }
+
+/// Type of the value serialized in the file `path`.
+pub fn file_type(path: &str) -> Result<BSType, String> {
+    let bytes = fs::read(path).map_err(|err| err.to_string())?;
+    serial::read_type(&bytes)
+}
+
+/// Bytes of `value`, raising an error if it can not be serialized.
+fn encode(value: &BSValue) -> i64 {
+    match serial::serialize(value) {
+        Ok(bytes) => BSValue::from(bytes).into_raw(),
+        Err(msg) => {
+            raise(&msg);
+            0
+        }
+    }
+}
+
+/// Writes the bytes of `value` to the file `path`, returning their number.
+fn write(path: i64, value: &BSValue) -> i64 {
+    let path = string(path);
+    let res = serial::serialize(value).and_then(|bytes| match fs::write(path, &bytes) {
+        Ok(()) => Ok(bytes.len() as i64),
+        Err(err) => Err(err.to_string()),
+    });
+    res.unwrap_or_else(|msg| {
+        raise_io(&msg, path, None);
+        0
+    })
+}
+
+pub(crate) extern "C" fn int_serialize(x: i64) -> i64 { encode(&BSValue::from(x)) }
+
+pub(crate) extern "C" fn float_serialize(x: f64) -> i64 { encode(&BSValue::from(x)) }
+
+pub(crate) extern "C" fn bool_serialize(x: bool) -> i64 { encode(&BSValue::from(x)) }
+
+pub(crate) extern "C" fn sym_serialize(x: i64) -> i64 { encode(&BSValue::from(Sym(x))) }
+
+/// Serializes the date or time `x`, of the type `T`.
+pub(crate) extern "C" fn temporal_serialize<T>(x: T) -> i64
+where
+    BSValue: From<T>,
+{
+    encode(&BSValue::from(x))
+}
+
+pub(crate) extern "C" fn int_serialize_file(path: i64, x: i64) -> i64 { write(path, &BSValue::from(x)) }
+
+pub(crate) extern "C" fn float_serialize_file(path: i64, x: f64) -> i64 { write(path, &BSValue::from(x)) }
+
+pub(crate) extern "C" fn bool_serialize_file(path: i64, x: bool) -> i64 { write(path, &BSValue::from(x)) }
+
+pub(crate) extern "C" fn sym_serialize_file(path: i64, x: i64) -> i64 { write(path, &BSValue::from(Sym(x))) }
+
+pub(crate) extern "C" fn temporal_serialize_file<T>(path: i64, x: T) -> i64
+where
+    BSValue: From<T>,
+{
+    write(path, &BSValue::from(x))
+}
+
+/// The string, vector, dictionary, table or list `raw`, of any type of the kind `ty`.
+fn rc_value(ty: BSType, raw: i64) -> BSValue {
+    match raw {
+        0 => BSValue::from_raw_parts(ty, 0),
+        raw => unsafe { BSValue::from_raw_borrowed(ty, raw) },
+    }
+}
+
+fn vec_value(vec: i64) -> BSValue {
+    match vec {
+        0 => rc_value(BSType::VecInt64, 0),
+        vec => rc_value(unsafe { VecHeader::from_raw(vec).tag.vec_type() }, vec),
+    }
+}
+
+fn dict_value(dict: i64) -> BSValue { rc_value(BSType::dict(BSType::Null, BSType::Null), dict) }
+
+pub(crate) extern "C" fn str_serialize(s: i64) -> i64 { encode(&rc_value(BSType::Str, s)) }
+
+pub(crate) extern "C" fn vec_serialize(vec: i64) -> i64 { encode(&vec_value(vec)) }
+
+pub(crate) extern "C" fn dict_serialize(dict: i64) -> i64 { encode(&dict_value(dict)) }
+
+pub(crate) extern "C" fn table_serialize(table: i64) -> i64 { encode(&rc_value(BSType::Table(vec![]), table)) }
+
+pub(crate) extern "C" fn list_serialize(list: i64) -> i64 { encode(&rc_value(BSType::List, list)) }
+
+pub(crate) extern "C" fn str_serialize_file(path: i64, s: i64) -> i64 { write(path, &rc_value(BSType::Str, s)) }
+
+pub(crate) extern "C" fn vec_serialize_file(path: i64, vec: i64) -> i64 { write(path, &vec_value(vec)) }
+
+pub(crate) extern "C" fn dict_serialize_file(path: i64, dict: i64) -> i64 { write(path, &dict_value(dict)) }
+
+pub(crate) extern "C" fn table_serialize_file(path: i64, table: i64) -> i64 {
+    write(path, &rc_value(BSType::Table(vec![]), table))
+}
+
+pub(crate) extern "C" fn list_serialize_file(path: i64, list: i64) -> i64 { write(path, &rc_value(BSType::List, list)) }
+
+/// Value of the type named `ty` deserialized from `bytes`, which must hold a value of that type.
+/// The type is compared by name, the name compiled being the name of the type of the value read.
+fn decode(bytes: &[u8], ty: &str, holder: &str) -> Result<BSValue, String> {
+    match serial::read_type(bytes)? {
+        bytes_ty if bytes_ty.to_string() == ty => serial::deserialize(bytes),
+        bytes_ty => Err(format!("{} a value of type {}, not {}", holder, bytes_ty, ty)),
+    }
+}
+
+/// Reads the value of the type named `ty` from the bytes `bytes`, raising an error if they do not
+/// hold a value of that type.
+fn read(bytes: i64, ty: i64) -> Option<BSValue> {
+    let bytes = unsafe { <&[u8]>::from_abi(bytes) };
+    decode(bytes, string(ty), "The bytes hold")
+        .map_err(|msg| raise(&msg))
+        .ok()
+}
+
+/// Reads the value of the type named `ty` from the file `path`, raising an error if it does not
+/// hold a value of that type.
+fn read_file(path: i64, ty: i64) -> Option<BSValue> {
+    let path = string(path);
+    let res = fs::read(path)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| decode(&bytes, string(ty), "The file holds"));
+    res.map_err(|msg| raise_io(&msg, path, None)).ok()
+}
+
+pub(crate) extern "C" fn deserialize(bytes: i64, ty: i64) -> i64 { read(bytes, ty).map_or(0, |v| v.into_raw()) }
+
+pub(crate) extern "C" fn deserialize_float(bytes: i64, ty: i64) -> f64 {
+    read(bytes, ty).map_or(NULL_FLOAT64, |v| v.into())
+}
+
+pub(crate) extern "C" fn deserialize_bool(bytes: i64, ty: i64) -> bool { read(bytes, ty).is_some_and(|v| v.into()) }
+
+pub(crate) extern "C" fn deserialize_file(path: i64, ty: i64) -> i64 { read_file(path, ty).map_or(0, |v| v.into_raw()) }
+
+pub(crate) extern "C" fn deserialize_file_float(path: i64, ty: i64) -> f64 {
+    read_file(path, ty).map_or(NULL_FLOAT64, |v| v.into())
+}
+
+pub(crate) extern "C" fn deserialize_file_bool(path: i64, ty: i64) -> bool {
+    read_file(path, ty).is_some_and(|v| v.into())
+}
@@ -0,0 +1,54 @@
+//! Splayed tables, written to a directory of a file per column and read back by mapping the files,
+//! see `ffi::values::splay`.
+//!
+//! The type of a table read is known when compiling, given by name, so the directory may be any
+//! String, or read from the directory named by a literal once, when the function reading it is
+//! defined, and the directory read when running must hold a table of that type.
+
+use crate::rt::error::raise_io;
+use ffi::external::HostArg;
+use ffi::values::splay;
+use ffi::values::table::Table;
+use ffi::values::Value as BSValue;
+use std::path::Path;
+
+fn string<'a>(raw: i64) -> &'a str { unsafe { <&str>::from_abi(raw) } }
+
+fn rows_or_raise(res: Result<usize, String>, dir: &str) -> i64 {
+    res.map_or_else(
+        |msg| {
+            raise_io(&msg, dir, None);
+            0
+        },
+        |rows| rows as i64,
+    )
+}
+
+/// Writes `table` to the directory `dir`, returning the number of rows.
+pub(crate) extern "C" fn splay_write(dir: i64, table: i64) -> i64 {
+    let (dir, table) = (string(dir), unsafe { Table::from_raw(table) });
+    rows_or_raise(splay::write(Path::new(dir), table), dir)
+}
+
+/// Appends the rows of `table` to the splayed table in the directory `dir`, returning their number.
+pub(crate) extern "C" fn splay_append(dir: i64, table: i64) -> i64 {
+    let (dir, table) = (string(dir), unsafe { Table::from_raw(table) });
+    rows_or_raise(splay::append(Path::new(dir), table), dir)
+}
+
+/// Opens the splayed table of the type named `ty` in the directory `dir`, the type being compared
+/// by name.
+pub(crate) extern "C" fn splay_read(dir: i64, ty: i64) -> i64 {
+    let (dir, ty) = (string(dir), string(ty));
+    let res = splay::read(Path::new(dir)).and_then(|table| match table.get_type() {
+        table_ty if table_ty.to_string() == ty => Ok(table),
+        table_ty => Err(format!("The directory holds a table of type {}, not {}", table_ty, ty)),
+    });
+    match res {
+        Ok(table) => BSValue::from(table).into_raw(),
+        Err(msg) => {
+            raise_io(&msg, dir, None);
+            0
+        }
+    }
+}
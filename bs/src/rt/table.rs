@@ -0,0 +1,119 @@
+//! Tables built and read by compiled code.
+//!
+//! The type of a table is known when compiling, so its builtins are compiled into calls of these
+//! functions, see `ops::table`. Tables and columns passed in are borrowed unless stated otherwise,
+//! and the values returned are owned.
+
+use crate::rt::error::raise;
+use ffi::types::Type as BSType;
+use ffi::values::sym::Sym;
+use ffi::values::table::Table;
+use ffi::values::vector::VecHeader;
+use ffi::values::Value as BSValue;
+
+/// Takes ownership of the raw table `raw`, of any columns.
+fn owned_table(raw: i64) -> BSValue { BSValue::from_raw_parts(BSType::Table(vec![]), raw) }
+
+/// Returns a new table without any columns.
+pub(crate) extern "C" fn table_new() -> i64 { BSValue::from(Table::default()).into_raw() }
+
+/// Returns the owned `table` with the owned `column` added as `name`.
+pub(crate) extern "C" fn table_add(table: i64, name: i64, column: i64) -> i64 {
+    let table = owned_table(table);
+    let ty = unsafe { VecHeader::from_raw(column).tag.vec_type() };
+    let column = BSValue::from_raw_parts(ty, column);
+
+    match unsafe { Table::from_raw(table.as_raw()) }.with_column(Sym(name).name(), column) {
+        Ok(table) => BSValue::from(table).into_raw(),
+        Err(msg) => {
+            raise(msg);
+            table.into_raw()
+        }
+    }
+}
+
+/// Returns the owned `table` with its column `name` replaced by, or else added as, the owned
+/// `column`.
+pub(crate) extern "C" fn table_set(table: i64, name: i64, column: i64) -> i64 {
+    let table = owned_table(table);
+    let ty = unsafe { VecHeader::from_raw(column).tag.vec_type() };
+    let column = BSValue::from_raw_parts(ty, column);
+
+    match unsafe { Table::from_raw(table.as_raw()) }.set_column(Sym(name).name(), column) {
+        Ok(table) => BSValue::from(table).into_raw(),
+        Err(msg) => {
+            raise(msg);
+            table.into_raw()
+        }
+    }
+}
+
+/// Returns the owned `table` without its column `name`.
+pub(crate) extern "C" fn table_drop(table: i64, name: i64) -> i64 {
+    let table = owned_table(table);
+    let table = unsafe { Table::from_raw(table.as_raw()) }.without_columns(&[Sym(name).name()]);
+    BSValue::from(table).into_raw()
+}
+
+pub(crate) extern "C" fn table_column(table: i64, index: i64) -> i64 {
+    unsafe { Table::from_raw(table) }.columns()[index as usize]
+        .clone()
+        .into_raw()
+}
+
+pub(crate) extern "C" fn table_count(table: i64) -> i64 { unsafe { Table::from_raw(table) }.len() as i64 }
+
+pub(crate) extern "C" fn table_cols(table: i64) -> i64 {
+    BSValue::from(names(unsafe { Table::from_raw(table) })).into_raw()
+}
+
+fn names(table: &Table) -> Vec<Sym> { table.names().iter().map(|n| Sym::new(n)).collect() }
+
+/// Returns a table of the name `c` and the element type `t` of every column.
+pub(crate) extern "C" fn table_meta(table: i64) -> i64 {
+    let table = unsafe { Table::from_raw(table) };
+    let types: Vec<Sym> = table
+        .columns()
+        .iter()
+        .map(|c| Sym::new(&c.get_type().elem_type().expect("vector").to_string()))
+        .collect();
+
+    let meta = Table::default()
+        .with_column("c", BSValue::from(names(table)))
+        .and_then(|meta| meta.with_column("t", BSValue::from(types)))
+        .expect("meta");
+    BSValue::from(meta).into_raw()
+}
+
+/// Returns the table of the row `index`, raising an error if it is out of bounds.
+pub(crate) extern "C" fn table_row(table: i64, index: i64) -> i64 {
+    let table = unsafe { Table::from_raw(table) };
+    rows(table, &[index])
+}
+
+/// Returns the table of the rows at the vector `indices`, raising an error if any is out of bounds.
+pub(crate) extern "C" fn table_rows(table: i64, indices: i64) -> i64 {
+    let table = unsafe { Table::from_raw(table) };
+    rows(table, unsafe { VecHeader::from_raw(indices).as_slice() })
+}
+
+fn rows(table: &Table, indices: &[i64]) -> i64 {
+    match indices.iter().all(|i| (*i as u64) < table.len() as u64) {
+        true => BSValue::from(table.rows(indices)).into_raw(),
+        false => {
+            raise("Index out of bounds");
+            0
+        }
+    }
+}
+
+/// Returns the table of the rows but those at the vector `indices`, which are in bounds.
+pub(crate) extern "C" fn table_delete_rows(table: i64, indices: i64) -> i64 {
+    let table = unsafe { Table::from_raw(table) };
+    let mut kept = vec![true; table.len()];
+    for i in unsafe { VecHeader::from_raw(indices).as_slice::<i64>() } {
+        kept[*i as usize] = false;
+    }
+    let rows: Vec<i64> = (0..table.len() as i64).filter(|i| kept[*i as usize]).collect();
+    BSValue::from(table.rows(&rows)).into_raw()
+}
@@ -25,3 +25,255 @@ bs_test!(binop1, "1+1", "2");
 bs_test!(binop2, "4-3", "1");
 bs_test!(binop3, "4 - 3", "1");
 bs_test!(binop4, "4- 3", "1");
+bs_test!(float1, "1.5", "1.50");
+bs_test!(float2, "1.5 + 2.25", "3.75");
+bs_test!(bool1, "1 < 2", "true");
+bs_test!(extern1, "extern \"libm.so.6\" cos |x:Float64| -> Float64\ncos(0.0)", "1.00");
+bs_test!(extern2, "extern \"libm.so.6\" pow |x:Float64, y:Float64| -> Float64; pow(2.0, 10.0)", "1024.00");
+bs_test!(ret1, "fn inc |x:Int64| -> Int64 { x + 1 }\ninc(1)", "2");
+bs_test!(len1, "len([1, 2, 3])", "3");
+bs_test!(len2, "fn n |v:Float64[]| { len(v) * 2 }\nn([1.5, 2.5])", "4");
+bs_test!(vec_bool1, "[true, false]", "[true, false]");
+bs_test!(sum1, "sum([1, 2, 3])", "6");
+bs_test!(sum2, "sum([1.5, 2.5])", "4.00");
+bs_test!(sum3, "sum([true, false, true])", "2");
+bs_test!(sum4, "sum([])", "0.00");
+bs_test!(prod1, "prod([2, 3, 4])", "24");
+bs_test!(min1, "min([3, 1, 2])", "1");
+bs_test!(min2, "min([])", "0n");
+bs_test!(max1, "max([0.5, 2.5, 1.5])", "2.50");
+bs_test!(max2, "max([false, true])", "true");
+bs_test!(avg1, "avg([1, 2])", "1.50");
+bs_test!(count1, "count([1.0, 2.0, 3.0])", "3");
+bs_test!(first1, "first([4, 5])", "4");
+bs_test!(last1, "last([4, 5])", "5");
+bs_test!(dev1, "dev([1.0, 3.0])", "1.00");
+bs_test!(var1, "var([1.0, 3.0, 5.0, 7.0])", "5.00");
+bs_test!(til1, "til(5)", "[0, 1, 2, 3, 4]");
+bs_test!(enlist1, "enlist(1.5)", "[1.5]");
+bs_test!(join1, "join([1, 2], [3])", "[1, 2, 3]");
+bs_test!(take1, "take(5, [1, 2])", "[1, 2, 1, 2, 1]");
+bs_test!(take2, "take(-3, [1, 2, 3, 4])", "[2, 3, 4]");
+bs_test!(drop1, "drop(1, [1, 2, 3])", "[2, 3]");
+bs_test!(drop2, "drop(-1, [1.0, 2.0, 3.0])", "[1.0, 2.0]");
+bs_test!(reverse1, "reverse([true, false, false])", "[false, false, true]");
+bs_test!(distinct1, "distinct([1, 2, 1, 3, 2])", "[1, 2, 3]");
+bs_test!(where1, "where([true, false, true])", "[0, 2]");
+bs_test!(where2, "where([2, 0, 1])", "[0, 0, 2]");
+bs_test!(fill1, "fill(join([1], take(2, drop(1, [1]))), 7)", "[1, 7, 7]");
+bs_test!(fill2, "fill(take(2, []), 1.5)", "[1.5, 1.5]");
+bs_test!(rotate1, "rotate(1, [1, 2, 3])", "[2, 3, 1]");
+bs_test!(rotate2, "rotate(-1, [1, 2, 3])", "[3, 1, 2]");
+bs_test!(overload1, "fn f |x:Int64| { x + 1 }\nfn f |x:Float64| { x * 2.0 }\nf(2)", "3");
+bs_test!(overload2, "fn f |x:Int64| { x + 1 }\nfn f |x:Float64| { x * 2.0 }\nf(1.5)", "3.00");
+bs_test!(overload3, "fn f |x:Float64| { x }\nf(null)", "0n");
+bs_test!(generic1, "fn square<T> |x:T| { x * x }\nsquare(3)", "9");
+bs_test!(generic2, "fn square<T> |x:T| { x * x }\nsquare(1.5)", "2.25");
+bs_test!(generic3, "fn fib<T> |n:T| -> T { if n < 2 { n } else { fib(n - 1) + fib(n - 2) } }\nfib(10)", "55");
+bs_test!(asc1, "asc([3, 1, 2, 1])", "[1, 1, 2, 3]");
+bs_test!(asc2, "first(asc(join(til(300), take(1, drop(1, [1])))))", "0N");
+bs_test!(desc1, "desc([3.5, -1.0, 0.0, 2.0])", "[3.5, 2.0, 0.0, -1.0]");
+bs_test!(desc2, "last(desc(join([2.5, 1.5], take(1, []))))", "0n");
+bs_test!(iasc1, "iasc([3, 1, 2, 1])", "[1, 3, 2, 0]");
+bs_test!(idesc1, "idesc([3, 1, 2, 1])", "[0, 2, 1, 3]");
+bs_test!(idesc2, "first(idesc(join(til(300), [299])))", "299");
+bs_test!(rank1, "rank([30.5, 10.5, 20.5])", "[2, 0, 1]");
+bs_test!(xrank1, "xrank(2, [30, 10, 20, 40])", "[1, 0, 0, 1]");
+bs_test!(
+    xrank2,
+    "xrank(4611686018427387904, [30, 10, 20, 40])",
+    "[2305843009213693952, 0, 1152921504606846976, 3458764513820540928]"
+);
+bs_test!(sym_sort1, "s = `b`a; asc(join(`c`b, s))", "`a`b`b`c");
+bs_test!(sym_sort2, "desc(`b`a`c)", "`c`b`a");
+bs_test!(sym_sort3, "iasc(join(`b`a, take(1, drop(2, `x`y))))", "[2, 1, 0]");
+bs_test!(sym_sort4, "rank(`bb`a`b)", "[2, 0, 1]");
+bs_test!(null1, "[1, 0N, 3]", "[1, 0N, 3]");
+bs_test!(null2, "1 + 0N", "0N");
+bs_test!(null3, "null([1.5, 0n])", "[false, true]");
+bs_test!(null4, "sum([1, 0N, 3])", "4");
+bs_test!(null5, "avg([1.0, 0n, 3.0])", "2.00");
+bs_test!(null6, "0n == 0n", "true");
+bs_test!(null7, "0n < -1.0", "true");
+bs_test!(at1, "at([1, 2, 3], 1)", "2");
+bs_test!(at2, "fn second |v:Float64[]| { at(v, 1) }\nsecond([1.5, 2.5])", "2.50");
+bs_test!(at3, "at([true, false], 0)", "true");
+bs_test!(str1, "\"abc\"", "\"abc\"");
+bs_test!(str2, r#""a\"b\n\tc\r\\""#, r#""a\"b\n\tc\r\\""#);
+bs_test!(try1, "try { at([1, 2], 5) } catch e { 0 }", "0");
+bs_test!(try2, "try { signal \"boom\"; 1 } catch e { 2 }", "2");
+bs_test!(try3, "try { signal(\"boom\"); \"ok\" } catch e { e }", "\"boom\"");
+bs_test!(try4, "fn get |v:Int64[], i:Int64| { at(v, i) }\ntry { get([1], 0) } catch e { 0N }", "1");
+bs_test!(
+    try5,
+    "fn safe |v:Int64[], i:Int64| { try { at(v, i) } catch e { 0N } }\nsafe([1, 2], 1) + safe([1, 2], 2)",
+    "0N"
+);
+bs_test!(try6, "fn safe |v:Int64[], i:Int64| { try { at(v, i) } catch e { 0N } }\nsafe([1, 2], 1)", "2");
+bs_test!(dict1, "[1, 2]![1.5, 2.5]", "[1, 2]![1.5, 2.5]");
+bs_test!(dict2, "d = [1, 2, 1]![10, 20, 30]; d[1] + d[3]", "0N");
+bs_test!(dict3, "d = [1, 2]![true, false]; key(d)", "[1, 2]");
+bs_test!(dict4, "a = [1, 2]![1, 2]; b = [2, 3]![10, 20]; a - b", "[1, 2, 3]![1, -8, -20]");
+bs_test!(index1, "v = [4, 5, 6]; v[2]", "6");
+bs_test!(sym1, "`a`b`a", "`a`b`a");
+bs_test!(sym2, "`a == `b", "false");
+bs_test!(table1, "([] s: `a`b; p: [1.5, 10.25])", "s p\n-------\na 1.50\nb 10.25");
+bs_test!(table2, "t = ([] s: `a`b; q: [1, 2]); t.q", "[1, 2]");
+bs_test!(table3, "t = ([] s: `a`b`c; q: [1, 2, 3]); count(t[[0, 2]])", "2");
+bs_test!(table4, "cols(([] s: `a`b; q: [1, 2]))", "`s`q");
+bs_test!(
+    query1,
+    "t = ([] s: `a`b`a; q: [1, 2, 3]); select s, d: q * 2 from t where q > 1",
+    "s d\n---\nb 4\na 6"
+);
+bs_test!(
+    query2,
+    "t = ([] s: `a`b`a; q: [1, 2, 3]); select n: count(q), sum(q) from t where s == `a",
+    "n q\n---\n2 4"
+);
+bs_test!(
+    query3,
+    "t = ([] s: `a`b`a; q: [1, 2, 3]); select m: max(q), sum(q) by s from t",
+    "s m q\n-----\na 3 4\nb 2 2"
+);
+bs_test!(query_where1, "t = ([] s: `a`b; q: [1, 2]); exec avg(q) from t where s != `b", "1.00");
+bs_test!(query4, "t = ([] s: `a`b`a; q: [1, 2, 3]); exec sum(q) by s from t", "`a`b![4, 2]");
+bs_test!(
+    query5,
+    "t = ([] s: `a`b`a; q: [1, 2, 3]); exec q from update q: 0 from t where s == `b",
+    "[1, 0, 3]"
+);
+bs_test!(query6, "t = ([] s: `a`b`a; q: [1, 2, 3]); exec q from update q: sum(q) by s from t", "[4, 2, 4]");
+bs_test!(
+    query7,
+    "t = ([] s: `a`b`a; q: [1, 2, 3]); count(delete from t where q < 3) + count(cols(delete q from t))",
+    "2"
+);
+bs_test!(
+    query8,
+    "t = ([] s: `a`b`a`a; b: [true, false, true, false]; q: [1, 2, 3, 4]); select sum(q), n: count(q) by s, b from t",
+    "s b     q n\n-----------\na true  4 2\nb false 2 1\na false 4 1"
+);
+bs_test!(
+    query9,
+    "t = ([] s: `a`b`a; q: [1.5, 2.0, 3.0]); select l: last(q), d: max(q) + min(q) by s from t",
+    "s l    d\n-----------\na 3.00 4.50\nb 2.00 4.00"
+);
+bs_test!(group1, "group(`a`b`a)", "`a`b!([0, 2]; [1])");
+bs_test!(group2, "g = group([3, 1, 3, 3]); at(g, 3)", "[0, 2, 3]");
+bs_test!(group3, "key(group([1.5, 1.5, 0.5]))", "[1.5, 0.5]");
+bs_test!(
+    group4,
+    "t = ([] q: til(1000000)); exec sum(q) by k: q / 250000 from t",
+    "[0, 1, 2, 3]![31249875000, 93749875000, 156249875000, 218749875000]"
+);
+bs_test!(group5, "t = ([] q: til(1000000)); count(select n: count(q) by k: q / 3, j: q / 5 from t)", "466667");
+bs_test!(group6, "count(key(group(til(1000000))))", "1000000");
+bs_test!(
+    table_join1,
+    "t = ([] s: `a`b`a; q: [1, 2, 3]); r = ([] s: `a`c; p: [1.5, 2.5]; q: [10, 30]); lj(`s, t, r)",
+    "s q  p\n---------\na 10 1.50\nb 2  0n\na 10 1.50"
+);
+bs_test!(
+    table_join2,
+    "t = ([] s: `a`b`a; q: [1, 2, 3]); r = ([] s: `a`c; p: [1.5, 2.5]); exec p from ij(`s, t, r)",
+    "[1.5, 1.5]"
+);
+bs_test!(table_join3, "t = ([] s: `a`b; q: [1, 2]); cols(uj(t, ([] p: [true])))", "`s`q`p");
+bs_test!(
+    table_join4,
+    "t = ([] s: `a`b`a; time: [1, 2, 5]); q = ([] s: `a`b`a; time: [0, 3, 3]; px: [1, 2, 3]); exec px from \
+     aj(`s`time, t, q)",
+    "[1, 0N, 3]"
+);
+bs_test!(json1, "to_json([1, 0N, 3])", "\"[1,null,3]\"");
+bs_test!(
+    json2,
+    "to_json(([] s: `a`b; q: [1.5, 2.0]))",
+    "\"[{\\\"s\\\":\\\"a\\\",\\\"q\\\":1.5},{\\\"s\\\":\\\"b\\\",\\\"q\\\":2.0}]\""
+);
+bs_test!(json3, "from_json(\"[1, 2.5, null]\")", "[1.0, 2.5, 0n]");
+bs_test!(json4, "from_json(\"{\\\"a\\\": [1], \\\"b\\\": []}\")", "`a`b!([1]; [])");
+bs_test!(json5, "s = to_json([1, 2]![true, false]); from_json(s, \"Int64[]!Bool[]\")", "[1, 2]![true, false]");
+bs_test!(
+    json6,
+    "t = ([] s: `a`b; px: [1.5, 0n]; n: [1, 0N]); from_json(to_json(t), \"Table(s: Symbol[], px: Float64[], n: \
+     Int64[])\")",
+    "s px   n\n---------\na 1.50 1\nb 0n   0N"
+);
+bs_test!(json7, r#"from_json("[{\"a\": 1}, {\"b\": true}]")"#, "a  b\n--------\n1  false\n0N true");
+bs_test!(json8, r#"try { from_json("[1, true]", "Float64[]") } catch e { [0.0] }"#, "[0.0]");
+bs_test!(
+    json9,
+    "to_json(([] s: `a`b; px: [1.5, 0n]; n: [1, 0N]))",
+    "\"[{\\\"s\\\":\\\"a\\\",\\\"px\\\":1.5,\\\"n\\\":1},{\\\"s\\\":\\\"b\\\",\\\"px\\\":null,\\\"n\\\":null}]\""
+);
+bs_test!(serial1, "serialize(1)", "0x42535601020100000000000000");
+bs_test!(serial2, "deserialize(serialize([1, 0N]), \"Int64[]\")", "[1, 0N]");
+bs_test!(serial3, "deserialize(serialize(1.5), \"Float64\") + 1.0", "2.50");
+bs_test!(serial4, "deserialize(serialize(group(`a`b`a)), \"Symbol[]![]\")", "`a`b!([0, 2]; [1])");
+bs_test!(
+    serial5,
+    "deserialize(serialize(([] s: `a`b; q: [1, 2])), \"Table(s: Symbol[], q: Int64[])\")",
+    "s q\n---\na 1\nb 2"
+);
+bs_test!(div_zero1, "1 / 0", "0N");
+bs_test!(div_zero2, "(5 / 0) + 1", "0N");
+bs_test!(div_zero3, "1.0 / 0.0", "inf");
+bs_test!(date1, "2026.10.18", "2026.10.18");
+bs_test!(date2, "2026.10.18 + 14", "2026.11.01");
+bs_test!(date3, "2026.10.18 - 2026.01.01", "290");
+bs_test!(date4, "(year(2026.10.18) * 100) + month(2026.10.18)", "202610");
+bs_test!(date5, "day(2024.02.29)", "29");
+bs_test!(time1, "09:30", "09:30:00.000");
+bs_test!(time2, "09:30:00.000 + 0D00:00:00.000001", "09:30:00.000001000");
+bs_test!(time3, "(hour(09:45:12.000) * 100) + minute(09:45:12.000)", "945");
+bs_test!(time4, "second(2026.10.18D09:45:12.500)", "12");
+bs_test!(timestamp1, "2026.10.18 + 09:30:00.000", "2026.10.18D09:30:00.000000000");
+bs_test!(timestamp2, "2026.10.18D12:00:00 - 2026.10.17D09:30:00", "1D02:30:00.000000000");
+bs_test!(timestamp3, "2026.10.18D09:30:00 < 2026.10.18D09:30:01", "true");
+bs_test!(temporal_null1, "0Nd + 1", "0Nd");
+bs_test!(temporal_null2, "hour(0Np)", "0N");
+bs_test!(xbar1, "xbar(0D00:05:00, 09:32:10.000)", "09:30:00.000");
+bs_test!(xbar2, "xbar(0D01:00:00, 2026.10.18D09:32:10)", "2026.10.18D09:00:00.000000000");
+bs_test!(xbar3, "xbar(5, [1, 7, 12, -3])", "[0, 5, 10, -5]");
+bs_test!(temporal_vec1, "[2026.10.18, 0Nd, 2026.01.01]", "[2026.10.18, 0Nd, 2026.01.01]");
+bs_test!(temporal_vec2, "asc([09:30, 0Nt, 08:00])", "[0Nt, 08:00:00.000, 09:30:00.000]");
+bs_test!(
+    temporal_vec3,
+    "t = ([] d: [2026.10.18, 2026.10.19, 2026.10.18]; q: [1, 2, 3]); select q: sum(q), n: count(d) by d from t",
+    "d          q n\n--------------\n2026.10.18 4 2\n2026.10.19 2 1"
+);
+bs_test!(
+    temporal_vec4,
+    "t = ([] d: [2026.10.18, 2026.10.19]; q: [1, 2]); exec q from t where d > 2026.10.18",
+    "[2]"
+);
+bs_test!(
+    temporal_vec5,
+    "t = ([] s: `a`a; time: [09:30, 10:00]); q = ([] s: `a`a; time: [09:00, 09:45]; px: [1, 2]); exec px from \
+     aj(`s`time, t, q)",
+    "[1, 2]"
+);
+bs_test!(
+    temporal_vec6,
+    "exec max(t) by s from ([] s: `a`b`a; t: [09:30, 10:00, 11:00])",
+    "`a`b![11:00:00.000, 10:00:00.000]"
+);
+bs_test!(
+    temporal_json1,
+    "to_json([2026.10.18D09:30:00, 0Np])",
+    "\"[\\\"2026.10.18D09:30:00.000000000\\\",null]\""
+);
+bs_test!(temporal_json2, "from_json(to_json(1D02:00:00), \"Timespan\")", "1D02:00:00.000000000");
+bs_test!(
+    temporal_json3,
+    "t = ([] d: [2026.10.18, 0Nd]; n: [1, 2]); from_json(to_json(t), \"Table(d: Date[], n: Int64[])\")",
+    "d          n\n------------\n2026.10.18 1\n0Nd        2"
+);
+bs_test!(temporal_serial1, "deserialize(serialize(09:30), \"Time\")", "09:30:00.000");
+bs_test!(
+    temporal_serial2,
+    "deserialize(serialize([2026.10.18, 0Nd]![1, 2]), \"Date[]!Int64[]\")",
+    "[2026.10.18, 0Nd]![1, 2]"
+);
@@ -0,0 +1,617 @@
+extern crate bs;
+
+use bs::parse::diagnostic::Diagnostic;
+use bs::result::{BSError, BSResult};
+use bs::rt::engine::Engine;
+use ffi::values::serial;
+use ffi::values::splay;
+use ffi::values::vector::{VecHeader, VecStorage};
+use ffi::values::Value as BSValue;
+use std::fmt::Display;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn engine_with(src: &str) -> Engine {
+    let mut engine = Engine::new().expect("Failed to create engine");
+    match engine.compile(src) {
+        BSResult::Ok(_) => engine,
+        BSResult::Err(err) => panic!("{:?}", err),
+    }
+}
+
+/// Evaluates `src`, returning its value as displayed.
+fn eval_str(engine: &mut Engine, src: &str) -> String {
+    match engine.eval(src) {
+        BSResult::Ok(value) => format!("{}", value),
+        BSResult::Err(err) => panic!("{}: {:?}", src, err),
+    }
+}
+
+/// Evaluates `src`, which must raise the runtime error `msg`, returning where it is located.
+fn assert_runtime_error(engine: &mut Engine, src: &str, msg: &str) -> Option<usize> {
+    match engine.eval(src) {
+        BSResult::Err(BSError::RuntimeError { msg: raised, span }) => {
+            assert_eq!(raised, msg, "{}", src);
+            span.map(|s| s.label_start)
+        }
+        BSResult::Err(err) => panic!("{}: {:?}", src, err),
+        BSResult::Ok(value) => panic!("{}: {}", src, value),
+    }
+}
+
+/// Evaluates `src`, which must raise the I/O error `msg`, returning its file and line.
+fn assert_io_error(engine: &mut Engine, src: &str, msg: &str) -> (String, Option<usize>) {
+    io_error(engine.eval(src), src, msg)
+}
+
+/// File and line of the I/O error `msg` of the result of `src`.
+fn io_error<T: Display>(res: BSResult<T>, src: &str, msg: &str) -> (String, Option<usize>) {
+    match res {
+        BSResult::Err(BSError::IOError { msg: raised, path, line, .. }) => {
+            assert_eq!(raised, msg, "{}", src);
+            (path, line)
+        }
+        BSResult::Err(err) => panic!("{}: {:?}", src, err),
+        BSResult::Ok(value) => panic!("{}: {}", src, value),
+    }
+}
+
+fn assert_compile_error(engine: &mut Engine, src: &str) {
+    assert!(matches!(engine.eval(src), BSResult::Err(BSError::CompileError { .. })), "{}", src);
+}
+
+#[test]
+fn typed_call() {
+    let engine = engine_with("fn scale |x:Int64, k:Float64| { k * 2.0 }");
+    let scale = engine.get_fn::<(i64, f64), f64>("scale").expect("scale");
+    assert_eq!(scale.call((1, 1.5)), 3.0);
+    assert_eq!(scale.call((1, 4.0)), 8.0);
+}
+
+#[test]
+fn typed_call_mismatch() {
+    let engine = engine_with("fn inc |x:Int64| { x + 1 }");
+    assert!(matches!(engine.get_fn::<(f64,), i64>("inc"), BSResult::Err(_)));
+    assert!(matches!(engine.get_fn::<(i64,), f64>("inc"), BSResult::Err(_)));
+    assert!(matches!(engine.get_fn::<(i64,), i64>("missing"), BSResult::Err(_)));
+}
+
+#[test]
+fn scripts_see_previous_functions() {
+    let mut engine = engine_with("fn inc |x:Int64| { x + 1 }");
+    let script = engine.compile("fn inc2 |x:Int64| { inc(inc(x)) }").expect("compile");
+    assert_eq!(script.functions(), &["inc2".to_string()]);
+    let inc2 = engine.get_fn::<(i64,), i64>("inc2").expect("inc2");
+    assert_eq!(inc2.call((40,)), 42);
+}
+
+#[test]
+fn globals() {
+    let mut engine = Engine::new().expect("Failed to create engine");
+    engine.set_global("limit", 10).expect("limit");
+    engine.compile("fn over |x:Int64| { x > limit }").expect("compile");
+    assert!(engine.get_fn::<(i64,), bool>("over").expect("over").call((11,)));
+
+    engine.set_global("limit", 20).expect("limit");
+    assert!(!engine.get_fn::<(i64,), bool>("over").expect("over").call((11,)));
+
+    // `over` is compiled for an Int64 limit
+    assert!(matches!(engine.set_global("limit", 10.5), BSResult::Err(BSError::RuntimeError { .. })));
+    assert!(!engine.get_fn::<(i64,), bool>("over").expect("over").call((11,)));
+
+    // a global whose assignment raised an error holds the null of its type
+    assert_runtime_error(&mut engine, "n = at([1], 5)", "Index out of bounds");
+    assert_eq!(eval_str(&mut engine, "n"), "0N");
+    assert_runtime_error(&mut engine, "px = at([1.5], 5)", "Index out of bounds");
+    assert_eq!(eval_str(&mut engine, "px"), "0n");
+
+    assert_eq!(engine.get_global::<i64>("limit").expect("limit"), 20);
+    assert!(matches!(engine.get_global::<f64>("limit"), BSResult::Err(_)));
+    assert!(matches!(engine.get_global::<i64>("missing"), BSResult::Err(_)));
+}
+
+#[test]
+fn host_fn() {
+    let mut engine = Engine::new().expect("Failed to create engine");
+    let k = 10.0;
+    engine.register_fn("weighted", move |x: i64, v: &[f64]| -> f64 { x as f64 * k + v.iter().sum::<f64>() });
+    engine.register_fn("evens", |n: i64| -> Vec<i64> { (0..n).map(|i| i * 2).collect() });
+
+    assert_eq!(eval_str(&mut engine, "weighted(2, [1.5, 2.5])"), "24.00");
+    assert_eq!(eval_str(&mut engine, "evens(4)"), "[0, 2, 4, 6]");
+
+    engine
+        .compile("fn f |x:Int64| { weighted(x, [0.5]) }")
+        .expect("compile");
+    assert_eq!(engine.get_fn::<(i64,), f64>("f").expect("f").call((1,)), 10.5);
+}
+
+#[test]
+fn host_fn_type_check() {
+    let mut engine = Engine::new().expect("Failed to create engine");
+    engine.register_fn("half", |x: f64| -> f64 { x / 2.0 });
+    assert_compile_error(&mut engine, "half(1)");
+}
+
+#[test]
+fn host_fn_panic() {
+    let mut engine = Engine::new().expect("Failed to create engine");
+    engine.register_fn("first", |v: &[i64]| -> i64 { v[0] });
+
+    let msg = "Host function panicked: index out of bounds: the len is 0 but the index is 0";
+    assert_runtime_error(&mut engine, "first(drop(1, [1]))", msg);
+    assert_eq!(eval_str(&mut engine, "first([7, 8])"), "7");
+}
+
+#[test]
+fn isolated_runtimes() {
+    let mut a = Engine::new().expect("Failed to create engine");
+    let mut b = Engine::new().expect("Failed to create engine");
+    a.register_fn("host", |x: i64| -> i64 { x + 1 });
+    b.register_fn("host", |x: i64| -> i64 { x * 100 });
+    a.set_global("g", 1).expect("g");
+    b.set_global("g", 2).expect("g");
+
+    assert_eq!(eval_str(&mut a, "host(g)"), "2");
+    assert_eq!(eval_str(&mut b, "host(g)"), "200");
+    assert_eq!(eval_str(&mut a, "host(g)"), "2");
+}
+
+#[test]
+fn runtime_is_send() {
+    let mut engine = Engine::new().expect("Failed to create engine");
+    engine.compile("fn sq |x:Int64| { x * x }").expect("compile");
+
+    let handles: Vec<_> = (0..4)
+        .map(|i| {
+            std::thread::spawn(move || {
+                let mut engine = Engine::new().expect("Failed to create engine");
+                engine.compile("fn sq |x:Int64| { x * x }").expect("compile");
+                engine.get_fn::<(i64,), i64>("sq").expect("sq").call((i,))
+            })
+        })
+        .collect();
+    let results: Vec<i64> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    assert_eq!(results, vec![0, 1, 4, 9]);
+
+    let moved = std::thread::spawn(move || engine.get_fn::<(i64,), i64>("sq").expect("sq").call((7,)));
+    assert_eq!(moved.join().unwrap(), 49);
+}
+
+#[test]
+fn extern_fn() {
+    let mut engine = engine_with("extern \"libm.so.6\" sqrt |x:Float64| -> Float64\nfn root |x:Float64| { sqrt(x) }");
+    assert_eq!(engine.get_fn::<(f64,), f64>("root").expect("root").call((16.0,)), 4.0);
+    assert!(matches!(engine.compile("extern \"missing.so\" f |x:Int64| -> Int64"), BSResult::Err(_)));
+    assert!(matches!(engine.compile("extern \"libm.so.6\" missing |x:Int64| -> Int64"), BSResult::Err(_)));
+    assert_compile_error(&mut engine, "extern \"libm.so.6\" cos |x:Float64|");
+    assert_compile_error(&mut engine, "extern \"libm.so.6\" cos |x:Float64[]| -> Float64");
+    assert_compile_error(&mut engine, "extern \"libc.so.6\" getenv |name:String| -> String");
+    assert!(matches!(engine.compile("fn f |x:Int64| -> Float64 { x }"), BSResult::Err(_)));
+}
+
+#[test]
+fn host_fn_overloads() {
+    let mut engine = Engine::new().expect("engine");
+    engine.register_fn("twice", |x: i64| x * 2);
+    engine.register_fn("twice", |x: f64| x * 2.0);
+    assert_eq!(eval_str(&mut engine, "twice(2)"), "4");
+    assert_eq!(eval_str(&mut engine, "twice(1.5)"), "3.00");
+    assert_compile_error(&mut engine, "twice(true)");
+}
+
+#[test]
+fn user_fn_overloads() {
+    let mut engine = engine_with("fn f |x:Int64| { x + 1 }\nfn f |x:Float64| { x * 2.0 }\nfn g |x:Int64| { f(x) }");
+    assert_eq!(engine.get_fn::<(i64,), i64>("f").expect("f").call((1,)), 2);
+    assert_eq!(engine.get_fn::<(f64,), f64>("f").expect("f").call((1.5,)), 3.0);
+    assert_eq!(engine.get_fn::<(i64,), i64>("g").expect("g").call((2,)), 3);
+    assert!(matches!(engine.get_fn::<(bool,), i64>("f"), BSResult::Err(_)));
+
+    // null converts to either parameter
+    assert_compile_error(&mut engine, "f(null)");
+    assert_compile_error(&mut engine, "f(true)");
+}
+
+#[test]
+fn generic_fns() {
+    let mut engine = engine_with("fn square<T> |x:T| { x * x }\nfn area |r:Float64| { square(r) * 3.0 }");
+    assert_eq!(engine.get_fn::<(f64,), f64>("area").expect("area").call((2.0,)), 12.0);
+    assert_eq!(eval_str(&mut engine, "square(3) + square(4)"), "25");
+
+    engine.compile("fn both<T> |a:T, b:T| { a }").expect("compile");
+    assert_compile_error(&mut engine, "both(1, 2.0)");
+
+    // the return type of a recursive specialization can not be inferred
+    engine
+        .compile("fn fact<T> |n:T| { if n < 2 { 1 } else { n * fact(n - 1) } }")
+        .expect("compile");
+    assert_compile_error(&mut engine, "fact(5)");
+}
+
+#[test]
+fn checked_arithmetic() {
+    let mut engine = Engine::new().expect("Failed to create engine");
+    engine.set_checked_arithmetic(true);
+    engine.compile("fn add |a:Int64, b:Int64| { a + b }").expect("compile");
+    let add = engine.get_fn::<(i64, i64), i64>("add").expect("add");
+    assert_eq!(add.try_call((1, 2)).expect("add"), 3);
+    assert!(matches!(add.try_call((i64::MAX, 1)), BSResult::Err(_)));
+
+    assert_eq!(assert_runtime_error(&mut engine, "2 * 9223372036854775807", "Integer overflow"), Some(2));
+    assert_eq!(eval_str(&mut engine, "0N * 2"), "0N");
+
+    // a date and a time are added as the nanoseconds of a timestamp
+    assert_runtime_error(&mut engine, "2262.04.12 + 00:00", "Integer overflow");
+    assert_runtime_error(&mut engine, "2262.04.11 + 23:59", "Integer overflow");
+    assert_eq!(eval_str(&mut engine, "2262.04.11 + 23:00"), eval_str(&mut engine, "2262.04.11D23:00"));
+    assert_eq!(eval_str(&mut engine, "0Nd + 23:00"), eval_str(&mut engine, "0Np"));
+}
+
+#[test]
+fn runtime_errors() {
+    let mut engine = engine_with("fn get |v:Int64[], i:Int64| { at(v, i) * 2 }");
+    assert_runtime_error(&mut engine, "get([1, 2], 0) + get([1, 2], 5)", "Index out of bounds");
+    assert_eq!(eval_str(&mut engine, "get([1, 2], 1)"), "4");
+
+    // errors raised by builtins are located at their call
+    let msg = "xrank takes a positive number of buckets";
+    assert_eq!(assert_runtime_error(&mut engine, "len(xrank(0, [1, 2]))", msg), Some(4));
+
+    // errors raised for a row end the query
+    engine.eval("t = ([] s: `a`b; q: [1, 2])").expect("eval");
+    assert_runtime_error(&mut engine, "select v: at([1], q) from t", "Index out of bounds");
+    assert_runtime_error(&mut engine, "([] a: [1, 2]; b: [1.5])", "Table columns must have the same length");
+}
+
+#[test]
+fn errors_show_their_source() {
+    let mut engine = engine_with("fn f |i:Int64| {\n  v = [1];\n  at(v, i)\n}");
+    let src = "x = 1; f(5)";
+    let err = match engine.eval(src) {
+        BSResult::Err(err) => err,
+        BSResult::Ok(value) => panic!("{}: {}", src, value),
+    };
+    let shown = format!("{}", Diagnostic::new("REPL", src, err));
+    assert!(shown.contains(":3:") && shown.contains("  at(v, i)"), "{}", shown);
+}
+
+#[test]
+fn try_catch() {
+    let mut engine = engine_with("");
+
+    // both branches share a type
+    assert_compile_error(&mut engine, "try { 1 } catch e { 1.0 }");
+
+    // errors raised by the handler are not caught
+    assert_runtime_error(&mut engine, "try { signal \"first\" } catch e { signal \"second\" }", "second");
+}
+
+#[test]
+fn tables_share_columns() {
+    let mut engine = engine_with("");
+    let v = engine.eval("v = [1.5, 2.5]").expect("eval");
+    let t = engine.eval("t = ([] s: `a`b; v: v)").expect("eval");
+    let column = t.as_table().expect("table").column("v").expect("column");
+    assert_eq!(column.as_raw(), v.as_raw());
+    assert_eq!(engine.eval("t.v").expect("eval").as_raw(), v.as_raw());
+    assert_compile_error(&mut engine, "t.w");
+}
+
+#[test]
+fn query_errors() {
+    let mut engine = engine_with("");
+    engine.eval("t = ([] s: `a`b; q: [1, 2])").expect("eval");
+
+    // reductions of the rows are only selected along with the rows by group
+    assert_compile_error(&mut engine, "select q, sum(q) from t");
+    assert_compile_error(&mut engine, "select q by s from t");
+    assert_compile_error(&mut engine, "select from t where q");
+    assert_compile_error(&mut engine, "update q: 1.5 from t");
+}
+
+#[test]
+fn join_errors() {
+    let mut engine = engine_with("");
+    engine.eval("t = ([] s: `a`b; q: [1, 2]; x: [1.5, 2.5])").expect("eval");
+    engine.eval("r = ([] s: `a`b; q: [1.5, 2.5])").expect("eval");
+
+    // the keys and shared columns of both tables must be of the same type
+    for src in ["lj(`q, t, r)", "lj(`s, t, r)", "uj(t, r)", "k = `s`q; ij(k, t, r)", "aj(`s, t, ([] s: `a))"] {
+        assert_compile_error(&mut engine, src);
+    }
+
+    let msg = "The times of the right table of 'aj' must be sorted";
+    assert_runtime_error(&mut engine, "aj(`x, t, ([] x: [2.5, 1.5]))", msg);
+}
+
+/// Path of the file `name` in a temporary directory of the test `test`.
+fn temp_file(test: &str, name: &str) -> String {
+    let dir: PathBuf = std::env::temp_dir().join(format!("bs-{}-{}", test, std::process::id()));
+    fs::create_dir_all(&dir).expect("temp dir");
+    dir.join(name).to_str().expect("path").to_string()
+}
+
+#[test]
+fn csv_round_trip() {
+    let (src, out) = (temp_file("csv", "trades.csv"), temp_file("csv", "out.csv"));
+    fs::write(&src, "sym,px,size\na,1.5,100\n\"b,\"\"c\"\"\",2,\n").expect("write");
+
+    let mut engine = engine_with("");
+    engine.eval(&format!("t = read_csv(\"{}\")", src)).expect("eval");
+    assert_eq!(eval_str(&mut engine, "exec t from meta(t)"), "`Symbol`Float64`Int64");
+    assert_eq!(eval_str(&mut engine, "exec size from t"), "[100, 0N]");
+    assert_eq!(eval_str(&mut engine, &format!("write_csv(\"{}\", t, \";\")", out)), "2");
+    assert_eq!(fs::read_to_string(&out).expect("read"), "sym;px;size\na;1.5;100\n\"b,\"\"c\"\"\";2.0;\n");
+
+    let t = engine
+        .eval(&format!("read_csv(\"{}\", \"S F\", \";\")", out))
+        .expect("eval");
+    assert_eq!(format!("{}", t), "sym   size\n------------\na     100.00\nb,\"c\" 0n");
+}
+
+#[test]
+fn csv_errors() {
+    let path = temp_file("csv-errors", "bad.csv");
+    fs::write(&path, "a,b\n1,2\n\n3,x\n").expect("write");
+    let mut engine = engine_with("");
+
+    // the columns are read when compiling, the rows when running
+    let src = format!("read_csv(\"{}\", \"JJ\")", path);
+    assert_eq!(assert_io_error(&mut engine, &src, "Invalid Int64 'x' in column 'b'"), (path.clone(), Some(4)));
+    assert!(matches!(
+        engine.eval("read_csv(\"missing.csv\")"),
+        BSResult::Err(BSError::IOError { line: None, .. })
+    ));
+    assert!(matches!(
+        engine.eval(&format!("read_csv(\"{}\", \"J\")", path)),
+        BSResult::Err(BSError::IOError { .. })
+    ));
+    assert_compile_error(&mut engine, &format!("p = \"{}\"; read_csv(p)", path));
+
+    // a file rewritten since compiling must still hold a table of the type compiled
+    let path = temp_file("csv-errors", "changed.csv");
+    fs::write(&path, "a,b\n1,2\n").expect("write");
+    engine
+        .compile(&format!("fn load || {{ count(read_csv(\"{}\")) }}", path))
+        .expect("compile");
+    let load = engine.get_fn::<(), i64>("load").expect("load");
+    fs::write(&path, "a,c\n1,2\n").expect("write");
+    let msg = "The file holds a table of type Table(a: Int64[], c: Int64[]), not Table(a: Int64[], b: Int64[])";
+    assert_eq!(io_error(load.try_call(()), "load()", msg), (path.clone(), Some(1)));
+}
+
+#[test]
+fn csv_schema() {
+    let path = temp_file("csv-schema", "trades.csv");
+    fs::write(&path, "sym,px,size\na,1.5,100\nb,2,200\n").expect("write");
+    let mut engine = engine_with("");
+
+    // the columns named by the table type are read by name, so the path need not be a literal
+    engine
+        .eval("fn sizes |p:String| { exec size from read_csv(p, \"Table(size: Int64[], sym: Symbol[])\") }")
+        .expect("eval");
+    assert_eq!(eval_str(&mut engine, &format!("sizes(\"{}\")", path)), "[100, 200]");
+    assert_io_error(
+        &mut engine,
+        "read_csv(\"trades.csv\", \"Table(qty: Int64[])\")",
+        "No such file or directory (os error 2)",
+    );
+    let src = format!("read_csv(\"{}\", \"Table(qty: Int64[])\")", path);
+    assert_io_error(&mut engine, &src, "Missing column 'qty'");
+    assert_compile_error(&mut engine, "read_csv(\"trades.csv\", \"Table(s: String)\")");
+
+    // a file named by a literal is read once, when the function reading it is defined
+    engine
+        .eval(&format!("fn n || {{ count(read_csv(\"{}\")) }}", path))
+        .expect("eval");
+    fs::remove_file(&path).expect("remove");
+    assert_eq!(eval_str(&mut engine, "1 + 1"), "2");
+    assert_eq!(io_error(engine.eval("n()"), "n()", "No such file or directory (os error 2)").0, path);
+}
+
+#[test]
+fn json_errors() {
+    let mut engine = engine_with("");
+    assert_compile_error(&mut engine, r#"from_json("[1, ")"#);
+    assert_compile_error(&mut engine, r#"s = "[1]"; from_json(s)"#);
+    assert_compile_error(&mut engine, r#"from_json("1", "Int64[")"#);
+
+    let msg = "Invalid JSON: Expected Int64[] in JSON, found an object";
+    assert_runtime_error(&mut engine, r#"s = "{\"a\": 1}"; from_json(s, "Int64[]")"#, msg);
+}
+
+#[test]
+fn temporal_errors() {
+    let mut engine = engine_with("");
+    // invalid dates and times, and those which overflow
+    for src in [
+        "2026.02.30",
+        "24:00:00",
+        "09:60",
+        "2026.10.18D25:00:00",
+        "99999999999999999999D00:00",
+        "9999.12.31D00:00",
+    ] {
+        assert!(matches!(engine.eval(src), BSResult::Err(BSError::ParseError { .. })), "{}", src);
+    }
+    assert_compile_error(&mut engine, "2026.10.18 + 2026.10.18");
+    assert_compile_error(&mut engine, "09:30 + 1");
+}
+
+#[test]
+fn serial_round_trip() {
+    let path = temp_file("serial", "t.bsv");
+    let mut engine = engine_with("");
+    engine
+        .eval("t = ([] s: `a`b; px: [1.5, 0n]; n: [1, 0N])")
+        .expect("eval");
+    let written = engine.eval(&format!("serialize(\"{}\", t)", path)).expect("eval");
+    assert_eq!(fs::metadata(&path).expect("file").len() as i64, written.into());
+
+    // the type is read from the file when compiling
+    let t = engine.eval(&format!("deserialize(\"{}\")", path)).expect("eval");
+    assert_eq!(format!("{}", t), "s px   n\n---------\na 1.50 1\nb 0n   0N");
+
+    let bytes = fs::read(&path).expect("read");
+    assert_eq!(serial::serialize(&t), Ok(bytes.clone()));
+    let value = serial::deserialize(&bytes).expect("deserialize");
+    assert_eq!(value.get_type(), t.get_type());
+    assert_eq!(format!("{}", value), format!("{}", t));
+
+    let src = format!("serialize(\"{0}\", group(`a`b`a)); deserialize(\"{0}\", \"Symbol[]![]\")", path);
+    assert_eq!(eval_str(&mut engine, &src), "`a`b!([0, 2]; [1])");
+}
+
+#[test]
+fn serial_errors() {
+    let path = temp_file("serial-errors", "x.bsv");
+    let mut engine = engine_with("");
+    engine.eval(&format!("serialize(\"{}\", [1, 2])", path)).expect("eval");
+
+    let src = format!("p = \"{}\"; deserialize(p, \"Float64[]\")", path);
+    let msg = "The file holds a value of type Int64[], not Float64[]";
+    assert_eq!(assert_io_error(&mut engine, &src, msg), (path.clone(), None));
+    assert!(matches!(engine.eval("deserialize(\"missing.bsv\")"), BSResult::Err(BSError::IOError { .. })));
+    assert_compile_error(&mut engine, &format!("p = \"{}\"; deserialize(p)", path));
+
+    // a file rewritten since compiling must still hold a value of the type compiled
+    engine
+        .compile(&format!("fn load || {{ count(deserialize(\"{}\")) }}", path))
+        .expect("compile");
+    engine.eval(&format!("serialize(\"{}\", `a`b)", path)).expect("eval");
+    let load = engine.get_fn::<(), i64>("load").expect("load");
+    let msg = "The file holds a value of type Symbol[], not Int64[]";
+    assert_eq!(io_error(load.try_call(()), "load()", msg), (path.clone(), None));
+
+    // bytes are read when running, of the type given
+    let msg = "The bytes hold a value of type Int64, not Float64";
+    assert_runtime_error(&mut engine, "deserialize(serialize(1), \"Float64\")", msg);
+    assert_compile_error(&mut engine, "deserialize([66, 83, 86], \"Int64\")");
+    assert_compile_error(&mut engine, "deserialize(serialize(1))");
+    assert_compile_error(&mut engine, "deserialize([1.5], \"Int64\")");
+
+    let bytes = fs::read(&path).expect("read");
+    assert!(serial::deserialize(&bytes[..bytes.len() - 1]).is_err());
+    assert!(serial::deserialize(b"BSV\x02\x02").is_err());
+    assert!(serial::deserialize(b"BSV\x01\x01\x02").is_err());
+
+    // the type of a file named by a literal is read once, when the function reading it is defined
+    fs::remove_file(&path).expect("remove");
+    assert_eq!(eval_str(&mut engine, "1 + 1"), "2");
+    let msg = "No such file or directory (os error 2)";
+    assert_eq!(assert_io_error(&mut engine, "load()", msg), (path.clone(), None));
+}
+
+#[test]
+fn splayed_tables() {
+    let dir = temp_file("splay", "trades");
+    let mut engine = engine_with("");
+    engine
+        .eval("t = ([] s: `a`b; px: [1.5, 0n]; n: [1, 0N])")
+        .expect("eval");
+    let rows = engine.eval(&format!("write_splayed(\"{}\", t)", dir)).expect("eval");
+    assert_eq!(rows.into_raw(), 2);
+    let rows = engine.eval(&format!("append_splayed(\"{}\", t)", dir)).expect("eval");
+    assert_eq!(rows.into_raw(), 2);
+
+    // the columns are read when compiling, the rows when running
+    let t = engine.eval(&format!("read_splayed(\"{}\")", dir)).expect("eval");
+    assert_eq!(format!("{}", t), "s px   n\n---------\na 1.50 1\nb 0n   0N\na 1.50 1\nb 0n   0N");
+    let table = splay::read(Path::new(&dir)).expect("read");
+    assert_eq!(table.get_type(), *t.get_type());
+    let px = table.column("px").expect("px");
+    assert_eq!(unsafe { VecHeader::from_raw(px.as_raw()) }.storage(), VecStorage::Mapped);
+
+    let msg = "Rows of type Table(s: Symbol[], px: Float64[]) can not be appended to a splayed table of type Table(s: \
+               Symbol[], px: Float64[], n: Int64[])";
+    assert_io_error(&mut engine, &format!("append_splayed(\"{}\", ([] s: `c`d; px: [1.0, 2.0]))", dir), msg);
+    assert!(matches!(
+        engine.eval(&format!("read_splayed(\"{}\", \"Table(s: Symbol[])\")", dir)),
+        BSResult::Err(BSError::IOError { .. })
+    ));
+    assert!(matches!(engine.eval("read_splayed(\"missing\")"), BSResult::Err(BSError::IOError { .. })));
+
+    // a directory rewritten since compiling must still hold a table of the type compiled
+    engine
+        .compile(&format!("fn load || {{ count(read_splayed(\"{}\")) }}", dir))
+        .expect("compile");
+    engine
+        .eval(&format!("write_splayed(\"{}\", ([] s: `c`d))", dir))
+        .expect("eval");
+    let load = engine.get_fn::<(), i64>("load").expect("load");
+    let msg =
+        "The directory holds a table of type Table(s: Symbol[]), not Table(s: Symbol[], px: Float64[], n: Int64[])";
+    assert_eq!(io_error(load.try_call(()), "load()", msg), (dir.clone(), None));
+
+    // a directory which is not a literal is read as the table type named
+    engine
+        .eval("fn syms |dir:String| { exec s from read_splayed(dir, \"Table(s: Symbol[])\") }")
+        .expect("eval");
+    assert_eq!(eval_str(&mut engine, &format!("syms(\"{}\")", dir)), "`c`d");
+    assert_compile_error(&mut engine, "fn rows |dir:String| { count(read_splayed(dir)) }");
+
+    // the type of a directory named by a literal is read once, when the function reading it is defined
+    fs::remove_dir_all(&dir).expect("remove");
+    assert_eq!(eval_str(&mut engine, "1 + 1"), "2");
+    assert!(matches!(engine.eval("load()"), BSResult::Err(BSError::IOError { .. })));
+}
+
+#[test]
+fn temporal_columns() {
+    let (src, out) = (temp_file("temporal", "trades.csv"), temp_file("temporal", "out.csv"));
+    fs::write(&src, "d,t,n\n2026.10.18,09:30:00.000,1\n,10:00:00.500,2\n").expect("write");
+    let mut engine = engine_with("");
+
+    // dates and times are inferred, or given by their letters
+    engine.eval(&format!("t = read_csv(\"{}\")", src)).expect("eval");
+    assert_eq!(eval_str(&mut engine, "exec t from meta(t)"), "`Date`Time`Int64");
+    assert_eq!(eval_str(&mut engine, "exec d from t"), "[2026.10.18, 0Nd]");
+    engine.eval(&format!("write_csv(\"{}\", t)", out)).expect("eval");
+    assert_eq!(fs::read_to_string(&out).expect("read"), "d,t,n\n2026.10.18,09:30:00.000,1\n,10:00:00.500,2\n");
+    let src = format!("read_csv(\"{}\", \"PN \")", out);
+    assert_io_error(&mut engine, &src, "Invalid Timestamp '2026.10.18' in column 'd'");
+
+    // the columns are mapped from a splayed table
+    let dir = temp_file("temporal", "trades");
+    engine.eval(&format!("write_splayed(\"{}\", t)", dir)).expect("eval");
+    engine.eval(&format!("append_splayed(\"{}\", t)", dir)).expect("eval");
+    let table = splay::read(Path::new(&dir)).expect("read");
+    let d = table.column("d").expect("d");
+    assert_eq!(unsafe { VecHeader::from_raw(d.as_raw()) }.storage(), VecStorage::Mapped);
+    assert_eq!(format!("{}", d), "[2026.10.18, 0Nd, 2026.10.18, 0Nd]");
+    let src = format!("exec t from read_splayed(\"{}\") where d == 2026.10.18", dir);
+    assert_eq!(eval_str(&mut engine, &src), "[09:30:00.000, 09:30:00.000]");
+
+    let table = BSValue::from(table);
+    let value = serial::deserialize(&serial::serialize(&table).expect("serialize")).expect("deserialize");
+    assert_eq!(value.get_type(), table.get_type());
+    assert_eq!(format!("{}", value), format!("{}", table));
+}
+
+#[test]
+fn splayed_append_errors() {
+    let dir = temp_file("splay-append", "trades");
+    let mut engine = engine_with("");
+    engine.eval("t = ([] px: [1.5, 2.5]; s: `a`b)").expect("eval");
+    engine.eval(&format!("write_splayed(\"{}\", t)", dir)).expect("eval");
+
+    // a column which can not be appended to leaves the others as they were
+    let (px, s) = (Path::new(&dir).join("px"), Path::new(&dir).join("s"));
+    let bytes = fs::read(&s).expect("read");
+    fs::write(&s, &bytes[..bytes.len() - 1]).expect("write");
+    let px_len = fs::metadata(&px).expect("px").len();
+    let msg = format!("{}: Invalid symbol", s.display());
+    assert_io_error(&mut engine, &format!("append_splayed(\"{}\", t)", dir), &msg);
+    assert_eq!(fs::metadata(&px).expect("px").len(), px_len);
+    assert_eq!(splay::read(Path::new(&dir)).map(|t| t.len()), Err(format!("{}: Invalid symbol", s.display())));
+
+    // the columns of a table read must have the same number of elements
+    fs::write(&s, &bytes).expect("write");
+    let mut px_bytes = fs::read(&px).expect("read");
+    px_bytes[8] = 3;
+    fs::write(&px, &px_bytes).expect("write");
+    let msg = format!("{}: Expected a column of 3 elements, found 2", s.display());
+    assert_eq!(splay::read_type(Path::new(&dir)), Err(msg.clone()));
+    assert_io_error(&mut engine, &format!("read_splayed(\"{}\")", dir), &msg);
+}
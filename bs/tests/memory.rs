@@ -0,0 +1,214 @@
+extern crate bs;
+
+use bs::parse::diagnostic::Diagnostic;
+use bs::result::BSResult;
+use bs::rt::runtime::Runtime;
+use ffi::types::Type;
+use ffi::values::rc::live_values;
+use ffi::values::splay;
+use ffi::values::table::Table;
+use ffi::values::vector::*;
+use ffi::values::Value;
+use std::mem::{offset_of, size_of};
+use std::sync::Mutex;
+
+// the count of live values is process wide, so tests observing it must not run concurrently
+static LOCK: Mutex<()> = Mutex::new(());
+
+fn eval(runtime: &mut Runtime, src: &str) -> String {
+    match runtime.parse_eval(src) {
+        BSResult::Ok(result) => format!("{}", result),
+        BSResult::Err(err) => panic!("{}", Diagnostic::new("TEST", src, err)),
+    }
+}
+
+const SESSION: &[&str] = &[
+    "[1, 2, 3]",
+    "[1.5, 2.5]",
+    "test()",
+    "fn id |v:Int64[]| { a = v; b = [4, 5]; a }",
+    "id([7, 8])",
+    "id(test())",
+    "fn pick |c:Bool| { if c { [1.0] } else { [2.0, 3.0] } }",
+    "pick(true)",
+    "pick(false)",
+    "t = [9]",
+    "t = test()",
+    "len(t) + len([1.5])",
+    "try { at(test(), 5); \"ok\" } catch e { e }",
+    "s = \"abc\"",
+    "d = [1, 2]![1.5, 2.5]",
+    "key(d + ([2, 3]![1.0, 1.0]))",
+    "tb = ([] s: `a`b; q: take(2, test()))",
+    "meta(tb[1])",
+    "select n: count(q), m: max(q) by s from tb where q > 0",
+    "exec sum(q) by s from tb",
+    "exec q from update q: sum(q) by s from tb",
+    "count(delete from tb where s == `a) + count(cols(delete q from tb))",
+    "try { count(select v: at([1], q) from tb) } catch e { 0 }",
+    "at(group(`a`b`a), `a)",
+    "count(lj(`s, tb, ([] s: `a`c; p: [1.5, 2.5]))) + count(uj(tb, tb))",
+    "try { aj(`q, tb, ([] q: [2, 1])) } catch e { tb }",
+    "key(from_json(to_json(group(`a`b`a)), \"Symbol[]![]\"))",
+    "from_json(to_json(tb), \"Table(s: Symbol[], q: Int64[])\")",
+    "try { from_json(\"[[1], true]\", \"Symbol[]\") } catch e { `x`y }",
+    "b = serialize(tb)",
+    "deserialize(b, \"Table(s: Symbol[], q: Int64[])\")",
+    "try { deserialize(b, \"Int64\") } catch e { 0 }",
+];
+
+#[test]
+fn session_does_not_leak() {
+    let _lock = LOCK.lock().unwrap();
+    let before = live_values();
+
+    {
+        let mut runtime = Runtime::new().expect("Failed to create runtime");
+        for src in SESSION {
+            eval(&mut runtime, src);
+        }
+        let live = live_values();
+
+        for _ in 0..20 {
+            for src in SESSION {
+                eval(&mut runtime, src);
+            }
+        }
+        assert_eq!(live_values(), live);
+    }
+
+    assert_eq!(live_values(), before);
+}
+
+#[test]
+fn runtime_errors_release_locals() {
+    let _lock = LOCK.lock().unwrap();
+    let mut runtime = Runtime::new().expect("Failed to create runtime");
+    eval(&mut runtime, "fn f |i:Int64| { v = [1, 2, 3]; w = test(); at(v, i) + at(w, i) }");
+    eval(&mut runtime, "f(0)");
+
+    let live = live_values();
+    for _ in 0..10 {
+        assert!(matches!(runtime.parse_eval("f(3)"), BSResult::Err(_)));
+    }
+    assert_eq!(live_values(), live);
+}
+
+#[test]
+fn caught_errors_release_arguments() {
+    let _lock = LOCK.lock().unwrap();
+    let mut runtime = Runtime::new().expect("Failed to create runtime");
+    eval(&mut runtime, "fn f |v:Int64[], x:Null| { len(v) }");
+    eval(&mut runtime, "fn g |v:Int64[], i:Int64| { len(v) + i }");
+    eval(&mut runtime, "fn h |i:Int64| { at([1], i) }");
+    let srcs = [
+        ("try { f(til(3), signal(\"x\")) } catch e { 0 }", "0"),
+        ("try { g(til(3), h(5)) } catch e { 1 }", "1"),
+        ("try { at(til(3), at([1], 5)) } catch e { 2 }", "2"),
+        ("try { count(([] a: til(3); b: take(at([1], 5), [1.5]))) } catch e { 3 }", "3"),
+    ];
+    srcs.iter().for_each(|(src, _)| drop(eval(&mut runtime, src)));
+
+    let live = live_values();
+    for _ in 0..10 {
+        for (src, expected) in srcs {
+            assert_eq!(eval(&mut runtime, src), expected);
+        }
+    }
+    assert_eq!(live_values(), live);
+}
+
+#[test]
+fn globals_keep_values_alive() {
+    let _lock = LOCK.lock().unwrap();
+    let mut runtime = Runtime::new().expect("Failed to create runtime");
+
+    eval(&mut runtime, "x = [1, 2, 3]");
+    eval(&mut runtime, "y = test()");
+    eval(&mut runtime, "fn first || { x }");
+
+    // the module holding the literal is recompiled on every evaluation
+    for _ in 0..10 {
+        eval(&mut runtime, "[4, 5, 6]");
+    }
+
+    assert_eq!(eval(&mut runtime, "x"), "[1, 2, 3]");
+    assert_eq!(eval(&mut runtime, "y"), "[1, 2, 3]");
+    assert_eq!(eval(&mut runtime, "first()"), "[1, 2, 3]");
+
+    // the previous value of a global is freed once it is overwritten
+    let live = live_values();
+    eval(&mut runtime, "x = y");
+    assert_eq!(live_values(), live - 1);
+    assert_eq!(eval(&mut runtime, "first()"), "[1, 2, 3]");
+}
+
+#[test]
+fn values_shared_across_threads() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Value>();
+
+    let _lock = LOCK.lock().unwrap();
+    let mut runtime = Runtime::new().expect("Failed to create runtime");
+    let mut results = vec![];
+    for src in ["x = [1, 2, 3]", "s = \"abc\"", "t = ([] a: x)"] {
+        results.push(runtime.parse_eval(src).expect("eval"));
+    }
+    let live = live_values();
+
+    // the values share their counts with the globals, which the runtime uses meanwhile
+    let handles: Vec<_> = results
+        .iter()
+        .map(|value| {
+            let value = value.clone();
+            std::thread::spawn(move || (0..10000).for_each(|_| drop(value.clone())))
+        })
+        .collect();
+    for _ in 0..20 {
+        eval(&mut runtime, "y = t.a");
+    }
+    handles.into_iter().for_each(|h| h.join().unwrap());
+
+    assert_eq!(live_values(), live);
+    drop(results);
+    assert_eq!(eval(&mut runtime, "x"), "[1, 2, 3]");
+    assert_eq!(eval(&mut runtime, "s"), "\"abc\"");
+}
+
+#[test]
+fn vec_header_layout() {
+    assert_eq!(size_of::<VecHeader>(), 40);
+    assert_eq!(offset_of!(VecHeader, rc), VEC_RC as usize * 8);
+    assert_eq!(offset_of!(VecHeader, len), VEC_LEN as usize * 8);
+    assert_eq!(offset_of!(VecHeader, cap), VEC_CAP as usize * 8);
+    assert_eq!(offset_of!(VecHeader, tag), VEC_TAG as usize * 8);
+    assert_eq!(offset_of!(VecHeader, data), VEC_DATA as usize * 8);
+}
+
+#[test]
+fn borrowed_and_mapped_vectors() {
+    let _lock = LOCK.lock().unwrap();
+    let before = live_values();
+
+    // a borrowed vector leaves its data to its owner
+    let data = vec![1i64, 2, 3];
+    let vec = Value::from_raw_parts(Type::VecInt64, unsafe { VecHeader::borrow(data.as_ptr(), data.len()) });
+    assert_eq!(unsafe { VecHeader::from_raw(vec.as_raw()) }.storage(), VecStorage::Borrowed);
+    assert_eq!(format!("{}", vec.clone()), "[1, 2, 3]");
+    drop(vec);
+    assert_eq!(data, [1, 2, 3]);
+
+    let dir = std::env::temp_dir().join(format!("bs-mapped-{}", std::process::id()));
+    let table = Table::default()
+        .with_column("x", Value::from(vec![1.5, 2.5]))
+        .expect("column");
+    splay::write(&dir, &table).expect("write");
+    let mapped = splay::read(&dir).expect("read");
+    let x = mapped.column("x").expect("x");
+    assert_eq!(unsafe { VecHeader::from_raw(x.as_raw()) }.storage(), VecStorage::Mapped);
+    assert_eq!(format!("{}", x), "[1.5, 2.5]");
+    drop((table, mapped));
+    std::fs::remove_dir_all(&dir).expect("remove");
+
+    assert_eq!(live_values(), before);
+}
@@ -1,17 +1,428 @@
+use crate::types::fn_type::FnType;
+use crate::types::Type;
+use crate::values::dict::{Dict, DictKey, DictRef, GroupDict, GroupDictRef, TypedDict};
 use crate::values::fn_value::FnValue;
+use crate::values::sym::Sym;
+use crate::values::time::{Date, Time, Timespan, Timestamp};
+use crate::values::vector::VecElement;
+use crate::values::{rc_from_raw, slice_from_raw, str_from_raw, Value, NULL_FLOAT64, NULL_INT64, NULL_VALUE};
+use std::any::Any;
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::OnceLock;
 
-lazy_static! {
-    static ref EXTERNAL_FNS: Mutex<HashMap<String, FnValue>> = Mutex::new(HashMap::new());
+/// Registry of the host functions a runtime exposes to compiled code.
+/// A name can be registered once per list of argument types, forming an overload set.
+#[derive(Default)]
+pub struct Externals {
+    fns: HashMap<String, Vec<FnValue>>,
+    envs: Vec<Box<dyn Any + Send>>,
 }
 
-pub fn register_external(name: String, val: FnValue) { EXTERNAL_FNS.lock().unwrap().insert(name, val); }
+impl Externals {
+    /// Registers `val` under `name`, replacing the overload taking the same argument types.
+    pub fn register(&mut self, name: &str, val: FnValue) {
+        let overloads = self.fns.entry(name.to_string()).or_default();
+        match overloads.iter_mut().find(|f| f.get_type().args == val.get_type().args) {
+            Some(f) => *f = val,
+            None => overloads.push(val),
+        }
+    }
 
-pub fn with<F, R>(mut f: F) -> R
+    /// Registers a Rust function or closure, deriving its `FnType` from the Rust signature.
+    /// The closure is kept alive by the registry and passed to a generated C-ABI trampoline.
+    pub fn register_fn<Args, F: HostFn<Args>>(&mut self, name: &str, f: F) -> FnValue {
+        let env = Box::new(f);
+        let fn_val = FnValue::with_env(F::fn_type(), F::trampoline(), env.as_ref() as *const F as _);
+        self.envs.push(env);
+        self.register(name, fn_val.clone());
+        fn_val
+    }
+
+    /// Overloads registered under `name`.
+    pub fn get(&self, name: &str) -> &[FnValue] { self.fns.get(name).map(|f| f.as_slice()).unwrap_or_default() }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &FnValue)> {
+        self.fns.iter().flat_map(|(name, f)| f.iter().map(move |f| (name, f)))
+    }
+}
+
+/// Argument types a host function can receive from compiled code.
+pub trait HostArg {
+    type Abi: Copy;
+    /// The argument as passed to a host function, which can only borrow it for `'a`, the
+    /// duration of the call.
+    type Item<'a>;
+
+    fn bs_type() -> Type;
+
+    /// # Safety
+    /// `abi` must be a live value of `Self::bs_type()` for `'a`.
+    unsafe fn from_abi<'a>(abi: Self::Abi) -> Self::Item<'a>;
+}
+
+/// Arguments passed by value, which borrow nothing from compiled code.
+pub trait HostValue: for<'a> HostArg<Item<'a> = Self> {}
+
+impl<T: for<'a> HostArg<Item<'a> = T>> HostValue for T {}
+
+/// Types a host function can return to compiled code.
+pub trait HostRet {
+    type Abi;
+
+    fn bs_type() -> Type;
+
+    fn into_abi(self) -> Self::Abi;
+
+    /// Null returned in place of a result when the host function panics.
+    fn null_abi() -> Self::Abi;
+}
+
+impl HostArg for i64 {
+    type Abi = i64;
+    type Item<'a> = i64;
+
+    fn bs_type() -> Type { Type::Int64 }
+
+    unsafe fn from_abi<'a>(abi: i64) -> Self::Item<'a> { abi }
+}
+
+impl HostArg for f64 {
+    type Abi = f64;
+    type Item<'a> = f64;
+
+    fn bs_type() -> Type { Type::Float64 }
+
+    unsafe fn from_abi<'a>(abi: f64) -> Self::Item<'a> { abi }
+}
+
+impl HostArg for bool {
+    type Abi = bool;
+    type Item<'a> = bool;
+
+    fn bs_type() -> Type { Type::Bool }
+
+    unsafe fn from_abi<'a>(abi: bool) -> Self::Item<'a> { abi }
+}
+
+impl<T: VecElement + 'static> HostArg for &[T] {
+    type Abi = i64;
+    type Item<'a> = &'a [T];
+
+    fn bs_type() -> Type { T::VEC_TYPE }
+
+    unsafe fn from_abi<'a>(abi: i64) -> Self::Item<'a> { slice_from_raw(abi) }
+}
+
+impl HostArg for Sym {
+    type Abi = i64;
+    type Item<'a> = Sym;
+
+    fn bs_type() -> Type { Type::Sym }
+
+    unsafe fn from_abi<'a>(abi: i64) -> Self::Item<'a> { Sym(abi) }
+}
+
+/// Dates and times, passed as their `Int64`, and their vectors.
+macro_rules! impl_host_temporal {
+    ($($ty:ident),*) => {
+        $(
+            impl HostArg for $ty {
+                type Abi = i64;
+                type Item<'a> = $ty;
+
+                fn bs_type() -> Type { Type::$ty }
+
+                unsafe fn from_abi<'a>(abi: i64) -> Self::Item<'a> { $ty(abi) }
+            }
+
+            impl HostRet for $ty {
+                type Abi = i64;
+
+                fn bs_type() -> Type { Type::$ty }
+
+                fn into_abi(self) -> i64 { self.0 }
+
+                fn null_abi() -> i64 { NULL_INT64 }
+            }
+
+            impl HostRet for Vec<$ty> {
+                type Abi = i64;
+
+                fn bs_type() -> Type { <$ty as VecElement>::VEC_TYPE }
+
+                fn into_abi(self) -> i64 { Value::from(self).into_raw() }
+
+                fn null_abi() -> i64 { 0 }
+            }
+        )*
+    };
+}
+
+impl_host_temporal!(Date, Time, Timestamp, Timespan);
+
+impl HostArg for &str {
+    type Abi = i64;
+    type Item<'a> = &'a str;
+
+    fn bs_type() -> Type { Type::Str }
+
+    unsafe fn from_abi<'a>(abi: i64) -> &'a str {
+        match abi {
+            0 => "",
+            abi => str_from_raw(abi),
+        }
+    }
+}
+
+impl HostArg for &[u8] {
+    type Abi = i64;
+    type Item<'a> = &'a [u8];
+
+    fn bs_type() -> Type { Type::Bytes }
+
+    unsafe fn from_abi<'a>(abi: i64) -> &'a [u8] {
+        match abi {
+            0 => &[],
+            abi => rc_from_raw::<Vec<u8>>(abi),
+        }
+    }
+}
+
+impl<K: DictKey, V: VecElement> HostArg for DictRef<'_, K, V> {
+    type Abi = i64;
+    type Item<'a> = DictRef<'a, K, V>;
+
+    fn bs_type() -> Type { Type::dict(K::VEC_TYPE, V::VEC_TYPE) }
+
+    unsafe fn from_abi<'a>(abi: i64) -> DictRef<'a, K, V> { DictRef::new(rc_from_raw::<Dict>(abi)) }
+}
+
+impl<K: DictKey> HostArg for GroupDictRef<'_, K> {
+    type Abi = i64;
+    type Item<'a> = GroupDictRef<'a, K>;
+
+    fn bs_type() -> Type { Type::dict(K::VEC_TYPE, Type::List) }
+
+    unsafe fn from_abi<'a>(abi: i64) -> GroupDictRef<'a, K> { GroupDictRef::new(rc_from_raw::<Dict>(abi)) }
+}
+
+impl HostRet for i64 {
+    type Abi = i64;
+
+    fn bs_type() -> Type { Type::Int64 }
+
+    fn into_abi(self) -> i64 { self }
+
+    fn null_abi() -> i64 { NULL_INT64 }
+}
+
+impl HostRet for f64 {
+    type Abi = f64;
+
+    fn bs_type() -> Type { Type::Float64 }
+
+    fn into_abi(self) -> f64 { self }
+
+    fn null_abi() -> f64 { NULL_FLOAT64 }
+}
+
+impl HostRet for bool {
+    type Abi = bool;
+
+    fn bs_type() -> Type { Type::Bool }
+
+    fn into_abi(self) -> bool { self }
+
+    fn null_abi() -> bool { false }
+}
+
+impl HostRet for () {
+    type Abi = i64;
+
+    fn bs_type() -> Type { Type::Null }
+
+    fn into_abi(self) -> i64 { Value::from(()).into_raw() }
+
+    fn null_abi() -> i64 { NULL_VALUE }
+}
+
+impl HostRet for Vec<i64> {
+    type Abi = i64;
+
+    fn bs_type() -> Type { Type::VecInt64 }
+
+    fn into_abi(self) -> i64 { Value::from(self).into_raw() }
+
+    fn null_abi() -> i64 { 0 }
+}
+
+impl HostRet for Vec<f64> {
+    type Abi = i64;
+
+    fn bs_type() -> Type { Type::VecFloat64 }
+
+    fn into_abi(self) -> i64 { Value::from(self).into_raw() }
+
+    fn null_abi() -> i64 { 0 }
+}
+
+impl HostRet for Vec<bool> {
+    type Abi = i64;
+
+    fn bs_type() -> Type { Type::VecBool }
+
+    fn into_abi(self) -> i64 { Value::from(self).into_raw() }
+
+    fn null_abi() -> i64 { 0 }
+}
+
+impl HostRet for Sym {
+    type Abi = i64;
+
+    fn bs_type() -> Type { Type::Sym }
+
+    fn into_abi(self) -> i64 { self.0 }
+
+    fn null_abi() -> i64 { Sym::NULL.0 }
+}
+
+impl HostRet for Vec<Sym> {
+    type Abi = i64;
+
+    fn bs_type() -> Type { Type::VecSym }
+
+    fn into_abi(self) -> i64 { Value::from(self).into_raw() }
+
+    fn null_abi() -> i64 { 0 }
+}
+
+impl HostRet for String {
+    type Abi = i64;
+
+    fn bs_type() -> Type { Type::Str }
+
+    fn into_abi(self) -> i64 { Value::from(self).into_raw() }
+
+    fn null_abi() -> i64 { 0 }
+}
+
+impl HostRet for Vec<u8> {
+    type Abi = i64;
+
+    fn bs_type() -> Type { Type::Bytes }
+
+    fn into_abi(self) -> i64 { Value::from(self).into_raw() }
+
+    fn null_abi() -> i64 { 0 }
+}
+
+impl<K: DictKey, V: VecElement> HostRet for TypedDict<K, V>
 where
-    F: FnMut(&mut HashMap<String, FnValue>) -> R,
+    Value: From<Vec<K>> + From<Vec<V>>,
 {
-    let mut map = EXTERNAL_FNS.lock().unwrap();
-    f(&mut map)
+    type Abi = i64;
+
+    fn bs_type() -> Type { Type::dict(K::VEC_TYPE, V::VEC_TYPE) }
+
+    fn into_abi(self) -> i64 { Value::from(self.into_dict()).into_raw() }
+
+    fn null_abi() -> i64 { 0 }
 }
+
+impl<K: DictKey> HostRet for GroupDict<K>
+where
+    Value: From<Vec<K>>,
+{
+    type Abi = i64;
+
+    fn bs_type() -> Type { Type::dict(K::VEC_TYPE, Type::List) }
+
+    fn into_abi(self) -> i64 { <Value as From<Dict>>::from(self.into_dict()).into_raw() }
+
+    fn null_abi() -> i64 { 0 }
+}
+
+/// Called with the message of a host function which panics, which then returns the null of its
+/// result type. Set once by the runtime, see [`set_panic_handler`].
+static PANIC_HANDLER: OnceLock<fn(&str)> = OnceLock::new();
+
+/// Sets the function reporting the panics of host functions, which can not unwind through
+/// compiled code. Only the first handler set is kept.
+pub fn set_panic_handler(handler: fn(&str)) { PANIC_HANDLER.get_or_init(|| handler); }
+
+/// Calls `f`, reporting a panic to the panic handler and returning `R::null_abi()` instead.
+fn catch_panic<R: HostRet>(f: impl FnOnce() -> R::Abi) -> R::Abi {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(abi) => abi,
+        Err(payload) => {
+            let msg = match payload.downcast_ref::<&str>() {
+                Some(msg) => msg.to_string(),
+                None => payload.downcast_ref::<String>().cloned().unwrap_or_default(),
+            };
+            if let Some(handler) = PANIC_HANDLER.get() {
+                handler(&format!("Host function panicked: {}", msg));
+            }
+            R::null_abi()
+        }
+    }
+}
+
+/// Rust functions and closures which can be registered as host functions,
+/// implemented for every `Fn` whose arguments are `HostArg`s and result is a `HostRet`.
+///
+/// Arguments borrowed from compiled code, such as slices and strings, are only valid during the
+/// call, so a function must accept them for any lifetime:
+///
+/// ```compile_fail
+/// use ffi::external::Externals;
+/// use std::sync::Mutex;
+///
+/// static KEPT: Mutex<Vec<&'static [i64]>> = Mutex::new(vec![]);
+/// Externals::default().register_fn("keep", |v: &'static [i64]| KEPT.lock().unwrap().push(v));
+/// ```
+pub trait HostFn<Args>: Send + 'static {
+    fn fn_type() -> FnType;
+
+    /// Address of an `extern "C"` function taking a pointer to `Self` followed by the
+    /// arguments' ABI values.
+    fn trampoline() -> *const ();
+}
+
+// `Fn($arg)` infers the argument types, `for<'a> Fn($arg::Item<'a>)` keeps borrowed ones from
+// outliving the call.
+macro_rules! impl_host_fn {
+    ($($arg:ident),*) => {
+        impl<Func, Ret, $($arg),*> HostFn<($($arg,)*)> for Func
+        where
+            Func: Fn($($arg),*) -> Ret + for<'a> Fn($($arg::Item<'a>),*) -> Ret + Send + 'static,
+            Ret: HostRet,
+            $($arg: HostArg),*
+        {
+            fn fn_type() -> FnType { FnType::new(vec![$($arg::bs_type()),*], Ret::bs_type()) }
+
+            fn trampoline() -> *const () {
+                #[allow(non_snake_case)]
+                extern "C" fn trampoline<Func, Ret, $($arg),*>(env: *const Func, $($arg: $arg::Abi),*) -> Ret::Abi
+                where
+                    Func: for<'a> Fn($($arg::Item<'a>),*) -> Ret + 'static,
+                    Ret: HostRet,
+                    $($arg: HostArg),*
+                {
+                    let f = unsafe { &*env };
+                    catch_panic::<Ret>(|| f($(unsafe { $arg::from_abi($arg) }),*).into_abi())
+                }
+
+                trampoline::<Func, Ret, $($arg),*> as *const ()
+            }
+        }
+    };
+}
+
+impl_host_fn!();
+impl_host_fn!(A);
+impl_host_fn!(A, B);
+impl_host_fn!(A, B, C);
+impl_host_fn!(A, B, C, D);
+impl_host_fn!(A, B, C, D, E);
+impl_host_fn!(A, B, C, D, E, F);
@@ -1,4 +1,5 @@
 pub mod fn_type;
+pub mod native_type;
 
 use fn_type::FnType;
 use std::fmt;
@@ -9,10 +10,37 @@ pub enum Type {
     Bool,
     Int64,
     Float64,
+    /// Days since 1970.01.01, see `values::time`.
+    Date,
+    /// Nanoseconds since midnight.
+    Time,
+    /// Nanoseconds since 1970.01.01.
+    Timestamp,
+    /// Nanoseconds, the difference of two timestamps.
+    Timespan,
     VecInt64,
     VecFloat64,
+    VecBool,
+    /// Vectors of dates and times, of `Int64` elements underneath.
+    VecDate,
+    VecTime,
+    VecTimestamp,
+    VecTimespan,
+    /// Interned string, see `values::sym`.
+    Sym,
+    VecSym,
+    /// Immutable UTF-8 string.
+    Str,
+    /// Immutable bytes, e.g. of a serialized value.
+    Bytes,
+    /// Dictionary of a key vector type and a value vector type, e.g. `Int64[]!Float64[]`.
+    Dict(Box<Type>, Box<Type>),
+    /// Table of named vector columns, e.g. `Table(price: Float64[], qty: Int64[])`.
+    Table(Vec<(String, Type)>),
     List,
     Fn(FnType),
+    /// Type parameter of a generic function, replaced by a concrete type at every call.
+    Param(String),
 }
 
 impl TryFrom<&str> for Type {
@@ -23,8 +51,21 @@ impl TryFrom<&str> for Type {
             "Bool" => Ok(Type::Bool),
             "Int64" => Ok(Type::Int64),
             "Float64" => Ok(Type::Float64),
+            "Date" => Ok(Type::Date),
+            "Time" => Ok(Type::Time),
+            "Timestamp" => Ok(Type::Timestamp),
+            "Timespan" => Ok(Type::Timespan),
             "Int64[]" => Ok(Type::VecInt64),
             "Float64[]" => Ok(Type::VecFloat64),
+            "Bool[]" => Ok(Type::VecBool),
+            "Date[]" => Ok(Type::VecDate),
+            "Time[]" => Ok(Type::VecTime),
+            "Timestamp[]" => Ok(Type::VecTimestamp),
+            "Timespan[]" => Ok(Type::VecTimespan),
+            "Symbol" => Ok(Type::Sym),
+            "Symbol[]" => Ok(Type::VecSym),
+            "String" => Ok(Type::Str),
+            "Bytes" => Ok(Type::Bytes),
             "[]" => Ok(Type::List),
             _ => Err(()),
         }
@@ -38,9 +79,34 @@ impl fmt::Display for Type {
             Type::Bool => write!(f, "Bool"),
             Type::Int64 => write!(f, "Int64"),
             Type::Float64 => write!(f, "Float64"),
+            Type::Date => write!(f, "Date"),
+            Type::Time => write!(f, "Time"),
+            Type::Timestamp => write!(f, "Timestamp"),
+            Type::Timespan => write!(f, "Timespan"),
             Type::VecInt64 => write!(f, "Int64[]"),
             Type::VecFloat64 => write!(f, "Float64[]"),
+            Type::VecBool => write!(f, "Bool[]"),
+            Type::VecDate => write!(f, "Date[]"),
+            Type::VecTime => write!(f, "Time[]"),
+            Type::VecTimestamp => write!(f, "Timestamp[]"),
+            Type::VecTimespan => write!(f, "Timespan[]"),
+            Type::Sym => write!(f, "Symbol"),
+            Type::VecSym => write!(f, "Symbol[]"),
+            Type::Str => write!(f, "String"),
+            Type::Bytes => write!(f, "Bytes"),
+            Type::Dict(ref keys, ref values) => write!(f, "{}!{}", keys, values),
+            Type::Table(ref columns) => {
+                write!(f, "Table(")?;
+                for (i, (name, ty)) in columns.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", name, ty)?;
+                }
+                write!(f, ")")
+            }
             Type::List => write!(f, "[]"),
+            Type::Param(ref name) => write!(f, "{}", name),
             Type::Fn(ref fn_type) => {
                 write!(f, "Fn(")?;
                 for (i, arg) in fn_type.args.iter().enumerate() {
@@ -58,8 +124,69 @@ impl fmt::Display for Type {
 impl Type {
     pub fn is_scalar(&self) -> bool {
         match self {
-            Type::Null | Type::Int64 | Type::Float64 | Type::Bool => true,
-            _ => false,
+            Type::Null | Type::Int64 | Type::Float64 | Type::Bool | Type::Sym => true,
+            ty => ty.is_temporal(),
+        }
+    }
+
+    /// Whether the type is a date or time, an `Int64` underneath.
+    pub fn is_temporal(&self) -> bool { matches!(self, Type::Date | Type::Time | Type::Timestamp | Type::Timespan) }
+
+    pub fn is_vec(&self) -> bool {
+        matches!(self, Type::VecInt64 | Type::VecFloat64 | Type::VecBool | Type::VecSym) || self.is_temporal_vec()
+    }
+
+    /// Whether the type is a vector of dates or times, an `Int64[]` underneath.
+    pub fn is_temporal_vec(&self) -> bool {
+        matches!(self, Type::VecDate | Type::VecTime | Type::VecTimestamp | Type::VecTimespan)
+    }
+
+    pub fn is_dict(&self) -> bool { matches!(self, Type::Dict(..)) }
+
+    pub fn is_table(&self) -> bool { matches!(self, Type::Table(_)) }
+
+    /// Names and types of the columns of a table type, in order.
+    pub fn table_columns(&self) -> &[(String, Type)] {
+        match self {
+            Type::Table(columns) => columns,
+            _ => &[],
+        }
+    }
+
+    /// Type of the column `name` of a table type.
+    pub fn column_type(&self, name: &str) -> Option<&Type> {
+        self.table_columns().iter().find(|(n, _)| n == name).map(|(_, ty)| ty)
+    }
+
+    pub fn dict(keys: Type, values: Type) -> Type { Type::Dict(Box::new(keys), Box::new(values)) }
+
+    /// Type of the vectors of a scalar type.
+    pub fn vec_type(&self) -> Option<Type> {
+        match self {
+            Type::Int64 => Some(Type::VecInt64),
+            Type::Float64 => Some(Type::VecFloat64),
+            Type::Bool => Some(Type::VecBool),
+            Type::Sym => Some(Type::VecSym),
+            Type::Date => Some(Type::VecDate),
+            Type::Time => Some(Type::VecTime),
+            Type::Timestamp => Some(Type::VecTimestamp),
+            Type::Timespan => Some(Type::VecTimespan),
+            _ => None,
+        }
+    }
+
+    /// Type of the elements of a vector type.
+    pub fn elem_type(&self) -> Option<Type> {
+        match self {
+            Type::VecInt64 => Some(Type::Int64),
+            Type::VecFloat64 => Some(Type::Float64),
+            Type::VecBool => Some(Type::Bool),
+            Type::VecSym => Some(Type::Sym),
+            Type::VecDate => Some(Type::Date),
+            Type::VecTime => Some(Type::Time),
+            Type::VecTimestamp => Some(Type::Timestamp),
+            Type::VecTimespan => Some(Type::Timespan),
+            _ => None,
         }
     }
 }
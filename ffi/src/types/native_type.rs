@@ -0,0 +1,43 @@
+use super::Type;
+use crate::values::Value;
+
+/// Rust scalar types which cross the JIT boundary by value, using the same
+/// machine representation as their BitSaber counterpart.
+pub trait NativeType: Copy {
+    fn bs_type() -> Type;
+
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+impl NativeType for i64 {
+    fn bs_type() -> Type { Type::Int64 }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value.get_type() {
+            Type::Int64 => Some(value.as_raw()),
+            _ => None,
+        }
+    }
+}
+
+impl NativeType for f64 {
+    fn bs_type() -> Type { Type::Float64 }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value.get_type() {
+            Type::Float64 => Some(f64::from_bits(value.as_raw() as u64)),
+            _ => None,
+        }
+    }
+}
+
+impl NativeType for bool {
+    fn bs_type() -> Type { Type::Bool }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        match value.get_type() {
+            Type::Bool => Some(value.as_raw() != 0),
+            _ => None,
+        }
+    }
+}
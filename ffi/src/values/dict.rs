@@ -0,0 +1,174 @@
+//! Dictionaries, mapping the elements of a key vector to the elements of a value vector of the same
+//! length.
+//!
+//! As in k, the keys need not be unique, a key is looked up at its first position. The position of
+//! every key is kept in a hash index, built along with the dictionary.
+
+use super::sym::Sym;
+use super::time::{Date, Time, Timespan, Timestamp};
+use super::vector::{VecElement, VecHeader};
+use super::{rc_from_raw, slice_from_raw, Value};
+use crate::types::Type;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Vector elements which can be the keys of a dictionary.
+pub trait DictKey: VecElement {
+    /// Bits identifying the key, equal for equal keys.
+    fn bits(self) -> u64;
+}
+
+impl DictKey for i64 {
+    fn bits(self) -> u64 { self as u64 }
+}
+
+impl DictKey for f64 {
+    // every NaN is the same null, and -0.0 equals 0.0
+    fn bits(self) -> u64 {
+        match self.is_nan() {
+            true => f64::NAN.to_bits(),
+            false => (self + 0.0).to_bits(),
+        }
+    }
+}
+
+impl DictKey for bool {
+    fn bits(self) -> u64 { self as u64 }
+}
+
+impl DictKey for Sym {
+    fn bits(self) -> u64 { self.0 as u64 }
+}
+
+impl DictKey for Date {
+    fn bits(self) -> u64 { self.0 as u64 }
+}
+
+impl DictKey for Time {
+    fn bits(self) -> u64 { self.0 as u64 }
+}
+
+impl DictKey for Timestamp {
+    fn bits(self) -> u64 { self.0 as u64 }
+}
+
+impl DictKey for Timespan {
+    fn bits(self) -> u64 { self.0 as u64 }
+}
+
+pub struct Dict {
+    keys: Value,
+    values: Value,
+    index: HashMap<u64, usize>,
+}
+
+impl Dict {
+    /// Creates a dictionary sharing the vectors `keys` and `values`, which must be vector values of
+    /// the same length, with `K` the element type of `keys`.
+    pub fn new<K: DictKey>(keys: Value, values: Value) -> Self {
+        let mut index = HashMap::new();
+        for (i, key) in unsafe { slice_from_raw::<K>(keys.as_raw()) }.iter().enumerate() {
+            index.entry(key.bits()).or_insert(i);
+        }
+
+        Dict { keys, values, index }
+    }
+
+    pub fn keys(&self) -> &Value { &self.keys }
+
+    pub fn values(&self) -> &Value { &self.values }
+
+    pub fn get_type(&self) -> Type { Type::dict(self.keys.get_type().clone(), self.values.get_type().clone()) }
+
+    pub fn len(&self) -> usize { unsafe { VecHeader::from_raw(self.keys.as_raw()).len as usize } }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Position of `key`, with `K` the element type of the keys.
+    pub fn find<K: DictKey>(&self, key: K) -> Option<usize> { self.index.get(&key.bits()).copied() }
+}
+
+/// A dictionary borrowed by a host function, with keys of type `K` and values of type `V`.
+pub struct DictRef<'a, K, V> {
+    dict: &'a Dict,
+    _phantom: PhantomData<(K, V)>,
+}
+
+impl<'a, K: DictKey, V: VecElement> DictRef<'a, K, V> {
+    /// # Safety
+    /// `K` and `V` must be the element types of the keys and values of `dict`.
+    pub unsafe fn new(dict: &'a Dict) -> Self { DictRef { dict, _phantom: PhantomData } }
+
+    pub fn keys(&self) -> &'a [K] { unsafe { slice_from_raw(self.dict.keys.as_raw()) } }
+
+    pub fn values(&self) -> &'a [V] { unsafe { slice_from_raw(self.dict.values.as_raw()) } }
+
+    pub fn find(&self, key: K) -> Option<usize> { self.dict.find(key) }
+
+    pub fn get(&self, key: K) -> Option<V> { self.find(key).map(|i| self.values()[i]) }
+}
+
+/// A new dictionary returned by a host function, with keys of type `K` and values of type `V`.
+pub struct TypedDict<K, V> {
+    keys: Vec<K>,
+    values: Vec<V>,
+}
+
+impl<K: DictKey, V: VecElement> TypedDict<K, V> {
+    /// Creates a dictionary of `keys` and `values`, which must be of the same length.
+    pub fn new(keys: Vec<K>, values: Vec<V>) -> Self {
+        assert_eq!(keys.len(), values.len(), "keys and values of a dictionary differ in length");
+        TypedDict { keys, values }
+    }
+
+    pub fn into_dict(self) -> Dict
+    where
+        Value: From<Vec<K>> + From<Vec<V>>,
+    {
+        Dict::new::<K>(Value::from(self.keys), Value::from(self.values))
+    }
+}
+
+/// A dictionary of keys of type `K` to lists of the indices at which they occur, as built by `group`.
+pub struct GroupDict<K> {
+    keys: Vec<K>,
+    indices: Vec<Vec<i64>>,
+}
+
+impl<K: DictKey> GroupDict<K> {
+    /// Creates a dictionary of `keys` and `indices`, which must be of the same length.
+    pub fn new(keys: Vec<K>, indices: Vec<Vec<i64>>) -> Self {
+        assert_eq!(keys.len(), indices.len(), "keys and values of a dictionary differ in length");
+        GroupDict { keys, indices }
+    }
+
+    pub fn into_dict(self) -> Dict
+    where
+        Value: From<Vec<K>>,
+    {
+        let indices: Vec<Value> = self.indices.into_iter().map(<Value as From<Vec<i64>>>::from).collect();
+        Dict::new::<K>(Value::from(self.keys), <Value as From<Vec<Value>>>::from(indices))
+    }
+}
+
+/// A dictionary of keys of type `K` to lists of indices borrowed by a host function.
+pub struct GroupDictRef<'a, K> {
+    dict: &'a Dict,
+    _phantom: PhantomData<K>,
+}
+
+impl<'a, K: DictKey> GroupDictRef<'a, K> {
+    /// # Safety
+    /// `K` must be the element type of the keys of `dict`, whose values must be lists of `Int64[]`.
+    pub unsafe fn new(dict: &'a Dict) -> Self { GroupDictRef { dict, _phantom: PhantomData } }
+
+    pub fn keys(&self) -> &'a [K] { unsafe { slice_from_raw(self.dict.keys.as_raw()) } }
+
+    /// Indices of `key`, if it is a key.
+    pub fn get(&self, key: K) -> Option<&'a [i64]> {
+        let lists = unsafe { rc_from_raw::<Vec<Value>>(self.dict.values.as_raw()) };
+        self.dict
+            .find(key)
+            .map(|i| unsafe { slice_from_raw(lists[i].as_raw()) })
+    }
+}
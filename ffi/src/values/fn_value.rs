@@ -4,14 +4,26 @@ use crate::types::fn_type::FnType;
 pub struct FnValue {
     ty: FnType,
     ptr: *const (),
+    env: *const (),
 }
 
 impl FnValue {
-    pub fn new(ty: FnType, ptr: *const ()) -> Self { Self { ty, ptr } }
+    pub fn new(ty: FnType, ptr: *const ()) -> Self { Self { ty, ptr, env: std::ptr::null() } }
+
+    /// Creates a function which expects `env` as a hidden first argument.
+    pub fn with_env(ty: FnType, ptr: *const (), env: *const ()) -> Self { Self { ty, ptr, env } }
 
     pub fn get_type(&self) -> &FnType { &self.ty }
 
     pub fn get_ptr(&self) -> *const () { self.ptr }
+
+    pub fn get_env(&self) -> Option<*const ()> {
+        if self.env.is_null() {
+            None
+        } else {
+            Some(self.env)
+        }
+    }
 }
 
 unsafe impl Send for FnValue {}
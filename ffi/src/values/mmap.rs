@@ -0,0 +1,66 @@
+//! Memory-mapped files, the data of mapped vectors.
+//!
+//! A file is mapped whole, and the mapping is known by the address of the data of the vector it
+//! holds, so that it is unmapped along with the vector.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::sync::{Mutex, OnceLock};
+
+/// Base address and length of the mapping of the data at every address.
+fn mappings() -> &'static Mutex<HashMap<usize, (usize, usize)>> {
+    static MAPPINGS: OnceLock<Mutex<HashMap<usize, (usize, usize)>>> = OnceLock::new();
+    MAPPINGS.get_or_init(Default::default)
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::ffi::{c_int, c_void};
+
+    pub const PROT_READ: c_int = 1;
+    pub const PROT_WRITE: c_int = 2;
+    pub const MAP_PRIVATE: c_int = 2;
+    pub const MAP_FAILED: *mut c_void = !0 as *mut c_void;
+
+    extern "C" {
+        pub fn mmap(addr: *mut c_void, len: usize, prot: c_int, flags: c_int, fd: c_int, offset: i64) -> *mut c_void;
+        pub fn munmap(addr: *mut c_void, len: usize) -> c_int;
+    }
+}
+
+/// Maps the whole of `file` copy-on-write, and returns the address of its byte `offset`.
+#[cfg(unix)]
+pub(crate) fn map(file: &File, offset: usize) -> io::Result<*mut u8> {
+    use std::os::unix::io::AsRawFd;
+
+    let len = file.metadata()?.len() as usize;
+    if len == 0 {
+        return Ok(std::ptr::NonNull::<u64>::dangling().as_ptr() as *mut u8);
+    }
+    let prot = sys::PROT_READ | sys::PROT_WRITE;
+    let base = unsafe { sys::mmap(std::ptr::null_mut(), len, prot, sys::MAP_PRIVATE, file.as_raw_fd(), 0) };
+    if base == sys::MAP_FAILED {
+        return Err(io::Error::last_os_error());
+    }
+
+    let data = unsafe { (base as *mut u8).add(offset) };
+    mappings().lock().unwrap().insert(data as usize, (base as usize, len));
+    Ok(data)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn map(_file: &File, _offset: usize) -> io::Result<*mut u8> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "Memory-mapped files are not supported"))
+}
+
+/// Unmaps the file mapped by [`map`] with its data at `data`.
+///
+/// # Safety
+/// The mapped memory must not be used anymore.
+pub(crate) unsafe fn unmap(data: *mut u8) {
+    #[cfg(unix)]
+    if let Some((base, len)) = mappings().lock().unwrap().remove(&(data as usize)) {
+        sys::munmap(base as *mut _, len);
+    }
+}
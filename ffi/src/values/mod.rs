@@ -1,12 +1,21 @@
+pub mod dict;
 pub mod f64_value;
 pub mod fn_value;
 pub mod i64_value;
+mod mmap;
+pub mod rc;
+pub mod serial;
+pub mod splay;
+pub mod sym;
+pub mod table;
+pub mod time;
+pub mod vector;
 
 use crate::types::Type;
 use std::fmt;
 use std::mem::{forget, transmute};
 use std::ops::Deref;
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub mod prelude {
     pub use super::f64_value::F64Value;
@@ -14,9 +23,18 @@ pub mod prelude {
     pub use super::i64_value::I64Value;
 }
 
+use dict::Dict;
 use prelude::*;
+use sym::Sym;
+use table::Table;
+use time::{Date, Time, Timespan, Timestamp};
+use vector::{VecElement, VecHeader};
 
-pub const NULL_VALUE: i64 = std::i64::MAX;
+pub const NULL_VALUE: i64 = i64::MAX;
+/// Missing `Int64`, e.g. the minimum of an empty vector.
+pub const NULL_INT64: i64 = i64::MIN;
+/// Missing `Float64`, any NaN is treated as null.
+pub const NULL_FLOAT64: f64 = f64::NAN;
 pub type Discriminant = i64;
 
 #[derive(Debug, Clone)]
@@ -29,6 +47,8 @@ impl Deref for OpaqueValue {
     fn deref(&self) -> &Self::Target { &self.0 }
 }
 
+/// A value of any type. Heap values are reference counted atomically, so a value can be sent to
+/// and shared with other threads, see [`rc`].
 pub struct Value {
     ty: Type,
     val: OpaqueValue,
@@ -47,47 +67,107 @@ impl From<i64> for Value {
 }
 
 impl From<f64> for Value {
-    fn from(value: f64) -> Self { Value { ty: Type::Float64, val: OpaqueValue(unsafe { transmute(value) }) } }
+    fn from(value: f64) -> Self { Value { ty: Type::Float64, val: OpaqueValue(value.to_bits() as i64) } }
+}
+
+impl From<Date> for Value {
+    fn from(value: Date) -> Self { Value { ty: Type::Date, val: OpaqueValue(value.0) } }
+}
+
+impl From<Time> for Value {
+    fn from(value: Time) -> Self { Value { ty: Type::Time, val: OpaqueValue(value.0) } }
+}
+
+impl From<Timestamp> for Value {
+    fn from(value: Timestamp) -> Self { Value { ty: Type::Timestamp, val: OpaqueValue(value.0) } }
+}
+
+impl From<Timespan> for Value {
+    fn from(value: Timespan) -> Self { Value { ty: Type::Timespan, val: OpaqueValue(value.0) } }
 }
 
 impl From<Vec<i64>> for Value {
-    fn from(value: Vec<i64>) -> Self {
-        Value { ty: Type::VecInt64, val: OpaqueValue(unsafe { transmute(Rc::new(value)) }) }
-    }
+    fn from(value: Vec<i64>) -> Self { Value { ty: Type::VecInt64, val: OpaqueValue(VecHeader::alloc(value)) } }
 }
 
 impl From<Vec<f64>> for Value {
-    fn from(value: Vec<f64>) -> Self {
-        Value { ty: Type::VecFloat64, val: OpaqueValue(unsafe { transmute(Rc::new(value)) }) }
+    fn from(value: Vec<f64>) -> Self { Value { ty: Type::VecFloat64, val: OpaqueValue(VecHeader::alloc(value)) } }
+}
+
+impl From<Vec<bool>> for Value {
+    fn from(value: Vec<bool>) -> Self { Value { ty: Type::VecBool, val: OpaqueValue(VecHeader::alloc(value)) } }
+}
+
+impl From<Vec<Date>> for Value {
+    fn from(value: Vec<Date>) -> Self { Value { ty: Type::VecDate, val: OpaqueValue(VecHeader::alloc(value)) } }
+}
+
+impl From<Vec<Time>> for Value {
+    fn from(value: Vec<Time>) -> Self { Value { ty: Type::VecTime, val: OpaqueValue(VecHeader::alloc(value)) } }
+}
+
+impl From<Vec<Timestamp>> for Value {
+    fn from(value: Vec<Timestamp>) -> Self {
+        Value { ty: Type::VecTimestamp, val: OpaqueValue(VecHeader::alloc(value)) }
     }
 }
 
+impl From<Vec<Timespan>> for Value {
+    fn from(value: Vec<Timespan>) -> Self { Value { ty: Type::VecTimespan, val: OpaqueValue(VecHeader::alloc(value)) } }
+}
+
+impl From<Sym> for Value {
+    fn from(value: Sym) -> Self { Value { ty: Type::Sym, val: OpaqueValue(value.0) } }
+}
+
+impl From<Vec<Sym>> for Value {
+    fn from(value: Vec<Sym>) -> Self { Value { ty: Type::VecSym, val: OpaqueValue(VecHeader::alloc(value)) } }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self { Value { ty: Type::Str, val: OpaqueValue(rc::alloc(value)) } }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self { Value::from(value.to_string()) }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self { Value { ty: Type::Bytes, val: OpaqueValue(rc::alloc(value)) } }
+}
+
+impl From<Dict> for Value {
+    fn from(value: Dict) -> Self { Value { ty: value.get_type(), val: OpaqueValue(rc::alloc(value)) } }
+}
+
+impl From<Table> for Value {
+    fn from(value: Table) -> Self { Value { ty: value.get_type(), val: OpaqueValue(rc::alloc(value)) } }
+}
+
 impl From<Vec<Value>> for Value {
-    fn from(value: Vec<Value>) -> Self {
-        Value { ty: Type::List, val: OpaqueValue(unsafe { transmute(Rc::new(value)) }) }
-    }
+    fn from(value: Vec<Value>) -> Self { Value { ty: Type::List, val: OpaqueValue(rc::alloc(value)) } }
 }
 
 impl From<FnValue> for Value {
     fn from(value: FnValue) -> Self {
-        Value { ty: Type::Fn(value.get_type().clone()), val: OpaqueValue(unsafe { transmute(Rc::new(value)) }) }
+        Value { ty: Type::Fn(value.get_type().clone()), val: OpaqueValue(rc::alloc(value)) }
     }
 }
 
-impl Into<bool> for Value {
-    fn into(self) -> bool { *self.val != 0 }
+impl From<Value> for bool {
+    fn from(value: Value) -> Self { *value.val != 0 }
 }
 
-impl Into<i64> for Value {
-    fn into(self) -> i64 { *self.val }
+impl From<Value> for i64 {
+    fn from(value: Value) -> Self { *value.val }
 }
 
-impl Into<f64> for Value {
-    fn into(self) -> f64 { unsafe { transmute(*self.val) } }
+impl From<Value> for f64 {
+    fn from(value: Value) -> Self { f64::from_bits(*value.val as u64) }
 }
 
-impl Into<OpaqueValue> for Value {
-    fn into(self) -> OpaqueValue { OpaqueValue(*self.val) }
+impl From<Value> for OpaqueValue {
+    fn from(value: Value) -> Self { OpaqueValue(value.into_raw()) }
 }
 
 impl fmt::Display for Value {
@@ -95,25 +175,119 @@ impl fmt::Display for Value {
         match self.ty {
             Type::Null => write!(f, "null"),
             Type::Bool => write!(f, "{}", *self.val != 0),
+            Type::Int64 if *self.val == NULL_INT64 => write!(f, "0N"),
             Type::Int64 => write!(f, "{}", *self.val),
-            Type::Float64 => write!(f, "{:.2}", *self.val as f64),
-            Type::VecInt64 => unsafe {
-                let v: Rc<Vec<i64>> = transmute(*self.val);
-                let res = write!(f, "{:?}", v);
-                forget(v);
-                res
-            },
-            // VecFloat64 => write!(f, "{:?}", v),
-            // Value::List(v) => write!(f, "{:?}", v),
+            Type::Float64 if f64::from_bits(*self.val as u64).is_nan() => write!(f, "0n"),
+            Type::Float64 => write!(f, "{:.2}", f64::from_bits(*self.val as u64)),
+            ref ty if ty.is_temporal() => time::write(f, ty, *self.val),
+            ref ty if ty.is_vec() && *self.val == 0 => write!(f, "null"),
+            Type::VecInt64 => {
+                let v = unsafe { slice_from_raw::<i64>(*self.val) };
+                write_vec(f, v, |f, x| match *x {
+                    NULL_INT64 => write!(f, "0N"),
+                    x => write!(f, "{}", x),
+                })
+            }
+            Type::VecFloat64 => {
+                let v = unsafe { slice_from_raw::<f64>(*self.val) };
+                write_vec(f, v, |f, x| match x.is_nan() {
+                    true => write!(f, "0n"),
+                    false => write!(f, "{:?}", x),
+                })
+            }
+            Type::VecBool => write!(f, "{:?}", unsafe { slice_from_raw::<bool>(*self.val) }),
+            Type::VecDate => write_vec(f, unsafe { slice_from_raw::<Date>(*self.val) }, |f, x| write!(f, "{}", x)),
+            Type::VecTime => write_vec(f, unsafe { slice_from_raw::<Time>(*self.val) }, |f, x| write!(f, "{}", x)),
+            Type::VecTimestamp => {
+                write_vec(f, unsafe { slice_from_raw::<Timestamp>(*self.val) }, |f, x| write!(f, "{}", x))
+            }
+            Type::VecTimespan => {
+                write_vec(f, unsafe { slice_from_raw::<Timespan>(*self.val) }, |f, x| write!(f, "{}", x))
+            }
+            Type::Sym => write!(f, "`{}", Sym(*self.val).name()),
+            Type::VecSym => {
+                let v = unsafe { slice_from_raw::<Sym>(*self.val) };
+                match v.is_empty() {
+                    true => write!(f, "[]"),
+                    false => v.iter().try_for_each(|s| write!(f, "`{}", s.name())),
+                }
+            }
+            Type::Str if *self.val == 0 => write!(f, "null"),
+            Type::Str => write!(f, "{:?}", unsafe { str_from_raw(*self.val) }),
+            Type::Bytes if *self.val == 0 => write!(f, "null"),
+            // bytes are written as in q, e.g. `0x42ff`
+            Type::Bytes => {
+                write!(f, "0x")?;
+                unsafe { rc_from_raw::<Vec<u8>>(*self.val) }
+                    .iter()
+                    .try_for_each(|b| write!(f, "{:02x}", b))
+            }
+            Type::Dict(..) if *self.val == 0 => write!(f, "null"),
+            Type::Dict(..) => {
+                let dict = unsafe { rc_from_raw::<Dict>(*self.val) };
+                write!(f, "{}!{}", dict.keys(), dict.values())
+            }
+            Type::Table(_) if *self.val == 0 => write!(f, "null"),
+            Type::Table(_) => write!(f, "{}", unsafe { rc_from_raw::<Table>(*self.val) }),
+            Type::List if *self.val == 0 => write!(f, "null"),
+            // a list is written as in k, e.g. `([1, 2]; [3])`
+            Type::List => {
+                let list = unsafe { rc_from_raw::<Vec<Value>>(*self.val) };
+                let items: Vec<String> = list.iter().map(|v| v.to_string()).collect();
+                write!(f, "({})", items.join("; "))
+            }
             Type::Fn(_) => write!(f, "{}", self.get_type()),
             _ => write!(f, "{:?}", "ty"),
         }
     }
 }
 
+fn write_vec<T>(
+    f: &mut fmt::Formatter,
+    v: &[T],
+    write_elem: impl Fn(&mut fmt::Formatter, &T) -> fmt::Result,
+) -> fmt::Result {
+    write!(f, "[")?;
+    for (i, x) in v.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write_elem(f, x)?;
+    }
+    write!(f, "]")
+}
+
 impl Value {
+    /// Takes ownership of the raw value `val`.
     pub fn from_raw_parts(ty: Type, val: i64) -> Self { Value { ty, val: OpaqueValue(val) } }
 
+    /// Creates a value holding a new reference to the raw value `val`, which stays owned by the
+    /// caller.
+    ///
+    /// # Safety
+    /// `val` must be a live value of type `ty`.
+    pub unsafe fn from_raw_borrowed(ty: Type, val: i64) -> Self {
+        rc::retain(&ty, val);
+        Value { ty, val: OpaqueValue(val) }
+    }
+
+    /// Null value of `ty`: the null of a scalar, or a null pointer for a heap type, none for a type
+    /// without one, such as a function.
+    pub fn null(ty: &Type) -> Option<Self> {
+        let raw = match ty {
+            Type::Null => NULL_VALUE,
+            Type::Bool => 0,
+            Type::Int64 => NULL_INT64,
+            Type::Float64 => NULL_FLOAT64.to_bits() as i64,
+            Type::Sym => Sym::NULL.0,
+            ty if ty.is_temporal() => NULL_INT64,
+            ty if ty.is_vec() => 0,
+            Type::Str | Type::Bytes | Type::Dict(..) | Type::Table(_) | Type::List => 0,
+            _ => return None,
+        };
+        Some(Value::from_raw_parts(ty.clone(), raw))
+    }
+
     pub fn get_type(&self) -> &Type { &self.ty }
 
     pub fn is_null(&self) -> bool { self.ty == Type::Null }
@@ -129,16 +303,88 @@ impl Value {
 
     pub fn as_ptr(&self) -> *const () { &self.val as *const _ as _ }
 
-    pub fn into_raw(self) -> i64 { *self.val }
-}
+    /// Releases ownership of the raw value to the caller.
+    pub fn into_raw(self) -> i64 {
+        let raw = *self.val;
+        forget(self);
+        raw
+    }
 
-impl Drop for Value {
-    fn drop(&mut self) {
+    pub fn as_str(&self) -> Option<&str> {
         match self.ty {
-            Type::VecInt64 => {
-                // println!("DROP!!!!!!!");
-            }
-            _ => {}
+            Type::Str if *self.val != 0 => Some(unsafe { str_from_raw(*self.val) }),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self.ty {
+            Type::Bytes if *self.val != 0 => Some(unsafe { rc_from_raw::<Vec<u8>>(*self.val) }),
+            _ => None,
         }
     }
+
+    pub fn as_dict(&self) -> Option<&Dict> {
+        match self.ty {
+            Type::Dict(..) if *self.val != 0 => Some(unsafe { rc_from_raw(*self.val) }),
+            _ => None,
+        }
+    }
+
+    pub fn as_table(&self) -> Option<&Table> {
+        match self.ty {
+            Type::Table(_) if *self.val != 0 => Some(unsafe { rc_from_raw(*self.val) }),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self.ty {
+            Type::List if *self.val != 0 => Some(unsafe { rc_from_raw::<Vec<Value>>(*self.val) }),
+            _ => None,
+        }
+    }
+
+    pub fn as_fn(&self) -> Option<FnValue> {
+        match self.ty {
+            Type::Fn(_) => unsafe {
+                let f: Arc<FnValue> = transmute(*self.val);
+                let res = f.as_ref().clone();
+                forget(f);
+                Some(res)
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Borrows the elements of a raw vector value.
+///
+/// # Safety
+/// `raw` must be a vector of `T` which outlives the returned slice.
+pub(crate) unsafe fn slice_from_raw<'a, T: VecElement>(raw: i64) -> &'a [T] { VecHeader::from_raw(raw).as_slice() }
+
+/// Borrows a raw reference counted value of type `T`.
+///
+/// # Safety
+/// `raw` must be a `T` allocated by `rc::alloc` which outlives the returned reference.
+pub(crate) unsafe fn rc_from_raw<'a, T>(raw: i64) -> &'a T {
+    let rc: Arc<T> = transmute(raw);
+    let value: &'a T = &*(rc.as_ref() as *const T);
+    forget(rc);
+    value
+}
+
+/// Borrows a raw string value.
+///
+/// # Safety
+/// `raw` must be a string which outlives the returned `str`.
+pub(crate) unsafe fn str_from_raw<'a>(raw: i64) -> &'a str { rc_from_raw::<String>(raw).as_str() }
+
+impl Clone for Value {
+    fn clone(&self) -> Self { unsafe { Value::from_raw_borrowed(self.ty.clone(), *self.val) } }
+}
+
+impl Drop for Value {
+    fn drop(&mut self) { unsafe { rc::release(&self.ty, *self.val) } }
 }
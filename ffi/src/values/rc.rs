@@ -0,0 +1,145 @@
+//! Reference counting of heap values.
+//!
+//! Vectors, strings, dictionaries, tables and functions are stored in a `Value` as a raw pointer to a
+//! reference counted allocation. The ownership rules are:
+//!
+//! * A `Value` owns one reference: cloning it retains, dropping it releases.
+//! * Compiled expressions produce owned references. Variables, literals and call results are
+//!   retained when evaluated, and temporaries are released once they are no longer used.
+//! * Arguments are borrowed for the duration of a call, results are returned owned.
+//! * Literals embedded in compiled code are owned by their module and freed with it.
+//!
+//! Counts are atomic, so a `Value` can be cloned and dropped on any thread, including while the
+//! runtime whose globals it shares is used on another.
+
+use super::dict::Dict;
+use super::fn_value::FnValue;
+use super::table::Table;
+use super::vector::VecHeader;
+use super::Value;
+use crate::types::Type;
+use std::mem::{forget, transmute};
+use std::sync::atomic::{fence, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of heap values currently allocated in the process.
+pub fn live_values() -> usize { LIVE.load(Ordering::SeqCst) }
+
+pub(crate) fn count_alloc() { LIVE.fetch_add(1, Ordering::SeqCst); }
+
+pub(crate) fn count_free() { LIVE.fetch_sub(1, Ordering::SeqCst); }
+
+pub(crate) fn alloc<T>(value: T) -> i64 {
+    count_alloc();
+    unsafe { transmute(Arc::new(value)) }
+}
+
+unsafe fn retain_rc<T>(raw: i64) {
+    if raw == 0 {
+        return;
+    }
+    let rc: Arc<T> = transmute(raw);
+    forget(rc.clone());
+    forget(rc);
+}
+
+unsafe fn release_rc<T>(raw: i64) {
+    if raw == 0 {
+        return;
+    }
+    let rc: Arc<T> = transmute(raw);
+    if Arc::into_inner(rc).is_some() {
+        count_free();
+    }
+}
+
+unsafe fn retain_vec(raw: i64) {
+    if raw != 0 {
+        VecHeader::from_raw(raw).rc.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+unsafe fn release_vec(raw: i64) {
+    if raw == 0 {
+        return;
+    }
+    if VecHeader::from_raw(raw).rc.fetch_sub(1, Ordering::Release) == 1 {
+        // the uses of the vector by other threads happen before it is freed
+        fence(Ordering::Acquire);
+        VecHeader::free(raw);
+    }
+}
+
+/// Adds a reference to the heap value `raw` of type `ty`, scalars and null pointers are ignored.
+///
+/// # Safety
+/// `raw` must be null or a live value of type `ty`.
+pub unsafe fn retain(ty: &Type, raw: i64) {
+    match ty {
+        ty if ty.is_vec() => retain_vec(raw),
+        Type::Str => retain_rc::<String>(raw),
+        Type::Bytes => retain_rc::<Vec<u8>>(raw),
+        Type::Dict(..) => retain_rc::<Dict>(raw),
+        Type::Table(_) => retain_rc::<Table>(raw),
+        Type::List => retain_rc::<Vec<Value>>(raw),
+        Type::Fn(_) => retain_rc::<FnValue>(raw),
+        _ => {}
+    }
+}
+
+/// Drops a reference to the heap value `raw` of type `ty`, freeing it with the last one.
+///
+/// # Safety
+/// `raw` must be null or a live value of type `ty` whose reference is owned by the caller.
+pub unsafe fn release(ty: &Type, raw: i64) {
+    match ty {
+        ty if ty.is_vec() => release_vec(raw),
+        Type::Str => release_rc::<String>(raw),
+        Type::Bytes => release_rc::<Vec<u8>>(raw),
+        Type::Dict(..) => release_rc::<Dict>(raw),
+        Type::Table(_) => release_rc::<Table>(raw),
+        Type::List => release_rc::<Vec<Value>>(raw),
+        Type::Fn(_) => release_rc::<FnValue>(raw),
+        _ => {}
+    }
+}
+
+extern "C" fn retain_vec_raw(raw: i64) { unsafe { retain_vec(raw) } }
+
+extern "C" fn release_vec_raw(raw: i64) { unsafe { release_vec(raw) } }
+
+extern "C" fn retain_str_raw(raw: i64) { unsafe { retain_rc::<String>(raw) } }
+
+extern "C" fn release_str_raw(raw: i64) { unsafe { release_rc::<String>(raw) } }
+
+extern "C" fn retain_bytes_raw(raw: i64) { unsafe { retain_rc::<Vec<u8>>(raw) } }
+
+extern "C" fn release_bytes_raw(raw: i64) { unsafe { release_rc::<Vec<u8>>(raw) } }
+
+extern "C" fn retain_dict_raw(raw: i64) { unsafe { retain_rc::<Dict>(raw) } }
+
+extern "C" fn release_dict_raw(raw: i64) { unsafe { release_rc::<Dict>(raw) } }
+
+extern "C" fn retain_table_raw(raw: i64) { unsafe { retain_rc::<Table>(raw) } }
+
+extern "C" fn release_table_raw(raw: i64) { unsafe { release_rc::<Table>(raw) } }
+
+extern "C" fn retain_list_raw(raw: i64) { unsafe { retain_rc::<Vec<Value>>(raw) } }
+
+extern "C" fn release_list_raw(raw: i64) { unsafe { release_rc::<Vec<Value>>(raw) } }
+
+/// Addresses of the `extern "C" fn(raw)` retaining and releasing values of type `ty` from
+/// compiled code, `None` for types which are passed by value. Both accept a null pointer.
+pub fn rc_fns(ty: &Type) -> Option<(*const (), *const ())> {
+    match ty {
+        ty if ty.is_vec() => Some((retain_vec_raw as *const (), release_vec_raw as *const ())),
+        Type::Str => Some((retain_str_raw as *const (), release_str_raw as *const ())),
+        Type::Bytes => Some((retain_bytes_raw as *const (), release_bytes_raw as *const ())),
+        Type::Dict(..) => Some((retain_dict_raw as *const (), release_dict_raw as *const ())),
+        Type::Table(_) => Some((retain_table_raw as *const (), release_table_raw as *const ())),
+        Type::List => Some((retain_list_raw as *const (), release_list_raw as *const ())),
+        _ => None,
+    }
+}
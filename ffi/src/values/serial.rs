@@ -0,0 +1,439 @@
+//! Binary serialization of values, to persist them or send them between processes.
+//!
+//! A serialized value is the magic bytes `BSV`, the version of the format, the type of the value
+//! and then its body:
+//!
+//! | type      | encoding of the type                                | body                              |
+//! |-----------|-----------------------------------------------------|-----------------------------------|
+//! | scalar    | tag                                                 | 1 or 8 bytes                      |
+//! | `Str`     | tag                                                 | length, UTF-8 bytes               |
+//! | `Sym`     | tag                                                 | as the string of its name         |
+//! | vector    | tag                                                 | length, elements                  |
+//! | `Dict`    | tag, type of the keys, type of the values           | keys, values                      |
+//! | `Table`   | tag, number of columns, name and type of each       | every column                      |
+//! | `List`    | tag                                                 | length, type and body of each     |
+//!
+//! Lengths and numbers are little-endian `u64`, as are `Int64`, `Float64` and dates and times, and
+//! a `Bool` is a byte of 0 or 1. The elements of the vectors of all these scalars are encoded as
+//! the scalars, so on little-endian targets a vector is copied at once from or to its data.
+
+use super::dict::Dict;
+use super::sym::Sym;
+use super::table::Table;
+use super::time::{Date, Time, Timespan, Timestamp};
+use super::vector::{VecElement, VecHeader};
+use super::Value;
+use crate::types::Type;
+use std::borrow::Cow;
+use std::mem::size_of;
+
+pub const MAGIC: &[u8; 3] = b"BSV";
+pub const VERSION: u8 = 1;
+
+const NULL: u8 = 0;
+const BOOL: u8 = 1;
+const INT64: u8 = 2;
+const FLOAT64: u8 = 3;
+const SYM: u8 = 4;
+const STR: u8 = 5;
+const VEC_BOOL: u8 = 6;
+const VEC_INT64: u8 = 7;
+const VEC_FLOAT64: u8 = 8;
+const VEC_SYM: u8 = 9;
+const DICT: u8 = 10;
+const TABLE: u8 = 11;
+const LIST: u8 = 12;
+const DATE: u8 = 13;
+const TIME: u8 = 14;
+const TIMESTAMP: u8 = 15;
+const TIMESPAN: u8 = 16;
+const VEC_DATE: u8 = 17;
+const VEC_TIME: u8 = 18;
+const VEC_TIMESTAMP: u8 = 19;
+const VEC_TIMESPAN: u8 = 20;
+
+/// Serializes `value`, which can not be a function, nor a null string, vector, dictionary, table or
+/// list.
+pub fn serialize(value: &Value) -> Result<Vec<u8>, String> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(VERSION);
+    let ty = value_type(value)?;
+    write_type(&mut bytes, &ty)?;
+    write_body(&mut bytes, value)?;
+    Ok(bytes)
+}
+
+/// Deserializes a value serialized by [`serialize`].
+pub fn deserialize(bytes: &[u8]) -> Result<Value, String> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let ty = reader.header()?;
+    let value = reader.body(&ty)?;
+    match reader.pos == bytes.len() {
+        true => Ok(value),
+        false => Err(format!("{} bytes are left after the value", bytes.len() - reader.pos)),
+    }
+}
+
+/// Type of the value serialized in `bytes`, read from their beginning.
+pub fn read_type(bytes: &[u8]) -> Result<Type, String> { Reader { bytes, pos: 0 }.header() }
+
+/// Type of `value`, with the types of the keys and values of a dictionary and of the columns of a
+/// table, which compiled code does not keep.
+fn value_type(value: &Value) -> Result<Type, String> {
+    let ty = value.get_type();
+    if !ty.is_scalar() && value.as_raw() == 0 {
+        return Err(format!("Null values of type {} can not be serialized", ty));
+    }
+    match ty {
+        Type::Dict(..) => Ok(value.as_dict().expect("dict").get_type()),
+        Type::Table(_) => Ok(value.as_table().expect("table").get_type()),
+        Type::Fn(_) | Type::Param(_) => Err(format!("Values of type {} can not be serialized", ty)),
+        ty => Ok(ty.clone()),
+    }
+}
+
+fn write_len(bytes: &mut Vec<u8>, len: usize) { bytes.extend_from_slice(&(len as u64).to_le_bytes()) }
+
+fn write_str(bytes: &mut Vec<u8>, s: &str) {
+    write_len(bytes, s.len());
+    bytes.extend_from_slice(s.as_bytes());
+}
+
+fn write_type(bytes: &mut Vec<u8>, ty: &Type) -> Result<(), String> {
+    match ty {
+        Type::Null => bytes.push(NULL),
+        Type::Bool => bytes.push(BOOL),
+        Type::Int64 => bytes.push(INT64),
+        Type::Float64 => bytes.push(FLOAT64),
+        Type::Sym => bytes.push(SYM),
+        Type::Str => bytes.push(STR),
+        Type::VecBool => bytes.push(VEC_BOOL),
+        Type::VecInt64 => bytes.push(VEC_INT64),
+        Type::VecFloat64 => bytes.push(VEC_FLOAT64),
+        Type::VecSym => bytes.push(VEC_SYM),
+        Type::Date => bytes.push(DATE),
+        Type::Time => bytes.push(TIME),
+        Type::Timestamp => bytes.push(TIMESTAMP),
+        Type::Timespan => bytes.push(TIMESPAN),
+        Type::VecDate => bytes.push(VEC_DATE),
+        Type::VecTime => bytes.push(VEC_TIME),
+        Type::VecTimestamp => bytes.push(VEC_TIMESTAMP),
+        Type::VecTimespan => bytes.push(VEC_TIMESPAN),
+        Type::Dict(keys, values) => {
+            bytes.push(DICT);
+            write_type(bytes, keys)?;
+            write_type(bytes, values)?;
+        }
+        Type::Table(columns) => {
+            bytes.push(TABLE);
+            write_len(bytes, columns.len());
+            for (name, ty) in columns {
+                write_str(bytes, name);
+                write_type(bytes, ty)?;
+            }
+        }
+        Type::List => bytes.push(LIST),
+        ty => return Err(format!("Values of type {} can not be serialized", ty)),
+    }
+    Ok(())
+}
+
+/// Elements of vectors encoded as little-endian scalars.
+pub(crate) trait Scalar: VecElement {
+    fn write_le(self, bytes: &mut Vec<u8>);
+    fn read_le(bytes: &[u8]) -> Self;
+    /// Whether `bytes` encode an element, every bit pattern does but those of a `bool`.
+    fn is_valid(_bytes: &[u8]) -> bool { true }
+}
+
+impl Scalar for i64 {
+    fn write_le(self, bytes: &mut Vec<u8>) { bytes.extend_from_slice(&self.to_le_bytes()) }
+
+    fn read_le(bytes: &[u8]) -> Self { i64::from_le_bytes(bytes.try_into().expect("8 bytes")) }
+}
+
+impl Scalar for f64 {
+    fn write_le(self, bytes: &mut Vec<u8>) { bytes.extend_from_slice(&self.to_le_bytes()) }
+
+    fn read_le(bytes: &[u8]) -> Self { f64::from_le_bytes(bytes.try_into().expect("8 bytes")) }
+}
+
+impl Scalar for bool {
+    fn write_le(self, bytes: &mut Vec<u8>) { bytes.push(self as u8) }
+
+    fn read_le(bytes: &[u8]) -> Self { bytes[0] != 0 }
+
+    fn is_valid(bytes: &[u8]) -> bool { bytes.iter().all(|b| *b <= 1) }
+}
+
+impl Scalar for Date {
+    fn write_le(self, bytes: &mut Vec<u8>) { self.0.write_le(bytes) }
+
+    fn read_le(bytes: &[u8]) -> Self { Date(i64::read_le(bytes)) }
+}
+
+impl Scalar for Time {
+    fn write_le(self, bytes: &mut Vec<u8>) { self.0.write_le(bytes) }
+
+    fn read_le(bytes: &[u8]) -> Self { Time(i64::read_le(bytes)) }
+}
+
+impl Scalar for Timestamp {
+    fn write_le(self, bytes: &mut Vec<u8>) { self.0.write_le(bytes) }
+
+    fn read_le(bytes: &[u8]) -> Self { Timestamp(i64::read_le(bytes)) }
+}
+
+impl Scalar for Timespan {
+    fn write_le(self, bytes: &mut Vec<u8>) { self.0.write_le(bytes) }
+
+    fn read_le(bytes: &[u8]) -> Self { Timespan(i64::read_le(bytes)) }
+}
+
+/// Encoding of the elements `vec`, which is their memory on little-endian targets.
+pub(crate) fn le_bytes<T: Scalar>(vec: &[T]) -> Cow<'_, [u8]> {
+    if cfg!(target_endian = "little") {
+        Cow::Borrowed(unsafe { std::slice::from_raw_parts(vec.as_ptr() as *const u8, std::mem::size_of_val(vec)) })
+    } else {
+        let mut bytes = Vec::with_capacity(std::mem::size_of_val(vec));
+        vec.iter().for_each(|x| x.write_le(&mut bytes));
+        Cow::Owned(bytes)
+    }
+}
+
+/// Elements encoded by `data`, copied at once on little-endian targets.
+pub(crate) fn from_le_bytes<T: Scalar>(data: &[u8]) -> Result<Vec<T>, String> {
+    if !T::is_valid(data) {
+        return Err("Invalid serialized Bool".to_string());
+    }
+    let len = data.len() / size_of::<T>();
+    let mut vec = Vec::<T>::with_capacity(len);
+    if cfg!(target_endian = "little") {
+        unsafe {
+            std::ptr::copy_nonoverlapping(data.as_ptr(), vec.as_mut_ptr() as *mut u8, len * size_of::<T>());
+            vec.set_len(len);
+        }
+    } else {
+        vec.extend(data.chunks_exact(size_of::<T>()).map(T::read_le));
+    }
+    Ok(vec)
+}
+
+fn write_vec<T: Scalar>(bytes: &mut Vec<u8>, vec: &[T]) {
+    write_len(bytes, vec.len());
+    bytes.extend_from_slice(&le_bytes(vec));
+}
+
+fn write_body(bytes: &mut Vec<u8>, value: &Value) -> Result<(), String> {
+    let slice = |value: &Value| unsafe { VecHeader::from_raw(value.as_raw()) };
+    match value.get_type() {
+        Type::Null => {}
+        Type::Bool => bytes.push(value.as_raw() as u8),
+        Type::Int64 | Type::Float64 | Type::Date | Type::Time | Type::Timestamp | Type::Timespan => {
+            bytes.extend_from_slice(&value.as_raw().to_le_bytes())
+        }
+        Type::Sym => write_str(bytes, Sym(value.as_raw()).name()),
+        Type::Str => write_str(bytes, value.as_str().expect("str")),
+        Type::VecBool => write_vec(bytes, unsafe { slice(value).as_slice::<bool>() }),
+        Type::VecInt64 => write_vec(bytes, unsafe { slice(value).as_slice::<i64>() }),
+        Type::VecFloat64 => write_vec(bytes, unsafe { slice(value).as_slice::<f64>() }),
+        Type::VecDate => write_vec(bytes, unsafe { slice(value).as_slice::<Date>() }),
+        Type::VecTime => write_vec(bytes, unsafe { slice(value).as_slice::<Time>() }),
+        Type::VecTimestamp => write_vec(bytes, unsafe { slice(value).as_slice::<Timestamp>() }),
+        Type::VecTimespan => write_vec(bytes, unsafe { slice(value).as_slice::<Timespan>() }),
+        Type::VecSym => {
+            let syms = unsafe { slice(value).as_slice::<Sym>() };
+            write_len(bytes, syms.len());
+            syms.iter().for_each(|s| write_str(bytes, s.name()));
+        }
+        Type::Dict(..) => {
+            let dict = value.as_dict().expect("dict");
+            write_body(bytes, dict.keys())?;
+            write_body(bytes, dict.values())?;
+        }
+        Type::Table(_) => {
+            let table = value.as_table().expect("table");
+            for column in table.columns() {
+                write_body(bytes, column)?;
+            }
+        }
+        Type::List => {
+            let items = value.as_list().expect("list");
+            write_len(bytes, items.len());
+            for item in items {
+                write_type(bytes, &value_type(item)?)?;
+                write_body(bytes, item)?;
+            }
+        }
+        ty => return Err(format!("Values of type {} can not be serialized", ty)),
+    }
+    Ok(())
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        match self.pos.checked_add(len).filter(|end| *end <= self.bytes.len()) {
+            Some(end) => {
+                let bytes = &self.bytes[self.pos..end];
+                self.pos = end;
+                Ok(bytes)
+            }
+            None => Err("The serialized value is truncated".to_string()),
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, String> { Ok(self.take(1)?[0]) }
+
+    fn len(&mut self) -> Result<usize, String> {
+        let len = u64::from_le_bytes(self.take(8)?.try_into().expect("8 bytes"));
+        usize::try_from(len).map_err(|_| "The serialized value is truncated".to_string())
+    }
+
+    fn str(&mut self) -> Result<&'a str, String> {
+        let len = self.len()?;
+        std::str::from_utf8(self.take(len)?).map_err(|_| "Invalid UTF-8 in a serialized string".to_string())
+    }
+
+    fn header(&mut self) -> Result<Type, String> {
+        if self.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+            return Err("Not a serialized value".to_string());
+        }
+        match self.byte()? {
+            VERSION => self.value_type(),
+            version => Err(format!("Unsupported version {} of the serialization format", version)),
+        }
+    }
+
+    fn value_type(&mut self) -> Result<Type, String> {
+        let ty = match self.byte()? {
+            NULL => Type::Null,
+            BOOL => Type::Bool,
+            INT64 => Type::Int64,
+            FLOAT64 => Type::Float64,
+            SYM => Type::Sym,
+            STR => Type::Str,
+            VEC_BOOL => Type::VecBool,
+            VEC_INT64 => Type::VecInt64,
+            VEC_FLOAT64 => Type::VecFloat64,
+            VEC_SYM => Type::VecSym,
+            DATE => Type::Date,
+            TIME => Type::Time,
+            TIMESTAMP => Type::Timestamp,
+            TIMESPAN => Type::Timespan,
+            VEC_DATE => Type::VecDate,
+            VEC_TIME => Type::VecTime,
+            VEC_TIMESTAMP => Type::VecTimestamp,
+            VEC_TIMESPAN => Type::VecTimespan,
+            DICT => {
+                let keys = self.value_type()?;
+                let values = self.value_type()?;
+                if !keys.is_vec() || !(values.is_vec() || values == Type::List) {
+                    return Err(format!("Invalid serialized dictionary of type {}!{}", keys, values));
+                }
+                Type::dict(keys, values)
+            }
+            TABLE => {
+                let mut columns = vec![];
+                for _ in 0..self.len()? {
+                    let name = self.str()?.to_string();
+                    let ty = self.value_type()?;
+                    if !ty.is_vec() {
+                        return Err(format!("Invalid serialized table column of type {}", ty));
+                    }
+                    columns.push((name, ty));
+                }
+                Type::Table(columns)
+            }
+            LIST => Type::List,
+            tag => return Err(format!("Invalid serialized type {}", tag)),
+        };
+        Ok(ty)
+    }
+
+    fn vec<T: Scalar>(&mut self) -> Result<Vec<T>, String> {
+        let len = self.len()?;
+        let data = self.take(
+            len.checked_mul(size_of::<T>())
+                .ok_or("The serialized value is truncated")?,
+        )?;
+        from_le_bytes(data)
+    }
+
+    fn body(&mut self, ty: &Type) -> Result<Value, String> {
+        let value = match ty {
+            Type::Null => Value::from(()),
+            Type::Bool => match self.byte()? {
+                b @ (0 | 1) => Value::from(b == 1),
+                _ => return Err("Invalid serialized Bool".to_string()),
+            },
+            Type::Int64 => Value::from(i64::read_le(self.take(8)?)),
+            Type::Float64 => Value::from(f64::read_le(self.take(8)?)),
+            Type::Date => Value::from(Date::read_le(self.take(8)?)),
+            Type::Time => Value::from(Time::read_le(self.take(8)?)),
+            Type::Timestamp => Value::from(Timestamp::read_le(self.take(8)?)),
+            Type::Timespan => Value::from(Timespan::read_le(self.take(8)?)),
+            Type::Sym => Value::from(Sym::new(self.str()?)),
+            Type::Str => Value::from(self.str()?),
+            Type::VecBool => Value::from(self.vec::<bool>()?),
+            Type::VecInt64 => Value::from(self.vec::<i64>()?),
+            Type::VecFloat64 => Value::from(self.vec::<f64>()?),
+            Type::VecDate => Value::from(self.vec::<Date>()?),
+            Type::VecTime => Value::from(self.vec::<Time>()?),
+            Type::VecTimestamp => Value::from(self.vec::<Timestamp>()?),
+            Type::VecTimespan => Value::from(self.vec::<Timespan>()?),
+            Type::VecSym => {
+                let len = self.len()?;
+                let mut syms = Vec::with_capacity(len.min(self.bytes.len()));
+                for _ in 0..len {
+                    syms.push(Sym::new(self.str()?));
+                }
+                Value::from(syms)
+            }
+            Type::Dict(keys, values) => {
+                let (keys_ty, keys, values) = (keys.as_ref().clone(), self.body(keys)?, self.body(values)?);
+                let len = |v: &Value| match v.as_list() {
+                    Some(items) => items.len(),
+                    None => unsafe { VecHeader::from_raw(v.as_raw()).len as usize },
+                };
+                if len(&keys) != len(&values) {
+                    return Err("The keys and values of a serialized dictionary have different lengths".to_string());
+                }
+                let dict = match keys_ty {
+                    Type::VecInt64 => Dict::new::<i64>(keys, values),
+                    Type::VecFloat64 => Dict::new::<f64>(keys, values),
+                    Type::VecBool => Dict::new::<bool>(keys, values),
+                    Type::VecDate => Dict::new::<Date>(keys, values),
+                    Type::VecTime => Dict::new::<Time>(keys, values),
+                    Type::VecTimestamp => Dict::new::<Timestamp>(keys, values),
+                    Type::VecTimespan => Dict::new::<Timespan>(keys, values),
+                    _ => Dict::new::<Sym>(keys, values),
+                };
+                Value::from(dict)
+            }
+            Type::Table(columns) => {
+                let mut table = Table::default();
+                for (name, ty) in columns {
+                    let column = self.body(ty)?;
+                    table = table.with_column(name, column)?;
+                }
+                Value::from(table)
+            }
+            Type::List => {
+                let len = self.len()?;
+                let mut items = Vec::with_capacity(len.min(self.bytes.len()));
+                for _ in 0..len {
+                    let ty = self.value_type()?;
+                    items.push(self.body(&ty)?);
+                }
+                Value::from(items)
+            }
+            ty => return Err(format!("Values of type {} can not be deserialized", ty)),
+        };
+        Ok(value)
+    }
+}
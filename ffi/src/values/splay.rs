@@ -0,0 +1,289 @@
+//! Splayed tables, saved as a directory of a file per column and opened again by mapping the files
+//! of the columns, so that tables bigger than memory are read without copying.
+//!
+//! The directory holds the file `.d`, the names of the columns serialized as a `Symbol[]`, see
+//! `serial`, and a file named after every column:
+//!
+//! | bytes  | content                                          |
+//! |--------|--------------------------------------------------|
+//! | 0..3   | magic bytes `BSV`                                |
+//! | 3      | version of the format, that of `serial`          |
+//! | 4      | element type, a [`VecTag`]                       |
+//! | 5..8   | zero                                             |
+//! | 8..16  | number of elements, a little-endian `u64`        |
+//! | 16..   | elements                                         |
+//!
+//! The elements of `Int64`, `Float64`, `Bool`, date and time columns are encoded as by `serial`,
+//! and are mapped on little-endian targets. Symbols are stored by name, each its length and UTF-8 bytes, and read
+//! into memory. Rows are appended in place, the elements of every column first and then their
+//! numbers, so that a failed append leaves the rows of the table as they were. The columns of a
+//! table read must have the same number of elements.
+
+use super::serial::{self, from_le_bytes, le_bytes, Scalar, MAGIC, VERSION};
+use super::sym::Sym;
+use super::table::Table;
+use super::time::{Date, Time, Timespan, Timestamp};
+use super::vector::{VecHeader, VecTag};
+use super::Value;
+use crate::types::Type;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::mem::size_of;
+use std::path::Path;
+
+/// Name of the file of the names of the columns.
+const NAMES: &str = ".d";
+/// Length of the header of a column file, the offset of its elements.
+const HEADER_LEN: u64 = 16;
+
+fn io_err(file: &Path) -> impl Fn(io::Error) -> String + '_ { move |err| format!("{}: {}", file.display(), err) }
+
+fn tag_of(ty: &Type) -> VecTag {
+    match ty {
+        Type::VecInt64 => VecTag::Int64,
+        Type::VecFloat64 => VecTag::Float64,
+        Type::VecBool => VecTag::Bool,
+        Type::VecDate => VecTag::Date,
+        Type::VecTime => VecTag::Time,
+        Type::VecTimestamp => VecTag::Timestamp,
+        Type::VecTimespan => VecTag::Timespan,
+        _ => VecTag::Sym,
+    }
+}
+
+fn column_path(dir: &Path, name: &str) -> Result<std::path::PathBuf, String> {
+    match name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        true => Err(format!("Column '{}' can not be named by a file", name)),
+        false => Ok(dir.join(name)),
+    }
+}
+
+fn write_header(file: &mut impl Write, tag: VecTag, len: usize) -> io::Result<()> {
+    file.write_all(MAGIC)?;
+    file.write_all(&[VERSION, tag as u8, 0, 0, 0])?;
+    file.write_all(&(len as u64).to_le_bytes())
+}
+
+/// Element type and number of elements of the column file `file`.
+fn read_header(file: &mut File, path: &Path) -> Result<(VecTag, usize), String> {
+    let mut header = [0; HEADER_LEN as usize];
+    file.read_exact(&mut header).map_err(io_err(path))?;
+    if &header[..3] != MAGIC || header[3] != VERSION {
+        return Err(format!("{}: Not a column of a splayed table", path.display()));
+    }
+    let tag = match header[4] {
+        0 => VecTag::Int64,
+        1 => VecTag::Float64,
+        2 => VecTag::Bool,
+        3 => VecTag::Sym,
+        4 => VecTag::Date,
+        5 => VecTag::Time,
+        6 => VecTag::Timestamp,
+        7 => VecTag::Timespan,
+        tag => return Err(format!("{}: Invalid element type {}", path.display(), tag)),
+    };
+    let len = u64::from_le_bytes(header[8..].try_into().expect("8 bytes"));
+    Ok((tag, len as usize))
+}
+
+/// Writes the elements of the vector `column` at the current position of `file`.
+fn write_elems(file: &mut impl Write, column: &Value) -> io::Result<()> {
+    fn write<T: Scalar>(file: &mut impl Write, column: &Value) -> io::Result<()> {
+        file.write_all(&le_bytes(unsafe { VecHeader::from_raw(column.as_raw()).as_slice::<T>() }))
+    }
+
+    match column.get_type() {
+        Type::VecInt64 => write::<i64>(file, column),
+        Type::VecFloat64 => write::<f64>(file, column),
+        Type::VecBool => write::<bool>(file, column),
+        Type::VecDate => write::<Date>(file, column),
+        Type::VecTime => write::<Time>(file, column),
+        Type::VecTimestamp => write::<Timestamp>(file, column),
+        Type::VecTimespan => write::<Timespan>(file, column),
+        _ => unsafe { VecHeader::from_raw(column.as_raw()).as_slice::<Sym>() }
+            .iter()
+            .try_for_each(|s| {
+                file.write_all(&(s.name().len() as u64).to_le_bytes())?;
+                file.write_all(s.name().as_bytes())
+            }),
+    }
+}
+
+fn read_names(dir: &Path) -> Result<Vec<String>, String> {
+    let path = dir.join(NAMES);
+    let bytes = fs::read(&path).map_err(io_err(&path))?;
+    let names = serial::deserialize(&bytes).map_err(|msg| format!("{}: {}", path.display(), msg))?;
+    match names.get_type() {
+        Type::VecSym => {
+            let names = unsafe { VecHeader::from_raw(names.as_raw()).as_slice::<Sym>() };
+            Ok(names.iter().map(|s| s.name().to_string()).collect())
+        }
+        ty => Err(format!("{}: Expected the names of the columns, found a value of type {}", path.display(), ty)),
+    }
+}
+
+/// Writes `table` to the directory `dir` as a splayed table, returning its number of rows.
+pub fn write(dir: &Path, table: &Table) -> Result<usize, String> {
+    fs::create_dir_all(dir).map_err(io_err(dir))?;
+    for (name, column) in table.names().iter().zip(table.columns()) {
+        let path = column_path(dir, name)?;
+        let mut file = BufWriter::new(File::create(&path).map_err(io_err(&path))?);
+        write_header(&mut file, tag_of(column.get_type()), table.len())
+            .and_then(|_| write_elems(&mut file, column))
+            .and_then(|_| file.flush())
+            .map_err(io_err(&path))?;
+    }
+
+    // the names are written last, the table is not read until its columns are
+    let names: Vec<Sym> = table.names().iter().map(|n| Sym::new(n)).collect();
+    let bytes = serial::serialize(&Value::from(names))?;
+    let path = dir.join(NAMES);
+    fs::write(&path, bytes).map_err(io_err(&path))?;
+    Ok(table.len())
+}
+
+/// Checks that the column `path` has `len` elements, as the columns before it, if there are some.
+fn check_len(rows: &mut Option<usize>, len: usize, path: &Path) -> Result<(), String> {
+    match *rows.get_or_insert(len) {
+        rows if rows == len => Ok(()),
+        rows => Err(format!("{}: Expected a column of {} elements, found {}", path.display(), rows, len)),
+    }
+}
+
+/// Type of the splayed table in the directory `dir`.
+pub fn read_type(dir: &Path) -> Result<Type, String> {
+    let (mut columns, mut rows) = (vec![], None);
+    for name in read_names(dir)? {
+        let path = column_path(dir, &name)?;
+        let mut file = File::open(&path).map_err(io_err(&path))?;
+        let (tag, len) = read_header(&mut file, &path)?;
+        check_len(&mut rows, len, &path)?;
+        columns.push((name, tag.vec_type()));
+    }
+    Ok(Type::Table(columns))
+}
+
+/// Reads the column of `len` elements of `T` following the header of `file`, mapping it on
+/// little-endian targets.
+fn read_scalars<T: Scalar>(file: &mut File, len: usize) -> io::Result<Value> {
+    let ty = T::VEC_TYPE;
+    if cfg!(target_endian = "little") {
+        let raw = VecHeader::map::<T>(file, HEADER_LEN as usize, len)?;
+        let column = Value::from_raw_parts(ty, raw);
+        let data = unsafe { std::slice::from_raw_parts(VecHeader::from_raw(raw).data, len * size_of::<T>()) };
+        return match T::is_valid(data) {
+            true => Ok(column),
+            false => Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid Bool")),
+        };
+    }
+
+    let size = len.checked_mul(size_of::<T>());
+    let mut data = vec![0; size.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid length"))?];
+    file.read_exact(&mut data)?;
+    let elems = from_le_bytes::<T>(&data).map_err(|msg| io::Error::new(io::ErrorKind::InvalidData, msg))?;
+    Ok(Value::from_raw_parts(ty, VecHeader::alloc(elems)))
+}
+
+fn read_syms(file: &mut File, len: usize) -> io::Result<Value> {
+    let mut bytes = vec![];
+    file.read_to_end(&mut bytes)?;
+    let mut rest = bytes.as_slice();
+    let mut syms = Vec::with_capacity(len.min(bytes.len()));
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "Invalid symbol");
+    for _ in 0..len {
+        let (name_len, tail) = rest.split_first_chunk::<8>().ok_or_else(invalid)?;
+        let name_len = usize::try_from(u64::from_le_bytes(*name_len)).map_err(|_| invalid())?;
+        let name = tail.get(..name_len).ok_or_else(invalid)?;
+        syms.push(Sym::new(std::str::from_utf8(name).map_err(|_| invalid())?));
+        rest = &tail[name_len..];
+    }
+    Ok(Value::from(syms))
+}
+
+/// Opens the splayed table in the directory `dir`, mapping its columns but the symbol ones.
+pub fn read(dir: &Path) -> Result<Table, String> {
+    let (mut table, mut rows) = (Table::default(), None);
+    for name in read_names(dir)? {
+        let path = column_path(dir, &name)?;
+        let mut file = File::open(&path).map_err(io_err(&path))?;
+        let (tag, len) = read_header(&mut file, &path)?;
+        check_len(&mut rows, len, &path)?;
+        let column = match tag {
+            VecTag::Int64 => read_scalars::<i64>(&mut file, len),
+            VecTag::Float64 => read_scalars::<f64>(&mut file, len),
+            VecTag::Bool => read_scalars::<bool>(&mut file, len),
+            VecTag::Date => read_scalars::<Date>(&mut file, len),
+            VecTag::Time => read_scalars::<Time>(&mut file, len),
+            VecTag::Timestamp => read_scalars::<Timestamp>(&mut file, len),
+            VecTag::Timespan => read_scalars::<Timespan>(&mut file, len),
+            VecTag::Sym => read_syms(&mut file, len),
+        };
+        let column = column.map_err(io_err(&path))?;
+        table = table
+            .with_column(&name, column)
+            .map_err(|msg| format!("{}: {}", path.display(), msg))?;
+    }
+    Ok(table)
+}
+
+/// Offset of the end of the `len` symbols following the header of `file`.
+fn syms_end(file: &mut File, len: usize) -> io::Result<u64> {
+    let file_len = file.metadata()?.len();
+    let mut end = HEADER_LEN;
+    for _ in 0..len {
+        let mut name_len = [0; 8];
+        file.seek(SeekFrom::Start(end))?;
+        file.read_exact(&mut name_len)?;
+        end = end
+            .checked_add(8 + u64::from_le_bytes(name_len))
+            .filter(|end| *end <= file_len)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid symbol"))?;
+    }
+    Ok(end)
+}
+
+/// Appends the rows of `table`, of the same columns, to the splayed table in the directory `dir`,
+/// returning the number of rows appended.
+pub fn append(dir: &Path, table: &Table) -> Result<usize, String> {
+    let ty = read_type(dir)?;
+    if table.get_type() != ty {
+        return Err(format!("Rows of type {} can not be appended to a splayed table of type {}", table.get_type(), ty));
+    }
+
+    // the ends of all the columns are found before any is written
+    let mut files = vec![];
+    for name in table.names() {
+        let path = column_path(dir, name)?;
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(&path)
+            .map_err(io_err(&path))?;
+        let (tag, len) = read_header(&mut file, &path)?;
+        let end = match tag {
+            VecTag::Bool => HEADER_LEN + len as u64,
+            VecTag::Sym => syms_end(&mut file, len).map_err(io_err(&path))?,
+            _ => HEADER_LEN + len as u64 * 8,
+        };
+        files.push((path, file, len, end));
+    }
+
+    // the rows are not read until the numbers of elements of all the columns are written
+    for ((path, file, _, end), column) in files.iter_mut().zip(table.columns()) {
+        let mut write = || -> io::Result<()> {
+            file.seek(SeekFrom::Start(*end))?;
+            let mut out = BufWriter::new(&mut *file);
+            write_elems(&mut out, column)?;
+            out.flush()
+        };
+        write().map_err(io_err(path))?;
+    }
+    for (path, file, len, _) in files.iter_mut() {
+        let mut write = || -> io::Result<()> {
+            file.seek(SeekFrom::Start(8))?;
+            file.write_all(&((*len + table.len()) as u64).to_le_bytes())
+        };
+        write().map_err(io_err(path))?;
+    }
+    Ok(table.len())
+}
@@ -0,0 +1,65 @@
+//! Symbols, interned strings which are compared and hashed as integers.
+//!
+//! A symbol is the index of its name in a table shared by the whole process, and names are never
+//! freed. The empty name has index 0 and is the null symbol.
+
+use super::vector::{VecElement, VecTag};
+use crate::types::Type;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+#[repr(transparent)]
+pub struct Sym(pub i64);
+
+#[derive(Default)]
+struct Interner {
+    names: Vec<&'static str>,
+    ids: HashMap<&'static str, i64>,
+}
+
+fn interner() -> &'static Mutex<Interner> {
+    static INTERNER: OnceLock<Mutex<Interner>> = OnceLock::new();
+    INTERNER.get_or_init(|| {
+        let mut interner = Interner::default();
+        interner.names.push("");
+        interner.ids.insert("", 0);
+        Mutex::new(interner)
+    })
+}
+
+impl Sym {
+    pub const NULL: Sym = Sym(0);
+
+    /// Interns `name`, returning the symbol it is known as.
+    pub fn new(name: &str) -> Sym {
+        let mut interner = interner().lock().unwrap();
+        if let Some(id) = interner.ids.get(name) {
+            return Sym(*id);
+        }
+
+        let name: &'static str = Box::leak(name.to_string().into_boxed_str());
+        let id = interner.names.len() as i64;
+        interner.names.push(name);
+        interner.ids.insert(name, id);
+        Sym(id)
+    }
+
+    pub fn name(self) -> &'static str {
+        interner()
+            .lock()
+            .unwrap()
+            .names
+            .get(self.0 as usize)
+            .copied()
+            .unwrap_or("")
+    }
+
+    pub fn is_null(self) -> bool { self == Sym::NULL }
+}
+
+impl VecElement for Sym {
+    const TAG: VecTag = VecTag::Sym;
+    const VEC_TYPE: Type = Type::VecSym;
+    const NULL: Self = Sym::NULL;
+}
@@ -0,0 +1,172 @@
+//! Tables, ordered sets of named vector columns of the same length.
+//!
+//! A table shares its columns with the vectors it was made of: building a table or taking one of
+//! its columns does not copy any elements.
+
+use super::sym::Sym;
+use super::time::{Date, Time, Timespan, Timestamp};
+use super::vector::{VecElement, VecHeader};
+use super::{rc_from_raw, slice_from_raw, Value};
+use crate::types::Type;
+use std::fmt;
+
+#[derive(Clone, Default)]
+pub struct Table {
+    names: Vec<String>,
+    columns: Vec<Value>,
+}
+
+impl Table {
+    /// Adds the column `name`, which must be a vector of as many elements as the other columns.
+    pub fn with_column(&self, name: &str, column: Value) -> Result<Table, &'static str> {
+        if !column.get_type().is_vec() || column.as_raw() == 0 {
+            return Err("Table columns must be vectors");
+        }
+        if self.names.iter().any(|n| n == name) {
+            return Err("Table columns must have distinct names");
+        }
+        if !self.columns.is_empty() && column_len(&column) != self.len() {
+            return Err("Table columns must have the same length");
+        }
+
+        let mut table = self.clone();
+        table.names.push(name.to_string());
+        table.columns.push(column);
+        Ok(table)
+    }
+
+    /// Borrows the raw table value `raw`.
+    ///
+    /// # Safety
+    /// `raw` must be a live table which outlives the returned reference.
+    pub unsafe fn from_raw<'a>(raw: i64) -> &'a Table { rc_from_raw(raw) }
+
+    /// Replaces the column `name` by `column`, or adds it if there is no such column.
+    pub fn set_column(&self, name: &str, column: Value) -> Result<Table, &'static str> {
+        match self.names.iter().position(|n| n == name) {
+            Some(i) if column.get_type().is_vec() && column_len(&column) == self.len() => {
+                let mut table = self.clone();
+                table.columns[i] = column;
+                Ok(table)
+            }
+            Some(_) => Err("Table columns must have the same length"),
+            None => self.with_column(name, column),
+        }
+    }
+
+    /// Table of the columns but those in `names`.
+    pub fn without_columns(&self, names: &[&str]) -> Table {
+        let (names, columns) = self
+            .names
+            .iter()
+            .zip(&self.columns)
+            .filter(|(name, _)| !names.contains(&name.as_str()))
+            .map(|(name, column)| (name.clone(), column.clone()))
+            .unzip();
+        Table { names, columns }
+    }
+
+    pub fn names(&self) -> &[String] { &self.names }
+
+    pub fn columns(&self) -> &[Value] { &self.columns }
+
+    pub fn column(&self, name: &str) -> Option<&Value> {
+        self.names.iter().position(|n| n == name).map(|i| &self.columns[i])
+    }
+
+    pub fn get_type(&self) -> Type {
+        Type::Table(
+            self.names
+                .iter()
+                .cloned()
+                .zip(self.columns.iter().map(|c| c.get_type().clone()))
+                .collect(),
+        )
+    }
+
+    /// Number of rows.
+    pub fn len(&self) -> usize { self.columns.first().map(column_len).unwrap_or(0) }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Table of the rows at `indices`, which must be in bounds.
+    pub fn rows(&self, indices: &[i64]) -> Table {
+        let columns = self.columns.iter().map(|c| take(c, indices)).collect();
+        Table { names: self.names.clone(), columns }
+    }
+}
+
+fn column_len(column: &Value) -> usize { unsafe { VecHeader::from_raw(column.as_raw()).len as usize } }
+
+/// Vector of the elements of the vector `column` at `indices`, which must be in bounds.
+pub fn take(column: &Value, indices: &[i64]) -> Value {
+    fn take<T: VecElement>(column: &Value, indices: &[i64]) -> Vec<T> {
+        let v = unsafe { slice_from_raw::<T>(column.as_raw()) };
+        indices.iter().map(|i| v[*i as usize]).collect()
+    }
+
+    match column.get_type() {
+        Type::VecInt64 => Value::from(take::<i64>(column, indices)),
+        Type::VecFloat64 => Value::from(take::<f64>(column, indices)),
+        Type::VecBool => Value::from(take::<bool>(column, indices)),
+        Type::VecSym => Value::from(take::<Sym>(column, indices)),
+        Type::VecDate => Value::from(take::<Date>(column, indices)),
+        Type::VecTime => Value::from(take::<Time>(column, indices)),
+        Type::VecTimestamp => Value::from(take::<Timestamp>(column, indices)),
+        Type::VecTimespan => Value::from(take::<Timespan>(column, indices)),
+        ty => unreachable!("table column of type {}", ty),
+    }
+}
+
+/// Text of the element `row` of `column`, as shown in a table.
+fn cell(column: &Value, row: usize) -> String {
+    unsafe {
+        match column.get_type() {
+            Type::VecInt64 => Value::from(slice_from_raw::<i64>(column.as_raw())[row]).to_string(),
+            Type::VecFloat64 => Value::from(slice_from_raw::<f64>(column.as_raw())[row]).to_string(),
+            Type::VecBool => Value::from(slice_from_raw::<bool>(column.as_raw())[row]).to_string(),
+            Type::VecSym => slice_from_raw::<Sym>(column.as_raw())[row].name().to_string(),
+            Type::VecDate => Value::from(slice_from_raw::<Date>(column.as_raw())[row]).to_string(),
+            Type::VecTime => Value::from(slice_from_raw::<Time>(column.as_raw())[row]).to_string(),
+            Type::VecTimestamp => Value::from(slice_from_raw::<Timestamp>(column.as_raw())[row]).to_string(),
+            Type::VecTimespan => Value::from(slice_from_raw::<Timespan>(column.as_raw())[row]).to_string(),
+            _ => String::new(),
+        }
+    }
+}
+
+/// Renders the table as a grid of left aligned columns under a header.
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cells: Vec<Vec<String>> = self
+            .columns
+            .iter()
+            .map(|c| (0..self.len()).map(|row| cell(c, row)).collect())
+            .collect();
+        let widths: Vec<usize> = self
+            .names
+            .iter()
+            .zip(&cells)
+            .map(|(name, cells)| {
+                cells
+                    .iter()
+                    .map(|c| c.chars().count())
+                    .fold(name.chars().count(), usize::max)
+            })
+            .collect();
+
+        let line = |f: &mut fmt::Formatter, texts: &mut dyn Iterator<Item = &str>| -> fmt::Result {
+            let line: Vec<String> = texts.zip(&widths).map(|(t, w)| format!("{:<1$}", t, w)).collect();
+            write!(f, "{}", line.join(" ").trim_end())
+        };
+
+        line(f, &mut self.names.iter().map(|n| n.as_str()))?;
+        let total = widths.iter().sum::<usize>() + widths.len().saturating_sub(1);
+        write!(f, "\n{}", "-".repeat(total))?;
+        for row in 0..self.len() {
+            writeln!(f)?;
+            line(f, &mut cells.iter().map(|c| c[row].as_str()))?;
+        }
+        Ok(())
+    }
+}
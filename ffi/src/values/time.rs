@@ -0,0 +1,177 @@
+//! Dates and times, stored as `Int64`s:
+//!
+//! | type        | value                                 | written as                      |
+//! |-------------|---------------------------------------|---------------------------------|
+//! | `Date`      | days since 1970.01.01                 | `2026.10.18`                    |
+//! | `Time`      | nanoseconds since midnight            | `09:30:00.000`                  |
+//! | `Timestamp` | nanoseconds since 1970.01.01D00:00    | `2026.10.18D09:30:00.000000000` |
+//! | `Timespan`  | nanoseconds                           | `0D01:30:00.000000000`          |
+//!
+//! A time is written to the millisecond unless it has a finer part. Nulls are `NULL_INT64`, written
+//! `0Nd`, `0Nt`, `0Np` and `0Nn`. Vectors of dates and times hold `Int64` elements as well, tagged
+//! with their type.
+
+use super::vector::{VecElement, VecTag};
+use super::{Value, NULL_INT64};
+use crate::types::Type;
+use std::fmt;
+
+pub const NS_PER_SEC: i64 = 1_000_000_000;
+pub const NS_PER_MIN: i64 = 60 * NS_PER_SEC;
+pub const NS_PER_HOUR: i64 = 60 * NS_PER_MIN;
+pub const NS_PER_DAY: i64 = 24 * NS_PER_HOUR;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[repr(transparent)]
+pub struct Date(pub i64);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[repr(transparent)]
+pub struct Time(pub i64);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[repr(transparent)]
+pub struct Timestamp(pub i64);
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[repr(transparent)]
+pub struct Timespan(pub i64);
+
+impl VecElement for Date {
+    const TAG: VecTag = VecTag::Date;
+    const VEC_TYPE: Type = Type::VecDate;
+    const NULL: Self = Date(NULL_INT64);
+}
+
+impl VecElement for Time {
+    const TAG: VecTag = VecTag::Time;
+    const VEC_TYPE: Type = Type::VecTime;
+    const NULL: Self = Time(NULL_INT64);
+}
+
+impl VecElement for Timestamp {
+    const TAG: VecTag = VecTag::Timestamp;
+    const VEC_TYPE: Type = Type::VecTimestamp;
+    const NULL: Self = Timestamp(NULL_INT64);
+}
+
+impl VecElement for Timespan {
+    const TAG: VecTag = VecTag::Timespan;
+    const VEC_TYPE: Type = Type::VecTimespan;
+    const NULL: Self = Timespan(NULL_INT64);
+}
+
+/// Days since 1970.01.01 of the date `year.month.day`, if there is such a date.
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> Option<i64> {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    if !(1..=days_in_month).contains(&day) {
+        return None;
+    }
+
+    // the days of a 400 year era, of years starting in March
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146097 + day_of_era - 719468)
+}
+
+/// Year, month and day of the date `days` since 1970.01.01.
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn write_date(f: &mut fmt::Formatter, days: i64) -> fmt::Result {
+    let (year, month, day) = civil_from_days(days);
+    write!(f, "{:04}.{:02}.{:02}", year, month, day)
+}
+
+/// Writes the nanoseconds `ns` of a day, with `digits` digits of a second.
+fn write_time(f: &mut fmt::Formatter, ns: i64, digits: usize) -> fmt::Result {
+    let (h, m, s) = (ns / NS_PER_HOUR, ns / NS_PER_MIN % 60, ns / NS_PER_SEC % 60);
+    let fraction = ns % NS_PER_SEC / 10i64.pow(9 - digits as u32);
+    write!(f, "{:02}:{:02}:{:02}.{:0digits$}", h, m, s, fraction, digits = digits)
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            NULL_INT64 => write!(f, "0Nd"),
+            days => write_date(f, days),
+        }
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            NULL_INT64 => write!(f, "0Nt"),
+            ns if ns < 0 => write!(f, "-{}", Time(-ns)),
+            ns if ns % 1_000_000 == 0 => write_time(f, ns, 3),
+            ns => write_time(f, ns, 9),
+        }
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            NULL_INT64 => write!(f, "0Np"),
+            ns => {
+                write_date(f, ns.div_euclid(NS_PER_DAY))?;
+                write!(f, "D")?;
+                write_time(f, ns.rem_euclid(NS_PER_DAY), 9)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Timespan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            NULL_INT64 => write!(f, "0Nn"),
+            ns if ns < 0 => write!(f, "-{}", Timespan(-ns)),
+            ns => {
+                write!(f, "{}D", ns / NS_PER_DAY)?;
+                write_time(f, ns % NS_PER_DAY, 9)
+            }
+        }
+    }
+}
+
+/// Vector of the vector type `ty` of dates or times, of the raw values `raw`.
+pub fn vec(ty: &Type, raw: Vec<i64>) -> Value {
+    match ty {
+        Type::VecDate => Value::from(raw.into_iter().map(Date).collect::<Vec<_>>()),
+        Type::VecTime => Value::from(raw.into_iter().map(Time).collect::<Vec<_>>()),
+        Type::VecTimestamp => Value::from(raw.into_iter().map(Timestamp).collect::<Vec<_>>()),
+        _ => Value::from(raw.into_iter().map(Timespan).collect::<Vec<_>>()),
+    }
+}
+
+/// Writes the raw value `raw` of the date or time type `ty`.
+pub(crate) fn write(f: &mut fmt::Formatter, ty: &Type, raw: i64) -> fmt::Result {
+    match ty {
+        Type::Date => write!(f, "{}", Date(raw)),
+        Type::Time => write!(f, "{}", Time(raw)),
+        Type::Timestamp => write!(f, "{}", Timestamp(raw)),
+        _ => write!(f, "{}", Timespan(raw)),
+    }
+}
@@ -0,0 +1,186 @@
+//! Memory layout of vector values.
+//!
+//! A vector value is a pointer to a [`VecHeader`], shared by Rust and compiled code:
+//!
+//! | field  | type   | offset | description                                   |
+//! |--------|--------|--------|-----------------------------------------------|
+//! | `rc`   | `i64`  | 0      | number of references to the vector, atomic    |
+//! | `len`  | `i64`  | 8      | number of elements                            |
+//! | `cap`  | `i64`  | 16     | number of elements the data buffer can hold   |
+//! | `tag`  | `i64`  | 24     | element type, a [`VecTag`]                    |
+//! | `data` | `*T`   | 32     | elements, allocated as a Rust `Vec<T>`        |
+//!
+//! The data is pointed to rather than stored inline, so a `Vec<T>` is converted from and to a
+//! vector value without copying its elements. The data can also be memory owned elsewhere, or a
+//! mapped file, which is then never freed as a `Vec<T>`: the `cap` of such a vector is negative and
+//! tells its [`VecStorage`].
+
+use super::{mmap, rc};
+use crate::types::Type;
+use std::fs::File;
+use std::io;
+use std::mem::{size_of, ManuallyDrop};
+use std::sync::atomic::AtomicI64;
+
+pub const VEC_RC: u32 = 0;
+pub const VEC_LEN: u32 = 1;
+pub const VEC_CAP: u32 = 2;
+pub const VEC_TAG: u32 = 3;
+pub const VEC_DATA: u32 = 4;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(i64)]
+pub enum VecTag {
+    Int64 = 0,
+    Float64,
+    Bool,
+    Sym,
+    Date,
+    Time,
+    Timestamp,
+    Timespan,
+}
+
+impl VecTag {
+    /// Type of the vectors with the tag.
+    pub fn vec_type(self) -> Type {
+        match self {
+            VecTag::Int64 => Type::VecInt64,
+            VecTag::Float64 => Type::VecFloat64,
+            VecTag::Bool => Type::VecBool,
+            VecTag::Sym => Type::VecSym,
+            VecTag::Date => Type::VecDate,
+            VecTag::Time => Type::VecTime,
+            VecTag::Timestamp => Type::VecTimestamp,
+            VecTag::Timespan => Type::VecTimespan,
+        }
+    }
+}
+
+const CAP_BORROWED: i64 = -1;
+const CAP_MAPPED: i64 = -2;
+
+/// Owner of the data of a vector.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum VecStorage {
+    /// A Rust `Vec<T>` of `cap` elements, freed along with the vector.
+    Owned,
+    /// Memory owned elsewhere, which outlives the vector.
+    Borrowed,
+    /// A memory-mapped file, unmapped along with the vector.
+    Mapped,
+}
+
+#[repr(C)]
+pub struct VecHeader {
+    pub rc: AtomicI64,
+    pub len: i64,
+    pub cap: i64,
+    pub tag: VecTag,
+    pub data: *mut u8,
+}
+
+/// Element types of vector values.
+pub trait VecElement: Copy {
+    const TAG: VecTag;
+    /// Type of vectors of the element.
+    const VEC_TYPE: Type;
+    /// Missing element, e.g. where a dictionary lacks a key.
+    const NULL: Self;
+}
+
+impl VecElement for i64 {
+    const TAG: VecTag = VecTag::Int64;
+    const VEC_TYPE: Type = Type::VecInt64;
+    const NULL: Self = super::NULL_INT64;
+}
+
+impl VecElement for f64 {
+    const TAG: VecTag = VecTag::Float64;
+    const VEC_TYPE: Type = Type::VecFloat64;
+    const NULL: Self = super::NULL_FLOAT64;
+}
+
+impl VecElement for bool {
+    const TAG: VecTag = VecTag::Bool;
+    const VEC_TYPE: Type = Type::VecBool;
+    const NULL: Self = false;
+}
+
+impl VecHeader {
+    /// Moves `vec` into a new vector value with a single reference.
+    pub fn alloc<T: VecElement>(vec: Vec<T>) -> i64 {
+        let mut vec = ManuallyDrop::new(vec);
+        Self::alloc_header::<T>(vec.as_mut_ptr() as *mut u8, vec.len(), vec.capacity() as i64)
+    }
+
+    /// Creates a vector value with a single reference, of the `len` elements at `data`, which it
+    /// does not own.
+    ///
+    /// # Safety
+    /// `data` must hold `len` elements of `T`, and outlive the vector.
+    pub unsafe fn borrow<T: VecElement>(data: *const T, len: usize) -> i64 {
+        Self::alloc_header::<T>(data as *mut u8, len, CAP_BORROWED)
+    }
+
+    /// Maps the file `file` and creates a vector value with a single reference, of the `len`
+    /// elements at `offset` in it, which must be aligned for `T`. The file is mapped copy-on-write,
+    /// so changing the elements does not change the file.
+    pub fn map<T: VecElement>(file: &File, offset: usize, len: usize) -> io::Result<i64> {
+        let end = len
+            .checked_mul(size_of::<T>())
+            .and_then(|size| size.checked_add(offset));
+        let file_len = file.metadata()?.len();
+        if end.is_none_or(|end| end as u64 > file_len) {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "The file is too short"));
+        }
+        let data = mmap::map(file, offset)?;
+        Ok(Self::alloc_header::<T>(data, len, CAP_MAPPED))
+    }
+
+    fn alloc_header<T: VecElement>(data: *mut u8, len: usize, cap: i64) -> i64 {
+        let header = VecHeader { rc: AtomicI64::new(1), len: len as i64, cap, tag: T::TAG, data };
+        rc::count_alloc();
+        Box::into_raw(Box::new(header)) as i64
+    }
+
+    pub fn storage(&self) -> VecStorage {
+        match self.cap {
+            CAP_BORROWED => VecStorage::Borrowed,
+            CAP_MAPPED => VecStorage::Mapped,
+            _ => VecStorage::Owned,
+        }
+    }
+
+    /// # Safety
+    /// `raw` must be a live vector value.
+    pub unsafe fn from_raw<'a>(raw: i64) -> &'a mut VecHeader { &mut *(raw as *mut VecHeader) }
+
+    /// Borrows the elements of the vector.
+    ///
+    /// # Safety
+    /// `T` must be the element type of the vector, which must outlive the returned slice.
+    pub unsafe fn as_slice<'a, T: VecElement>(&self) -> &'a [T] {
+        debug_assert_eq!(self.tag, T::TAG);
+        std::slice::from_raw_parts(self.data as *const T, self.len as usize)
+    }
+
+    /// Frees the vector `raw` and its elements.
+    ///
+    /// # Safety
+    /// `raw` must be a vector value without any references left.
+    pub(crate) unsafe fn free(raw: i64) {
+        let header = Box::from_raw(raw as *mut VecHeader);
+        let (len, cap) = (header.len as usize, header.cap as usize);
+        match (header.storage(), header.tag) {
+            (VecStorage::Borrowed, _) => {}
+            (VecStorage::Mapped, _) => mmap::unmap(header.data),
+            (VecStorage::Owned, VecTag::Int64) => drop(Vec::from_raw_parts(header.data as *mut i64, len, cap)),
+            (VecStorage::Owned, VecTag::Float64) => drop(Vec::from_raw_parts(header.data as *mut f64, len, cap)),
+            (VecStorage::Owned, VecTag::Bool) => drop(Vec::from_raw_parts(header.data as *mut bool, len, cap)),
+            // symbols, dates and times are all stored as `i64`
+            (VecStorage::Owned, _) => drop(Vec::from_raw_parts(header.data as *mut i64, len, cap)),
+        }
+        rc::count_free();
+    }
+}
@@ -131,6 +131,8 @@ impl<'a> Builder<'a> {
         }
     }
 
+    /// # Safety
+    /// `indexes` must stay within the object `ptr` points to when the generated code runs.
     pub unsafe fn build_gep(&self, ptr: Value<'a>, indexes: &[Value<'a>], name: &str) -> Value<'a> {
         let c_string = to_c_str(name);
 
@@ -148,6 +150,36 @@ impl<'a> Builder<'a> {
         Value::new(value)
     }
 
+    /// Pointer to the field `index` of the struct of type `struct_ty` which `ptr` points to.
+    pub fn build_struct_gep(&self, struct_ty: Type<'a>, ptr: Value<'a>, index: u32, name: &str) -> Value<'a> {
+        unsafe {
+            let c_string = to_c_str(name);
+            Value::new(LLVMBuildStructGEP2(
+                self.llvm_builder,
+                struct_ty.as_llvm_type_ref(),
+                ptr.as_llvm_value_ref(),
+                index,
+                c_string.as_ptr(),
+            ))
+        }
+    }
+
+    /// Pointer to the element `index` of the array of `elem_ty` which `ptr` points to.
+    pub fn build_in_bounds_gep(&self, elem_ty: Type<'a>, ptr: Value<'a>, index: Value<'a>, name: &str) -> Value<'a> {
+        unsafe {
+            let c_string = to_c_str(name);
+            let mut indexes = [index.as_llvm_value_ref()];
+            Value::new(LLVMBuildInBoundsGEP2(
+                self.llvm_builder,
+                elem_ty.as_llvm_type_ref(),
+                ptr.as_llvm_value_ref(),
+                indexes.as_mut_ptr(),
+                1,
+                c_string.as_ptr(),
+            ))
+        }
+    }
+
     // -- OPS
 
     pub fn build_int_add(&self, lhs: Value<'a>, rhs: Value<'a>, name: &str) -> Value<'a> {
@@ -376,6 +408,19 @@ impl<'a> Builder<'a> {
         }
     }
 
+    pub fn build_select(&self, cond: Value<'a>, then: Value<'a>, otherwise: Value<'a>, name: &str) -> Value<'a> {
+        unsafe {
+            let c_string = to_c_str(name);
+            Value::new(LLVMBuildSelect(
+                self.llvm_builder,
+                cond.as_llvm_value_ref(),
+                then.as_llvm_value_ref(),
+                otherwise.as_llvm_value_ref(),
+                c_string.as_ptr(),
+            ))
+        }
+    }
+
     pub fn build_extract_value(&self, value: Value<'a>, index: u32, name: &str) -> Option<Value<'a>> {
         unsafe {
             // let size = value.get_type().count_fields();
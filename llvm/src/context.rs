@@ -10,6 +10,25 @@ use llvm_sys::execution_engine::*;
 use llvm_sys::prelude::LLVMContextRef;
 use llvm_sys::prelude::LLVMTypeRef;
 use llvm_sys::target::*;
+use std::sync::OnceLock;
+
+/// Target registration is process-wide, so it's done once and shared by every context.
+static NATIVE_TARGET: OnceLock<Result<(), &'static str>> = OnceLock::new();
+
+fn init_native_target() -> Result<(), &'static str> {
+    unsafe {
+        let r = LLVM_InitializeNativeTarget();
+        if r != 0 {
+            return Err("Context: could not initialize native target");
+        }
+        let r = LLVM_InitializeNativeAsmPrinter();
+        if r != 0 {
+            return Err("Context: could not initialize native asm printer");
+        }
+        LLVMLinkInMCJIT();
+        Ok(())
+    }
+}
 
 pub struct Context {
     llvm_context: LLVMContextRef,
@@ -25,17 +44,9 @@ impl Drop for Context {
 
 impl Context {
     pub fn new() -> Result<Context, &'static str> {
-        unsafe {
-            let r = LLVM_InitializeNativeTarget();
-            if r != 0 {
-                return Err("Context: could not initialize native target");
-            }
-            let r = LLVM_InitializeNativeAsmPrinter();
-            if r != 0 {
-                return Err("Context: could not initialize native asm printer");
-            }
-            LLVMLinkInMCJIT();
+        (*NATIVE_TARGET.get_or_init(init_native_target))?;
 
+        unsafe {
             let llvm_context = LLVMContextCreate();
             if llvm_context.is_null() {
                 return Err("Context: could not create LLVM context");
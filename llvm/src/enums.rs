@@ -15,9 +15,9 @@ pub enum IntPredicate {
     SLE = 41,
 }
 
-impl Into<LLVMIntPredicate> for IntPredicate {
-    fn into(self) -> LLVMIntPredicate {
-        match self {
+impl From<IntPredicate> for LLVMIntPredicate {
+    fn from(pred: IntPredicate) -> Self {
+        match pred {
             IntPredicate::EQ => LLVMIntPredicate::LLVMIntEQ,
             IntPredicate::NE => LLVMIntPredicate::LLVMIntNE,
             IntPredicate::UGT => LLVMIntPredicate::LLVMIntUGT,
@@ -52,9 +52,9 @@ pub enum FloatPredicate {
     UNO,
 }
 
-impl Into<LLVMRealPredicate> for FloatPredicate {
-    fn into(self) -> LLVMRealPredicate {
-        match self {
+impl From<FloatPredicate> for LLVMRealPredicate {
+    fn from(pred: FloatPredicate) -> Self {
+        match pred {
             FloatPredicate::OEQ => LLVMRealPredicate::LLVMRealOEQ,
             FloatPredicate::OGE => LLVMRealPredicate::LLVMRealOGE,
             FloatPredicate::OGT => LLVMRealPredicate::LLVMRealOGT,
@@ -1,4 +1,6 @@
 use crate::utils::to_c_str;
+use crate::values::fn_value::FnValue;
+use crate::values::ValueIntrinsics;
 use llvm_sys::execution_engine::*;
 use std::marker::PhantomData;
 
@@ -36,4 +38,11 @@ impl<'a> ExecutionEngine<'a> {
 
         Ok(address as usize)
     }
+
+    /// Resolves `function` to `addr` for code compiled by this engine only.
+    pub fn add_global_mapping(&self, function: FnValue<'_>, addr: usize) {
+        unsafe {
+            LLVMAddGlobalMapping(self.llvm_execution_engine, function.as_llvm_value_ref(), addr as _);
+        }
+    }
 }
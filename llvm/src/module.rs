@@ -5,14 +5,11 @@ use crate::types::TypeIntrinsics;
 use crate::utils::to_c_str;
 use crate::values::fn_value::FnValue;
 use crate::values::Value;
-use crate::values::ValueIntrinsics;
 use llvm_sys::core::LLVMAddFunction;
-use llvm_sys::core::LLVMAddGlobal;
 use llvm_sys::core::LLVMAddGlobalInAddressSpace;
 use llvm_sys::core::LLVMDumpModule;
 use llvm_sys::core::LLVMGetNamedFunction;
 use llvm_sys::core::LLVMGetNamedGlobal;
-use llvm_sys::core::LLVMSetInitializer;
 use llvm_sys::execution_engine::LLVMCreateExecutionEngineForModule;
 use llvm_sys::prelude::LLVMModuleRef;
 use std::marker::PhantomData;
@@ -68,7 +65,7 @@ impl<'a> Module<'a> {
         }
     }
 
-    pub fn add_global(&self, name: &str, ty: Type<'a>) -> Value {
+    pub fn add_global(&self, name: &str, ty: Type<'a>) -> Value<'_> {
         unsafe {
             let c_string = to_c_str(name);
             let global = LLVMAddGlobalInAddressSpace(self.llvm_module, ty.as_llvm_type_ref(), c_string.as_ptr(), 0);
@@ -1,4 +1,4 @@
-use super::{Type, TypeRef};
+use super::TypeRef;
 use crate::types::TypeIntrinsics;
 use crate::values::f64_value::*;
 use llvm_sys::core::LLVMConstReal;
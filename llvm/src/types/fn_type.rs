@@ -1,7 +1,5 @@
-use super::{Type, TypeRef};
+use super::TypeRef;
 use crate::types::TypeIntrinsics;
-use crate::values::i64_value::*;
-use llvm_sys::core::LLVMConstInt;
 use llvm_sys::prelude::LLVMTypeRef;
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
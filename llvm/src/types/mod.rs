@@ -38,8 +38,8 @@ impl<'a> TypeRef<'a> {
     }
 }
 
-impl Into<LLVMTypeRef> for TypeRef<'_> {
-    fn into(self) -> LLVMTypeRef { self.llvm_type }
+impl From<TypeRef<'_>> for LLVMTypeRef {
+    fn from(ty: TypeRef<'_>) -> Self { ty.llvm_type }
 }
 
 #[derive(Clone, Debug)]
@@ -86,17 +86,17 @@ impl<'a> From<VecType<'a>> for Type<'a> {
     fn from(ty: VecType<'a>) -> Self { Self::Vec(ty) }
 }
 
-impl<'a> Into<PtrType<'a>> for Type<'a> {
-    fn into(self) -> PtrType<'a> {
-        match self {
+impl<'a> From<Type<'a>> for PtrType<'a> {
+    fn from(ty: Type<'a>) -> Self {
+        match ty {
             Type::Ptr(t) => t,
-            _ => panic!("Cannot convert {:?} to PtrType", self),
+            _ => panic!("Cannot convert {:?} to PtrType", ty),
         }
     }
 }
 
 impl<'a> Type<'a> {
-    pub fn new(llvm_type: LLVMTypeRef) -> Type<'a> {
+    pub(crate) fn new(llvm_type: LLVMTypeRef) -> Type<'a> {
         match unsafe { llvm_sys::core::LLVMGetTypeKind(llvm_type) } {
             llvm_sys::LLVMTypeKind::LLVMVoidTypeKind => Type::Null(VoidType::new(llvm_type)),
             llvm_sys::LLVMTypeKind::LLVMIntegerTypeKind => unsafe {
@@ -1,4 +1,4 @@
-use super::{Type, TypeRef};
+use super::TypeRef;
 use crate::types::TypeIntrinsics;
 // use llvm_sys::core::LLVMGetElementType;
 use crate::values::prelude::PtrValue;
@@ -26,18 +26,6 @@ pub struct LLVMString {
 
 impl LLVMString {
     pub(crate) unsafe fn new(ptr: *const c_char) -> Self { LLVMString { ptr } }
-
-    pub fn to_string(&self) -> String { (*self).to_string_lossy().into_owned() }
-
-    pub(crate) fn create_from_c_str(string: &CStr) -> LLVMString {
-        unsafe { LLVMString::new(LLVMCreateMessage(string.as_ptr() as *const _)) }
-    }
-
-    pub(crate) fn create_from_str(string: &str) -> LLVMString {
-        debug_assert_eq!(string.as_bytes()[string.as_bytes().len() - 1], 0);
-
-        unsafe { LLVMString::new(LLVMCreateMessage(string.as_ptr() as *const _)) }
-    }
 }
 
 impl Deref for LLVMString {
@@ -51,7 +39,7 @@ impl fmt::Debug for LLVMString {
 }
 
 impl fmt::Display for LLVMString {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> { write!(f, "{:?}", self.deref()) }
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> { f.write_str(&self.to_string_lossy()) }
 }
 
 impl PartialEq for LLVMString {
@@ -15,13 +15,12 @@ impl<'a> F64Value<'a> {
 
     pub fn get_constant(self) -> f64 {
         let mut lossy = 0;
-        let constant = unsafe { LLVMConstRealGetDouble(self.val.as_llvm_value_ref(), &mut lossy) };
-        constant
+        unsafe { LLVMConstRealGetDouble(self.val.as_llvm_value_ref(), &mut lossy) }
     }
 }
 
-impl Into<f64> for F64Value<'_> {
-    fn into(self) -> f64 { self.get_constant() }
+impl From<F64Value<'_>> for f64 {
+    fn from(val: F64Value<'_>) -> Self { val.get_constant() }
 }
 
 impl ValueIntrinsics for F64Value<'_> {
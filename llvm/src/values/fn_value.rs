@@ -1,12 +1,10 @@
 use super::{Value, ValueRef};
 use crate::basic_block::BasicBlock;
-use crate::types::Type;
 use crate::values::ValueIntrinsics;
 use llvm_sys::analysis::{LLVMVerifierFailureAction, LLVMVerifyFunction};
 use llvm_sys::core::*;
 use llvm_sys::prelude::LLVMTypeRef;
 use llvm_sys::prelude::LLVMValueRef;
-use llvm_sys::LLVMTypeKind;
 use std::ffi::CStr;
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
@@ -41,7 +39,7 @@ impl<'a> FnValue<'a> {
     }
 
     pub fn get_params_iter(&'a self) -> impl Iterator<Item = Value<'a>> + 'a {
-        (0..self.get_param_count()).map(move |i| self.get_param(i)).flatten()
+        (0..self.get_param_count()).filter_map(move |i| self.get_param(i))
     }
 
     pub fn get_first_basic_block(self) -> Option<BasicBlock<'a>> {
@@ -81,14 +79,7 @@ impl ValueIntrinsics for FnValue<'_> {
     fn get_name(&self) -> &CStr { self.val.get_name() }
 
     fn get_llvm_type_ref(&self) -> LLVMTypeRef {
-        unsafe {
-            let llvm_ty_ref = self.val.get_llvm_type_ref();
-            LLVMInt64Type()
-            // match LLVMGetTypeKind(llvm_ty_ref) {
-            //     LLVMTypeKind::LLVMFunctionTypeKind => llvm_ty_ref,
-            //     LLVMTypeKind::LLVMPointerTypeKind => LLVMGetElementType(llvm_ty_ref),
-            //     _ => unreachable!(),
-            // }
-        }
+        // functions are called through opaque pointers, their values are typed as i64
+        unsafe { LLVMInt64Type() }
     }
 }
@@ -26,6 +26,6 @@ impl ValueIntrinsics for I1Value<'_> {
     fn get_llvm_type_ref(&self) -> LLVMTypeRef { self.val.get_llvm_type_ref() }
 }
 
-impl Into<bool> for I1Value<'_> {
-    fn into(self) -> bool { self.get_constant() }
+impl From<I1Value<'_>> for bool {
+    fn from(val: I1Value<'_>) -> Self { val.get_constant() }
 }
@@ -24,8 +24,8 @@ impl<'a> I64Value<'a> {
     }
 }
 
-impl Into<i64> for I64Value<'_> {
-    fn into(self) -> i64 { self.get_constant() }
+impl From<I64Value<'_>> for i64 {
+    fn from(val: I64Value<'_>) -> Self { val.get_constant() }
 }
 
 impl ValueIntrinsics for I64Value<'_> {
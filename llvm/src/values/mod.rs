@@ -51,8 +51,8 @@ impl<'a> ValueRef<'a> {
     }
 }
 
-impl Into<LLVMValueRef> for ValueRef<'_> {
-    fn into(self) -> LLVMValueRef { self.llvm_value }
+impl From<ValueRef<'_>> for LLVMValueRef {
+    fn from(value: ValueRef<'_>) -> Self { value.llvm_value }
 }
 
 #[derive(Copy, Clone, PartialEq)]
@@ -105,82 +105,82 @@ impl<'a> From<VecValue<'a>> for Value<'a> {
     fn from(val: VecValue<'a>) -> Self { Self::Vec(val) }
 }
 
-impl<'a> Into<I1Value<'a>> for Value<'a> {
-    fn into(self) -> I1Value<'a> {
-        match self {
+impl<'a> From<Value<'a>> for I1Value<'a> {
+    fn from(value: Value<'a>) -> Self {
+        match value {
             Value::Bool(val) => val,
             _ => panic!("Expected I8Value"),
         }
     }
 }
 
-impl<'a> Into<I64Value<'a>> for Value<'a> {
-    fn into(self) -> I64Value<'a> {
-        match self {
-            Self::Int64(val) => val,
+impl<'a> From<Value<'a>> for I64Value<'a> {
+    fn from(value: Value<'a>) -> Self {
+        match value {
+            Value::Int64(val) => val,
             val => panic!("Expected I64Value, found: {:?}", val),
         }
     }
 }
 
-impl<'a> Into<F64Value<'a>> for Value<'a> {
-    fn into(self) -> F64Value<'a> {
-        match self {
-            Self::Float64(val) => val,
+impl<'a> From<Value<'a>> for F64Value<'a> {
+    fn from(value: Value<'a>) -> Self {
+        match value {
+            Value::Float64(val) => val,
             _ => panic!("Expected F64Value"),
         }
     }
 }
 
-impl<'a> Into<FnValue<'a>> for Value<'a> {
-    fn into(self) -> FnValue<'a> {
-        match self {
-            Self::Fn(val) => val,
+impl<'a> From<Value<'a>> for FnValue<'a> {
+    fn from(value: Value<'a>) -> Self {
+        match value {
+            Value::Fn(val) => val,
             _ => panic!("Expected FnValue"),
         }
     }
 }
 
-impl<'a> Into<StructValue<'a>> for Value<'a> {
-    fn into(self) -> StructValue<'a> {
-        match self {
-            Self::Struct(val) => val,
+impl<'a> From<Value<'a>> for StructValue<'a> {
+    fn from(value: Value<'a>) -> Self {
+        match value {
+            Value::Struct(val) => val,
             _ => panic!("Expected StructValue"),
         }
     }
 }
 
-impl<'a> Into<InstructionValue<'a>> for Value<'a> {
-    fn into(self) -> InstructionValue<'a> {
-        match self {
-            Self::Instruction(val) => val,
+impl<'a> From<Value<'a>> for InstructionValue<'a> {
+    fn from(value: Value<'a>) -> Self {
+        match value {
+            Value::Instruction(val) => val,
             _ => panic!("Expected InstructionValue"),
         }
     }
 }
 
-impl<'a> Into<PtrValue<'a>> for Value<'a> {
-    fn into(self) -> PtrValue<'a> {
-        match self {
-            Self::Ptr(val) => val,
+impl<'a> From<Value<'a>> for PtrValue<'a> {
+    fn from(value: Value<'a>) -> Self {
+        match value {
+            Value::Ptr(val) => val,
             _ => panic!("Expected PtrValue"),
         }
     }
 }
 
-impl<'a> Into<PhiValue<'a>> for Value<'a> {
-    fn into(self) -> PhiValue<'a> {
-        match self {
-            Self::Phi(val) => val,
+impl<'a> From<Value<'a>> for PhiValue<'a> {
+    fn from(value: Value<'a>) -> Self {
+        match value {
+            Value::Phi(val) => val,
             _ => panic!("Expected PhiValue"),
         }
     }
 }
 
-impl<'a> Into<VecValue<'a>> for Value<'a> {
-    fn into(self) -> VecValue<'a> {
-        match self {
-            Self::Vec(val) => val,
+impl<'a> From<Value<'a>> for VecValue<'a> {
+    fn from(value: Value<'a>) -> Self {
+        match value {
+            Value::Vec(val) => val,
             _ => panic!("Expected VecValue"),
         }
     }
@@ -210,6 +210,8 @@ impl<'a> Value<'a> {
                 LLVMTypeKind::LLVMFunctionTypeKind => Value::Fn(FnValue::new(llvm_value)),
                 LLVMTypeKind::LLVMPointerTypeKind => Value::Ptr(PtrValue::new(llvm_value)),
                 LLVMTypeKind::LLVMVectorTypeKind => Value::Vec(VecValue::new(llvm_value)),
+                // only instructions, such as calls to void functions, have no value
+                LLVMTypeKind::LLVMVoidTypeKind => Value::Instruction(InstructionValue::new(llvm_value)),
                 kind => panic!("Unknown value type: {:?}", kind),
             }
         }
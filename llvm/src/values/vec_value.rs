@@ -1,6 +1,5 @@
 use super::ValueRef;
 use crate::values::ValueIntrinsics;
-use llvm_sys::core::LLVMConstRealGetDouble;
 use llvm_sys::prelude::LLVMTypeRef;
 use llvm_sys::prelude::LLVMValueRef;
 use std::ffi::CStr;